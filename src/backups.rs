@@ -0,0 +1,246 @@
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::{
+  fs,
+  path::{Component, Path, PathBuf},
+  sync::OnceLock,
+};
+
+use anyhow::Context as _;
+use tracing::debug;
+
+use crate::errors::ErrorWithFilePath;
+
+/// Where the `_original` copies exiftool makes of written files are kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BackupMode {
+  /// Next to the written file, as `photo.jpg_original`.
+  Beside,
+  /// In a directory that mirrors the absolute paths of the written files.
+  Directory(PathBuf),
+}
+
+pub struct Backups {
+  mode: BackupMode,
+  count: AtomicUsize,
+  bytes: AtomicU64,
+}
+
+static BACKUPS: OnceLock<Backups> = OnceLock::new();
+
+/// Keeps backups of all files written by exiftool from now on.
+/// Has no effect if backups are already enabled.
+pub fn install_backups(mode: BackupMode) {
+  BACKUPS.get_or_init(|| Backups {
+    mode,
+    count: AtomicUsize::new(0),
+    bytes: AtomicU64::new(0),
+  });
+}
+
+/// The backups that are kept, if enabled.
+#[must_use]
+pub fn backups() -> Option<&'static Backups> {
+  BACKUPS.get()
+}
+
+/// The path exiftool writes the backup of `file_path` to.
+#[must_use]
+fn exiftool_backup_path(file_path: &Path) -> PathBuf {
+  let mut backup_path = file_path.as_os_str().to_owned();
+  backup_path.push("_original");
+  PathBuf::from(backup_path)
+}
+
+/// The path in `backup_dir` that the backup of `file_path` is moved to.
+#[must_use]
+fn backup_dir_path(backup_dir: &Path, file_path: &Path) -> PathBuf {
+  let file_path = std::path::absolute(file_path).unwrap_or_else(|_| file_path.to_path_buf());
+  backup_dir.join(
+    file_path
+      .components()
+      .filter(|component| matches!(component, Component::Normal(_)))
+      .collect::<PathBuf>(),
+  )
+}
+
+/// A backup that exiftool may create while writing a file.
+pub struct PendingBackup {
+  backups: &'static Backups,
+  file_path: PathBuf,
+  /// exiftool never replaces an existing backup, so it still holds an older version of the file.
+  existed: bool,
+}
+
+impl Backups {
+  /// Must be called before exiftool writes `file_path`.
+  #[must_use]
+  pub fn prepare(&'static self, file_path: &Path) -> PendingBackup {
+    PendingBackup {
+      backups: self,
+      file_path: file_path.to_path_buf(),
+      existed: exiftool_backup_path(file_path).exists(),
+    }
+  }
+
+  #[must_use]
+  pub fn count(&self) -> usize {
+    self.count.load(Ordering::Relaxed)
+  }
+
+  #[must_use]
+  pub fn bytes(&self) -> u64 {
+    self.bytes.load(Ordering::Relaxed)
+  }
+}
+
+impl PendingBackup {
+  /// Creates the backup like exiftool does, for files that are written without exiftool.
+  #[cfg(feature = "native-jpeg-write")]
+  pub fn copy_original(&self) -> anyhow::Result<()> {
+    if !self.existed {
+      fs::copy(&self.file_path, exiftool_backup_path(&self.file_path))
+        .context("Failed to create the backup")?;
+    }
+    Ok(())
+  }
+
+  /// Records the backup exiftool created and moves it into the backup directory.
+  pub fn finish(self) -> Result<(), ErrorWithFilePath> {
+    let backup_path = exiftool_backup_path(&self.file_path);
+    if self.existed {
+      return Ok(());
+    }
+    // Newly created files such as XMP sidecars have no backup.
+    let Ok(metadata) = fs::metadata(&backup_path) else {
+      return Ok(());
+    };
+    self.backups.count.fetch_add(1, Ordering::Relaxed);
+    self
+      .backups
+      .bytes
+      .fetch_add(metadata.len(), Ordering::Relaxed);
+
+    let BackupMode::Directory(backup_dir) = &self.backups.mode else {
+      return Ok(());
+    };
+    let target_path = backup_dir_path(backup_dir, &self.file_path);
+    if target_path.exists() {
+      // The file was written before, the older backup is closer to the original.
+      debug!(
+        file_path = %self.file_path.display(),
+        "Keeping the older backup \"{}\"",
+        target_path.display(),
+      );
+      return fs::remove_file(&backup_path)
+        .context("Failed to remove the redundant backup")
+        .map_err(ErrorWithFilePath::from_source(&self.file_path));
+    }
+    move_file(&backup_path, &target_path)
+      .with_context(|| format!("Failed to move the backup to \"{}\"", target_path.display()))
+      .map_err(ErrorWithFilePath::from_source(&self.file_path))
+  }
+}
+
+/// Moves a file, copying it if the target is on another file system.
+fn move_file(from: &Path, to: &Path) -> anyhow::Result<()> {
+  if let Some(parent) = to.parent() {
+    fs::create_dir_all(parent)?;
+  }
+  if fs::rename(from, to).is_err() {
+    fs::copy(from, to)?;
+    fs::remove_file(from)?;
+  }
+  Ok(())
+}
+
+/// Formats a byte count with binary units, e.g. `1.5 MiB`.
+#[must_use]
+pub fn pretty_size(bytes: u64) -> String {
+  const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+  let mut unit = 0;
+  let mut divisor = 1_u64;
+  while bytes / divisor >= 1024 && unit < UNITS.len() - 1 {
+    divisor *= 1024;
+    unit += 1;
+  }
+  if unit == 0 {
+    return format!("{bytes} B");
+  }
+  let tenths = u128::from(bytes) * 10 / u128::from(divisor);
+  format!("{}.{} {}", tenths / 10, tenths % 10, UNITS[unit])
+}
+
+/// Parses a byte count with an optional binary unit, e.g. `500`, `20K`, `1.5MiB` or `2 GB`.
+/// Units are case insensitive and always multiples of 1024.
+pub fn parse_size(size: &str) -> anyhow::Result<u64> {
+  const UNITS: [&str; 5] = ["", "K", "M", "G", "T"];
+  let size = size.trim();
+  let number_end = size
+    .find(|character: char| !character.is_ascii_digit() && character != '.')
+    .unwrap_or(size.len());
+  let (number, unit) = size.split_at(number_end);
+  let unit = unit.trim().to_ascii_uppercase();
+  let unit = unit
+    .strip_suffix("IB")
+    .or_else(|| unit.strip_suffix('B'))
+    .unwrap_or(&unit);
+  let exponent = UNITS
+    .iter()
+    .position(|known_unit| *known_unit == unit)
+    .with_context(|| format!("Unknown size unit in \"{size}\""))?;
+  let multiplier = 1024_u64.pow(u32::try_from(exponent)?);
+
+  // Fractions are parsed as tenths, thousandths and so on, so no float is needed.
+  let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+  let invalid_size = || anyhow::anyhow!("Invalid size \"{size}\"");
+  let whole = whole.parse::<u64>().map_err(|_| invalid_size())?;
+  let fraction_bytes = if fraction.is_empty() {
+    0
+  } else {
+    let denominator = 10_u64
+      .checked_pow(u32::try_from(fraction.len())?)
+      .ok_or_else(invalid_size)?;
+    fraction.parse::<u64>().map_err(|_| invalid_size())? * multiplier / denominator
+  };
+  whole
+    .checked_mul(multiplier)
+    .and_then(|bytes| bytes.checked_add(fraction_bytes))
+    .ok_or_else(invalid_size)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn backup_paths() {
+    assert_eq!(
+      exiftool_backup_path(Path::new("/photos/IMG_1234.JPG")),
+      Path::new("/photos/IMG_1234.JPG_original")
+    );
+    assert_eq!(
+      backup_dir_path(
+        Path::new("/backups"),
+        Path::new("/photos/2019/IMG_1234.JPG")
+      ),
+      Path::new("/backups/photos/2019/IMG_1234.JPG")
+    );
+  }
+
+  #[test]
+  fn sizes() {
+    assert_eq!(pretty_size(0), "0 B");
+    assert_eq!(pretty_size(1023), "1023 B");
+    assert_eq!(pretty_size(1536), "1.5 KiB");
+    assert_eq!(pretty_size(5 * 1024 * 1024 * 1024), "5.0 GiB");
+
+    assert_eq!(parse_size("500").unwrap(), 500);
+    assert_eq!(parse_size("20K").unwrap(), 20 * 1024);
+    assert_eq!(parse_size("1.5MiB").unwrap(), 1536 * 1024);
+    assert_eq!(parse_size("2 gb").unwrap(), 2 * 1024 * 1024 * 1024);
+    assert_eq!(parse_size("10B").unwrap(), 10);
+    assert!(parse_size("10X").is_err());
+    assert!(parse_size("").is_err());
+    assert!(parse_size("1.2.3M").is_err());
+  }
+}
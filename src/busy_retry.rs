@@ -0,0 +1,107 @@
+use core::time::Duration;
+use std::{io, path::Path};
+
+use tracing::debug;
+
+/// How often an operation on a busy file is tried before its error is counted.
+const BUSY_ATTEMPTS: u32 = 5;
+/// The wait before the first retry, doubled for every further retry.
+const FIRST_BUSY_DELAY: Duration = Duration::from_millis(100);
+
+/// `ERROR_SHARING_VIOLATION` and `ERROR_LOCK_VIOLATION`, e.g. while an indexer or virus scanner has the file open.
+const WINDOWS_BUSY_ERROR_CODES: [i32; 2] = [32, 33];
+
+/// Parts of the exiftool errors for files that are opened by another process.
+const BUSY_EXIFTOOL_MESSAGES: [&str; 5] = [
+  "being used by another process",
+  "sharing violation",
+  "Resource busy",
+  "Text file busy",
+  "Error renaming temporary file",
+];
+
+/// Whether an I/O error means that the file is opened or locked by another process.
+#[must_use]
+pub fn is_busy_io_error(e: &io::Error) -> bool {
+  matches!(
+    e.kind(),
+    io::ErrorKind::ResourceBusy | io::ErrorKind::ExecutableFileBusy
+  ) || (cfg!(windows)
+    && e
+      .raw_os_error()
+      .is_some_and(|code| WINDOWS_BUSY_ERROR_CODES.contains(&code)))
+}
+
+/// Whether the stderr of exiftool means that the file is opened or locked by another process.
+#[must_use]
+pub fn is_busy_exiftool_output(stderr: &str) -> bool {
+  BUSY_EXIFTOOL_MESSAGES
+    .iter()
+    .any(|message| stderr.contains(message))
+}
+
+/// Runs `operation` until `is_busy` no longer holds for its result, with an exponential backoff,
+/// so files briefly opened by an indexer or an SMB client do not fail.
+pub fn retry_while_busy<R>(
+  file_path: &Path,
+  mut operation: impl FnMut() -> R,
+  is_busy: impl Fn(&R) -> bool,
+) -> R {
+  let mut delay = FIRST_BUSY_DELAY;
+  let mut attempt = 1;
+  loop {
+    let result = operation();
+    if attempt >= BUSY_ATTEMPTS || !is_busy(&result) {
+      return result;
+    }
+    debug!(
+      file_path = %file_path.display(),
+      "File is busy, retrying in {}ms",
+      delay.as_millis(),
+    );
+    std::thread::sleep(delay);
+    delay *= 2;
+    attempt += 1;
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn busy_retries() {
+    let mut attempts = 0;
+    let result = retry_while_busy(
+      Path::new("/photos/IMG_1.jpg"),
+      || {
+        attempts += 1;
+        if attempts < 3 {
+          Err(io::Error::from(io::ErrorKind::ResourceBusy))
+        } else {
+          Ok(())
+        }
+      },
+      |result| result.as_ref().is_err_and(is_busy_io_error),
+    );
+    assert!(result.is_ok());
+    assert_eq!(attempts, 3);
+
+    let mut attempts = 0;
+    let result = retry_while_busy(
+      Path::new("/photos/IMG_1.jpg"),
+      || {
+        attempts += 1;
+        Err::<(), _>(io::Error::from(io::ErrorKind::PermissionDenied))
+      },
+      |result| result.as_ref().is_err_and(is_busy_io_error),
+    );
+    assert!(result.is_err());
+    assert_eq!(attempts, 1);
+
+    assert!(is_busy_exiftool_output(
+      "Error: Error renaming temporary file to C:/Photos/IMG_1.jpg"
+    ));
+    assert!(!is_busy_exiftool_output("Error: Not a valid JPEG"));
+  }
+}
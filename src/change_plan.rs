@@ -0,0 +1,294 @@
+use core::sync::atomic::Ordering;
+use std::{
+  path::{Path, PathBuf},
+  sync::Mutex,
+};
+
+use anyhow::Context as _;
+use chrono::NaiveDateTime;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{
+  ProcessState,
+  change_report::write_atomically,
+  date_extractors::{ConfidentNaiveDateTime, DateConfidence},
+  errors::ErrorWithFilePath,
+  event_stream::{FileEvent, emit_event},
+  exiftool::{set_exif_date, wrap_with_exiftool_repair},
+  get_modified_time, set_modified_time,
+  timezone::parse_utc_offset,
+  xmp_sidecar::date_write_target,
+};
+
+/// EXIF dates and modified times are written with their sub-second part, so the plan is applied exactly.
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f";
+
+/// The new EXIF date and modified time of one file, as written by `--plan` and executed by the `apply` subcommand.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct PlannedChange {
+  pub path: PathBuf,
+  /// `None` if the file has no EXIF date.
+  pub old_exif_date: Option<String>,
+  /// `None` if the EXIF date is left alone.
+  pub new_exif_date: Option<String>,
+  /// The UTC offset written along with the new EXIF date, e.g. `+02:00`.
+  pub new_exif_offset: Option<String>,
+  /// In UTC.
+  pub old_modified_time: Option<String>,
+  /// In UTC, `None` if the modified time is left alone.
+  pub new_modified_time: Option<String>,
+  /// Where the new EXIF date came from, e.g. `filename:whatsapp`.
+  pub source: Option<String>,
+  /// The confidence of the new EXIF date.
+  pub confidence: Option<DateConfidence>,
+}
+
+fn format_date(date: &NaiveDateTime) -> String {
+  date.format(DATE_FORMAT).to_string()
+}
+
+fn parse_date(date: Option<&str>) -> anyhow::Result<Option<NaiveDateTime>> {
+  date
+    .map(|date| {
+      NaiveDateTime::parse_from_str(date, DATE_FORMAT)
+        .with_context(|| format!("Invalid date in the plan: {date}"))
+    })
+    .transpose()
+}
+
+impl PlannedChange {
+  fn new_exif_date(&self) -> anyhow::Result<Option<ConfidentNaiveDateTime>> {
+    let Some(date) = parse_date(self.new_exif_date.as_deref())? else {
+      return Ok(None);
+    };
+    let mut date =
+      ConfidentNaiveDateTime::new(date, self.confidence.unwrap_or(DateConfidence::None));
+    if let Some(offset) = &self.new_exif_offset {
+      date = date.with_offset(parse_utc_offset(offset)?);
+    }
+    Ok(Some(date))
+  }
+}
+
+/// Collects the changes of a run for `--plan`, which scans like a dry run, and the change reports.
+pub struct ChangeList {
+  changes: Mutex<Vec<PlannedChange>>,
+}
+
+impl ChangeList {
+  #[must_use]
+  pub const fn new() -> Self {
+    Self {
+      changes: Mutex::new(Vec::new()),
+    }
+  }
+
+  pub fn record(
+    &self,
+    file_path: &Path,
+    old_exif_date: Option<&ConfidentNaiveDateTime>,
+    new_exif_date: Option<(&ConfidentNaiveDateTime, &str)>,
+    old_modified_time: Option<&NaiveDateTime>,
+    new_modified_time: Option<&NaiveDateTime>,
+  ) {
+    let change = PlannedChange {
+      path: file_path.to_path_buf(),
+      old_exif_date: old_exif_date.map(|date| format_date(&date.date)),
+      new_exif_date: new_exif_date.map(|(date, _)| format_date(&date.date)),
+      new_exif_offset: new_exif_date
+        .and_then(|(date, _)| date.offset)
+        .map(|offset| offset.to_string()),
+      old_modified_time: old_modified_time.map(format_date),
+      new_modified_time: new_modified_time.map(format_date),
+      source: new_exif_date
+        .map(|(_, source)| source)
+        .filter(|source| !source.is_empty())
+        .map(String::from),
+      confidence: new_exif_date.map(|(date, _)| date.confidence),
+    };
+    self
+      .changes
+      .lock()
+      .expect("Change plan lock should not be poisoned")
+      .push(change);
+  }
+
+  #[must_use]
+  pub fn len(&self) -> usize {
+    self
+      .changes
+      .lock()
+      .expect("Change plan lock should not be poisoned")
+      .len()
+  }
+
+  /// The changes sorted by file path.
+  #[must_use]
+  pub fn sorted(&self) -> Vec<PlannedChange> {
+    let mut changes = self
+      .changes
+      .lock()
+      .expect("Change plan lock should not be poisoned")
+      .clone();
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+    changes
+  }
+}
+
+/// Writes the changes as a JSON array.
+pub fn write_plan(changes: &[PlannedChange], plan_path: &Path) -> anyhow::Result<()> {
+  let contents = serde_json::to_string_pretty(changes).context("Failed to serialize the plan")?;
+  write_atomically(plan_path, &(contents + "\n"))
+    .with_context(|| format!("\"{}\": Failed to write the plan", plan_path.display()))
+}
+
+pub fn read_plan(plan_path: &Path) -> anyhow::Result<Vec<PlannedChange>> {
+  let contents = std::fs::read_to_string(plan_path)
+    .with_context(|| format!("\"{}\": Failed to read the plan", plan_path.display()))?;
+  serde_json::from_str(&contents)
+    .with_context(|| format!("\"{}\": Failed to parse the plan", plan_path.display()))
+}
+
+fn apply_change(
+  change: &PlannedChange,
+  process_state: &ProcessState,
+) -> Result<(), ErrorWithFilePath> {
+  let file_path = change.path.as_path();
+  let old_modified_time = parse_date(change.old_modified_time.as_deref())
+    .map_err(ErrorWithFilePath::from_source(file_path))?;
+  let new_modified_time = parse_date(change.new_modified_time.as_deref())
+    .map_err(ErrorWithFilePath::from_source(file_path))?;
+  let new_exif_date = change
+    .new_exif_date()
+    .map_err(ErrorWithFilePath::from_source(file_path))?;
+  let old_exif_date = parse_date(change.old_exif_date.as_deref())
+    .map_err(ErrorWithFilePath::from_source(file_path))?;
+
+  // The plan was reviewed for the files as they were, so files modified since are left alone.
+  if let Some(old_modified_time) = old_modified_time
+    && !get_modified_time(file_path)
+      .is_ok_and(|modified_time| process_state.mtimes_match(&modified_time, &old_modified_time))
+  {
+    return Err(ErrorWithFilePath::new(
+      file_path,
+      anyhow::anyhow!("The file was modified after the plan was written, skipping it"),
+    ));
+  }
+
+  if let Some(new_exif_date) = new_exif_date {
+    let write_path = date_write_target(file_path, process_state.options.sidecar_for_raw);
+    wrap_with_exiftool_repair(
+      &write_path,
+      process_state.options.repair_exif_errors,
+      process_state.options.dry_run,
+      || {
+        set_exif_date(
+          &write_path,
+          &new_exif_date,
+          change.source.as_deref().unwrap_or_default(),
+          process_state.options.exif_write_options(),
+        )
+      },
+    )
+    .map_err(|e| e.context(format!("Failed to set EXIF date to {new_exif_date}")))?;
+    let source = change.source.as_deref().unwrap_or_default();
+    emit_event(&if let Some(old_exif_date) = &old_exif_date {
+      FileEvent::overwritten(file_path, old_exif_date, &new_exif_date.date, source)
+    } else {
+      FileEvent::guessed(file_path, &new_exif_date.date, source)
+    });
+    if old_exif_date.is_some() {
+      process_state
+        .stat_exif_overwritten
+        .fetch_add(1, Ordering::Relaxed);
+    } else {
+      process_state
+        .stat_exif_updated
+        .fetch_add(1, Ordering::Relaxed);
+    }
+  }
+
+  if let Some(new_modified_time) = new_modified_time {
+    set_modified_time(file_path, &new_modified_time, process_state)
+      .map_err(ErrorWithFilePath::from_source(file_path))?;
+    process_state
+      .stat_modified_time_updated
+      .fetch_add(1, Ordering::Relaxed);
+  }
+  Ok(())
+}
+
+/// Implements the `apply` subcommand, which executes the changes of a plan written with `--plan` as they are.
+pub fn apply_change_plan(plan_path: &Path, process_state: &ProcessState) -> anyhow::Result<()> {
+  let changes = read_plan(plan_path)?;
+  info!(
+    "Applying {} planned changes from \"{}\"",
+    changes.len(),
+    plan_path.display()
+  );
+  changes.par_iter().for_each(|change| {
+    if process_state.should_exit.load(Ordering::Relaxed) {
+      return;
+    }
+    process_state
+      .stat_files_checked
+      .fetch_add(1, Ordering::Relaxed);
+    if let Err(e) = apply_change(change, process_state) {
+      e.log_error();
+      process_state
+        .stat_files_errors
+        .fetch_add(1, Ordering::Relaxed);
+      process_state.record_error(&e.file_path, &e.source);
+    }
+  });
+  Ok(())
+}
+
+#[cfg(test)]
+mod test {
+  use chrono::FixedOffset;
+
+  use super::*;
+
+  #[test]
+  fn planned_changes() {
+    let date = |date: &str| NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S%.f").unwrap();
+    let new_exif_date =
+      ConfidentNaiveDateTime::new(date("2019-08-18 14:30:00"), DateConfidence::Day)
+        .with_offset(FixedOffset::east_opt(7200).unwrap());
+
+    let plan = ChangeList::new();
+    plan.record(
+      Path::new("/photos/IMG-20190818-WA0001.jpg"),
+      None,
+      Some((&new_exif_date, "filename:whatsapp")),
+      Some(&date("2023-03-03 10:00:00.5")),
+      None,
+    );
+    plan.record(
+      Path::new("/photos/a.jpg"),
+      None,
+      None,
+      Some(&date("2099-01-01 00:00:00")),
+      Some(&date("2024-05-01 10:00:00")),
+    );
+    assert_eq!(plan.len(), 2);
+
+    let changes = plan.sorted();
+    let json = serde_json::to_string(&changes).unwrap();
+    assert_eq!(
+      serde_json::from_str::<Vec<PlannedChange>>(&json).unwrap(),
+      changes
+    );
+    assert_eq!(
+      changes[0].old_modified_time.as_deref(),
+      Some("2023-03-03 10:00:00.500")
+    );
+    assert_eq!(changes[0].source.as_deref(), Some("filename:whatsapp"));
+    assert_eq!(changes[0].new_exif_date().unwrap(), Some(new_exif_date));
+    assert_eq!(changes[1].new_exif_date().unwrap(), None);
+    assert_eq!(changes[1].confidence, None);
+  }
+}
@@ -0,0 +1,135 @@
+use core::fmt::Write as _;
+use std::{ffi::OsString, path::Path};
+
+use anyhow::Context as _;
+use serde::Serialize;
+
+use crate::change_plan::PlannedChange;
+
+/// Raised whenever a field of the JSON report is renamed, removed or changes its meaning.
+const JSON_REPORT_SCHEMA_VERSION: u32 = 1;
+
+/// Writes a file by renaming a temporary file next to it, so readers never see a partially written file.
+pub fn write_atomically(file_path: &Path, contents: &str) -> anyhow::Result<()> {
+  let mut temporary_name = OsString::from(".");
+  temporary_name.push(file_path.file_name().unwrap_or_default());
+  temporary_name.push(".tmp");
+  let temporary_path = file_path.with_file_name(temporary_name);
+  std::fs::write(&temporary_path, contents)?;
+  std::fs::rename(&temporary_path, file_path).inspect_err(|_| {
+    let _ = std::fs::remove_file(&temporary_path);
+  })?;
+  Ok(())
+}
+
+/// Quotes a CSV field if it contains a separator, quote or line break.
+fn csv_field(field: &str) -> String {
+  if field.contains([',', '"', '\n', '\r']) {
+    format!("\"{}\"", field.replace('"', "\"\""))
+  } else {
+    field.to_string()
+  }
+}
+
+fn csv_report(changes: &[PlannedChange]) -> anyhow::Result<String> {
+  let mut contents = String::from(
+    "file,old EXIF date,new EXIF date,old modified time (UTC),new modified time (UTC),source,confidence\n",
+  );
+  for change in changes {
+    let confidence = change
+      .confidence
+      .map(|confidence| format!("{confidence:?}"))
+      .unwrap_or_default();
+    let fields = [
+      change.path.to_string_lossy().as_ref(),
+      change.old_exif_date.as_deref().unwrap_or_default(),
+      change.new_exif_date.as_deref().unwrap_or_default(),
+      change.old_modified_time.as_deref().unwrap_or_default(),
+      change.new_modified_time.as_deref().unwrap_or_default(),
+      change.source.as_deref().unwrap_or_default(),
+      &confidence,
+    ]
+    .map(csv_field);
+    writeln!(contents, "{}", fields.join(","))?;
+  }
+  Ok(contents)
+}
+
+/// Writes one row per changed file, or per file that would be changed in a dry run.
+pub fn write_csv_report(changes: &[PlannedChange], report_path: &Path) -> anyhow::Result<()> {
+  write_atomically(report_path, &csv_report(changes)?).with_context(|| {
+    format!(
+      "\"{}\": Failed to write the CSV report",
+      report_path.display()
+    )
+  })
+}
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+  schema_version: u32,
+  changes: &'a [PlannedChange],
+}
+
+fn json_report(changes: &[PlannedChange]) -> anyhow::Result<String> {
+  Ok(serde_json::to_string_pretty(&JsonReport {
+    schema_version: JSON_REPORT_SCHEMA_VERSION,
+    changes,
+  })?)
+}
+
+/// Writes the same content as the CSV report as JSON, along with its schema version.
+pub fn write_json_report(changes: &[PlannedChange], report_path: &Path) -> anyhow::Result<()> {
+  write_atomically(report_path, &json_report(changes)?).with_context(|| {
+    format!(
+      "\"{}\": Failed to write the JSON report",
+      report_path.display()
+    )
+  })
+}
+
+#[cfg(test)]
+mod test {
+  use std::path::PathBuf;
+
+  use super::*;
+  use crate::date_extractors::DateConfidence;
+
+  fn changes() -> [PlannedChange; 1] {
+    [PlannedChange {
+      path: PathBuf::from("/photos/Urlaub, Italien/IMG_1.jpg"),
+      old_exif_date: None,
+      new_exif_date: Some(String::from("2019-08-18 00:00:00")),
+      new_exif_offset: None,
+      old_modified_time: Some(String::from("2023-03-03 10:00:00")),
+      new_modified_time: None,
+      source: Some(String::from("folder:year_in_folder_name")),
+      confidence: Some(DateConfidence::Year),
+    }]
+  }
+
+  #[test]
+  fn csv() {
+    let changes = changes();
+    assert_eq!(
+      csv_report(&changes).unwrap().lines().nth(1),
+      Some(
+        "\"/photos/Urlaub, Italien/IMG_1.jpg\",,2019-08-18 00:00:00,2023-03-03 10:00:00,,folder:year_in_folder_name,Year"
+      )
+    );
+    assert_eq!(csv_field("say \"cheese\""), "\"say \"\"cheese\"\"\"");
+  }
+
+  #[test]
+  fn json() {
+    let report: serde_json::Value =
+      serde_json::from_str(&json_report(&changes()).unwrap()).unwrap();
+    assert_eq!(report["schema_version"], JSON_REPORT_SCHEMA_VERSION);
+    assert_eq!(report["changes"][0]["new_exif_date"], "2019-08-18 00:00:00");
+    assert_eq!(
+      report["changes"][0]["old_exif_date"],
+      serde_json::Value::Null
+    );
+    assert_eq!(report["changes"][0]["confidence"], "Year");
+  }
+}
@@ -0,0 +1,210 @@
+use core::time::Duration;
+use std::{
+  collections::HashSet,
+  fs::File,
+  io::{self, BufRead as _, BufReader, BufWriter, Read as _, Seek as _, SeekFrom, Write as _},
+  path::{Path, PathBuf},
+  sync::Mutex,
+  time::Instant,
+};
+
+use anyhow::Context as _;
+use tracing::{info, warn};
+
+/// How often the processed files are written to the checkpoint, so an interrupted run loses at most this much progress.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+struct CheckpointWriter {
+  writer: BufWriter<File>,
+  last_flush: Instant,
+}
+
+/// The files a run has finished without errors, one JSON string per line, so an interrupted run can be resumed with `--resume`.
+pub struct Checkpoint {
+  path: PathBuf,
+  /// The files finished by the runs that were resumed.
+  finished_files: HashSet<PathBuf>,
+  writer: Mutex<CheckpointWriter>,
+}
+
+fn read_finished_files(checkpoint_path: &Path) -> anyhow::Result<HashSet<PathBuf>> {
+  let file = File::open(checkpoint_path)?;
+  let mut finished_files = HashSet::new();
+  for line in BufReader::new(file).lines() {
+    let line = line?;
+    // The last line may be cut off if the run was killed while writing it.
+    match serde_json::from_str::<PathBuf>(&line) {
+      Ok(file_path) => {
+        finished_files.insert(file_path);
+      },
+      Err(e) => warn!(
+        file_path = %checkpoint_path.display(),
+        "Ignoring invalid line in the checkpoint: {e}",
+      ),
+    }
+  }
+  Ok(finished_files)
+}
+
+/// Ends the last line of the checkpoint if it was cut off, e.g. because the run was killed while writing it.
+/// Otherwise the first file recorded would be appended to the cut off line, and be processed again on the next resume.
+fn end_cut_off_line(file: &mut File) -> io::Result<()> {
+  if file.seek(SeekFrom::End(0))? == 0 {
+    return Ok(());
+  }
+  file.seek(SeekFrom::End(-1))?;
+  let mut last_byte = [0];
+  file.read_exact(&mut last_byte)?;
+  if last_byte != *b"\n" {
+    file.write_all(b"\n")?;
+  }
+  Ok(())
+}
+
+impl Checkpoint {
+  /// Starts a new checkpoint, or continues the given one if `resume` is set.
+  pub fn open(checkpoint_path: &Path, resume: bool) -> anyhow::Result<Self> {
+    let finished_files = if resume {
+      let finished_files = read_finished_files(checkpoint_path).with_context(|| {
+        format!(
+          "\"{}\": Failed to read the checkpoint",
+          checkpoint_path.display()
+        )
+      })?;
+      info!(
+        "Resuming the run of \"{}\", skipping {} already processed files",
+        checkpoint_path.display(),
+        finished_files.len()
+      );
+      finished_files
+    } else {
+      HashSet::new()
+    };
+    let mut file = File::options()
+      .create(true)
+      .append(resume)
+      .read(resume)
+      .write(true)
+      .truncate(!resume)
+      .open(checkpoint_path)
+      .with_context(|| {
+        format!(
+          "\"{}\": Failed to open the checkpoint",
+          checkpoint_path.display()
+        )
+      })?;
+    if resume {
+      end_cut_off_line(&mut file).with_context(|| {
+        format!(
+          "\"{}\": Failed to end the cut off line of the checkpoint",
+          checkpoint_path.display()
+        )
+      })?;
+    }
+    Ok(Self {
+      path: checkpoint_path.to_path_buf(),
+      finished_files,
+      writer: Mutex::new(CheckpointWriter {
+        writer: BufWriter::new(file),
+        last_flush: Instant::now(),
+      }),
+    })
+  }
+
+  #[must_use]
+  pub fn is_finished(&self, file_path: &Path) -> bool {
+    self.finished_files.contains(file_path)
+  }
+
+  pub fn record(&self, file_path: &Path) {
+    // Paths that are not valid UTF-8 are processed again when resuming.
+    let Ok(line) = serde_json::to_string(file_path) else {
+      return;
+    };
+    let mut writer = self
+      .writer
+      .lock()
+      .expect("Checkpoint lock should not be poisoned");
+    let mut result = writeln!(writer.writer, "{line}");
+    if writer.last_flush.elapsed() >= FLUSH_INTERVAL {
+      result = result.and_then(|()| writer.writer.flush());
+      writer.last_flush = Instant::now();
+    }
+    drop(writer);
+    if let Err(e) = result {
+      warn!(
+        file_path = %self.path.display(),
+        "Failed to write the checkpoint: {e}",
+      );
+    }
+  }
+
+  /// Removes the checkpoint if the run went through, otherwise writes the rest of it for `--resume`.
+  pub fn finish(&self, interrupted: bool) -> anyhow::Result<()> {
+    let mut writer = self
+      .writer
+      .lock()
+      .expect("Checkpoint lock should not be poisoned");
+    writer.writer.flush()?;
+    drop(writer);
+    if interrupted {
+      info!(
+        "Continue the interrupted run with --resume \"{}\"",
+        self.path.display()
+      );
+    } else {
+      std::fs::remove_file(&self.path).with_context(|| {
+        format!(
+          "\"{}\": Failed to remove the checkpoint",
+          self.path.display()
+        )
+      })?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn resume() {
+    let checkpoint_path = std::env::temp_dir().join(format!(
+      "image-date-fixer-checkpoint-test-{}.jsonl",
+      std::process::id()
+    ));
+    let checkpoint = Checkpoint::open(&checkpoint_path, false).unwrap();
+    checkpoint.record(Path::new("/photos/IMG_1.jpg"));
+    checkpoint.record(Path::new("/photos/new\nline.jpg"));
+    checkpoint.finish(true).unwrap();
+
+    let checkpoint = Checkpoint::open(&checkpoint_path, true).unwrap();
+    assert!(checkpoint.is_finished(Path::new("/photos/IMG_1.jpg")));
+    assert!(checkpoint.is_finished(Path::new("/photos/new\nline.jpg")));
+    assert!(!checkpoint.is_finished(Path::new("/photos/IMG_2.jpg")));
+    checkpoint.finish(false).unwrap();
+    assert!(!checkpoint_path.exists());
+  }
+
+  #[test]
+  fn resume_cut_off() {
+    let checkpoint_path = std::env::temp_dir().join(format!(
+      "image-date-fixer-checkpoint-cut-off-test-{}.jsonl",
+      std::process::id()
+    ));
+    // The run was killed while writing the second line.
+    std::fs::write(&checkpoint_path, "\"/photos/IMG_1.jpg\"\n\"/photos/IMG_2").unwrap();
+
+    let checkpoint = Checkpoint::open(&checkpoint_path, true).unwrap();
+    assert!(checkpoint.is_finished(Path::new("/photos/IMG_1.jpg")));
+    checkpoint.record(Path::new("/photos/IMG_3.jpg"));
+    checkpoint.finish(true).unwrap();
+
+    let checkpoint = Checkpoint::open(&checkpoint_path, true).unwrap();
+    assert!(checkpoint.is_finished(Path::new("/photos/IMG_1.jpg")));
+    assert!(!checkpoint.is_finished(Path::new("/photos/IMG_2.jpg")));
+    assert!(checkpoint.is_finished(Path::new("/photos/IMG_3.jpg")));
+    checkpoint.finish(false).unwrap();
+  }
+}
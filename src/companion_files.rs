@@ -0,0 +1,144 @@
+use core::sync::atomic::Ordering;
+use std::path::{Path, PathBuf};
+
+use tracing::info;
+
+use crate::{
+  ProcessState,
+  date_extractors::ConfidentNaiveDateTime,
+  errors::ErrorWithFilePath,
+  exiftool::{exiftool_writable_file_extensions, get_exif_date, set_exif_date},
+  get_modified_time, set_modified_time,
+};
+
+/// Extensions of files that accompany a primary media file with the same stem.
+/// `AAE`: Apple edit sidecars, `THM`: camera thumbnails of videos.
+const COMPANION_FILE_EXTENSIONS: [&str; 2] = ["AAE", "THM"];
+
+#[must_use]
+pub fn is_companion_file(file_path: &Path) -> bool {
+  file_path.extension().is_some_and(|ext| {
+    COMPANION_FILE_EXTENSIONS
+      .iter()
+      .any(|companion_ext| ext.eq_ignore_ascii_case(companion_ext))
+  })
+}
+
+/// Lists the files in the same folder that have the same stem as `file_path`.
+fn files_with_same_stem(file_path: &Path) -> Vec<PathBuf> {
+  let Some(file_stem) = file_path.file_stem() else {
+    return Vec::new();
+  };
+  let folder_path = file_path
+    .parent()
+    .filter(|folder_path| !folder_path.as_os_str().is_empty())
+    .unwrap_or_else(|| Path::new("."));
+  let Ok(read_dir) = std::fs::read_dir(folder_path) else {
+    return Vec::new();
+  };
+
+  read_dir
+    .flatten()
+    .map(|entry| file_path.with_file_name(entry.file_name()))
+    .filter(|path| path != file_path && path.file_stem() == Some(file_stem) && path.is_file())
+    .collect()
+}
+
+/// Finds the primary media file of a companion file.
+/// Returns `None` if the file is not a companion file or has no primary file.
+#[must_use]
+pub fn find_primary_file(file_path: &Path) -> Option<PathBuf> {
+  if !is_companion_file(file_path) {
+    return None;
+  }
+  files_with_same_stem(file_path).into_iter().find(|path| {
+    !is_companion_file(path)
+      && !path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("xmp"))
+  })
+}
+
+/// Finds the companion files of a primary media file.
+#[must_use]
+pub fn find_companion_files(file_path: &Path) -> Vec<PathBuf> {
+  if is_companion_file(file_path) {
+    return Vec::new();
+  }
+  files_with_same_stem(file_path)
+    .into_iter()
+    .filter(|path| is_companion_file(path))
+    .collect()
+}
+
+/// Applies the date chosen for a primary file to its companion files.
+/// The modified time is always synced and the EXIF date is synced if exiftool can write the companion.
+pub fn sync_companion_files(
+  file_path: &Path,
+  confident_date: &ConfidentNaiveDateTime,
+  process_state: &ProcessState,
+) -> Vec<ErrorWithFilePath> {
+  let mut errors = Vec::new();
+  let date = &confident_date.date;
+
+  for companion_path in find_companion_files(file_path) {
+    let mut synced = false;
+
+    match get_modified_time(&companion_path) {
+      Ok(modified_time) if modified_time == *date => {},
+      Ok(_) => {
+        info!(
+          file_path = %companion_path.display(),
+          "Syncing modified time of companion file to {}",
+          date.format("%Y-%m-%d %H:%M:%S"),
+        );
+        match set_modified_time(&companion_path, date, process_state) {
+          Ok(()) => synced = true,
+          Err(e) => errors.push(ErrorWithFilePath::new(&companion_path, e)),
+        }
+      },
+      Err(e) => errors.push(e),
+    }
+
+    let is_writable = companion_path
+      .extension()
+      .and_then(|ext| ext.to_str())
+      .map(str::to_ascii_uppercase)
+      .is_some_and(|ext| {
+        exiftool_writable_file_extensions().is_ok_and(|extensions| extensions.contains(&ext))
+      });
+    if is_writable {
+      match get_exif_date(
+        &companion_path,
+        process_state.options.ignore_minor_exif_errors,
+      ) {
+        Ok(Some(exif_date)) if exif_date == *date => {},
+        Ok(_) => {
+          info!(
+            file_path = %companion_path.display(),
+            "Syncing EXIF date of companion file to {}",
+            date.format("%Y-%m-%d %H:%M:%S"),
+          );
+          match set_exif_date(
+            &companion_path,
+            confident_date,
+            "companion",
+            process_state.options.exif_write_options(),
+          ) {
+            Ok(()) => synced = true,
+            Err(e) => errors.push(e),
+          }
+        },
+        Err(e) => errors.push(e),
+      }
+    }
+
+    if synced {
+      process_state
+        .stat_companion_files_synced
+        .fetch_add(1, Ordering::Relaxed);
+    }
+  }
+
+  errors
+}
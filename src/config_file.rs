@@ -0,0 +1,352 @@
+use std::{
+  ffi::OsString,
+  path::{Path, PathBuf},
+};
+
+use anyhow::{Context as _, bail};
+use clap::{ArgAction, parser::ValueSource};
+
+/// The config file used if `--config` is not given and it exists.
+/// `$XDG_CONFIG_HOME/image-date-fixer/config.toml`, `~/.config/image-date-fixer/config.toml` or `%APPDATA%\image-date-fixer\config.toml`.
+#[must_use]
+pub fn default_config_path() -> Option<PathBuf> {
+  let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+    .filter(|dir| !dir.is_empty())
+    .map(PathBuf::from)
+    .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+    .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))?;
+  Some(config_dir.join("image-date-fixer").join("config.toml"))
+}
+
+/// Inserts the options of the config file given with `--config` (or the default config file) into the command line arguments.
+/// Options given on the command line take precedence over the config file.
+///
+/// Every option can be set by its long name, the options of a subcommand in a table named after it,
+/// which are only used with that subcommand, e.g.:
+/// ```toml
+/// files = ["/photos"]
+/// exclude-files = ["/photos/private"]
+/// dry-run = true
+/// fix-future-modified-times = 2
+/// only-extensions = ["jpg", "heic"]
+///
+/// [organize]
+/// target = "/photos/sorted"
+/// mode = "copy"
+/// ```
+pub fn args_with_config_file(
+  command: &clap::Command,
+  args: Vec<OsString>,
+) -> anyhow::Result<Vec<OsString>> {
+  // Invalid command lines, `--help` and `--version` are reported by the actual parse.
+  // Required options may be set by the config file, so missing ones are no error yet.
+  let Ok(matches) = command
+    .clone()
+    .ignore_errors(true)
+    .try_get_matches_from(&args)
+  else {
+    return Ok(args);
+  };
+  let config_path = match matches.get_one::<PathBuf>("config") {
+    Some(config_path) => config_path.clone(),
+    None => match default_config_path() {
+      Some(config_path) if config_path.is_file() => config_path,
+      _ => return Ok(args),
+    },
+  };
+
+  let config = std::fs::read_to_string(&config_path).with_context(|| {
+    format!(
+      "\"{}\": Failed to read the config file",
+      config_path.display()
+    )
+  })?;
+  let config_args = config_args(command, &matches, &config).with_context(|| {
+    format!(
+      "\"{}\": Failed to parse the config file",
+      config_path.display()
+    )
+  })?;
+  Ok(config_args.insert_into(args, command, &matches))
+}
+
+/// The command line arguments of a config file.
+#[derive(Debug, Default)]
+struct ConfigArgs {
+  args: Vec<OsString>,
+  /// The options of the subcommand that is run.
+  subcommand_args: Vec<OsString>,
+}
+
+impl ConfigArgs {
+  /// Inserts the options before the ones of the command line, so clap reads them as given first.
+  /// The options of the subcommand go right after its name.
+  fn insert_into(
+    self,
+    mut args: Vec<OsString>,
+    command: &clap::Command,
+    matches: &clap::ArgMatches,
+  ) -> Vec<OsString> {
+    if let Some(subcommand) = matches
+      .subcommand_name()
+      .and_then(|subcommand_name| command.find_subcommand(subcommand_name))
+      && let Some(subcommand_index) = args.iter().skip(1).position(|arg| {
+        arg.to_str().is_some_and(|arg| {
+          arg == subcommand.get_name() || subcommand.get_all_aliases().any(|alias| alias == arg)
+        })
+      })
+    {
+      let insert_at = subcommand_index + 2;
+      args.splice(insert_at..insert_at, self.subcommand_args);
+    }
+    let mut args = args.into_iter();
+    args
+      .next()
+      .into_iter()
+      .chain(self.args)
+      .chain(args)
+      .collect()
+  }
+}
+
+/// Converts the options of a config file to command line arguments, skipping the ones given on the command line.
+fn config_args(
+  command: &clap::Command,
+  matches: &clap::ArgMatches,
+  config: &str,
+) -> anyhow::Result<ConfigArgs> {
+  let config = config.parse::<toml::Table>()?;
+  let mut config_args = ConfigArgs::default();
+  for (key, value) in config {
+    if let toml::Value::Table(subcommand_config) = &value
+      && let Some(subcommand) = command.find_subcommand(&key)
+    {
+      let subcommand_matches = matches.subcommand_matches(subcommand.get_name());
+      let mut subcommand_args = Vec::new();
+      for (key, value) in subcommand_config {
+        subcommand_args.extend(
+          option_args(subcommand, subcommand_matches, key, value)
+            .with_context(|| format!("In [{}]", subcommand.get_name()))?,
+        );
+      }
+      if subcommand_matches.is_some() {
+        config_args.subcommand_args = subcommand_args;
+      }
+      continue;
+    }
+    config_args
+      .args
+      .extend(option_args(command, Some(matches), &key, &value)?);
+  }
+  Ok(config_args)
+}
+
+/// Converts one option of a config file to command line arguments, none if it was given on the command line.
+fn option_args(
+  command: &clap::Command,
+  matches: Option<&clap::ArgMatches>,
+  key: &str,
+  value: &toml::Value,
+) -> anyhow::Result<Vec<OsString>> {
+  let Some(arg) = command
+    .get_arguments()
+    .find(|arg| arg.get_long() == Some(key) && key != "config")
+  else {
+    bail!("Unknown option: {key}");
+  };
+  if matches.is_some_and(|matches| {
+    matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine)
+  }) {
+    return Ok(Vec::new());
+  }
+
+  let flag = format!("--{key}");
+  let mut args = Vec::new();
+  match value {
+    toml::Value::Boolean(true) => args.push(OsString::from(flag)),
+    toml::Value::Boolean(false) => {},
+    toml::Value::Array(values) => {
+      let values = values
+        .iter()
+        .map(|value| config_value_to_string(key, value))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+      match arg.get_value_delimiter() {
+        Some(delimiter) => {
+          args.push(format!("{flag}={}", values.join(&delimiter.to_string())).into());
+        },
+        None if matches!(arg.get_action(), ArgAction::Append) => {
+          args.extend(values.iter().map(|value| format!("{flag}={value}").into()));
+        },
+        None => bail!("Option {key} takes a single value"),
+      }
+    },
+    value => {
+      if matches!(arg.get_action(), ArgAction::SetTrue) {
+        bail!("Option {key} must be true or false");
+      }
+      args.push(format!("{flag}={}", config_value_to_string(key, value)?).into());
+    },
+  }
+  Ok(args)
+}
+
+fn config_value_to_string(key: &str, value: &toml::Value) -> anyhow::Result<String> {
+  match value {
+    toml::Value::String(value) => Ok(value.clone()),
+    toml::Value::Integer(value) => Ok(value.to_string()),
+    toml::Value::Float(value) => Ok(value.to_string()),
+    toml::Value::Boolean(value) => Ok(value.to_string()),
+    toml::Value::Datetime(value) => Ok(value.to_string()),
+    toml::Value::Array(_) | toml::Value::Table(_) => {
+      bail!("Option {key} must be a string, number or boolean")
+    },
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::new_argparser;
+
+  fn matches_with_config(cli_args: &[&str], config: &str) -> clap::ArgMatches {
+    let command = new_argparser();
+    let cli_args = cli_args.iter().map(OsString::from).collect::<Vec<_>>();
+    let matches = command
+      .clone()
+      .ignore_errors(true)
+      .try_get_matches_from(&cli_args)
+      .unwrap();
+    let config_args = config_args(&command, &matches, config).unwrap();
+    let args = config_args.insert_into(cli_args, &command, &matches);
+    command.try_get_matches_from(args).unwrap()
+  }
+
+  #[test]
+  fn config_options() {
+    let matches = matches_with_config(
+      &["image-date-fixer", "/photos"],
+      r#"
+        exclude-files = ["/photos/a", "/photos/b"]
+        dry-run = true
+        skip-hidden-files = false
+        fix-future-modified-times = 2
+        only-extensions = ["jpg", "heic"]
+        mtime-as-fallback = "day"
+      "#,
+    );
+    assert_eq!(
+      matches
+        .get_many::<PathBuf>("exclude-files")
+        .unwrap()
+        .collect::<Vec<_>>(),
+      [Path::new("/photos/a"), Path::new("/photos/b")]
+    );
+    assert_eq!(
+      matches
+        .get_many::<PathBuf>("positional_files")
+        .unwrap()
+        .collect::<Vec<_>>(),
+      [Path::new("/photos")]
+    );
+    assert_eq!(matches.get_one::<bool>("dry-run"), Some(&true));
+    assert_eq!(matches.get_one::<bool>("skip-hidden-files"), Some(&false));
+    assert_eq!(
+      matches.get_one::<u64>("fix-future-modified-times"),
+      Some(&2)
+    );
+    assert_eq!(
+      matches
+        .get_many::<String>("only-extensions")
+        .unwrap()
+        .collect::<Vec<_>>(),
+      ["jpg", "heic"]
+    );
+    assert_eq!(
+      matches
+        .get_one::<String>("mtime-as-fallback")
+        .map(String::as_str),
+      Some("day")
+    );
+  }
+
+  #[test]
+  fn command_line_overrides_config() {
+    let matches = matches_with_config(
+      &[
+        "image-date-fixer",
+        "--fix-future-modified-times",
+        "5",
+        "parse",
+        "IMG_20200101.jpg",
+      ],
+      "fix-future-modified-times = 2\nmin-year = 1950\n",
+    );
+    assert_eq!(
+      matches.get_one::<u64>("fix-future-modified-times"),
+      Some(&5)
+    );
+    assert_eq!(matches.get_one::<i32>("min-year"), Some(&1950));
+    assert!(matches.subcommand_matches("parse").is_some());
+  }
+
+  #[test]
+  fn subcommand_config() {
+    let config = r#"
+      dry-run = true
+
+      [organize]
+      target = "/photos/sorted"
+      mode = "copy"
+
+      [shift]
+      shift = "+1h"
+    "#;
+    let matches = matches_with_config(
+      &[
+        "image-date-fixer",
+        "organize",
+        "/photos",
+        "--mode",
+        "hardlink",
+      ],
+      config,
+    );
+    assert_eq!(matches.get_one::<bool>("dry-run"), Some(&true));
+    let organize_matches = matches.subcommand_matches("organize").unwrap();
+    assert_eq!(
+      organize_matches.get_one::<PathBuf>("target"),
+      Some(&PathBuf::from("/photos/sorted"))
+    );
+    assert_eq!(
+      organize_matches
+        .get_one::<String>("mode")
+        .map(String::as_str),
+      Some("hardlink")
+    );
+    assert_eq!(
+      organize_matches
+        .get_many::<PathBuf>("files")
+        .unwrap()
+        .collect::<Vec<_>>(),
+      [Path::new("/photos")]
+    );
+
+    // The options of other subcommands are not used.
+    let matches = matches_with_config(&["image-date-fixer", "/photos"], config);
+    assert!(matches.subcommand_name().is_none());
+  }
+
+  #[test]
+  fn invalid_config() {
+    let command = new_argparser();
+    let matches = command
+      .clone()
+      .try_get_matches_from(["image-date-fixer"])
+      .unwrap();
+    assert!(config_args(&command, &matches, "no-such-option = true").is_err());
+    assert!(config_args(&command, &matches, "dry-run = \"yes\"").is_err());
+    assert!(config_args(&command, &matches, "log-level = [\"INFO\"]").is_err());
+    assert!(config_args(&command, &matches, "[organize]\nno-such-option = true").is_err());
+    assert!(config_args(&command, &matches, "[no-such-command]\ndry-run = true").is_err());
+  }
+}
@@ -0,0 +1,175 @@
+use core::fmt::Write as _;
+use std::{
+  path::{Path, PathBuf},
+  sync::Mutex,
+};
+
+use anyhow::Context as _;
+use chrono::{NaiveDateTime, TimeDelta};
+
+use crate::date_extractors::{ConfidentNaiveDateTime, DateConfidence};
+
+/// The time span a date with the given confidence may lie in, starting at the date itself.
+/// A guess of confidence `Year` is January 1st, but the file could have been created at any point of that year.
+#[must_use]
+const fn confidence_span(confidence: DateConfidence) -> TimeDelta {
+  match confidence {
+    DateConfidence::None => TimeDelta::zero(),
+    DateConfidence::Decade => TimeDelta::days(3653),
+    DateConfidence::Year => TimeDelta::days(366),
+    DateConfidence::Month => TimeDelta::days(31),
+    DateConfidence::Day => TimeDelta::days(1),
+    DateConfidence::Hour => TimeDelta::hours(1),
+    DateConfidence::Minute => TimeDelta::minutes(1),
+    DateConfidence::Second | DateConfidence::Millisecond => TimeDelta::seconds(1),
+  }
+}
+
+/// Whether `modified_time` lies within `window` of the time span covered by `guess`.
+#[must_use]
+pub fn is_corroborated(
+  guess: &ConfidentNaiveDateTime,
+  modified_time: &NaiveDateTime,
+  window: TimeDelta,
+) -> bool {
+  let start = guess.date - window;
+  let end = guess.date + confidence_span(guess.confidence) + window;
+  (start..=end).contains(modified_time)
+}
+
+struct ReviewEntry {
+  file_path: PathBuf,
+  guessed_date: ConfidentNaiveDateTime,
+  modified_time: Option<NaiveDateTime>,
+}
+
+/// Collects guessed dates that were not written because the modified time of the file does not corroborate them.
+pub struct ReviewList {
+  entries: Mutex<Vec<ReviewEntry>>,
+}
+
+impl ReviewList {
+  #[must_use]
+  pub const fn new() -> Self {
+    Self {
+      entries: Mutex::new(Vec::new()),
+    }
+  }
+
+  pub fn record(
+    &self,
+    file_path: &Path,
+    guessed_date: ConfidentNaiveDateTime,
+    modified_time: Option<NaiveDateTime>,
+  ) {
+    self
+      .entries
+      .lock()
+      .expect("Review list lock should not be poisoned")
+      .push(ReviewEntry {
+        file_path: file_path.to_path_buf(),
+        guessed_date,
+        modified_time,
+      });
+  }
+
+  #[must_use]
+  pub fn len(&self) -> usize {
+    self
+      .entries
+      .lock()
+      .expect("Review list lock should not be poisoned")
+      .len()
+  }
+
+  /// Writes the review list as tab separated values sorted by file path.
+  pub fn write(&self, review_list_path: &Path) -> anyhow::Result<()> {
+    let mut entries = self
+      .entries
+      .lock()
+      .expect("Review list lock should not be poisoned");
+    entries.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+
+    let mut contents = String::from("file\tguessed date\tconfidence\tmodified time\n");
+    for entry in entries.iter() {
+      let modified_time = entry
+        .modified_time
+        .map(|modified_time| modified_time.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default();
+      writeln!(
+        contents,
+        "{}\t{}\t{:?}\t{modified_time}",
+        entry.file_path.display(),
+        entry.guessed_date,
+        entry.guessed_date.confidence,
+      )?;
+    }
+    drop(entries);
+
+    std::fs::write(review_list_path, contents).with_context(|| {
+      format!(
+        "\"{}\": Failed to write the review list",
+        review_list_path.display()
+      )
+    })
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn date(date: &str) -> NaiveDateTime {
+    NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S").unwrap()
+  }
+
+  #[test]
+  fn corroboration() {
+    let corroborated = |guess: &str, confidence, modified_time: &str| {
+      is_corroborated(
+        &ConfidentNaiveDateTime::new(date(guess), confidence),
+        &date(modified_time),
+        TimeDelta::days(30),
+      )
+    };
+    let guess = "2019-08-18 00:00:00";
+    assert!(corroborated(
+      guess,
+      DateConfidence::Day,
+      "2019-08-18 14:00:00"
+    ));
+    assert!(corroborated(
+      guess,
+      DateConfidence::Day,
+      "2019-07-20 00:00:00"
+    ));
+    assert!(corroborated(
+      guess,
+      DateConfidence::Day,
+      "2019-09-17 00:00:00"
+    ));
+    assert!(!corroborated(
+      guess,
+      DateConfidence::Day,
+      "2019-09-20 00:00:00"
+    ));
+    assert!(!corroborated(
+      guess,
+      DateConfidence::Day,
+      "2023-01-01 00:00:00"
+    ));
+
+    // The whole year is covered by a guess of confidence `Year`.
+    let guess = "2019-01-01 00:00:00";
+    assert!(corroborated(
+      guess,
+      DateConfidence::Year,
+      "2019-11-30 00:00:00"
+    ));
+    assert!(!corroborated(
+      guess,
+      DateConfidence::Year,
+      "2020-03-01 00:00:00"
+    ));
+  }
+}
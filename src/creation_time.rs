@@ -0,0 +1,125 @@
+use std::path::Path;
+#[cfg(windows)]
+use std::{
+  fs::{File, FileTimes},
+  os::windows::fs::FileTimesExt as _,
+};
+
+use anyhow::Context as _;
+use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
+use tracing::info;
+
+/// Whether [`sync_creation_time`] is supported.
+/// Linux records the creation (birth) time, but offers no way to set it.
+pub const CAN_SET_CREATION_TIME: bool = cfg!(any(windows, target_os = "macos"));
+
+/// The creation (birth) time of `file_path` in UTC, if the platform and file system record it.
+#[must_use]
+pub fn get_creation_time(file_path: &Path) -> Option<NaiveDateTime> {
+  let creation_time = std::fs::metadata(file_path).ok()?.created().ok()?;
+  Some(DateTime::<Utc>::from(creation_time).naive_utc())
+}
+
+/// Sets the creation time of `file_path`, which Windows and macOS apps sort by, to `creation_time` (in UTC).
+/// Creation times that already match within `granularity` are left alone.
+pub fn sync_creation_time(
+  file_path: &Path,
+  creation_time: &NaiveDateTime,
+  granularity: TimeDelta,
+  dry_run: bool,
+) -> anyhow::Result<()> {
+  let current_creation_time =
+    get_creation_time(file_path).context("Failed to get creation time")?;
+  if (current_creation_time - *creation_time).abs() < granularity {
+    return Ok(());
+  }
+
+  if dry_run {
+    info!(
+      file_path = %file_path.display(),
+      "Would set creation time to {}",
+      creation_time.format("%Y-%m-%d %H:%M:%S"),
+    );
+    return Ok(());
+  }
+
+  set_creation_time(file_path, creation_time).context("Failed to set creation time")
+}
+
+#[cfg(windows)]
+fn set_creation_time(file_path: &Path, creation_time: &NaiveDateTime) -> anyhow::Result<()> {
+  // `set_created` uses `SetFileTime`, which needs write access.
+  let file = File::options()
+    .write(true)
+    .open(file_path)
+    .context("Failed to open file")?;
+  let creation_time = DateTime::<Utc>::from_naive_utc_and_offset(*creation_time, Utc);
+  file.set_times(FileTimes::new().set_created(creation_time.into()))?;
+  Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn set_creation_time(file_path: &Path, creation_time: &NaiveDateTime) -> anyhow::Result<()> {
+  use std::{ffi::CString, os::unix::ffi::OsStrExt as _};
+
+  let path = CString::new(file_path.as_os_str().as_bytes())?;
+  let mut attributes = libc::attrlist {
+    bitmapcount: libc::ATTR_BIT_MAP_COUNT,
+    reserved: 0,
+    commonattr: libc::ATTR_CMN_CRTIME,
+    volattr: 0,
+    dirattr: 0,
+    fileattr: 0,
+    forkattr: 0,
+  };
+  let creation_time = creation_time.and_utc();
+  let mut timespec = libc::timespec {
+    tv_sec: creation_time.timestamp(),
+    tv_nsec: creation_time.timestamp_subsec_nanos().into(),
+  };
+  #[expect(
+    unsafe_code,
+    reason = "setattrlist is the only way to set the creation time on macOS. The pointers are valid for the duration of the call and the buffer holds exactly the requested attribute."
+  )]
+  let result = unsafe {
+    libc::setattrlist(
+      path.as_ptr(),
+      (&raw mut attributes).cast(),
+      (&raw mut timespec).cast(),
+      size_of::<libc::timespec>(),
+      0,
+    )
+  };
+  if result != 0 {
+    return Err(std::io::Error::last_os_error().into());
+  }
+  Ok(())
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn set_creation_time(_file_path: &Path, _creation_time: &NaiveDateTime) -> anyhow::Result<()> {
+  anyhow::bail!("Setting the creation time is not supported on this platform")
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn creation_times() {
+    let file_path = std::env::temp_dir().join("image-date-fixer-creation-time.jpg");
+    std::fs::write(&file_path, b"").unwrap();
+    // Not every file system records the creation time.
+    if let Some(creation_time) = get_creation_time(&file_path) {
+      let granularity = TimeDelta::seconds(1);
+      assert!(sync_creation_time(&file_path, &creation_time, granularity, false).is_ok());
+      let earlier_time = creation_time - TimeDelta::days(1);
+      assert!(sync_creation_time(&file_path, &earlier_time, granularity, true).is_ok());
+      assert_eq!(
+        sync_creation_time(&file_path, &earlier_time, granularity, false).is_ok(),
+        CAN_SET_CREATION_TIME
+      );
+    }
+    std::fs::remove_file(&file_path).unwrap();
+  }
+}
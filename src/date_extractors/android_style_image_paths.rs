@@ -1,7 +1,7 @@
 use core::str::FromStr as _;
 use std::{path::Path, sync::LazyLock};
 
-use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Timelike as _};
 use chumsky::{
   extra::ParserExtra,
   input::{SliceInput, StrInput},
@@ -12,21 +12,132 @@ use chumsky::{
 use nom::IResult;
 use regex::Regex;
 
-use super::{ChumError, ConfidentNaiveDateTime, DateConfidence};
+use super::{ChumError, ConfidentNaiveDateTime, DateConfidence, Explanation};
+
+/// Suffix appended by some vendors (e.g. Huawei/Honor) after the Android-style timestamp.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AndroidSuffix {
+  /// `_BURSTnnn`: one frame of a burst, carrying its index within the burst.
+  Burst(u32),
+  /// `_HDR`
+  Hdr,
+  /// `_PANO`
+  Pano,
+}
+
+/// The parsed components of an Android-style file name.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct AndroidFileName {
+  pub date_time: NaiveDateTime,
+  /// Whether the file name carried a millisecond field after the seconds.
+  pub has_milliseconds: bool,
+  pub suffix: Option<AndroidSuffix>,
+}
+
+impl AndroidFileName {
+  /// The index of the frame within a burst, if the file is part of one.
+  #[allow(dead_code)]
+  #[must_use]
+  pub const fn burst_index(&self) -> Option<u32> {
+    match self.suffix {
+      Some(AndroidSuffix::Burst(index)) => Some(index),
+      _ => None,
+    }
+  }
+}
 
 /// Extracts the date from Android-style image file paths.
 /// Example file paths:
 ///   * /storage/emulated/0/DCIM/Camera/IMG_20190818_130841<POSTFIX>.jpg
+///   * /storage/emulated/0/DCIM/Camera/IMG_20190818_130841_BURST001.jpg
+///   * /storage/emulated/0/DCIM/Camera/IMG_20190818_130841_HDR.jpg
+///   * /storage/emulated/0/DCIM/Camera/IMG_20190818_130841123.jpg
 pub fn get_date_from_android_filepath_nom(
   _file_path: &Path,
   file_name: &str,
 ) -> Option<ConfidentNaiveDateTime> {
+  parse_android_file_name(file_name).map(|android_file_name| {
+    let confidence = if android_file_name.has_milliseconds {
+      DateConfidence::Millisecond
+    } else {
+      DateConfidence::Second
+    };
+    ConfidentNaiveDateTime::new(android_file_name.date_time, confidence)
+  })
+}
+
+/// Explains where [`get_date_from_android_filepath_nom`] stopped parsing.
+#[must_use]
+pub fn explain_date_from_android_filepath_nom(file_path: &Path, file_name: &str) -> Explanation {
+  let error = match parse_android_nom(file_name) {
+    Ok((rest, _)) => {
+      let result = get_date_from_android_filepath_nom(file_path, file_name);
+      return Explanation {
+        span: Some(0..file_name.len() - rest.len()),
+        message: format!("Matched {}", result.expect("The file name was parsed")),
+        result,
+      };
+    },
+    Err(nom::Err::Error(error) | nom::Err::Failure(error)) => error,
+    Err(nom::Err::Incomplete(_)) => return Explanation::from_result(file_name, None),
+  };
+
+  let offset = file_name.len() - error.input.len();
+  let message = match &error.code {
+    nom::error::ErrorKind::Tag => "Expected the \"IMG_\" prefix",
+    nom::error::ErrorKind::Eof => "The timestamp is cut short",
+    nom::error::ErrorKind::MapRes => "Expected digits",
+    nom::error::ErrorKind::Char => "Expected \"_\" between the date and the time",
+    nom::error::ErrorKind::MapOpt | nom::error::ErrorKind::Verify => {
+      "This is not a valid date and time"
+    },
+    code => code.description(),
+  };
+  Explanation {
+    span: Some(offset..file_name.len().min(offset + 1)),
+    message: format!("Parsing failed here: {message}"),
+    result: None,
+  }
+}
+
+/// Parses an Android-style file name including the optional vendor suffix.
+#[must_use]
+pub fn parse_android_file_name(file_name: &str) -> Option<AndroidFileName> {
   parse_android_nom(file_name)
     .ok()
-    .map(|(_, datetime)| ConfidentNaiveDateTime::new(datetime, DateConfidence::Second))
+    .map(|(_, android_file_name)| android_file_name)
 }
 
-fn parse_android_nom(filename: &str) -> IResult<&str, NaiveDateTime> {
+fn parse_android_nom(filename: &str) -> IResult<&str, AndroidFileName> {
+  use nom::{Parser, combinator::opt};
+
+  let (input, date_time) = parse_android_date_time(filename)?;
+  let (input, millisecond) = opt(parse_millisecond).parse(input)?;
+  let (input, suffix) = opt(parse_android_suffix).parse(input)?;
+
+  let date_time = match millisecond {
+    Some(millisecond) => date_time
+      .with_nanosecond(millisecond * 1_000_000)
+      .ok_or_else(|| {
+        nom::Err::Error(nom::error::Error::new(
+          filename,
+          nom::error::ErrorKind::Verify,
+        ))
+      })?,
+    None => date_time,
+  };
+
+  Ok((
+    input,
+    AndroidFileName {
+      date_time,
+      has_milliseconds: millisecond.is_some(),
+      suffix,
+    },
+  ))
+}
+
+fn parse_android_date_time(filename: &str) -> IResult<&str, NaiveDateTime> {
   use nom::{Parser, bytes::complete::tag, character::complete::char, combinator::map_opt};
 
   let (input, _) = tag("IMG_")(filename)?;
@@ -50,6 +161,40 @@ fn parse_android_nom(filename: &str) -> IResult<&str, NaiveDateTime> {
   .parse(input)
 }
 
+fn parse_android_suffix(input: &str) -> IResult<&str, AndroidSuffix> {
+  use nom::{
+    Parser,
+    branch::alt,
+    bytes::complete::tag_no_case,
+    character::complete::{char, digit1},
+    combinator::{map_res, value},
+    sequence::preceded,
+  };
+
+  preceded(
+    char('_'),
+    alt((
+      map_res(preceded(tag_no_case("BURST"), digit1), |index: &str| {
+        index.parse::<u32>().map(AndroidSuffix::Burst)
+      }),
+      value(AndroidSuffix::Hdr, tag_no_case("HDR")),
+      value(AndroidSuffix::Pano, tag_no_case("PANO")),
+    )),
+  )
+  .parse(input)
+}
+
+/// Unlike `parse_num`, only accepts digits, as `u32::from_str` also accepts e.g. `+12`.
+fn parse_millisecond(input: &str) -> IResult<&str, u32> {
+  use nom::{Parser, bytes::complete::take_while_m_n, combinator::map_res};
+
+  map_res(
+    take_while_m_n(3, 3, |c: char| c.is_ascii_digit()),
+    u32::from_str,
+  )
+  .parse(input)
+}
+
 fn parse_num<const N: usize>(num: &str) -> IResult<&str, u32> {
   use nom::{Parser, bytes::complete::take, combinator::map_res};
 
@@ -182,9 +327,105 @@ pub mod test {
         file_path: "/home/user/Pictures/IMG_20191318_130841POSTFIX.jpg",
         expected_result: None,
       },
+      TestCase {
+        file_path: "/home/user/Pictures/IMG_20190818_130841_BURST001.jpg",
+        expected_result: Some(ConfidentNaiveDateTime::new(
+          NaiveDateTime::parse_from_str("20190818130841", "%Y%m%d%H%M%S").unwrap(),
+          DateConfidence::Second,
+        )),
+      },
+      TestCase {
+        file_path: "/home/user/Pictures/IMG_20190818_130841_HDR.jpg",
+        expected_result: Some(ConfidentNaiveDateTime::new(
+          NaiveDateTime::parse_from_str("20190818130841", "%Y%m%d%H%M%S").unwrap(),
+          DateConfidence::Second,
+        )),
+      },
+      TestCase {
+        file_path: "/home/user/Pictures/IMG_20190818_130841_1.jpg",
+        expected_result: Some(ConfidentNaiveDateTime::new(
+          NaiveDateTime::parse_from_str("20190818130841", "%Y%m%d%H%M%S").unwrap(),
+          DateConfidence::Second,
+        )),
+      },
     ]
   });
 
+  #[test]
+  fn android_filepath_nom_milliseconds() {
+    assert_eq!(
+      get_date_from_android_filepath_nom(
+        Path::new("/home/user/Pictures/IMG_20190818_130841123.jpg"),
+        "IMG_20190818_130841123.jpg",
+      ),
+      Some(ConfidentNaiveDateTime::new(
+        NaiveDateTime::parse_from_str("20190818130841.123", "%Y%m%d%H%M%S%.3f").unwrap(),
+        DateConfidence::Millisecond,
+      ))
+    );
+    assert_eq!(
+      parse_android_file_name("IMG_20190818_130841123_BURST002.jpg"),
+      Some(AndroidFileName {
+        date_time: NaiveDateTime::parse_from_str("20190818130841.123", "%Y%m%d%H%M%S%.3f").unwrap(),
+        has_milliseconds: true,
+        suffix: Some(AndroidSuffix::Burst(2)),
+      })
+    );
+    // Only three digits are a millisecond value, so these keep second precision.
+    for file_name in ["IMG_20190818_130841+12.jpg", "IMG_20190818_130841 12.jpg"] {
+      assert_eq!(
+        get_date_from_android_filepath_nom(Path::new(file_name), file_name),
+        Some(ConfidentNaiveDateTime::new(
+          NaiveDateTime::parse_from_str("20190818130841", "%Y%m%d%H%M%S").unwrap(),
+          DateConfidence::Second,
+        ))
+      );
+    }
+  }
+
+  #[test]
+  fn android_filepath_nom_explanations() {
+    let explain = |file_name: &str| {
+      let explanation = explain_date_from_android_filepath_nom(Path::new(file_name), file_name);
+      (explanation.span, explanation.result.is_some())
+    };
+    assert_eq!(explain("IMG_20190818_130841_HDR.jpg"), (Some(0..23), true));
+    assert_eq!(explain("VID_20190818_130841.jpg"), (Some(0..1), false));
+    assert_eq!(explain("IMG_20190818-130841.jpg"), (Some(12..13), false));
+    assert_eq!(explain("IMG_2019x818_130841.jpg"), (Some(8..9), false));
+    assert_eq!(explain("IMG_20191318_130841.jpg"), (Some(4..5), false));
+  }
+
+  #[test]
+  fn android_file_name_suffixes() {
+    let parse_suffix =
+      |file_name: &str| parse_android_file_name(file_name).and_then(|parsed| parsed.suffix);
+
+    assert_eq!(
+      parse_suffix("IMG_20190818_130841_BURST001.jpg"),
+      Some(AndroidSuffix::Burst(1))
+    );
+    assert_eq!(
+      parse_suffix("IMG_20190818_130841_BURST012_COVER.jpg"),
+      Some(AndroidSuffix::Burst(12))
+    );
+    assert_eq!(
+      parse_suffix("IMG_20190818_130841_HDR.jpg"),
+      Some(AndroidSuffix::Hdr)
+    );
+    assert_eq!(
+      parse_suffix("IMG_20190818_130841_PANO.jpg"),
+      Some(AndroidSuffix::Pano)
+    );
+    assert_eq!(parse_suffix("IMG_20190818_130841_1.jpg"), None);
+    assert_eq!(parse_suffix("IMG_20190818_130841.jpg"), None);
+    assert_eq!(
+      parse_android_file_name("IMG_20190818_130841_BURST003.jpg")
+        .and_then(|parsed| parsed.burst_index()),
+      Some(3)
+    );
+  }
+
   #[test]
   fn android_filepath_nom() {
     test_test_cases(
@@ -1,6 +1,6 @@
 use std::{path::Path, sync::LazyLock};
 
-use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use chrono::{FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
 use regex::Regex;
 
 use super::{ConfidentNaiveDateTime, DateConfidence};
@@ -95,6 +95,12 @@ fn parse_month_from_str(month_str: &str) -> Option<u32> {
   None
 }
 
+pub static CUSTOM_DATE_PREFIX_RE: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(
+    r"^(?P<year>\d{4})?(?P<w1>[-_\s])?(?P<month>(?:\d{2}|[A-Za-z]{3,9}))?(?P<w2>[-_\s])?(?P<day>\d{2})?(?P<w3>[-_\sT])?(?P<hour>\d{2})?(?P<w4>[-_\s:.])?(?P<minute>\d{2})?(?P<w5>[-_\s:.])?(?P<second>\d{2})?(?P<offset>Z|[+-]\d{2}:\d{2})?"
+  ).unwrap()
+});
+
 /// Extracts the date and optional time from filenames prefixed with a date. The date can be in
 /// numeric format (YYYY, YYYYMM, YYYYMMDD, etc.) or the month may be a string (e.g., "2020-Mar-10").
 /// The regex is built to allow alphabetic months (case insensitive, and the first 3 letters are enough).
@@ -103,6 +109,7 @@ fn parse_month_from_str(month_str: &str) -> Option<u32> {
 ///   * /.../2024-03-23_21.45.17_mull.jpg
 ///   * /.../2020-Mar-10 21:10:56.png
 ///   * /.../2020-oct-10.png
+///   * /.../2024-03-23T21:45:17+01:00.jpg (ISO 8601, the UTC offset is kept)
 ///
 /// Unsupported:
 ///   * /.../2563.jpg
@@ -113,14 +120,8 @@ pub fn get_date_from_custom_date_prefixed_filepath_regex(
   file_path: &Path,
   _file_name: &str,
 ) -> Option<ConfidentNaiveDateTime> {
-  static RE: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(
-      r"^(?P<year>\d{4})?(?P<w1>[-_\s])?(?P<month>(?:\d{2}|[A-Za-z]{3,9}))?(?P<w2>[-_\s])?(?P<day>\d{2})?(?P<w3>[-_\s])?(?P<hour>\d{2})?(?P<w4>[-_\s:.])?(?P<minute>\d{2})?(?P<w5>[-_\s:.])?(?P<second>\d{2})?"
-    ).unwrap()
-  });
-
   let file_name_no_ext = file_path.file_stem()?.to_str()?;
-  let captures = RE.captures(file_name_no_ext)?;
+  let captures = CUSTOM_DATE_PREFIX_RE.captures(file_name_no_ext)?;
 
   // Parse year (required).
   let year_str = captures.name("year")?.as_str();
@@ -197,13 +198,25 @@ pub fn get_date_from_custom_date_prefixed_filepath_regex(
   }
 
   // Build the NaiveDateTime using the parsed values.
-  Some(ConfidentNaiveDateTime::new(
+  let date = ConfidentNaiveDateTime::new(
     NaiveDateTime::new(
       NaiveDate::from_ymd_opt(year, month, day)?,
       NaiveTime::from_hms_opt(hour, minute, second)?,
     ),
     confidence,
-  ))
+  );
+
+  // An ISO 8601 UTC offset is only accepted after a full time.
+  let offset = maybe_second
+    .and_then(|_| captures.name("offset"))
+    .and_then(|offset_match| match offset_match.as_str() {
+      "Z" => FixedOffset::east_opt(0),
+      offset => offset.parse::<FixedOffset>().ok(),
+    });
+  Some(match offset {
+    Some(offset) => date.with_offset(offset),
+    None => date,
+  })
 }
 
 #[cfg(test)]
@@ -221,6 +234,26 @@ pub mod test {
           DateConfidence::Second,
         )),
       },
+      TestCase {
+        file_path: "/home/user/Pictures/2024-03-23T21:45:17+01:00.jpg",
+        expected_result: Some(
+          ConfidentNaiveDateTime::new(
+            NaiveDateTime::parse_from_str("2024-03-23 21:45:17", "%Y-%m-%d %H:%M:%S").unwrap(),
+            DateConfidence::Second,
+          )
+          .with_offset(FixedOffset::east_opt(3600).unwrap()),
+        ),
+      },
+      TestCase {
+        file_path: "/home/user/Pictures/20240323T214517Z.jpg",
+        expected_result: Some(
+          ConfidentNaiveDateTime::new(
+            NaiveDateTime::parse_from_str("2024-03-23 21:45:17", "%Y-%m-%d %H:%M:%S").unwrap(),
+            DateConfidence::Second,
+          )
+          .with_offset(FixedOffset::east_opt(0).unwrap()),
+        ),
+      },
       // Numeric-only examples
       TestCase {
         file_path: "/storage/emulated/0/DCIM/Camera/2563.jpg",
@@ -0,0 +1,171 @@
+use core::str::FromStr;
+use std::{path::Path, sync::LazyLock};
+
+use chrono::{Days, NaiveDate, NaiveDateTime, NaiveTime};
+use regex::Regex;
+
+use super::{ConfidentNaiveDateTime, DateConfidence};
+
+/// Which date of a date range is used for the contained files.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum DateRangePolicy {
+  #[default]
+  Start,
+  Midpoint,
+  End,
+}
+
+impl FromStr for DateRangePolicy {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "start" => Ok(Self::Start),
+      "midpoint" => Ok(Self::Midpoint),
+      "end" => Ok(Self::End),
+      _ => Err(anyhow::anyhow!("Unknown date range policy: {s}")),
+    }
+  }
+}
+
+pub static DATE_RANGE_FOLDER_RE: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(
+    r"^(?P<year>\d{4})-(?P<month>\d{2})-(?P<day>\d{2})(?:\s+-\s+|\s*–\s*|\s+(?:bis|to)\s+)(?:(?P<end_year>\d{4})-)?(?:(?P<end_month>\d{2})-)?(?P<end_day>\d{2})(?:$|[^\d\-:.]|[\-:.](?:$|\D))",
+  )
+  .unwrap()
+});
+
+/// Extracts the date from folder names describing a date range.
+/// The end date may omit the year and month if they are the same as for the start date.
+/// The dates are separated by ` - `, `–`, `bis` or `to`, so date times like `2019-07-01_13-45-00` are not taken for a range.
+/// Example folder paths:
+///   * /Photos/2019-07-01 - 2019-07-14 Kroatien
+///   * /Photos/2019-07-01 - 07-14 Kroatien
+///   * /Photos/2019-07-01 bis 14 Kroatien
+///
+/// Unsupported:
+///   * /Photos/2019-07-01_14 Kroatien
+///   * /Photos/2019-07-14 - 2019-07-01 Kroatien
+pub fn get_date_from_date_range_folder_regex(
+  _folder_path: &Path,
+  folder_name: &str,
+  policy: DateRangePolicy,
+) -> Option<ConfidentNaiveDateTime> {
+  let captures = DATE_RANGE_FOLDER_RE.captures(folder_name)?;
+
+  let parse = |name: &str| -> Option<u32> { captures.name(name)?.as_str().parse().ok() };
+
+  let year = parse("year")?;
+  let month = parse("month")?;
+  let start = NaiveDate::from_ymd_opt(year.try_into().ok()?, month, parse("day")?)?;
+  let end = NaiveDate::from_ymd_opt(
+    parse("end_year").unwrap_or(year).try_into().ok()?,
+    parse("end_month").unwrap_or(month),
+    parse("end_day")?,
+  )?;
+  if end < start {
+    return None;
+  }
+
+  let date = match policy {
+    DateRangePolicy::Start => start,
+    DateRangePolicy::Midpoint => {
+      let half_length = u64::try_from((end - start).num_days() / 2).ok()?;
+      start.checked_add_days(Days::new(half_length))?
+    },
+    DateRangePolicy::End => end,
+  };
+
+  Some(ConfidentNaiveDateTime::new(
+    NaiveDateTime::new(date, NaiveTime::from_hms_opt(0, 0, 0)?),
+    DateConfidence::Day,
+  ))
+}
+
+#[cfg(test)]
+pub mod test {
+  use super::*;
+  use crate::date_extractors::test::{TestCase, test_test_cases};
+  use std::sync::LazyLock;
+
+  fn day(date: &str) -> ConfidentNaiveDateTime {
+    ConfidentNaiveDateTime::new(
+      NaiveDate::parse_from_str(date, "%Y%m%d").unwrap().into(),
+      DateConfidence::Day,
+    )
+  }
+
+  pub static TESTS_DATE_RANGE_FOLDER: LazyLock<Vec<TestCase>> = LazyLock::new(|| {
+    vec![
+      TestCase {
+        file_path: "/Photos/2019-07-01 - 2019-07-14 Kroatien",
+        expected_result: Some(day("20190701")),
+      },
+      TestCase {
+        file_path: "/Photos/2019-07-01 - 07-14 Kroatien",
+        expected_result: Some(day("20190701")),
+      },
+      TestCase {
+        file_path: "/Photos/2019-07-01 bis 14 Kroatien",
+        expected_result: Some(day("20190701")),
+      },
+      TestCase {
+        file_path: "/Photos/2019-07-01–2019-07-14",
+        expected_result: Some(day("20190701")),
+      },
+      TestCase {
+        file_path: "/Photos/2019-07-01_14 Kroatien",
+        expected_result: None,
+      },
+      // Date times are not ranges.
+      TestCase {
+        file_path: "/Photos/2019-07-01_13-45-00",
+        expected_result: None,
+      },
+      TestCase {
+        file_path: "/Photos/2019-07-01-13-45-00",
+        expected_result: None,
+      },
+      TestCase {
+        file_path: "/Photos/2019-07-01 13-45-00 Kroatien",
+        expected_result: None,
+      },
+      TestCase {
+        file_path: "/Photos/2019-07-01 - 12-30-00 Kroatien",
+        expected_result: None,
+      },
+      TestCase {
+        file_path: "/Photos/2019-07-14 - 2019-07-01 Kroatien",
+        expected_result: None,
+      },
+      TestCase {
+        file_path: "/Photos/2019-07-01 Kroatien",
+        expected_result: None,
+      },
+    ]
+  });
+
+  #[test]
+  fn date_range_folder_regex() {
+    test_test_cases(
+      TESTS_DATE_RANGE_FOLDER.iter(),
+      |folder_path, folder_name| {
+        get_date_from_date_range_folder_regex(folder_path, folder_name, DateRangePolicy::Start)
+      },
+    );
+  }
+
+  #[test]
+  fn date_range_folder_policies() {
+    let parse = |policy| {
+      get_date_from_date_range_folder_regex(
+        Path::new("/Photos/2019-07-01 - 2019-07-14 Kroatien"),
+        "2019-07-01 - 2019-07-14 Kroatien",
+        policy,
+      )
+    };
+    assert_eq!(parse(DateRangePolicy::Start), Some(day("20190701")));
+    assert_eq!(parse(DateRangePolicy::Midpoint), Some(day("20190707")));
+    assert_eq!(parse(DateRangePolicy::End), Some(day("20190714")));
+  }
+}
@@ -0,0 +1,80 @@
+use core::ops::Range;
+
+use regex::Regex;
+
+use super::ConfidentNaiveDateTime;
+
+/// Describes why an extractor did or did not find a date in a file name.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Explanation {
+  /// The byte range of the file name the message refers to.
+  pub span: Option<Range<usize>>,
+  pub message: String,
+  pub result: Option<ConfidentNaiveDateTime>,
+}
+
+impl Explanation {
+  /// Explains a result without knowing which part of the file name was used.
+  #[must_use]
+  pub fn from_result(file_name: &str, result: Option<ConfidentNaiveDateTime>) -> Self {
+    match result {
+      Some(date) => Self {
+        span: Some(0..file_name.len()),
+        message: format!("Matched {date}"),
+        result,
+      },
+      None => Self {
+        span: None,
+        message: "Did not match".to_string(),
+        result,
+      },
+    }
+  }
+
+  /// Explains the result of an extractor that is based on `regex`.
+  #[must_use]
+  pub fn from_regex(
+    regex: &Regex,
+    file_name: &str,
+    result: Option<ConfidentNaiveDateTime>,
+  ) -> Self {
+    let Some(found) = regex.find(file_name) else {
+      return Self {
+        span: None,
+        message: format!("The pattern `{}` did not match", regex.as_str()),
+        result,
+      };
+    };
+    let message = match result {
+      Some(date) => format!("Matched {date}"),
+      None => "The pattern matched, but this is not a valid date".to_string(),
+    };
+    Self {
+      span: Some(found.range()),
+      message,
+      result,
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use chrono::NaiveDateTime;
+
+  use super::*;
+  use crate::date_extractors::DateConfidence;
+
+  #[test]
+  fn regex_explanations() {
+    let regex = Regex::new(r"IMG-(\d{8})").unwrap();
+    let date = ConfidentNaiveDateTime::new(NaiveDateTime::MIN, DateConfidence::Day);
+    assert_eq!(
+      Explanation::from_regex(&regex, "a_IMG-20190818.jpg", Some(date)).span,
+      Some(2..14)
+    );
+    let explanation = Explanation::from_regex(&regex, "a_IMG-20191318.jpg", None);
+    assert_eq!(explanation.span, Some(2..14));
+    assert!(explanation.message.contains("not a valid date"));
+    assert_eq!(Explanation::from_regex(&regex, "a.jpg", None).span, None);
+  }
+}
@@ -0,0 +1,125 @@
+use std::{path::Path, sync::LazyLock};
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use regex::Regex;
+
+use super::{ConfidentNaiveDateTime, DateConfidence};
+
+/// Extracts the date from year/month(/day) folder hierarchies by combining the ancestor directory names.
+/// The month and day folders may carry a description after a separator (e.g. `07 Juli`).
+/// Example file paths:
+///   * /Photos/2019/07/DSC_0001.jpg
+///   * /Photos/2019/07 Juli/15/DSC_0001.jpg
+///
+/// Unsupported:
+///   * /Photos/2019/DSC_0001.jpg
+pub fn get_date_from_folder_hierarchy_regex(
+  file_path: &Path,
+  _file_name: &str,
+) -> Option<ConfidentNaiveDateTime> {
+  static YEAR_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(\d{4})$").unwrap());
+  static MONTH_OR_DAY_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\d{2})(?:$|[-_\s])").unwrap());
+
+  let folder_names = file_path
+    .parent()?
+    .components()
+    .map(|component| component.as_os_str().to_str())
+    .collect::<Vec<_>>();
+
+  // Search from the deepest folder upwards so the most specific hierarchy wins.
+  for (year_index, folder_name) in folder_names.iter().enumerate().rev() {
+    let Some(year_captures) = folder_name.and_then(|name| YEAR_RE.captures(name)) else {
+      continue;
+    };
+    let Some(year) = year_captures
+      .get(1)
+      .and_then(|year| year.as_str().parse::<i32>().ok())
+    else {
+      continue;
+    };
+
+    let parse_following = |offset: usize| -> Option<u32> {
+      let folder_name = (*folder_names.get(year_index + offset)?)?;
+      MONTH_OR_DAY_RE
+        .captures(folder_name)?
+        .get(1)?
+        .as_str()
+        .parse()
+        .ok()
+    };
+
+    let Some(month) = parse_following(1).filter(|month| (1..=12).contains(month)) else {
+      continue;
+    };
+    let day = parse_following(2).filter(|day| NaiveDate::from_ymd_opt(year, month, *day).is_some());
+
+    let datetime = NaiveDateTime::new(
+      NaiveDate::from_ymd_opt(year, month, day.unwrap_or(1))?,
+      NaiveTime::from_hms_opt(0, 0, 0)?,
+    );
+    let confidence = if day.is_some() {
+      DateConfidence::Day
+    } else {
+      DateConfidence::Month
+    };
+    return Some(ConfidentNaiveDateTime::new(datetime, confidence));
+  }
+
+  None
+}
+
+#[cfg(test)]
+pub mod test {
+  use super::*;
+  use crate::date_extractors::test::{TestCase, test_test_cases};
+  use std::sync::LazyLock;
+
+  pub static TESTS_FOLDER_HIERARCHY_FILEPATH: LazyLock<Vec<TestCase>> = LazyLock::new(|| {
+    vec![
+      TestCase {
+        file_path: "/Photos/2019/07/DSC_0001.jpg",
+        expected_result: Some(ConfidentNaiveDateTime::new(
+          NaiveDate::parse_from_str("20190701", "%Y%m%d")
+            .unwrap()
+            .into(),
+          DateConfidence::Month,
+        )),
+      },
+      TestCase {
+        file_path: "/Photos/2019/07 Juli/15/DSC_0001.jpg",
+        expected_result: Some(ConfidentNaiveDateTime::new(
+          NaiveDate::parse_from_str("20190715", "%Y%m%d")
+            .unwrap()
+            .into(),
+          DateConfidence::Day,
+        )),
+      },
+      TestCase {
+        file_path: "/Photos/2019/07/Sommerfest/DSC_0001.jpg",
+        expected_result: Some(ConfidentNaiveDateTime::new(
+          NaiveDate::parse_from_str("20190701", "%Y%m%d")
+            .unwrap()
+            .into(),
+          DateConfidence::Month,
+        )),
+      },
+      TestCase {
+        file_path: "/Photos/2019/13/DSC_0001.jpg",
+        expected_result: None,
+      },
+      TestCase {
+        file_path: "/Photos/2019/DSC_0001.jpg",
+        expected_result: None,
+      },
+    ]
+  });
+
+  #[test]
+  fn folder_hierarchy_regex() {
+    test_test_cases(
+      TESTS_FOLDER_HIERARCHY_FILEPATH.iter(),
+      get_date_from_folder_hierarchy_regex,
+    );
+  }
+}
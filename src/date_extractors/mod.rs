@@ -1,7 +1,7 @@
 //type ChumError = chumsky::error::Simple<char>;
 type ChumError = chumsky::error::Cheap;
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Serialize, Deserialize)]
 pub enum DateConfidence {
   None,
   Decade,
@@ -11,25 +11,73 @@ pub enum DateConfidence {
   Hour,
   Minute,
   Second,
+  Millisecond,
+}
+
+impl DateConfidence {
+  /// How many levels `self` is more precise than `other`, negative if it is less precise.
+  #[must_use]
+  pub const fn levels_above(self, other: Self) -> i16 {
+    self as i16 - other as i16
+  }
+}
+
+impl core::str::FromStr for DateConfidence {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "none" => Ok(Self::None),
+      "decade" => Ok(Self::Decade),
+      "year" => Ok(Self::Year),
+      "month" => Ok(Self::Month),
+      "day" => Ok(Self::Day),
+      "hour" => Ok(Self::Hour),
+      "minute" => Ok(Self::Minute),
+      "second" => Ok(Self::Second),
+      "millisecond" => Ok(Self::Millisecond),
+      _ => Err(anyhow::anyhow!("Unknown date confidence: {s}")),
+    }
+  }
 }
 
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub struct ConfidentNaiveDateTime {
   pub date: NaiveDateTime,
   pub confidence: DateConfidence,
+  /// The UTC offset of `date`, if the source states it.
+  pub offset: Option<FixedOffset>,
 }
 impl ConfidentNaiveDateTime {
   #[must_use]
   pub const fn new(date: NaiveDateTime, confidence: DateConfidence) -> Self {
-    Self { date, confidence }
+    Self {
+      date,
+      confidence,
+      offset: None,
+    }
+  }
+
+  #[must_use]
+  pub const fn with_offset(self, offset: FixedOffset) -> Self {
+    Self {
+      offset: Some(offset),
+      ..self
+    }
   }
 }
 impl core::fmt::Display for ConfidentNaiveDateTime {
   fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    // Only show the sub-second part if the source actually provided it.
+    let format = if self.confidence >= DateConfidence::Millisecond {
+      "%Y-%m-%d %H:%M:%S%.3f"
+    } else {
+      "%Y-%m-%d %H:%M:%S"
+    };
     write!(
       f,
       "{} (confidence: {:?})",
-      self.date.format("%Y-%m-%d %H:%M:%S"),
+      self.date.format(format),
       self.confidence
     )
   }
@@ -53,8 +101,32 @@ pub use custom_date_prefixed_style_image_paths::*;
 mod unix_time_stamp_prefixed_style_image_paths;
 pub use unix_time_stamp_prefixed_style_image_paths::*;
 
-use chrono::NaiveDateTime;
-use std::path::Path;
+mod snapchat_memories_image_paths;
+pub use snapchat_memories_image_paths::*;
+
+mod threema_style_image_paths;
+pub use threema_style_image_paths::*;
+
+mod folder_hierarchy_image_paths;
+pub use folder_hierarchy_image_paths::*;
+
+mod year_in_folder_name_image_paths;
+pub use year_in_folder_name_image_paths::*;
+
+mod date_range_folder_image_paths;
+pub use date_range_folder_image_paths::*;
+
+mod explanation;
+pub use explanation::*;
+
+mod regex_extractor;
+pub use regex_extractor::*;
+
+mod registry;
+pub use registry::*;
+
+use chrono::{FixedOffset, NaiveDateTime};
+use serde::{Deserialize, Serialize};
 
 /// Prints the reports from the vector of errors
 #[allow(dead_code)]
@@ -70,46 +142,20 @@ pub fn print_chumsky_errors<'a>(errors: impl Iterator<Item = &'a ChumError>, sou
   });
 }
 
-pub fn get_date_for_file(
-  file_path: &Path,
-  file_name: &str,
-  current_time: NaiveDateTime,
-) -> Option<ConfidentNaiveDateTime> {
-  // the uuid handler MUST come first!
-  let handler_functions = vec![
-    get_date_from_screenshot_prefixed_filepath_regex,
-    get_date_from_uuid_prefixed_filepath_regex,
-    get_date_from_unix_timestamp_prefixed_filepath_regex,
-    get_date_from_android_filepath_nom,
-    get_date_from_whatsapp_filepath_regex,
-    get_date_from_custom_date_prefixed_filepath_regex,
-  ];
-
-  for handler in handler_functions {
-    let ret = handler(file_path, file_name);
-    if let Some(ret) = ret {
-      // check if the date is in the future
-      if ret.date > current_time {
-        // skip the handler if it returns an invalid date
-        continue;
-      }
-      return Some(ret);
-    }
-  }
-  None
-}
-
 #[cfg(test)]
 mod test {
   use super::{
     android_style_image_paths::test::TESTS_ANDROID_FILEPATH,
     custom_date_prefixed_style_image_paths::test::TESTS_CUSTOM_DATE_PREFIXED_FILEPATH,
+    folder_hierarchy_image_paths::test::TESTS_FOLDER_HIERARCHY_FILEPATH,
     screenshot_prefixed_style_image_paths::test::TESTS_SCREENSHOT_PREFIXED_FILEPATH,
+    snapchat_memories_image_paths::test::TESTS_SNAPCHAT_MEMORIES_FILEPATH,
+    threema_style_image_paths::test::TESTS_THREEMA_FILEPATH,
     unix_time_stamp_prefixed_style_image_paths::test::TESTS_UNIX_TIMESTAMP_PREFIXED_FILEPATH,
     uuid_timestamp_prefixed_image_paths::test::TESTS_UUID_TIMESTAMP_PREFIXED_FILEPATH,
     whatsapp_style_image_paths::test::TESTS_WHATSAPP_FILEPATH, *,
   };
-  use std::sync::LazyLock;
+  use std::{path::Path, sync::LazyLock};
 
   #[derive(PartialEq, Eq, Debug, Clone)]
   pub struct TestCase {
@@ -141,6 +187,9 @@ mod test {
     assert!(DateConfidence::Day < DateConfidence::Hour);
     assert!(DateConfidence::Hour < DateConfidence::Minute);
     assert!(DateConfidence::Minute < DateConfidence::Second);
+    assert!(DateConfidence::Second < DateConfidence::Millisecond);
+    assert_eq!(DateConfidence::Day.levels_above(DateConfidence::Year), 2);
+    assert_eq!(DateConfidence::Year.levels_above(DateConfidence::Day), -2);
   }
 
   fn get_all_test_data() -> impl Iterator<Item = &'static TestCase> {
@@ -152,6 +201,9 @@ mod test {
         TESTS_SCREENSHOT_PREFIXED_FILEPATH.as_slice(),
         TESTS_CUSTOM_DATE_PREFIXED_FILEPATH.as_slice(),
         TESTS_UNIX_TIMESTAMP_PREFIXED_FILEPATH.as_slice(),
+        TESTS_SNAPCHAT_MEMORIES_FILEPATH.as_slice(),
+        TESTS_THREEMA_FILEPATH.as_slice(),
+        TESTS_FOLDER_HIERARCHY_FILEPATH.as_slice(),
       ]
       .concat()
     });
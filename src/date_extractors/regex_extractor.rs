@@ -0,0 +1,230 @@
+use std::path::Path;
+
+use anyhow::{Context as _, bail};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use regex::Regex;
+
+use super::{ConfidentNaiveDateTime, DateConfidence, DateExtractor, Explanation};
+
+/// The named groups a user-defined regex may contain. Only `year` is required.
+const DATE_GROUP_NAMES: [&str; 7] = [
+  "year",
+  "month",
+  "day",
+  "hour",
+  "minute",
+  "second",
+  "millisecond",
+];
+
+/// A user-defined extractor that matches file names against a regex with named date groups.
+/// Groups that are missing from the regex or did not participate in the match default to their lowest value.
+pub struct RegexDateExtractor {
+  name: String,
+  priority: i32,
+  regex: Regex,
+  confidence: DateConfidence,
+}
+
+impl RegexDateExtractor {
+  pub fn new(
+    name: impl Into<String>,
+    priority: i32,
+    pattern: &str,
+    confidence: DateConfidence,
+  ) -> anyhow::Result<Self> {
+    let name = name.into();
+    let regex = Regex::new(pattern)
+      .with_context(|| format!("Invalid regex for date extractor \"{name}\""))?;
+
+    let group_names = regex.capture_names().flatten().collect::<Vec<_>>();
+    if !group_names.contains(&"year") {
+      bail!("The regex of date extractor \"{name}\" must contain a named group \"year\"");
+    }
+    if let Some(unknown) = group_names
+      .iter()
+      .find(|group_name| !DATE_GROUP_NAMES.contains(group_name))
+    {
+      bail!(
+        "The regex of date extractor \"{name}\" contains the unknown group \"{unknown}\", expected one of: {}",
+        DATE_GROUP_NAMES.join(", ")
+      );
+    }
+
+    Ok(Self {
+      name,
+      priority,
+      regex,
+      confidence,
+    })
+  }
+
+  /// Compiles a strftime-style pattern (e.g. `backup_%Y%m%d_%H%M%S`) that must match the start of the file name.
+  /// The confidence is derived from the most precise specifier in the pattern.
+  pub fn from_strftime(
+    name: impl Into<String>,
+    priority: i32,
+    pattern: &str,
+  ) -> anyhow::Result<Self> {
+    let (regex, confidence) = strftime_to_regex(pattern)?;
+    Self::new(name, priority, &regex, confidence)
+  }
+}
+
+/// Translates a strftime-style pattern into a regex with named date groups.
+fn strftime_to_regex(pattern: &str) -> anyhow::Result<(String, DateConfidence)> {
+  let mut regex = String::from("^");
+  let mut confidence = DateConfidence::None;
+  let mut chars = pattern.chars();
+  while let Some(c) = chars.next() {
+    if c != '%' {
+      regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4])));
+      continue;
+    }
+
+    let (group, specifier_confidence) = match chars.next() {
+      Some('Y') => (r"(?P<year>\d{4})", DateConfidence::Year),
+      Some('m') => (r"(?P<month>\d{2})", DateConfidence::Month),
+      Some('d') => (r"(?P<day>\d{2})", DateConfidence::Day),
+      Some('H') => (r"(?P<hour>\d{2})", DateConfidence::Hour),
+      Some('M') => (r"(?P<minute>\d{2})", DateConfidence::Minute),
+      Some('S') => (r"(?P<second>\d{2})", DateConfidence::Second),
+      Some('f') => (r"(?P<millisecond>\d{3})", DateConfidence::Millisecond),
+      Some('%') => ("%", DateConfidence::None),
+      Some(specifier) => bail!(
+        "Unsupported specifier \"%{specifier}\" in pattern \"{pattern}\", expected one of: %Y, %m, %d, %H, %M, %S, %f, %%"
+      ),
+      None => bail!("Pattern \"{pattern}\" ends with an incomplete specifier"),
+    };
+    if regex.contains(group) && specifier_confidence != DateConfidence::None {
+      bail!("Pattern \"{pattern}\" contains a specifier more than once");
+    }
+    regex.push_str(group);
+    confidence = confidence.max(specifier_confidence);
+  }
+  Ok((regex, confidence))
+}
+
+impl DateExtractor for RegexDateExtractor {
+  fn name(&self) -> &str {
+    &self.name
+  }
+
+  fn priority(&self) -> i32 {
+    self.priority
+  }
+
+  fn extract(&self, _file_path: &Path, file_name: &str) -> Option<ConfidentNaiveDateTime> {
+    let captures = self.regex.captures(file_name)?;
+    let parse = |name: &str, default: u32| -> Option<u32> {
+      captures
+        .name(name)
+        .map_or(Some(default), |group| group.as_str().parse().ok())
+    };
+
+    let datetime = NaiveDateTime::new(
+      NaiveDate::from_ymd_opt(
+        captures.name("year")?.as_str().parse().ok()?,
+        parse("month", 1)?,
+        parse("day", 1)?,
+      )?,
+      NaiveTime::from_hms_milli_opt(
+        parse("hour", 0)?,
+        parse("minute", 0)?,
+        parse("second", 0)?,
+        parse("millisecond", 0)?,
+      )?,
+    );
+    Some(ConfidentNaiveDateTime::new(datetime, self.confidence))
+  }
+
+  fn explain(&self, file_path: &Path, file_name: &str) -> Explanation {
+    Explanation::from_regex(&self.regex, file_name, self.extract(file_path, file_name))
+  }
+}
+
+#[cfg(test)]
+pub mod test {
+  use super::*;
+
+  #[test]
+  fn regex_extractor() {
+    let extractor = RegexDateExtractor::new(
+      "scan",
+      0,
+      r"^scan_\d+_(?P<year>\d{4})(?P<month>\d{2})\.",
+      DateConfidence::Month,
+    )
+    .unwrap();
+    assert_eq!(
+      extractor.extract(Path::new("/a/scan_12_199807.tif"), "scan_12_199807.tif"),
+      Some(ConfidentNaiveDateTime::new(
+        NaiveDateTime::parse_from_str("19980701000000", "%Y%m%d%H%M%S").unwrap(),
+        DateConfidence::Month,
+      ))
+    );
+    assert_eq!(
+      extractor.extract(Path::new("/a/scan_12_199813.tif"), "scan_12_199813.tif"),
+      None
+    );
+    assert_eq!(
+      extractor.extract(Path::new("/a/IMG_199807.tif"), "IMG_199807.tif"),
+      None
+    );
+  }
+
+  #[test]
+  fn regex_extractor_invalid_groups() {
+    assert!(RegexDateExtractor::new("a", 0, r"(?P<month>\d{2})", DateConfidence::Month).is_err());
+    assert!(
+      RegexDateExtractor::new(
+        "a",
+        0,
+        r"(?P<year>\d{4})(?P<mon>\d{2})",
+        DateConfidence::Month
+      )
+      .is_err()
+    );
+    assert!(RegexDateExtractor::new("a", 0, r"(?P<year>\d{4}", DateConfidence::Year).is_err());
+  }
+
+  #[test]
+  fn strftime_extractor() {
+    let extractor = RegexDateExtractor::from_strftime("backup", 0, "backup_%Y%m%d_%H%M%S").unwrap();
+    assert_eq!(
+      extractor.extract(
+        Path::new("/a/backup_20200102_030405.jpg"),
+        "backup_20200102_030405.jpg"
+      ),
+      Some(ConfidentNaiveDateTime::new(
+        NaiveDateTime::parse_from_str("20200102030405", "%Y%m%d%H%M%S").unwrap(),
+        DateConfidence::Second,
+      ))
+    );
+    assert_eq!(
+      extractor.extract(
+        Path::new("/a/old_backup_20200102_030405.jpg"),
+        "old_backup_20200102_030405.jpg"
+      ),
+      None
+    );
+
+    let extractor = RegexDateExtractor::from_strftime("percent", 0, "100%%.%Y-%m").unwrap();
+    assert_eq!(
+      extractor.extract(Path::new("/a/100%.2020-05.jpg"), "100%.2020-05.jpg"),
+      Some(ConfidentNaiveDateTime::new(
+        NaiveDateTime::parse_from_str("20200501000000", "%Y%m%d%H%M%S").unwrap(),
+        DateConfidence::Month,
+      ))
+    );
+    assert_eq!(
+      extractor.extract(Path::new("/a/100%x2020-05.jpg"), "100%x2020-05.jpg"),
+      None
+    );
+
+    assert!(RegexDateExtractor::from_strftime("a", 0, "%Y%b").is_err());
+    assert!(RegexDateExtractor::from_strftime("a", 0, "%Y%m%Y").is_err());
+    assert!(RegexDateExtractor::from_strftime("a", 0, "%m%d").is_err());
+    assert!(RegexDateExtractor::from_strftime("a", 0, "%Y%").is_err());
+  }
+}
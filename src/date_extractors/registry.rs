@@ -0,0 +1,760 @@
+use core::ops::RangeInclusive;
+use std::{
+  path::Path,
+  sync::{LazyLock, OnceLock},
+};
+
+use anyhow::bail;
+use chrono::{Datelike as _, NaiveDateTime};
+use regex::Regex;
+use tracing::trace;
+
+use super::{
+  CUSTOM_DATE_PREFIX_RE, ConfidentNaiveDateTime, DATE_RANGE_FOLDER_RE, DateConfidence,
+  DateRangePolicy, Explanation, SCREENSHOT_PREFIX_RE, SNAPCHAT_MEMORIES_RE, THREEMA_RE,
+  UNIX_TIMESTAMP_PREFIX_RE, UUID_TIMESTAMP_PREFIX_RE, WHATSAPP_RE, YEAR_IN_FOLDER_NAME_RE,
+  explain_date_from_android_filepath_nom, get_date_from_android_filepath_nom,
+  get_date_from_custom_date_prefixed_filepath_regex, get_date_from_date_range_folder_regex,
+  get_date_from_folder_hierarchy_regex, get_date_from_screenshot_prefixed_filepath_regex,
+  get_date_from_snapchat_memories_filepath_regex, get_date_from_threema_filepath_regex,
+  get_date_from_unix_timestamp_prefixed_filepath_regex, get_date_from_uuid_prefixed_filepath_regex,
+  get_date_from_whatsapp_filepath_regex, get_date_from_year_in_folder_name_regex,
+};
+
+/// Dates before this year are rejected unless configured otherwise.
+pub const DEFAULT_MIN_YEAR: i32 = 1900;
+
+/// The priority of user-defined extractors, unless configured otherwise.
+/// They are tried before the built-in file name extractors.
+pub const DEFAULT_CUSTOM_EXTRACTOR_PRIORITY: i32 = 5;
+
+/// A source of dates parsed from file or folder names.
+pub trait DateExtractor: Send + Sync {
+  /// The unique name of the extractor.
+  fn name(&self) -> &str;
+
+  /// The default priority, which can be overridden in the registry.
+  /// Extractors with a lower priority are tried first.
+  fn priority(&self) -> i32;
+
+  /// Whether the extractor is too lenient for file names and must only be used for folder names.
+  fn folders_only(&self) -> bool {
+    false
+  }
+
+  /// Whether the extractor is so lenient that it must only provide a date if no other extractor does.
+  fn fallback(&self) -> bool {
+    false
+  }
+
+  fn extract(&self, file_path: &Path, file_name: &str) -> Option<ConfidentNaiveDateTime>;
+
+  /// Explains which part of the file name matched or why it did not.
+  fn explain(&self, file_path: &Path, file_name: &str) -> Explanation {
+    Explanation::from_result(file_name, self.extract(file_path, file_name))
+  }
+}
+
+/// Wraps a plain extractor function.
+pub struct FnDateExtractor {
+  name: &'static str,
+  priority: i32,
+  folders_only: bool,
+  fallback: bool,
+  extract_fn: fn(&Path, &str) -> Option<ConfidentNaiveDateTime>,
+  explain_fn: Option<fn(&Path, &str) -> Explanation>,
+  regex: Option<&'static LazyLock<Regex>>,
+}
+
+impl FnDateExtractor {
+  #[must_use]
+  pub const fn new(
+    name: &'static str,
+    priority: i32,
+    extract_fn: fn(&Path, &str) -> Option<ConfidentNaiveDateTime>,
+  ) -> Self {
+    Self {
+      name,
+      priority,
+      folders_only: false,
+      fallback: false,
+      extract_fn,
+      explain_fn: None,
+      regex: None,
+    }
+  }
+
+  #[must_use]
+  pub const fn folders_only(mut self) -> Self {
+    self.folders_only = true;
+    self
+  }
+
+  #[must_use]
+  pub const fn fallback(mut self) -> Self {
+    self.fallback = true;
+    self
+  }
+
+  /// Uses a dedicated function to explain the result.
+  #[must_use]
+  pub const fn with_explain(mut self, explain_fn: fn(&Path, &str) -> Explanation) -> Self {
+    self.explain_fn = Some(explain_fn);
+    self
+  }
+
+  /// Explains the result by the part of the file name the extractor's regex matches.
+  #[must_use]
+  pub const fn with_regex(mut self, regex: &'static LazyLock<Regex>) -> Self {
+    self.regex = Some(regex);
+    self
+  }
+}
+
+impl DateExtractor for FnDateExtractor {
+  fn name(&self) -> &str {
+    self.name
+  }
+
+  fn priority(&self) -> i32 {
+    self.priority
+  }
+
+  fn folders_only(&self) -> bool {
+    self.folders_only
+  }
+
+  fn fallback(&self) -> bool {
+    self.fallback
+  }
+
+  fn extract(&self, file_path: &Path, file_name: &str) -> Option<ConfidentNaiveDateTime> {
+    (self.extract_fn)(file_path, file_name)
+  }
+
+  fn explain(&self, file_path: &Path, file_name: &str) -> Explanation {
+    if let Some(explain_fn) = self.explain_fn {
+      return explain_fn(file_path, file_name);
+    }
+    let result = self.extract(file_path, file_name);
+    match self.regex {
+      Some(regex) => Explanation::from_regex(regex, file_name, result),
+      None => Explanation::from_result(file_name, result),
+    }
+  }
+}
+
+/// Extracts the date from folder names describing a date range, see [`get_date_from_date_range_folder_regex`].
+pub struct DateRangeFolderExtractor {
+  policy: DateRangePolicy,
+}
+
+impl DateRangeFolderExtractor {
+  #[must_use]
+  pub const fn new(policy: DateRangePolicy) -> Self {
+    Self { policy }
+  }
+}
+
+impl DateExtractor for DateRangeFolderExtractor {
+  fn name(&self) -> &'static str {
+    "date_range_folder"
+  }
+
+  fn priority(&self) -> i32 {
+    // MUST come before the file extractors, which would only see the start date.
+    0
+  }
+
+  fn folders_only(&self) -> bool {
+    true
+  }
+
+  fn extract(&self, file_path: &Path, file_name: &str) -> Option<ConfidentNaiveDateTime> {
+    get_date_from_date_range_folder_regex(file_path, file_name, self.policy)
+  }
+
+  fn explain(&self, file_path: &Path, file_name: &str) -> Explanation {
+    Explanation::from_regex(
+      &DATE_RANGE_FOLDER_RE,
+      file_name,
+      self.extract(file_path, file_name),
+    )
+  }
+}
+
+/// Pairs of extractors that must be tried in this order, with the reason why.
+const ORDERING_CONSTRAINTS: [(&str, &str, &str); 3] = [
+  (
+    "uuid_timestamp",
+    "unix_timestamp",
+    "UUID prefixed file names start with a timestamp that the unix timestamp extractor would misread",
+  ),
+  (
+    "date_range_folder",
+    "custom_date_prefixed",
+    "the date prefix extractor would only see the start of the date range",
+  ),
+  (
+    "date_range_folder",
+    "year_in_folder_name",
+    "the year extractor would only see the year of the start of the date range",
+  ),
+];
+
+struct RegisteredExtractor {
+  priority: i32,
+  /// Replaces the confidence reported by the extractor.
+  confidence: Option<DateConfidence>,
+  extractor: Box<dyn DateExtractor>,
+}
+
+/// The set of extractors that is consulted, in order of their priority.
+pub struct DateExtractorRegistry {
+  extractors: Vec<RegisteredExtractor>,
+  /// Dates outside of these years are implausible and rejected regardless of the extractor.
+  plausible_years: RangeInclusive<i32>,
+}
+
+impl DateExtractorRegistry {
+  #[must_use]
+  pub const fn new() -> Self {
+    Self {
+      extractors: Vec::new(),
+      plausible_years: DEFAULT_MIN_YEAR..=i32::MAX,
+    }
+  }
+
+  /// Rejects dates outside of `min_year..=max_year`.
+  pub fn set_plausible_years(&mut self, min_year: i32, max_year: i32) -> anyhow::Result<()> {
+    if min_year > max_year {
+      bail!("The minimum year {min_year} must not be after the maximum year {max_year}");
+    }
+    self.plausible_years = min_year..=max_year;
+    Ok(())
+  }
+
+  fn is_plausible(&self, date: &NaiveDateTime) -> bool {
+    self.plausible_years.contains(&date.year())
+  }
+
+  /// Creates a registry containing all built-in extractors.
+  #[must_use]
+  pub fn with_builtin_extractors(date_range_policy: DateRangePolicy) -> Self {
+    let mut registry = Self::new();
+    registry.register(Box::new(DateRangeFolderExtractor::new(date_range_policy)));
+    registry.register(Box::new(
+      FnDateExtractor::new(
+        "screenshot",
+        10,
+        get_date_from_screenshot_prefixed_filepath_regex,
+      )
+      .with_regex(&SCREENSHOT_PREFIX_RE),
+    ));
+    // The uuid extractor MUST come before the unix timestamp extractor, see `ORDERING_CONSTRAINTS`.
+    registry.register(Box::new(
+      FnDateExtractor::new(
+        "uuid_timestamp",
+        20,
+        get_date_from_uuid_prefixed_filepath_regex,
+      )
+      .with_regex(&UUID_TIMESTAMP_PREFIX_RE),
+    ));
+    registry.register(Box::new(
+      FnDateExtractor::new(
+        "unix_timestamp",
+        30,
+        get_date_from_unix_timestamp_prefixed_filepath_regex,
+      )
+      .with_regex(&UNIX_TIMESTAMP_PREFIX_RE),
+    ));
+    registry.register(Box::new(
+      FnDateExtractor::new("android", 40, get_date_from_android_filepath_nom)
+        .with_explain(explain_date_from_android_filepath_nom),
+    ));
+    registry.register(Box::new(
+      FnDateExtractor::new("whatsapp", 50, get_date_from_whatsapp_filepath_regex)
+        .with_regex(&WHATSAPP_RE),
+    ));
+    registry.register(Box::new(
+      FnDateExtractor::new("threema", 60, get_date_from_threema_filepath_regex)
+        .with_regex(&THREEMA_RE),
+    ));
+    registry.register(Box::new(
+      FnDateExtractor::new(
+        "snapchat_memories",
+        70,
+        get_date_from_snapchat_memories_filepath_regex,
+      )
+      .with_regex(&SNAPCHAT_MEMORIES_RE),
+    ));
+    registry.register(Box::new(
+      FnDateExtractor::new(
+        "custom_date_prefixed",
+        80,
+        get_date_from_custom_date_prefixed_filepath_regex,
+      )
+      .with_regex(&CUSTOM_DATE_PREFIX_RE)
+      .fallback(),
+    ));
+    // Only consulted if the file name itself carries no date.
+    registry.register(Box::new(
+      FnDateExtractor::new("folder_hierarchy", 90, get_date_from_folder_hierarchy_regex).fallback(),
+    ));
+    registry.register(Box::new(
+      FnDateExtractor::new(
+        "year_in_folder_name",
+        100,
+        get_date_from_year_in_folder_name_regex,
+      )
+      .with_regex(&YEAR_IN_FOLDER_NAME_RE)
+      .folders_only()
+      .fallback(),
+    ));
+    registry
+  }
+
+  /// Adds an extractor with its default priority, keeping the extractors ordered by priority.
+  /// Extractors with the same priority are tried in the order they were registered.
+  pub fn register(&mut self, extractor: Box<dyn DateExtractor>) {
+    let priority = extractor.priority();
+    self.insert(RegisteredExtractor {
+      priority,
+      confidence: None,
+      extractor,
+    });
+  }
+
+  fn insert(&mut self, registered: RegisteredExtractor) {
+    let index = self
+      .extractors
+      .partition_point(|other| other.priority <= registered.priority);
+    self.extractors.insert(index, registered);
+  }
+
+  #[must_use]
+  pub fn contains(&self, name: &str) -> bool {
+    self.position(name).is_some()
+  }
+
+  fn position(&self, name: &str) -> Option<usize> {
+    self
+      .extractors
+      .iter()
+      .position(|registered| registered.extractor.name() == name)
+  }
+
+  /// The names of the registered extractors, in the order they are tried.
+  pub fn names(&self) -> impl Iterator<Item = &str> {
+    self
+      .extractors
+      .iter()
+      .map(|registered| registered.extractor.name())
+  }
+
+  /// The names and priorities of the registered extractors, in the order they are tried.
+  pub fn priorities(&self) -> impl Iterator<Item = (&str, i32)> {
+    self
+      .extractors
+      .iter()
+      .map(|registered| (registered.extractor.name(), registered.priority))
+  }
+
+  fn check_names<'a>(&self, names: impl IntoIterator<Item = &'a str>) -> anyhow::Result<()> {
+    for name in names {
+      if !self.contains(name) {
+        bail!(
+          "Unknown date extractor \"{name}\", available extractors: {}",
+          self.names().collect::<Vec<_>>().join(", ")
+        );
+      }
+    }
+    Ok(())
+  }
+
+  /// Changes the priority of the named extractor.
+  pub fn set_priority(&mut self, name: &str, priority: i32) -> anyhow::Result<()> {
+    self.check_names([name])?;
+    if let Some(index) = self.position(name) {
+      let mut registered = self.extractors.remove(index);
+      registered.priority = priority;
+      self.insert(registered);
+    }
+    Ok(())
+  }
+
+  /// Overrides the confidence of the dates the named extractor returns.
+  pub fn set_confidence(&mut self, name: &str, confidence: DateConfidence) -> anyhow::Result<()> {
+    self.check_names([name])?;
+    if let Some(index) = self.position(name) {
+      self.extractors[index].confidence = Some(confidence);
+    }
+    Ok(())
+  }
+
+  /// Checks that the extractors which depend on being tried before others still are.
+  pub fn validate_order(&self) -> anyhow::Result<()> {
+    for (first, second, reason) in ORDERING_CONSTRAINTS {
+      if let (Some(first_index), Some(second_index)) = (self.position(first), self.position(second))
+        && first_index > second_index
+      {
+        bail!("The date extractor \"{first}\" must be tried before \"{second}\", because {reason}");
+      }
+    }
+    Ok(())
+  }
+
+  /// Removes the named extractors.
+  pub fn disable<'a>(
+    &mut self,
+    names: impl IntoIterator<Item = &'a str> + Clone,
+  ) -> anyhow::Result<()> {
+    self.check_names(names.clone())?;
+    let names = names.into_iter().collect::<Vec<_>>();
+    self
+      .extractors
+      .retain(|registered| !names.contains(&registered.extractor.name()));
+    Ok(())
+  }
+
+  /// Removes all extractors except the named ones.
+  pub fn retain_only<'a>(
+    &mut self,
+    names: impl IntoIterator<Item = &'a str> + Clone,
+  ) -> anyhow::Result<()> {
+    self.check_names(names.clone())?;
+    let names = names.into_iter().collect::<Vec<_>>();
+    self
+      .extractors
+      .retain(|registered| names.contains(&registered.extractor.name()));
+    Ok(())
+  }
+
+  /// Runs all extractors and returns the most confident date that is not in the future, see [`select_best_candidate`].
+  #[must_use]
+  pub fn extract(
+    &self,
+    file_path: &Path,
+    file_name: &str,
+    current_time: NaiveDateTime,
+    is_folder: bool,
+  ) -> Option<ConfidentNaiveDateTime> {
+    self
+      .extract_with_name(file_path, file_name, current_time, is_folder)
+      .map(|(_, ret)| ret)
+  }
+
+  /// Like [`Self::extract`] but also returns the name of the extractor that provided the date.
+  #[must_use]
+  pub fn extract_with_name(
+    &self,
+    file_path: &Path,
+    file_name: &str,
+    current_time: NaiveDateTime,
+    is_folder: bool,
+  ) -> Option<(&str, ConfidentNaiveDateTime)> {
+    let candidates = self.candidates(file_path, file_name, current_time, is_folder);
+    let best = select_best_candidate(&candidates)?;
+    trace!(
+      file_path = %file_path.display(),
+      "Extractor {} selected: {} (candidates: {})",
+      best.extractor_name,
+      best.date,
+      candidates
+        .iter()
+        .map(|candidate| format!("{}: {}", candidate.extractor_name, candidate.date))
+        .collect::<Vec<_>>()
+        .join(", "),
+    );
+    Some((best.extractor_name, best.date))
+  }
+
+  /// Runs all extractors and collects the dates that are not in the future, in the order the extractors are tried.
+  #[must_use]
+  pub fn candidates(
+    &self,
+    file_path: &Path,
+    file_name: &str,
+    current_time: NaiveDateTime,
+    is_folder: bool,
+  ) -> Vec<Candidate<'_>> {
+    self
+      .extractors
+      .iter()
+      .filter(|registered| is_folder || !registered.extractor.folders_only())
+      .filter_map(|registered| {
+        let mut date = registered.extractor.extract(file_path, file_name)?;
+        if let Some(confidence) = registered.confidence {
+          date.confidence = confidence;
+        }
+        Some(Candidate {
+          extractor_name: registered.extractor.name(),
+          fallback: registered.extractor.fallback(),
+          date,
+        })
+      })
+      // skip extractors that return a date in the future
+      .filter(|candidate| candidate.date.date <= current_time)
+      .filter(|candidate| {
+        let is_plausible = self.is_plausible(&candidate.date.date);
+        if !is_plausible {
+          trace!(
+            file_path = %file_path.display(),
+            "Extractor {} returned an implausible date: {}",
+            candidate.extractor_name,
+            candidate.date,
+          );
+        }
+        is_plausible
+      })
+      .collect()
+  }
+
+  /// Explains the result of every extractor, in the order they are tried.
+  #[must_use]
+  pub fn explain(
+    &self,
+    file_path: &Path,
+    file_name: &str,
+    current_time: NaiveDateTime,
+    is_folder: bool,
+  ) -> Vec<(&str, Explanation)> {
+    self
+      .extractors
+      .iter()
+      .filter(|registered| is_folder || !registered.extractor.folders_only())
+      .map(|registered| {
+        let mut explanation = registered.extractor.explain(file_path, file_name);
+        if let Some(result) = &mut explanation.result {
+          if let Some(confidence) = registered.confidence {
+            result.confidence = confidence;
+            explanation.message = format!("Matched {result} (confidence overridden)");
+          }
+          if result.date > current_time {
+            explanation.message = format!("{}, but the date is in the future", explanation.message);
+            explanation.result = None;
+          } else if !self.is_plausible(&result.date) {
+            explanation.message = format!(
+              "{}, but the year is outside of the plausible years {}..={}",
+              explanation.message,
+              self.plausible_years.start(),
+              self.plausible_years.end(),
+            );
+            explanation.result = None;
+          }
+        }
+        (registered.extractor.name(), explanation)
+      })
+      .collect()
+  }
+}
+
+/// A date found by an extractor.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Candidate<'a> {
+  pub extractor_name: &'a str,
+  pub fallback: bool,
+  pub date: ConfidentNaiveDateTime,
+}
+
+/// Picks the most confident candidate, only considering fallback candidates if there are no others.
+/// Ties are broken in favor of the candidate that comes first, i.e. the extractor with the lowest priority.
+#[must_use]
+pub fn select_best_candidate<'a, 'b>(candidates: &'b [Candidate<'a>]) -> Option<&'b Candidate<'a>> {
+  let most_confident = |fallback: bool| {
+    candidates
+      .iter()
+      .filter(|candidate| candidate.fallback == fallback)
+      .fold(None, |best: Option<&Candidate<'a>>, candidate| match best {
+        Some(best) if best.date.confidence >= candidate.date.confidence => Some(best),
+        _ => Some(candidate),
+      })
+  };
+  most_confident(false).or_else(|| most_confident(true))
+}
+
+static DATE_EXTRACTOR_REGISTRY: OnceLock<DateExtractorRegistry> = OnceLock::new();
+
+/// Installs the registry returned by [`date_extractor_registry`].
+/// Must be called before the first date is extracted, otherwise the built-in extractors are used.
+pub fn install_date_extractor_registry(registry: DateExtractorRegistry) -> anyhow::Result<()> {
+  DATE_EXTRACTOR_REGISTRY
+    .set(registry)
+    .map_err(|_| anyhow::anyhow!("The date extractor registry is already installed"))
+}
+
+#[must_use]
+pub fn date_extractor_registry() -> &'static DateExtractorRegistry {
+  DATE_EXTRACTOR_REGISTRY
+    .get_or_init(|| DateExtractorRegistry::with_builtin_extractors(DateRangePolicy::default()))
+}
+
+pub fn get_date_for_file(
+  file_path: &Path,
+  file_name: &str,
+  current_time: NaiveDateTime,
+) -> Option<ConfidentNaiveDateTime> {
+  date_extractor_registry().extract(file_path, file_name, current_time, false)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn registry_order() {
+    let registry = DateExtractorRegistry::with_builtin_extractors(DateRangePolicy::default());
+    assert!(
+      registry
+        .priorities()
+        .map(|(_, priority)| priority)
+        .is_sorted()
+    );
+    registry.validate_order().unwrap();
+  }
+
+  #[test]
+  fn registry_priorities() {
+    let mut registry = DateExtractorRegistry::with_builtin_extractors(DateRangePolicy::default());
+    registry.set_priority("whatsapp", 15).unwrap();
+    assert_eq!(
+      registry.names().take(4).collect::<Vec<_>>(),
+      [
+        "date_range_folder",
+        "screenshot",
+        "whatsapp",
+        "uuid_timestamp"
+      ]
+    );
+    registry.validate_order().unwrap();
+
+    registry.set_priority("uuid_timestamp", 35).unwrap();
+    assert!(registry.validate_order().is_err());
+    // The constraint no longer applies once one of the extractors is disabled.
+    registry.disable(["unix_timestamp"]).unwrap();
+    registry.validate_order().unwrap();
+
+    assert!(registry.set_priority("unix_timestamp", 0).is_err());
+  }
+
+  #[test]
+  fn best_candidate() {
+    let candidate = |extractor_name, fallback, confidence| Candidate {
+      extractor_name,
+      fallback,
+      date: ConfidentNaiveDateTime::new(NaiveDateTime::MIN, confidence),
+    };
+    let candidates = [
+      candidate("a", false, DateConfidence::Day),
+      candidate("b", true, DateConfidence::Millisecond),
+      candidate("c", false, DateConfidence::Second),
+      candidate("d", false, DateConfidence::Second),
+    ];
+    assert_eq!(
+      select_best_candidate(&candidates).map(|candidate| candidate.extractor_name),
+      Some("c")
+    );
+    assert_eq!(
+      select_best_candidate(&candidates[1..2]).map(|candidate| candidate.extractor_name),
+      Some("b")
+    );
+    assert_eq!(select_best_candidate(&[]), None);
+
+    // The folder hierarchy is only a fallback for the file name.
+    let registry = DateExtractorRegistry::with_builtin_extractors(DateRangePolicy::default());
+    let file_path = Path::new("/a/2019/07/IMG-20190818-WA0001.jpg");
+    assert_eq!(
+      registry
+        .candidates(
+          file_path,
+          "IMG-20190818-WA0001.jpg",
+          NaiveDateTime::MAX,
+          false
+        )
+        .len(),
+      2
+    );
+    assert_eq!(
+      registry
+        .extract_with_name(
+          file_path,
+          "IMG-20190818-WA0001.jpg",
+          NaiveDateTime::MAX,
+          false
+        )
+        .map(|(name, _)| name),
+      Some("whatsapp")
+    );
+  }
+
+  #[test]
+  fn plausible_years() {
+    let mut registry = DateExtractorRegistry::with_builtin_extractors(DateRangePolicy::default());
+    let extract = |registry: &DateExtractorRegistry, file_name: &str| {
+      registry.extract(Path::new(file_name), file_name, NaiveDateTime::MAX, false)
+    };
+    assert!(extract(&registry, "1850-01-01_scan.jpg").is_none());
+    assert!(extract(&registry, "1950-01-01_scan.jpg").is_some());
+
+    registry.set_plausible_years(1800, 1900).unwrap();
+    assert!(extract(&registry, "1850-01-01_scan.jpg").is_some());
+    assert!(extract(&registry, "1950-01-01_scan.jpg").is_none());
+    assert!(registry.set_plausible_years(1901, 1900).is_err());
+  }
+
+  #[test]
+  fn registry_confidence_overrides() {
+    let mut registry = DateExtractorRegistry::with_builtin_extractors(DateRangePolicy::default());
+    let file_path = Path::new("/a/IMG-20190818-WA0001.jpg");
+    let extract = |registry: &DateExtractorRegistry| {
+      registry
+        .extract(
+          file_path,
+          "IMG-20190818-WA0001.jpg",
+          NaiveDateTime::MAX,
+          false,
+        )
+        .map(|ret| ret.confidence)
+    };
+    assert_eq!(extract(&registry), Some(DateConfidence::Day));
+    registry
+      .set_confidence("whatsapp", DateConfidence::Hour)
+      .unwrap();
+    assert_eq!(extract(&registry), Some(DateConfidence::Hour));
+    // The override survives a priority change.
+    registry.set_priority("whatsapp", 15).unwrap();
+    assert_eq!(extract(&registry), Some(DateConfidence::Hour));
+    assert!(
+      registry
+        .set_confidence("unknown", DateConfidence::Hour)
+        .is_err()
+    );
+  }
+
+  #[test]
+  fn disable_extractors() {
+    let mut registry = DateExtractorRegistry::with_builtin_extractors(DateRangePolicy::default());
+    let file_path = Path::new("/a/1624280370243.jpg");
+    assert!(
+      registry
+        .extract(file_path, "1624280370243.jpg", NaiveDateTime::MAX, false)
+        .is_some()
+    );
+
+    registry.disable(["unix_timestamp"]).unwrap();
+    assert!(!registry.contains("unix_timestamp"));
+    assert!(
+      registry
+        .extract(file_path, "1624280370243.jpg", NaiveDateTime::MAX, false)
+        .is_none()
+    );
+    assert!(registry.disable(["unix_timestamp"]).is_err());
+
+    registry.retain_only(["android", "whatsapp"]).unwrap();
+    assert_eq!(
+      registry.names().collect::<Vec<_>>(),
+      ["android", "whatsapp"]
+    );
+    assert!(registry.retain_only(["screenshot"]).is_err());
+  }
+}
@@ -5,6 +5,9 @@ use regex::Regex;
 
 use super::{ConfidentNaiveDateTime, get_date_for_file};
 
+pub static SCREENSHOT_PREFIX_RE: LazyLock<Regex> =
+  LazyLock::new(|| Regex::new(r"(?i)^(screenshot[-_\s])").unwrap());
+
 /// Extracts the date from screenshot prefixed image file paths.
 /// Example file paths:
 ///   * /storage/emulated/0/DCIM/Camera/Screenshot 2020-09-15 191156.png
@@ -20,8 +23,7 @@ pub fn get_date_from_screenshot_prefixed_filepath_regex(
   file_path: &Path,
   file_name: &str,
 ) -> Option<ConfidentNaiveDateTime> {
-  static RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)^(screenshot[-_\s])").unwrap());
-  let captures = RE.captures(file_name)?;
+  let captures = SCREENSHOT_PREFIX_RE.captures(file_name)?;
 
   let prefix = captures.get(1)?.as_str();
   let unprefixed_file_name = file_name.strip_prefix(prefix).unwrap();
@@ -0,0 +1,82 @@
+use std::{path::Path, sync::LazyLock};
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use regex::Regex;
+
+use super::{ConfidentNaiveDateTime, DateConfidence};
+
+pub static SNAPCHAT_MEMORIES_RE: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(
+    r"^(\d{4})-(\d{2})-(\d{2})_[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}(?:-(?:main|overlay))?",
+  )
+  .unwrap()
+});
+
+/// Extracts the date from Snapchat Memories export file paths (date followed by a UUID).
+/// Example file paths:
+///   * /.../memories/2020-05-01_49b19a16-01a9-4a11-9789-e3005d827362-main.jpg
+///   * /.../memories/2020-05-01_49b19a16-01a9-4a11-9789-e3005d827362-overlay.png
+pub fn get_date_from_snapchat_memories_filepath_regex(
+  _file_path: &Path,
+  file_name: &str,
+) -> Option<ConfidentNaiveDateTime> {
+  let captures = SNAPCHAT_MEMORIES_RE.captures(file_name)?;
+
+  let year: u32 = captures.get(1)?.as_str().parse().ok()?;
+  let month: u32 = captures.get(2)?.as_str().parse().ok()?;
+  let day: u32 = captures.get(3)?.as_str().parse().ok()?;
+
+  let datetime = NaiveDateTime::new(
+    NaiveDate::from_ymd_opt(year.try_into().ok()?, month, day)?,
+    NaiveTime::from_hms_opt(0, 0, 0)?,
+  );
+  Some(ConfidentNaiveDateTime::new(datetime, DateConfidence::Day))
+}
+
+#[cfg(test)]
+pub mod test {
+  use super::*;
+  use crate::date_extractors::test::{TestCase, test_test_cases};
+  use std::sync::LazyLock;
+
+  pub static TESTS_SNAPCHAT_MEMORIES_FILEPATH: LazyLock<Vec<TestCase>> = LazyLock::new(|| {
+    vec![
+      TestCase {
+        file_path: "/home/user/memories/2020-05-01_49b19a16-01a9-4a11-9789-e3005d827362-main.jpg",
+        expected_result: Some(ConfidentNaiveDateTime::new(
+          NaiveDate::parse_from_str("20200501", "%Y%m%d")
+            .unwrap()
+            .into(),
+          DateConfidence::Day,
+        )),
+      },
+      // The UUID starts with digits that must not be mistaken for a time of day.
+      TestCase {
+        file_path: "/home/user/memories/2020-05-01_12345678-01a9-4a11-9789-e3005d827362-overlay.png",
+        expected_result: Some(ConfidentNaiveDateTime::new(
+          NaiveDate::parse_from_str("20200501", "%Y%m%d")
+            .unwrap()
+            .into(),
+          DateConfidence::Day,
+        )),
+      },
+      TestCase {
+        file_path: "/home/user/memories/2020-05-01_12345678-01A9-4A11-9789-E3005D827362.mp4",
+        expected_result: Some(ConfidentNaiveDateTime::new(
+          NaiveDate::parse_from_str("20200501", "%Y%m%d")
+            .unwrap()
+            .into(),
+          DateConfidence::Day,
+        )),
+      },
+    ]
+  });
+
+  #[test]
+  fn snapchat_memories_filepath_regex() {
+    test_test_cases(
+      TESTS_SNAPCHAT_MEMORIES_FILEPATH.iter(),
+      get_date_from_snapchat_memories_filepath_regex,
+    );
+  }
+}
@@ -0,0 +1,76 @@
+use std::{path::Path, sync::LazyLock};
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use regex::Regex;
+
+use super::{ConfidentNaiveDateTime, DateConfidence};
+
+pub static THREEMA_RE: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(r"(?i)^threema-(\d{4})(\d{2})(\d{2})-(\d{2})(\d{2})(\d{2})(\d{3})").unwrap()
+});
+
+/// Extracts the date from Threema media export file paths (time with milliseconds).
+/// Example file paths:
+///   * /.../Threema/threema-20210501-120000000.jpg
+///   * /.../Threema/threema-20210501-120000123<POSTFIX>.jpg
+pub fn get_date_from_threema_filepath_regex(
+  _file_path: &Path,
+  file_name: &str,
+) -> Option<ConfidentNaiveDateTime> {
+  let captures = THREEMA_RE.captures(file_name)?;
+
+  let year: u32 = captures.get(1)?.as_str().parse().ok()?;
+  let month: u32 = captures.get(2)?.as_str().parse().ok()?;
+  let day: u32 = captures.get(3)?.as_str().parse().ok()?;
+  let hour: u32 = captures.get(4)?.as_str().parse().ok()?;
+  let minute: u32 = captures.get(5)?.as_str().parse().ok()?;
+  let second: u32 = captures.get(6)?.as_str().parse().ok()?;
+  let millisecond: u32 = captures.get(7)?.as_str().parse().ok()?;
+
+  let datetime = NaiveDateTime::new(
+    NaiveDate::from_ymd_opt(year.try_into().ok()?, month, day)?,
+    NaiveTime::from_hms_milli_opt(hour, minute, second, millisecond)?,
+  );
+  Some(ConfidentNaiveDateTime::new(
+    datetime,
+    DateConfidence::Millisecond,
+  ))
+}
+
+#[cfg(test)]
+pub mod test {
+  use super::*;
+  use crate::date_extractors::test::{TestCase, test_test_cases};
+  use std::sync::LazyLock;
+
+  pub static TESTS_THREEMA_FILEPATH: LazyLock<Vec<TestCase>> = LazyLock::new(|| {
+    vec![
+      TestCase {
+        file_path: "/home/user/Threema/threema-20210501-120000000.jpg",
+        expected_result: Some(ConfidentNaiveDateTime::new(
+          NaiveDateTime::parse_from_str("20210501120000", "%Y%m%d%H%M%S").unwrap(),
+          DateConfidence::Millisecond,
+        )),
+      },
+      TestCase {
+        file_path: "/home/user/Threema/threema-20210501-134512345.jpg",
+        expected_result: Some(ConfidentNaiveDateTime::new(
+          NaiveDateTime::parse_from_str("20210501134512.345", "%Y%m%d%H%M%S%.3f").unwrap(),
+          DateConfidence::Millisecond,
+        )),
+      },
+      TestCase {
+        file_path: "/home/user/Threema/threema-20211301-120000000.jpg",
+        expected_result: None,
+      },
+    ]
+  });
+
+  #[test]
+  fn threema_filepath_regex() {
+    test_test_cases(
+      TESTS_THREEMA_FILEPATH.iter(),
+      get_date_from_threema_filepath_regex,
+    );
+  }
+}
@@ -1,10 +1,13 @@
 use std::{path::Path, sync::LazyLock};
 
-use chrono::DateTime;
+use chrono::{DateTime, FixedOffset};
 use regex::Regex;
 
 use super::{ConfidentNaiveDateTime, DateConfidence};
 
+pub static UNIX_TIMESTAMP_PREFIX_RE: LazyLock<Regex> =
+  LazyLock::new(|| Regex::new(r"^(\d{13})").unwrap());
+
 /// Extracts the date from unix timestamp prefixed image file paths.
 /// Example file paths:
 ///   * /storage/emulated/0/DCIM/Camera/1624280370243_postfix.jpg
@@ -12,15 +15,14 @@ pub fn get_date_from_unix_timestamp_prefixed_filepath_regex(
   _file_path: &Path,
   file_name: &str,
 ) -> Option<ConfidentNaiveDateTime> {
-  static RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(\d{13})").unwrap());
-  let captures = RE.captures(file_name)?;
+  let captures = UNIX_TIMESTAMP_PREFIX_RE.captures(file_name)?;
 
   let timestamp = captures.get(1)?.as_str().parse::<i64>().ok()?;
   let datetime = DateTime::from_timestamp(timestamp / 1000, 0)?;
-  Some(ConfidentNaiveDateTime::new(
-    datetime.naive_utc(),
-    DateConfidence::Second,
-  ))
+  Some(
+    ConfidentNaiveDateTime::new(datetime.naive_utc(), DateConfidence::Second)
+      .with_offset(FixedOffset::east_opt(0)?),
+  )
 }
 
 #[cfg(test)]
@@ -36,17 +38,23 @@ pub mod test {
       vec![
         TestCase {
           file_path: "/home/user/Pictures/1624280370243.jpg",
-          expected_result: Some(ConfidentNaiveDateTime::new(
-            NaiveDateTime::parse_from_str("20210621125930", "%Y%m%d%H%M%S").unwrap(),
-            DateConfidence::Second,
-          )),
+          expected_result: Some(
+            ConfidentNaiveDateTime::new(
+              NaiveDateTime::parse_from_str("20210621125930", "%Y%m%d%H%M%S").unwrap(),
+              DateConfidence::Second,
+            )
+            .with_offset(FixedOffset::east_opt(0).unwrap()),
+          ),
         },
         TestCase {
           file_path: "/home/user/Pictures/1624280370243_postfix.jpg",
-          expected_result: Some(ConfidentNaiveDateTime::new(
-            NaiveDateTime::parse_from_str("20210621125930", "%Y%m%d%H%M%S").unwrap(),
-            DateConfidence::Second,
-          )),
+          expected_result: Some(
+            ConfidentNaiveDateTime::new(
+              NaiveDateTime::parse_from_str("20210621125930", "%Y%m%d%H%M%S").unwrap(),
+              DateConfidence::Second,
+            )
+            .with_offset(FixedOffset::east_opt(0).unwrap()),
+          ),
         },
       ]
     });
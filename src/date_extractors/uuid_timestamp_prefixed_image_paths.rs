@@ -1,10 +1,14 @@
 use std::{path::Path, sync::LazyLock};
 
-use chrono::DateTime;
+use chrono::{DateTime, FixedOffset};
 use regex::Regex;
 
 use super::{ConfidentNaiveDateTime, DateConfidence};
 
+pub static UUID_TIMESTAMP_PREFIX_RE: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(r"^(\d+)-([0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12})").unwrap()
+});
+
 /// Extracts the date from uuid timestamp prefixed image file paths.
 /// Example file paths:
 ///   * /storage/emulated/0/DCIM/Camera/1606470461418-49b19a16-01a9-4a11-9789-e3005d827362postfix.jpg
@@ -12,17 +16,14 @@ pub fn get_date_from_uuid_prefixed_filepath_regex(
   _file_path: &Path,
   file_name: &str,
 ) -> Option<ConfidentNaiveDateTime> {
-  static RE: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(r"^(\d+)-([0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12})").unwrap()
-  });
-  let captures = RE.captures(file_name)?;
+  let captures = UUID_TIMESTAMP_PREFIX_RE.captures(file_name)?;
 
   let timestamp = captures.get(1)?.as_str().parse::<i64>().ok()?;
   let datetime = DateTime::from_timestamp(timestamp / 1000, 0)?;
-  Some(ConfidentNaiveDateTime::new(
-    datetime.naive_utc(),
-    DateConfidence::Second,
-  ))
+  Some(
+    ConfidentNaiveDateTime::new(datetime.naive_utc(), DateConfidence::Second)
+      .with_offset(FixedOffset::east_opt(0)?),
+  )
 }
 
 #[cfg(test)]
@@ -37,17 +38,23 @@ pub mod test {
       vec![
         TestCase {
           file_path: "/home/user/Pictures/1606470461418-49b19a16-01a9-4a11-9789-e3005d827362.jpg",
-          expected_result: Some(ConfidentNaiveDateTime::new(
-            NaiveDateTime::parse_from_str("20201127094741", "%Y%m%d%H%M%S").unwrap(),
-            DateConfidence::Second,
-          )),
+          expected_result: Some(
+            ConfidentNaiveDateTime::new(
+              NaiveDateTime::parse_from_str("20201127094741", "%Y%m%d%H%M%S").unwrap(),
+              DateConfidence::Second,
+            )
+            .with_offset(FixedOffset::east_opt(0).unwrap()),
+          ),
         },
         TestCase {
           file_path: "/home/user/Pictures/1606470461418-49b19a16-01a9-4a11-9789-e3005d827362postfix.jpg",
-          expected_result: Some(ConfidentNaiveDateTime::new(
-            NaiveDateTime::parse_from_str("20201127094741", "%Y%m%d%H%M%S").unwrap(),
-            DateConfidence::Second,
-          )),
+          expected_result: Some(
+            ConfidentNaiveDateTime::new(
+              NaiveDateTime::parse_from_str("20201127094741", "%Y%m%d%H%M%S").unwrap(),
+              DateConfidence::Second,
+            )
+            .with_offset(FixedOffset::east_opt(0).unwrap()),
+          ),
         },
       ]
     },
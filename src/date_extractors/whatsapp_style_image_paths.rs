@@ -5,6 +5,9 @@ use regex::Regex;
 
 use super::{ConfidentNaiveDateTime, DateConfidence};
 
+pub static WHATSAPP_RE: LazyLock<Regex> =
+  LazyLock::new(|| Regex::new(r"IMG-(\d{4})(\d{2})(\d{2})-WA\d+").unwrap());
+
 /// Extracts the date from WhatsApp-style filenames (e.g., IMG-20250127-WA0006.jpg).
 /// Example file paths:
 ///   * /storage/emulated/0/DCIM/Camera/IMG-20250127-WA0006<POSTFIX>.jpg
@@ -12,9 +15,7 @@ pub fn get_date_from_whatsapp_filepath_regex(
   _file_path: &Path,
   file_name: &str,
 ) -> Option<ConfidentNaiveDateTime> {
-  static RE: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"IMG-(\d{4})(\d{2})(\d{2})-WA\d+").unwrap());
-  let captures = RE.captures(file_name)?;
+  let captures = WHATSAPP_RE.captures(file_name)?;
 
   let year: u32 = captures.get(1)?.as_str().parse().ok()?;
   let month: u32 = captures.get(2)?.as_str().parse().ok()?;
@@ -0,0 +1,107 @@
+use std::{path::Path, sync::LazyLock};
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use regex::Regex;
+
+use super::{ConfidentNaiveDateTime, DateConfidence};
+
+pub static YEAR_IN_FOLDER_NAME_RE: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(r"(?:^|\D)(?P<year>(?:19|20)\d{2})(?:[-_.\s](?P<month>\d{2}))?(?:$|\D)").unwrap()
+});
+
+/// Extracts a year (and optional month) found anywhere in a folder name.
+/// This is too lenient for file names, so it is only used for folders.
+/// Example folder paths:
+///   * /Photos/Urlaub Italien 2018
+///   * /Photos/Italien 2018-07
+///   * /Photos/Sommer_2018_07_Italien
+///
+/// Unsupported:
+///   * /Photos/Urlaub 12018
+pub fn get_date_from_year_in_folder_name_regex(
+  _folder_path: &Path,
+  folder_name: &str,
+) -> Option<ConfidentNaiveDateTime> {
+  let captures = YEAR_IN_FOLDER_NAME_RE.captures(folder_name)?;
+
+  let year = captures.name("year")?.as_str().parse::<i32>().ok()?;
+  let month = captures
+    .name("month")
+    .and_then(|month| month.as_str().parse::<u32>().ok())
+    .filter(|month| (1..=12).contains(month));
+
+  let datetime = NaiveDateTime::new(
+    NaiveDate::from_ymd_opt(year, month.unwrap_or(1), 1)?,
+    NaiveTime::from_hms_opt(0, 0, 0)?,
+  );
+  let confidence = if month.is_some() {
+    DateConfidence::Month
+  } else {
+    DateConfidence::Year
+  };
+  Some(ConfidentNaiveDateTime::new(datetime, confidence))
+}
+
+#[cfg(test)]
+pub mod test {
+  use super::*;
+  use crate::date_extractors::test::{TestCase, test_test_cases};
+  use std::sync::LazyLock;
+
+  pub static TESTS_YEAR_IN_FOLDER_NAME: LazyLock<Vec<TestCase>> = LazyLock::new(|| {
+    vec![
+      TestCase {
+        file_path: "/Photos/Urlaub Italien 2018",
+        expected_result: Some(ConfidentNaiveDateTime::new(
+          NaiveDate::parse_from_str("20180101", "%Y%m%d")
+            .unwrap()
+            .into(),
+          DateConfidence::Year,
+        )),
+      },
+      TestCase {
+        file_path: "/Photos/Italien 2018-07",
+        expected_result: Some(ConfidentNaiveDateTime::new(
+          NaiveDate::parse_from_str("20180701", "%Y%m%d")
+            .unwrap()
+            .into(),
+          DateConfidence::Month,
+        )),
+      },
+      TestCase {
+        file_path: "/Photos/Sommer_2018_07_Italien",
+        expected_result: Some(ConfidentNaiveDateTime::new(
+          NaiveDate::parse_from_str("20180701", "%Y%m%d")
+            .unwrap()
+            .into(),
+          DateConfidence::Month,
+        )),
+      },
+      TestCase {
+        file_path: "/Photos/Italien 2018 13",
+        expected_result: Some(ConfidentNaiveDateTime::new(
+          NaiveDate::parse_from_str("20180101", "%Y%m%d")
+            .unwrap()
+            .into(),
+          DateConfidence::Year,
+        )),
+      },
+      TestCase {
+        file_path: "/Photos/Urlaub 12018",
+        expected_result: None,
+      },
+      TestCase {
+        file_path: "/Photos/Urlaub Italien",
+        expected_result: None,
+      },
+    ]
+  });
+
+  #[test]
+  fn year_in_folder_name_regex() {
+    test_test_cases(
+      TESTS_YEAR_IN_FOLDER_NAME.iter(),
+      get_date_from_year_in_folder_name_regex,
+    );
+  }
+}
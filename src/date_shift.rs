@@ -0,0 +1,228 @@
+use core::{str::FromStr, sync::atomic::Ordering};
+use std::path::Path;
+
+use anyhow::Context as _;
+use chrono::{Months, NaiveDateTime, TimeDelta};
+use tracing::{debug, info};
+
+use crate::{
+  ProcessState,
+  date_extractors::{ConfidentNaiveDateTime, DateConfidence},
+  errors::ErrorWithFilePath,
+  event_stream::{FileEvent, emit_event},
+  exiftool::{
+    exiftool_writable_file_extensions, get_exif_dates, set_exif_date, wrap_with_exiftool_repair,
+  },
+  get_modified_time, set_modified_time,
+};
+
+/// A signed offset like `+1y 3h` or `-30m` that dates are shifted by, e.g. to correct a misconfigured camera clock.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct DateShift {
+  negative: bool,
+  months: u32,
+  delta: TimeDelta,
+}
+
+impl FromStr for DateShift {
+  type Err = anyhow::Error;
+
+  /// Units: `y` years, `mo` months, `w` weeks, `d` days, `h` hours, `m` minutes and `s` seconds.
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let invalid = || anyhow::anyhow!("Invalid date shift: {s}, expected e.g. +1y 3h or -30m");
+
+    let s = s.trim();
+    let (negative, mut rest) = match s.strip_prefix('-') {
+      Some(rest) => (true, rest),
+      None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    if rest.trim().is_empty() {
+      return Err(invalid());
+    }
+    let mut months = 0_u32;
+    let mut delta = TimeDelta::zero();
+    while !rest.is_empty() {
+      rest = rest.trim_start();
+      let digits_end = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+      let value = rest[..digits_end].parse::<u32>().map_err(|_| invalid())?;
+      rest = &rest[digits_end..];
+      let unit_end = rest
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(rest.len());
+      let unit = rest[..unit_end].to_ascii_lowercase();
+      rest = rest[unit_end..].trim_start();
+      match unit.as_str() {
+        "y" => {
+          months = months
+            .checked_add(value.checked_mul(12).ok_or_else(invalid)?)
+            .ok_or_else(invalid)?;
+        },
+        "mo" => months = months.checked_add(value).ok_or_else(invalid)?,
+        unit => {
+          let unit_delta = match unit {
+            "w" => TimeDelta::try_weeks(value.into()),
+            "d" => TimeDelta::try_days(value.into()),
+            "h" => TimeDelta::try_hours(value.into()),
+            "m" => TimeDelta::try_minutes(value.into()),
+            "s" => TimeDelta::try_seconds(value.into()),
+            _ => None,
+          }
+          .ok_or_else(invalid)?;
+          delta = delta.checked_add(&unit_delta).ok_or_else(invalid)?;
+        },
+      }
+    }
+    Ok(Self {
+      negative,
+      months,
+      delta,
+    })
+  }
+}
+
+impl DateShift {
+  /// Shifts a date, `None` if the result is out of range.
+  /// Months are shifted first, so that `+1mo` keeps the time of day and clamps to the end of shorter months.
+  #[must_use]
+  pub fn apply(&self, date: &NaiveDateTime) -> Option<NaiveDateTime> {
+    if self.negative {
+      date
+        .checked_sub_months(Months::new(self.months))?
+        .checked_sub_signed(self.delta)
+    } else {
+      date
+        .checked_add_months(Months::new(self.months))?
+        .checked_add_signed(self.delta)
+    }
+  }
+}
+
+/// Shifts the EXIF date, and with `--mtime` the modified time, of a file instead of processing it.
+/// Files without an EXIF date are left alone.
+pub fn shift_file(
+  file_path: &Path,
+  date_shift: &DateShift,
+  shift_modified_time: bool,
+  process_state: &ProcessState,
+) -> Result<(), ErrorWithFilePath> {
+  let is_media_file = file_path
+    .extension()
+    .and_then(|ext| ext.to_str())
+    .map(str::to_ascii_uppercase)
+    .is_some_and(|ext| {
+      exiftool_writable_file_extensions().is_ok_and(|extensions| extensions.contains(&ext))
+    });
+  if !is_media_file {
+    return Ok(());
+  }
+  process_state
+    .stat_media_files_checked
+    .fetch_add(1, Ordering::Relaxed);
+
+  let exif_dates = match process_state.exif_dates_cache.take(file_path) {
+    Some(exif_dates) => exif_dates,
+    None => wrap_with_exiftool_repair(
+      file_path,
+      process_state.options.repair_exif_errors,
+      process_state.options.dry_run,
+      || get_exif_dates(file_path, process_state.options.ignore_minor_exif_errors),
+    )?,
+  };
+  let Some((tag, original_date)) = exif_dates.original() else {
+    debug!(
+      file_path = %file_path.display(),
+      "Not shifting the file, which has no EXIF date",
+    );
+    return Ok(());
+  };
+  let shifted_date = date_shift
+    .apply(&original_date)
+    .with_context(|| format!("Shifting {original_date} is out of range"))
+    .map_err(ErrorWithFilePath::from_source(file_path))?;
+  // The modified time is read before the write changes it.
+  let original_modified_time = if shift_modified_time {
+    Some(get_modified_time(file_path)?)
+  } else {
+    None
+  };
+
+  info!(
+    file_path = %file_path.display(),
+    "Shifting EXIF date from {tag} {} to {}",
+    original_date.format("%Y-%m-%d %H:%M:%S"),
+    shifted_date.format("%Y-%m-%d %H:%M:%S"),
+  );
+  set_exif_date(
+    file_path,
+    &ConfidentNaiveDateTime::new(shifted_date, DateConfidence::Second),
+    "shift",
+    process_state.options.exif_write_options(),
+  )?;
+  emit_event(&FileEvent::overwritten(
+    file_path,
+    &original_date,
+    &shifted_date,
+    "shift",
+  ));
+  process_state
+    .stat_exif_overwritten
+    .fetch_add(1, Ordering::Relaxed);
+  process_state.stat_extensions.record_updated(file_path);
+  process_state.stat_exif_years.record(&shifted_date);
+
+  if let Some(original_modified_time) = original_modified_time {
+    let shifted_modified_time = date_shift
+      .apply(&original_modified_time)
+      .with_context(|| {
+        format!("Shifting the modified time {original_modified_time} is out of range")
+      })
+      .map_err(ErrorWithFilePath::from_source(file_path))?;
+    set_modified_time(file_path, &shifted_modified_time, process_state)
+      .map_err(ErrorWithFilePath::from_source(file_path))?;
+    process_state
+      .stat_modified_time_updated
+      .fetch_add(1, Ordering::Relaxed);
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn date(date: &str) -> NaiveDateTime {
+    NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S").unwrap()
+  }
+
+  #[test]
+  fn date_shifts() {
+    let shift = |shift: &str, from: &str| {
+      DateShift::from_str(shift)
+        .unwrap()
+        .apply(&date(from))
+        .unwrap()
+    };
+    assert_eq!(
+      shift("+1y 3h", "2019-08-18 22:30:00"),
+      date("2020-08-19 01:30:00")
+    );
+    assert_eq!(
+      shift("-1y 3h", "2020-08-19 01:30:00"),
+      date("2019-08-18 22:30:00")
+    );
+    assert_eq!(
+      shift("1mo", "2019-01-31 12:00:00"),
+      date("2019-02-28 12:00:00")
+    );
+    assert_eq!(
+      shift("-1w2d30m15s", "2019-08-18 00:00:00"),
+      date("2019-08-08 23:29:45")
+    );
+    assert!(DateShift::from_str("").is_err());
+    assert!(DateShift::from_str("+").is_err());
+    assert!(DateShift::from_str("3x").is_err());
+    assert!(DateShift::from_str("1y-3h").is_err());
+  }
+}
@@ -0,0 +1,144 @@
+use alloc::collections::BTreeMap;
+use core::cmp::Reverse;
+use std::{
+  io,
+  path::{Path, PathBuf},
+  sync::Mutex,
+};
+
+/// How many paths are shown per error category.
+const EXAMPLE_COUNT: usize = 3;
+
+#[derive(Default)]
+struct ErrorCategory {
+  count: usize,
+  examples: Vec<PathBuf>,
+}
+
+/// Buckets the errors of a run by their cause, so they can be triaged without reading the whole log.
+pub struct ErrorSummary {
+  categories: Mutex<BTreeMap<String, ErrorCategory>>,
+}
+
+/// I/O errors are grouped by their kind, other errors by their root cause
+/// with quoted parts and numbers masked, as those usually differ per file.
+fn error_category(error: &anyhow::Error) -> String {
+  if let Some(io_error) = error
+    .chain()
+    .find_map(|cause| cause.downcast_ref::<io::Error>())
+  {
+    return format!("I/O error: {}", io_error.kind());
+  }
+  let message = error.root_cause().to_string();
+  let mut category = String::new();
+  let mut in_quotes = false;
+  let mut chars = message
+    .lines()
+    .next()
+    .unwrap_or_default()
+    .chars()
+    .peekable();
+  while let Some(c) = chars.next() {
+    if c == '"' {
+      in_quotes = !in_quotes;
+      if in_quotes {
+        category.push_str("\"...\"");
+      }
+    } else if in_quotes {
+      // The quoted part is masked.
+    } else if c.is_ascii_digit() {
+      while chars.next_if(char::is_ascii_digit).is_some() {}
+      category.push('N');
+    } else {
+      category.push(c);
+    }
+  }
+  category
+}
+
+impl ErrorSummary {
+  #[must_use]
+  pub const fn new() -> Self {
+    Self {
+      categories: Mutex::new(BTreeMap::new()),
+    }
+  }
+
+  pub fn record_error(&self, file_path: &Path, error: &anyhow::Error) {
+    let category_name = error_category(error);
+    let mut categories = self
+      .categories
+      .lock()
+      .expect("Error summary lock should not be poisoned");
+    let category = categories.entry(category_name).or_default();
+    category.count += 1;
+    if category.examples.len() < EXAMPLE_COUNT {
+      category.examples.push(file_path.to_path_buf());
+    }
+    drop(categories);
+  }
+
+  /// One line per category, most frequent first, each followed by indented example paths.
+  #[must_use]
+  pub fn lines(&self) -> Vec<String> {
+    let categories = self
+      .categories
+      .lock()
+      .expect("Error summary lock should not be poisoned");
+    let mut sorted_categories = categories.iter().collect::<Vec<_>>();
+    sorted_categories.sort_by_key(|(_, category)| Reverse(category.count));
+
+    let mut lines = Vec::new();
+    for (name, category) in sorted_categories {
+      lines.push(format!("{}: {name}", category.count));
+      lines.extend(
+        category
+          .examples
+          .iter()
+          .map(|file_path| format!("  {}", file_path.display())),
+      );
+      if category.count > category.examples.len() {
+        lines.push(format!(
+          "  ... and {} more",
+          category.count - category.examples.len()
+        ));
+      }
+    }
+    drop(categories);
+    lines
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use anyhow::Context as _;
+
+  use super::*;
+
+  #[test]
+  fn error_categories() {
+    let summary = ErrorSummary::new();
+    for index in 0..5 {
+      let error = Err::<(), _>(io::Error::from(io::ErrorKind::PermissionDenied))
+        .context(format!("Failed to set modified time of file {index}"))
+        .unwrap_err();
+      summary.record_error(&PathBuf::from(format!("/photos/{index}.jpg")), &error);
+    }
+    summary.record_error(
+      Path::new("/photos/scan.jpg"),
+      &anyhow::anyhow!("Invalid EXIF date \"2019:13:45 00:00:00\" in tag 36867"),
+    );
+    assert_eq!(
+      summary.lines(),
+      [
+        "5: I/O error: permission denied",
+        "  /photos/0.jpg",
+        "  /photos/1.jpg",
+        "  /photos/2.jpg",
+        "  ... and 2 more",
+        "1: Invalid EXIF date \"...\" in tag N",
+        "  /photos/scan.jpg",
+      ]
+    );
+  }
+}
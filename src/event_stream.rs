@@ -0,0 +1,164 @@
+use std::{
+  fs::File,
+  io::Write as _,
+  path::{Path, PathBuf},
+  sync::{Mutex, OnceLock},
+};
+
+use anyhow::Context as _;
+use chrono::{NaiveDateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use tracing::warn;
+
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// A decision about a file, written as one JSON line of the event stream.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum FileEvent {
+  /// An EXIF date was written to a file without one.
+  Guessed {
+    path: PathBuf,
+    date: String,
+    source: String,
+  },
+  /// An existing EXIF date was replaced.
+  Overwritten {
+    path: PathBuf,
+    old_date: String,
+    date: String,
+    source: String,
+  },
+  Skipped {
+    path: PathBuf,
+    reason: String,
+  },
+  Error {
+    path: PathBuf,
+    error: String,
+  },
+}
+
+impl FileEvent {
+  #[must_use]
+  pub fn guessed(path: &Path, date: &NaiveDateTime, source: &str) -> Self {
+    Self::Guessed {
+      path: path.to_path_buf(),
+      date: date.format(DATE_FORMAT).to_string(),
+      source: source.to_string(),
+    }
+  }
+
+  #[must_use]
+  pub fn overwritten(
+    path: &Path,
+    old_date: &NaiveDateTime,
+    date: &NaiveDateTime,
+    source: &str,
+  ) -> Self {
+    Self::Overwritten {
+      path: path.to_path_buf(),
+      old_date: old_date.format(DATE_FORMAT).to_string(),
+      date: date.format(DATE_FORMAT).to_string(),
+      source: source.to_string(),
+    }
+  }
+
+  #[must_use]
+  pub fn skipped(path: &Path, reason: &str) -> Self {
+    Self::Skipped {
+      path: path.to_path_buf(),
+      reason: reason.to_string(),
+    }
+  }
+
+  #[must_use]
+  pub fn error(path: &Path, error: &anyhow::Error) -> Self {
+    Self::Error {
+      path: path.to_path_buf(),
+      error: format!("{error:#}"),
+    }
+  }
+}
+
+#[derive(Serialize)]
+struct EventLine<'a> {
+  time: String,
+  #[serde(flatten)]
+  event: &'a FileEvent,
+}
+
+fn event_line(time: &chrono::DateTime<Utc>, event: &FileEvent) -> String {
+  let line = EventLine {
+    time: time.to_rfc3339_opts(SecondsFormat::Millis, true),
+    event,
+  };
+  let mut line = serde_json::to_string(&line).expect("File events should be serializable");
+  line.push('\n');
+  line
+}
+
+/// A file or FIFO receiving one JSON line per decision as it happens, so a wrapping UI or script can follow the run.
+struct EventStream {
+  file: Mutex<File>,
+}
+
+static EVENT_STREAM: OnceLock<EventStream> = OnceLock::new();
+
+/// Writes the events from now on to `events_path`. Opening a FIFO waits until it has a reader.
+pub fn install_event_stream(events_path: &Path) -> anyhow::Result<()> {
+  let file = File::create(events_path).with_context(|| {
+    format!(
+      "\"{}\": Failed to open the event stream",
+      events_path.display()
+    )
+  })?;
+  let _ = EVENT_STREAM.set(EventStream {
+    file: Mutex::new(file),
+  });
+  Ok(())
+}
+
+/// Writes the event to the `--events-out` file, if enabled.
+pub fn emit_event(event: &FileEvent) {
+  let Some(event_stream) = EVENT_STREAM.get() else {
+    return;
+  };
+  let line = event_line(&Utc::now(), event);
+  let mut file = event_stream
+    .file
+    .lock()
+    .expect("Event stream lock should not be poisoned");
+  // Written unbuffered, so the reader sees every event right away.
+  if let Err(e) = file.write_all(line.as_bytes()) {
+    warn!("Failed to write to the event stream: {e}");
+  }
+  drop(file);
+}
+
+#[cfg(test)]
+mod test {
+  use chrono::TimeZone as _;
+
+  use super::*;
+
+  #[test]
+  fn event_lines() {
+    let time = Utc.with_ymd_and_hms(2024, 5, 1, 8, 30, 0).unwrap();
+    let date = NaiveDateTime::parse_from_str("2019-08-18 14:03:00", DATE_FORMAT).unwrap();
+    assert_eq!(
+      event_line(
+        &time,
+        &FileEvent::guessed(Path::new("/photos/IMG_1.jpg"), &date, "filename")
+      ),
+      "{\"time\":\"2024-05-01T08:30:00.000Z\",\"event\":\"guessed\",\"path\":\"/photos/IMG_1.jpg\",\"date\":\"2019-08-18 14:03:00\",\"source\":\"filename\"}\n"
+    );
+    assert_eq!(
+      event_line(
+        &time,
+        &FileEvent::skipped(Path::new("/photos/IMG_1.xmp"), "companion file")
+      ),
+      "{\"time\":\"2024-05-01T08:30:00.000Z\",\"event\":\"skipped\",\"path\":\"/photos/IMG_1.xmp\",\"reason\":\"companion file\"}\n"
+    );
+  }
+}
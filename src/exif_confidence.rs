@@ -0,0 +1,256 @@
+use core::str::FromStr;
+
+use chrono::{Datelike as _, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Timelike as _};
+
+use crate::{
+  date_extractors::{ConfidentNaiveDateTime, DateConfidence},
+  exiftool::ExifDates,
+};
+
+/// Dates that cameras and tools write when their clock was never set or the date was lost.
+const PLACEHOLDER_DATES: [(i32, u32, u32); 5] = [
+  // Unix epoch and the day after, which old modified times used to be clamped to.
+  (1970, 1, 1),
+  (1970, 1, 2),
+  // FAT/DOS epoch, used by many cameras with a reset clock.
+  (1980, 1, 1),
+  (2000, 1, 1),
+  (2001, 1, 1),
+];
+
+/// How the confidence of a date read from EXIF is determined.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum ExifConfidencePolicy {
+  /// Real EXIF dates are trusted to the second, unless they are a known placeholder date.
+  #[default]
+  Placeholder,
+  /// The confidence is derived from the least significant nonzero date component.
+  /// Dates at midnight or on January 1st are considered imprecise.
+  Precision,
+  /// Every EXIF date is trusted to the second.
+  Trust,
+  /// Like `Placeholder`, but the confidence is lowered the further `CreateDate` and `ModifyDate` diverge from `DateTimeOriginal`.
+  Agreement,
+}
+
+impl FromStr for ExifConfidencePolicy {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "placeholder" => Ok(Self::Placeholder),
+      "precision" => Ok(Self::Precision),
+      "trust" => Ok(Self::Trust),
+      "agreement" => Ok(Self::Agreement),
+      _ => Err(anyhow::anyhow!("Unknown EXIF confidence policy: {s}")),
+    }
+  }
+}
+
+impl ExifConfidencePolicy {
+  /// The most trustworthy date of `dates` (see [`ExifDates::original`]) with its confidence.
+  #[must_use]
+  pub fn apply(self, dates: &ExifDates) -> Option<ConfidentNaiveDateTime> {
+    let (_, date) = dates.original()?;
+    let mut confidence = self.confidence_of(&date);
+    if self == Self::Agreement {
+      confidence = confidence.min(agreement_confidence(&date, dates));
+    }
+    Some(ConfidentNaiveDateTime::new(date, confidence))
+  }
+
+  /// The confidence of a single EXIF date, without looking at other tags.
+  #[must_use]
+  pub fn confidence_of(self, date: &NaiveDateTime) -> DateConfidence {
+    match self {
+      Self::Placeholder | Self::Agreement if is_placeholder_date(date) => DateConfidence::None,
+      Self::Placeholder | Self::Agreement | Self::Trust => {
+        if date.nanosecond() == 0 {
+          DateConfidence::Second
+        } else {
+          DateConfidence::Millisecond
+        }
+      },
+      Self::Precision => confidence_from_precision(date),
+    }
+  }
+}
+
+/// Whether `date` is exactly midnight of a known placeholder date.
+#[must_use]
+fn is_placeholder_date(date: &NaiveDateTime) -> bool {
+  date.time() == NaiveTime::MIN
+    && PLACEHOLDER_DATES
+      .iter()
+      .any(|&(year, month, day)| NaiveDate::from_ymd_opt(year, month, day) == Some(date.date()))
+}
+
+/// How much the other date tags corroborate `date`.
+/// Tags that are missing do not lower the confidence.
+#[must_use]
+fn agreement_confidence(date: &NaiveDateTime, dates: &ExifDates) -> DateConfidence {
+  let max_divergence = [
+    dates.create_date,
+    dates.media_create_date,
+    dates.xmp_date_created,
+    dates.png_creation_time,
+    dates.modify_date,
+  ]
+  .into_iter()
+  .flatten()
+  .map(|other| (other - *date).abs())
+  .max()
+  .unwrap_or_default();
+  if max_divergence <= TimeDelta::seconds(2) {
+    DateConfidence::Millisecond
+  } else if max_divergence <= TimeDelta::days(1) {
+    // Most likely a time zone mixup or a later edit on the same day.
+    DateConfidence::Day
+  } else if max_divergence <= TimeDelta::days(31) {
+    DateConfidence::Month
+  } else if max_divergence <= TimeDelta::days(366) {
+    DateConfidence::Year
+  } else {
+    DateConfidence::None
+  }
+}
+
+#[must_use]
+fn confidence_from_precision(date: &NaiveDateTime) -> DateConfidence {
+  if is_placeholder_date(date) {
+    return DateConfidence::None;
+  }
+  if date.nanosecond() != 0 {
+    return DateConfidence::Millisecond;
+  }
+  if date.second() != 0 {
+    return DateConfidence::Second;
+  }
+  if date.minute() != 0 {
+    return DateConfidence::Minute;
+  }
+  if date.hour() != 0 {
+    return DateConfidence::Hour;
+  }
+  if date.day() != 1 {
+    return DateConfidence::Day;
+  }
+  if date.month() != 1 {
+    return DateConfidence::Month;
+  }
+  if date.year() % 10 != 0 {
+    return DateConfidence::Year;
+  }
+  DateConfidence::Decade
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn date(date: &str) -> NaiveDateTime {
+    NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S%.f").unwrap()
+  }
+
+  #[test]
+  fn placeholder_policy() {
+    let policy = ExifConfidencePolicy::Placeholder;
+    assert_eq!(
+      policy.confidence_of(&date("2020-01-01 00:00:00.0")),
+      DateConfidence::Second
+    );
+    assert_eq!(
+      policy.confidence_of(&date("2019-08-18 14:30:00.250")),
+      DateConfidence::Millisecond
+    );
+    assert_eq!(
+      policy.confidence_of(&date("2000-01-01 00:00:00.0")),
+      DateConfidence::None
+    );
+    assert_eq!(
+      policy.confidence_of(&date("1970-01-02 00:00:00.0")),
+      DateConfidence::None
+    );
+    assert_eq!(
+      policy.confidence_of(&date("2000-01-01 00:00:01.0")),
+      DateConfidence::Second
+    );
+  }
+
+  #[test]
+  fn precision_policy() {
+    let policy = ExifConfidencePolicy::Precision;
+    assert_eq!(
+      policy.confidence_of(&date("2020-01-01 00:00:00.0")),
+      DateConfidence::Decade
+    );
+    assert_eq!(
+      policy.confidence_of(&date("2019-08-01 00:00:00.0")),
+      DateConfidence::Month
+    );
+    assert_eq!(
+      policy.confidence_of(&date("2019-08-18 14:30:12.0")),
+      DateConfidence::Second
+    );
+    assert_eq!(
+      policy.confidence_of(&date("1980-01-01 00:00:00.0")),
+      DateConfidence::None
+    );
+    assert_eq!(
+      policy.confidence_of(&date("1970-01-02 00:00:00.0")),
+      DateConfidence::None
+    );
+  }
+
+  #[test]
+  fn agreement_policy() {
+    let policy = ExifConfidencePolicy::Agreement;
+    let dates = |create_date: Option<&str>, modify_date: Option<&str>| ExifDates {
+      date_time_original: Some(date("2019-08-18 14:30:00.0")),
+      create_date: create_date.map(date),
+      modify_date: modify_date.map(date),
+      ..ExifDates::default()
+    };
+    let confidence = |dates: ExifDates| policy.apply(&dates).map(|date| date.confidence);
+
+    assert_eq!(confidence(dates(None, None)), Some(DateConfidence::Second));
+    assert_eq!(
+      confidence(dates(
+        Some("2019-08-18 14:30:00.0"),
+        Some("2019-08-18 14:30:01.0")
+      )),
+      Some(DateConfidence::Second)
+    );
+    assert_eq!(
+      confidence(dates(Some("2019-08-18 12:30:00.0"), None)),
+      Some(DateConfidence::Day)
+    );
+    assert_eq!(
+      confidence(dates(None, Some("2019-09-01 10:00:00.0"))),
+      Some(DateConfidence::Month)
+    );
+    assert_eq!(
+      confidence(dates(None, Some("2023-01-01 10:00:00.0"))),
+      Some(DateConfidence::None)
+    );
+    assert_eq!(policy.apply(&ExifDates::default()), None);
+
+    // Without DateTimeOriginal, the fallback tags are used.
+    let dates = ExifDates {
+      modify_date: Some(date("2019-08-18 14:30:00.0")),
+      ..ExifDates::default()
+    };
+    assert_eq!(
+      policy.apply(&dates).map(|date| date.date),
+      dates.modify_date
+    );
+  }
+
+  #[test]
+  fn trust_policy() {
+    assert_eq!(
+      ExifConfidencePolicy::Trust.confidence_of(&date("2000-01-01 00:00:00.0")),
+      DateConfidence::Second
+    );
+  }
+}
@@ -1,21 +1,40 @@
-use alloc::{borrow::Cow, collections::BTreeSet};
-use core::cell::RefCell;
+use alloc::{
+  borrow::Cow,
+  collections::{BTreeMap, BTreeSet},
+  sync::Arc,
+};
+use core::{
+  num::NonZeroUsize,
+  sync::atomic::{AtomicBool, Ordering},
+};
 use std::{
   io::{BufRead, BufReader, Write},
-  path::Path,
+  path::{Path, PathBuf},
   process::{Child, Command, Stdio},
-  sync::OnceLock,
+  sync::{Mutex, OnceLock, mpsc},
+  thread,
 };
 
 use anyhow::Context as _;
-use chrono::NaiveDateTime;
+use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, Utc};
 use tracing::info;
 
-use crate::{errors::ErrorWithFilePath, tie_command_to_self::tie_command_to_self};
-
-thread_local! {
-  static EXIFTOOL: RefCell<RespawningExifToolWorker> = const { RefCell::new(RespawningExifToolWorker::new()) };
-}
+#[cfg(feature = "native-exif-read")]
+use crate::native_exif::read_exif_dates_natively;
+#[cfg(feature = "native-jpeg-write")]
+use crate::native_exif::{NATIVE_WRITE_EXTENSIONS, write_date_time_original_natively};
+use crate::{
+  backups::{PendingBackup, backups},
+  busy_retry::{is_busy_exiftool_output, retry_while_busy},
+  date_extractors::{ConfidentNaiveDateTime, DateConfidence},
+  errors::ErrorWithFilePath,
+  provenance::{
+    DATE_SOURCE_TAG, date_source_value, parse_date_source_confidence, provenance_config_path,
+  },
+  tie_command_to_self::tie_command_to_self,
+  timezone::GpsPosition,
+  undo_journal::undo_journal,
+};
 
 struct CommandOutput {
   stdout: String,
@@ -30,7 +49,11 @@ struct ExifToolWorker {
 
 impl ExifToolWorker {
   fn new() -> anyhow::Result<Self> {
-    let mut command = Command::new("exiftool");
+    let mut command = exiftool_command();
+    // -config must be the first argument.
+    if let Some(config_path) = provenance_config_path() {
+      command.arg("-config").arg(config_path);
+    }
     command
       .arg("-stay_open")
       .arg("True")
@@ -43,6 +66,10 @@ impl ExifToolWorker {
     tie_command_to_self(&mut command);
 
     let mut process = command.spawn().context("Failed to spawn exiftool")?;
+    EXIFTOOL_WORKER_PIDS
+      .lock()
+      .expect("exiftool worker pids lock should not be poisoned")
+      .insert(process.id());
 
     let stdout = process
       .stdout
@@ -155,6 +182,52 @@ impl Drop for ExifToolWorker {
       let _ = writeln!(stdin, "-stay_open\nFalse");
     }
     let _ = self.process.wait();
+    EXIFTOOL_WORKER_PIDS
+      .lock()
+      .expect("exiftool worker pids lock should not be poisoned")
+      .remove(&self.process.id());
+  }
+}
+
+/// The process ids of the running exiftool workers, so they can be killed on a forced abort.
+static EXIFTOOL_WORKER_PIDS: Mutex<BTreeSet<u32>> = Mutex::new(BTreeSet::new());
+
+#[cfg(unix)]
+#[expect(
+  unsafe_code,
+  reason = "There is no safe API to send a signal to a process that is only known by its process id."
+)]
+fn kill_process(pid: u32) {
+  let Ok(pid) = libc::pid_t::try_from(pid) else {
+    return;
+  };
+  // SAFETY: kill only sends a signal and does not access any memory of this process.
+  unsafe {
+    libc::kill(pid, libc::SIGKILL);
+  }
+}
+
+/// Windows kills the child processes along with this process.
+#[cfg(not(unix))]
+const fn kill_process(_pid: u32) {}
+
+/// Lets the tests stand in for an exiftool worker with another process.
+#[cfg(test)]
+pub fn track_exiftool_worker(pid: u32) {
+  EXIFTOOL_WORKER_PIDS
+    .lock()
+    .expect("exiftool worker pids lock should not be poisoned")
+    .insert(pid);
+}
+
+/// Kills the exiftool workers right away, so a hung exiftool can not keep the process alive on a forced abort.
+pub fn kill_exiftool_workers() {
+  let pids = EXIFTOOL_WORKER_PIDS
+    .lock()
+    .expect("exiftool worker pids lock should not be poisoned")
+    .clone();
+  for pid in pids {
+    kill_process(pid);
   }
 }
 
@@ -184,106 +257,1007 @@ impl RespawningExifToolWorker {
   }
 }
 
+struct ExifToolRequest {
+  args: Vec<String>,
+  response_sender: mpsc::Sender<anyhow::Result<CommandOutput>>,
+}
+
+/// A fixed number of exiftool workers that execute requests sent over a channel.
+/// Each worker owns one exiftool process, which is respawned if it exits unexpectedly.
+struct ExifToolPool {
+  request_sender: mpsc::Sender<ExifToolRequest>,
+  #[cfg(test)]
+  jobs: usize,
+}
+
+impl ExifToolPool {
+  fn new(jobs: usize) -> Self {
+    let (request_sender, request_receiver) = mpsc::channel::<ExifToolRequest>();
+    let request_receiver = Arc::new(Mutex::new(request_receiver));
+    for index in 0..jobs {
+      let request_receiver = request_receiver.clone();
+      thread::Builder::new()
+        .name(format!("exiftool-{index}"))
+        .spawn(move || {
+          let mut worker = RespawningExifToolWorker::new();
+          loop {
+            // Only hold the lock while waiting for a request, so the other workers can pick up the next one.
+            let request = request_receiver
+              .lock()
+              .expect("exiftool request lock should not be poisoned")
+              .recv();
+            let Ok(request) = request else {
+              return;
+            };
+            let _ = request.response_sender.send(worker.execute(&request.args));
+          }
+        })
+        .expect("Failed to spawn exiftool worker thread");
+    }
+    Self {
+      request_sender,
+      #[cfg(test)]
+      jobs,
+    }
+  }
+
+  fn execute(&self, args: &[impl AsRef<str>]) -> anyhow::Result<CommandOutput> {
+    let (response_sender, response_receiver) = mpsc::channel();
+    self
+      .request_sender
+      .send(ExifToolRequest {
+        args: args.iter().map(|arg| arg.as_ref().to_string()).collect(),
+        response_sender,
+      })
+      .context("The exiftool workers stopped")?;
+    response_receiver
+      .recv()
+      .context("The exiftool worker stopped before responding")?
+  }
+}
+
+static EXIFTOOL_POOL: OnceLock<ExifToolPool> = OnceLock::new();
+
+/// Starts `jobs` exiftool workers, or one per CPU if `jobs` is `None`.
+/// Must be called before exiftool is run for the first time, fails if the pool is already running.
+pub fn install_exiftool_pool(jobs: Option<usize>) -> anyhow::Result<()> {
+  let jobs = jobs.unwrap_or_else(|| thread::available_parallelism().map_or(1, NonZeroUsize::get));
+  let mut started = false;
+  EXIFTOOL_POOL.get_or_init(|| {
+    started = true;
+    ExifToolPool::new(jobs.max(1))
+  });
+  anyhow::ensure!(started, "The exiftool workers were already started");
+  Ok(())
+}
+
+/// The number of exiftool workers, `None` if they were not started.
+#[cfg(test)]
+pub fn exiftool_pool_jobs() -> Option<usize> {
+  EXIFTOOL_POOL.get().map(|pool| pool.jobs)
+}
+
+/// Executes an exiftool command on the worker pool started by [`install_exiftool_pool`].
+fn execute(args: &[impl AsRef<str>]) -> anyhow::Result<CommandOutput> {
+  EXIFTOOL_POOL
+    .get()
+    .context("The exiftool workers were not started")?
+    .execute(args)
+}
+
+/// Runs an exiftool command that writes `file_path`, retrying while the file is locked by another process.
+fn execute_write(file_path: &Path, args: &[impl AsRef<str>]) -> anyhow::Result<CommandOutput> {
+  retry_while_busy(
+    file_path,
+    || execute(args),
+    |result| {
+      result
+        .as_ref()
+        .is_ok_and(|output| is_busy_exiftool_output(&output.stderr))
+    },
+  )
+}
+
+/// The oldest exiftool version that supports all options used, such as `-echo4` and `-api QuickTimeUTC`.
+pub const MIN_EXIFTOOL_VERSION: (u32, u32) = (10, 80);
+
+static EXIFTOOL_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// Runs `exiftool_path` instead of the `exiftool` found in `PATH`.
+/// Has no effect once exiftool has been started.
+pub fn set_exiftool_path(exiftool_path: PathBuf) {
+  let _ = EXIFTOOL_PATH.set(exiftool_path);
+}
+
+static EXIFTOOL_DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// Reads and writes dates without exiftool from now on, for systems where it is not installed.
+/// Only `DateTimeOriginal` of JPEG files can be written then.
+#[cfg(feature = "native-jpeg-write")]
+pub fn disable_exiftool() {
+  EXIFTOOL_DISABLED.store(true, Ordering::Relaxed);
+}
+
+#[must_use]
+fn exiftool_disabled() -> bool {
+  EXIFTOOL_DISABLED.load(Ordering::Relaxed)
+}
+
+#[must_use]
+fn exiftool_command() -> Command {
+  let mut command = match EXIFTOOL_PATH.get() {
+    Some(exiftool_path) => Command::new(exiftool_path),
+    None => Command::new("exiftool"),
+  };
+  // A Ctrl+C in the terminal is sent to the whole process group. exiftool is kept out of it,
+  // so that the writes in progress are finished during a graceful shutdown.
+  #[cfg(unix)]
+  std::os::unix::process::CommandExt::process_group(&mut command, 0);
+  command
+}
+
+/// Parses the output of `exiftool -ver`, e.g. `12.76`.
 #[must_use]
-pub fn has_exiftool() -> bool {
-  Command::new("exiftool")
+fn parse_exiftool_version(version: &str) -> Option<(u32, u32)> {
+  let (major, minor) = version.trim().split_once('.')?;
+  Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+/// The version of the installed exiftool.
+pub fn exiftool_version() -> anyhow::Result<(u32, u32)> {
+  let output = exiftool_command()
     .arg("-ver")
-    .stdout(Stdio::null())
     .stderr(Stdio::null())
-    .status()
-    .is_ok_and(|s| s.success())
+    .output()
+    .context("Failed to run exiftool")?;
+  anyhow::ensure!(
+    output.status.success(),
+    "exiftool -ver exited with {}",
+    output.status
+  );
+  let version = String::from_utf8_lossy(&output.stdout);
+  parse_exiftool_version(&version).with_context(|| {
+    format!(
+      "Failed to parse the exiftool version \"{}\"",
+      version.trim()
+    )
+  })
+}
+
+/// The date tags that are read from a file.
+/// `DateTimeOriginal` is the date that is fixed, the others are used as fallbacks and to judge its confidence.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct ExifDates {
+  pub date_time_original: Option<NaiveDateTime>,
+  pub create_date: Option<NaiveDateTime>,
+  /// Only present in videos.
+  pub media_create_date: Option<NaiveDateTime>,
+  pub xmp_date_created: Option<NaiveDateTime>,
+  /// The `Creation Time` text chunk of PNG files, which screenshots often carry instead of EXIF data.
+  pub png_creation_time: Option<NaiveDateTime>,
+  pub modify_date: Option<NaiveDateTime>,
+  /// Combined from `GPSDateStamp` and `GPSTimeStamp`, in UTC.
+  pub gps_date_time: Option<NaiveDateTime>,
+  /// The confidence recorded when the date was written by this tool with `--write-provenance`.
+  pub date_source_confidence: Option<DateConfidence>,
+  /// Signed degrees, north is positive.
+  pub gps_latitude: Option<f64>,
+  /// Signed degrees, east is positive.
+  pub gps_longitude: Option<f64>,
+}
+
+/// The date tags that are read for [`ExifDates`].
+const EXIF_DATE_READ_TAGS: [&str; 8] = [
+  "-DateTimeOriginal",
+  "-CreateDate",
+  "-MediaCreateDate",
+  "-XMP:DateCreated",
+  "-PNG:CreationTime",
+  "-ModifyDate",
+  "-GPSDateTime",
+  "-XMP-idf:DateSource",
+];
+
+/// The GPS position tags that are read for [`ExifDates`], as signed numbers.
+const GPS_POSITION_READ_TAGS: [&str; 2] = ["-Composite:GPSLatitude#", "-Composite:GPSLongitude#"];
+
+impl ExifDates {
+  /// Sets the date of a tag as printed by exiftool, other tags are ignored.
+  /// Only an invalid `DateTimeOriginal` is an error, the other tags are only fallbacks so invalid values are ignored.
+  fn set_tag(&mut self, tag: &str, value: &str) -> Result<(), chrono::ParseError> {
+    let date = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S");
+    match tag {
+      "DateTimeOriginal" => self.date_time_original = Some(date?),
+      "CreateDate" => self.create_date = date.ok(),
+      "MediaCreateDate" => self.media_create_date = date.ok(),
+      "DateCreated" => self.xmp_date_created = date.ok(),
+      "CreationTime" => {
+        self.png_creation_time = date.ok().or_else(|| parse_png_creation_time(value));
+      },
+      "ModifyDate" => self.modify_date = date.ok(),
+      "GPSDateTime" => self.gps_date_time = date.ok(),
+      "DateSource" => self.date_source_confidence = parse_date_source_confidence(value),
+      "GPSLatitude" => self.gps_latitude = value.parse().ok(),
+      "GPSLongitude" => self.gps_longitude = value.parse().ok(),
+      _ => {},
+    }
+    Ok(())
+  }
+
+  /// The most trustworthy date tag that is present, with its name.
+  /// Files without `DateTimeOriginal` (e.g. many PNGs, videos and scans) often carry one of the other tags.
+  /// For videos, this is usually the QuickTime `CreateDate`.
+  #[must_use]
+  pub fn original(&self) -> Option<(&'static str, NaiveDateTime)> {
+    [
+      ("DateTimeOriginal", self.date_time_original),
+      ("CreateDate", self.create_date),
+      ("MediaCreateDate", self.media_create_date),
+      ("XMP:DateCreated", self.xmp_date_created),
+      ("PNG:CreationTime", self.png_creation_time),
+      ("ModifyDate", self.modify_date),
+    ]
+    .into_iter()
+    .find_map(|(tag, date)| Some((tag, date?)))
+  }
+
+  #[must_use]
+  pub fn gps_position(&self) -> Option<GpsPosition> {
+    Some((self.gps_latitude?, self.gps_longitude?))
+  }
+}
+
+/// Free text tags that scanning software sometimes stores the capture date in, e.g. `Scanned 2003-05-12`.
+const EXIF_TEXT_READ_TAGS: [&str; 4] = [
+  "-ImageDescription",
+  "-UserComment",
+  "-XPComment",
+  "-XMP-dc:Description",
+];
+
+/// Reads the values of the free text tags that may mention a date.
+pub fn get_exif_text_values(
+  file_path: &Path,
+  ignore_minor_exif_errors: bool,
+) -> Result<Vec<String>, ErrorWithFilePath> {
+  if exiftool_disabled() {
+    return Ok(Vec::new());
+  }
+
+  let mut args = Vec::new();
+  if ignore_minor_exif_errors {
+    args.push(Cow::Borrowed("-m"));
+  }
+  args.extend(EXIF_TEXT_READ_TAGS.map(Cow::Borrowed));
+  args.push(Cow::Borrowed("-s2"));
+  args.push(file_path.to_string_lossy());
+
+  let exiftool_output = execute(&args)
+    .context("Failed to execute exiftool to get EXIF text tags")
+    .map_err(ErrorWithFilePath::from_source(file_path))?;
+  Ok(
+    exiftool_output
+      .stdout
+      .lines()
+      .filter_map(|line| Some(line.split_once(':')?.1.trim().to_string()))
+      .filter(|value| !value.is_empty())
+      .collect(),
+  )
+}
+
+/// Parses a PNG `Creation Time` that exiftool could not convert.
+/// The PNG specification recommends RFC 1123 dates such as `Sun, 18 Aug 2019 14:30:00 +0200`, but ISO 8601 is common too.
+#[must_use]
+fn parse_png_creation_time(value: &str) -> Option<NaiveDateTime> {
+  let value = value.trim();
+  DateTime::parse_from_rfc2822(value)
+    .or_else(|_| DateTime::parse_from_rfc3339(value))
+    .map(|date| date.naive_local())
+    .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S"))
+    .ok()
 }
 
 pub fn get_exif_date(
   file_path: &Path,
   ignore_minor_exif_errors: bool,
 ) -> Result<Option<NaiveDateTime>, ErrorWithFilePath> {
-  EXIFTOOL.with_borrow_mut(|et| {
-    let mut args = Vec::new();
-    if ignore_minor_exif_errors {
-      args.push(Cow::Borrowed("-m"));
-    }
-    args.push(Cow::Borrowed("-DateTimeOriginal"));
-    args.push(Cow::Borrowed("-d"));
-    args.push(Cow::Borrowed("%Y-%m-%d %H:%M:%S"));
-    args.push(Cow::Borrowed("-s3"));
-    if file_path.to_str().is_none() {
-      tracing::warn!(
-        file_path = %file_path.display(),
-        "File path is not valid UTF-8, exiftool may not be able to process it",
-      );
-    }
-    args.push(file_path.to_string_lossy());
+  get_exif_dates(file_path, ignore_minor_exif_errors)
+    .map(|dates| dates.original().map(|(_, date)| date))
+}
+
+/// Reads `DateTimeOriginal`, its fallback tags and the GPS date with a single exiftool call.
+pub fn get_exif_dates(
+  file_path: &Path,
+  ignore_minor_exif_errors: bool,
+) -> Result<ExifDates, ErrorWithFilePath> {
+  #[cfg(feature = "native-exif-read")]
+  if let Some(dates) = read_exif_dates_natively(file_path) {
+    return Ok(dates);
+  }
+  if exiftool_disabled() {
+    return Ok(ExifDates::default());
+  }
+
+  let mut args = Vec::new();
+  if ignore_minor_exif_errors {
+    args.push(Cow::Borrowed("-m"));
+  }
+  if is_quicktime_video(file_path) {
+    // Convert the UTC QuickTime dates to local time, like the naive EXIF dates.
+    args.push(Cow::Borrowed("-api"));
+    args.push(Cow::Borrowed("QuickTimeUTC"));
+  }
+  args.extend(EXIF_DATE_READ_TAGS.map(Cow::Borrowed));
+  args.extend(GPS_POSITION_READ_TAGS.map(Cow::Borrowed));
+  args.push(Cow::Borrowed("-d"));
+  args.push(Cow::Borrowed("%Y-%m-%d %H:%M:%S"));
+  args.push(Cow::Borrowed("-s2"));
+  if file_path.to_str().is_none() {
+    tracing::warn!(
+      file_path = %file_path.display(),
+      "File path is not valid UTF-8, exiftool may not be able to process it",
+    );
+  }
+  args.push(file_path.to_string_lossy());
+
+  let exiftool_output = execute(&args)
+    .context("Failed to execute exiftool to get EXIF date")
+    .map_err(ErrorWithFilePath::from_source(file_path))?;
+  let exiftool_stdout = exiftool_output.stdout.trim();
+  let exiftool_stderr = exiftool_output.stderr.trim();
 
-    let exiftool_output = et
-      .execute(&args)
-      .context("Failed to execute exiftool to get EXIF date")
+  // Each present tag is printed as `TagName: value`, missing tags are omitted.
+  let mut dates = ExifDates::default();
+  for line in exiftool_stdout.lines() {
+    let Some((tag, value)) = line.split_once(':') else {
+      continue;
+    };
+    dates
+      .set_tag(tag.trim(), value.trim())
+      .with_context(|| {
+        format!(
+          "Failed to parse the EXIF date. exiftool stderr:\n{exiftool_stderr}\nstdout:\n{exiftool_stdout}"
+        )
+      })
       .map_err(ErrorWithFilePath::from_source(file_path))?;
-    let exiftool_stdout = exiftool_output.stdout.trim();
-    let exiftool_stderr = exiftool_output.stderr.trim();
+  }
+  Ok(dates)
+}
+
+/// Reads the dates of many files with a single exiftool call.
+/// Files that exiftool reported an error for or whose `DateTimeOriginal` is invalid are left out,
+/// so that they can be read with [`get_exif_dates`] to get a proper error and repair attempt.
+pub fn get_exif_dates_batch(
+  file_paths: &[PathBuf],
+  ignore_minor_exif_errors: bool,
+) -> anyhow::Result<BTreeMap<PathBuf, ExifDates>> {
+  let mut args = Vec::new();
+  if ignore_minor_exif_errors {
+    args.push(Cow::Borrowed("-m"));
+  }
+  // Only affects QuickTime dates, so it is safe to use for all files.
+  args.push(Cow::Borrowed("-api"));
+  args.push(Cow::Borrowed("QuickTimeUTC"));
+  args.extend(EXIF_DATE_READ_TAGS.map(Cow::Borrowed));
+  args.extend(GPS_POSITION_READ_TAGS.map(Cow::Borrowed));
+  args.push(Cow::Borrowed("-d"));
+  args.push(Cow::Borrowed("%Y-%m-%d %H:%M:%S"));
+  args.push(Cow::Borrowed("-json"));
+  args.extend(
+    file_paths
+      .iter()
+      .map(|file_path| file_path.to_string_lossy()),
+  );
+
+  let exiftool_output = execute(&args)
+    .context("Failed to execute exiftool to get the EXIF dates of a batch of files")?;
+  parse_exif_dates_json(&exiftool_output.stdout)
+}
+
+/// Parses the `-json` output of exiftool into the dates of each source file.
+fn parse_exif_dates_json(json: &str) -> anyhow::Result<BTreeMap<PathBuf, ExifDates>> {
+  if json.trim().is_empty() {
+    // No file could be read at all.
+    return Ok(BTreeMap::new());
+  }
+  let files: Vec<BTreeMap<String, serde_json::Value>> =
+    serde_json::from_str(json).context("Failed to parse the JSON output of exiftool")?;
+
+  Ok(
+    files
+      .into_iter()
+      .filter(|tags| !tags.contains_key("Error"))
+      .filter_map(|tags| {
+        let file_path = PathBuf::from(tags.get("SourceFile")?.as_str()?);
+        let mut dates = ExifDates::default();
+        for (tag, value) in &tags {
+          match value {
+            serde_json::Value::String(value) => dates.set_tag(tag, value).ok()?,
+            // Numeric values such as the GPS position.
+            serde_json::Value::Number(value) => dates.set_tag(tag, &value.to_string()).ok()?,
+            _ => {},
+          }
+        }
+        Some((file_path, dates))
+      })
+      .collect(),
+  )
+}
 
-    if exiftool_stdout.is_empty() {
-      // If there is no DateTimeOriginal tag, exiftool returns an empty string.
-      return Ok(None);
+/// Dates read ahead of time with [`get_exif_dates_batch`], so that files can take their dates from here instead of calling exiftool one at a time.
+pub struct ExifDatesCache {
+  dates: Mutex<BTreeMap<PathBuf, ExifDates>>,
+}
+
+impl ExifDatesCache {
+  /// The maximum number of files that are read with one exiftool call.
+  const BATCH_SIZE: usize = 256;
+
+  #[must_use]
+  pub const fn new() -> Self {
+    Self {
+      dates: Mutex::new(BTreeMap::new()),
     }
+  }
 
-    // On success the exiftool output is the date_str
-    Ok(Some(
-      NaiveDateTime::parse_from_str(exiftool_stdout, "%Y-%m-%d %H:%M:%S")
-        .with_context(|| {
-          format!(
-            "Failed to parse the EXIF date. exiftool stderr:\n{exiftool_stderr}\nstdout:\n{exiftool_stdout}"
-          )
-        })
-        .map_err(ErrorWithFilePath::from_source(file_path))?,
-    ))
+  /// Reads the dates of `file_paths` in batches and caches them.
+  /// Failed batches are only logged, as the files fall back to being read one at a time.
+  pub fn prefetch(&self, file_paths: &[PathBuf], ignore_minor_exif_errors: bool) {
+    #[cfg(feature = "native-exif-read")]
+    let file_paths = &self.prefetch_natively(file_paths);
+    if exiftool_disabled() {
+      return;
+    }
+    for chunk in file_paths.chunks(Self::BATCH_SIZE) {
+      match get_exif_dates_batch(chunk, ignore_minor_exif_errors) {
+        Ok(dates) => self
+          .dates
+          .lock()
+          .expect("EXIF dates cache lock should not be poisoned")
+          .extend(dates),
+        Err(e) => tracing::debug!("Failed to read a batch of EXIF dates: {e:#}"),
+      }
+    }
+  }
+
+  /// Reads and caches the dates of the files that can be read without exiftool.
+  /// Returns the files that still have to be read with exiftool.
+  #[cfg(feature = "native-exif-read")]
+  fn prefetch_natively(&self, file_paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut remaining_file_paths = Vec::new();
+    for file_path in file_paths {
+      match read_exif_dates_natively(file_path) {
+        Some(dates) => {
+          self
+            .dates
+            .lock()
+            .expect("EXIF dates cache lock should not be poisoned")
+            .insert(file_path.clone(), dates);
+        },
+        None => remaining_file_paths.push(file_path.clone()),
+      }
+    }
+    remaining_file_paths
+  }
+
+  #[cfg(test)]
+  pub fn insert(&self, file_path: &Path, dates: ExifDates) {
+    self
+      .dates
+      .lock()
+      .expect("EXIF dates cache lock should not be poisoned")
+      .insert(file_path.to_path_buf(), dates);
+  }
+
+  /// Removes and returns the cached dates of a file.
+  #[must_use]
+  pub fn take(&self, file_path: &Path) -> Option<ExifDates> {
+    self
+      .dates
+      .lock()
+      .expect("EXIF dates cache lock should not be poisoned")
+      .remove(file_path)
+  }
+}
+
+/// How [`set_exif_date`] writes dates.
+#[derive(Debug, Default, Clone, Copy)]
+#[expect(
+  clippy::struct_excessive_bools,
+  reason = "The flags are independent command line options that are passed through to the write."
+)]
+pub struct ExifWriteOptions {
+  pub dry_run: bool,
+  pub ignore_minor_exif_errors: bool,
+  /// Write `DateTimeOriginal`, `CreateDate`, `ModifyDate` and `XMP:DateCreated` instead of only `DateTimeOriginal`.
+  pub write_all_dates: bool,
+  /// Keep the file modification time instead of setting it to the time of the write.
+  pub preserve_modified_time: bool,
+  /// Record how the date was determined in [`DATE_SOURCE_TAG`].
+  pub write_provenance: bool,
+  /// The UTC offset of dates whose source does not state one.
+  pub default_utc_offset: Option<FixedOffset>,
+  /// Also write `IPTC:DateCreated` and `IPTC:TimeCreated`, which news and archive software reads instead of EXIF.
+  pub write_iptc: bool,
+}
+
+/// Extensions of QuickTime based video formats.
+const QUICKTIME_VIDEO_EXTENSIONS: [&str; 5] = ["MP4", "M4V", "MOV", "3GP", "3G2"];
+
+/// Whether `file_path` is a QuickTime based video, whose dates are defined to be in UTC.
+#[must_use]
+pub fn is_quicktime_video(file_path: &Path) -> bool {
+  file_path.extension().is_some_and(|ext| {
+    QUICKTIME_VIDEO_EXTENSIONS
+      .iter()
+      .any(|video_ext| ext.eq_ignore_ascii_case(video_ext))
   })
 }
 
-pub fn set_exif_date(
-  file_path: &Path,
+/// Extensions of formats whose EXIF data many viewers ignore, so their dates are written to XMP only.
+const XMP_ONLY_EXTENSIONS: [&str; 1] = ["WEBP"];
+
+/// Whether the dates of `file_path` are written to XMP instead of EXIF.
+#[must_use]
+fn is_xmp_only_format(file_path: &Path) -> bool {
+  file_path.extension().is_some_and(|ext| {
+    XMP_ONLY_EXTENSIONS
+      .iter()
+      .any(|xmp_ext| ext.eq_ignore_ascii_case(xmp_ext))
+  })
+}
+
+/// The exiftool arguments that write `date_str` to the XMP equivalents of the EXIF tags.
+/// XMP dates carry their own sub-seconds and UTC offset.
+fn xmp_date_write_args(date_str: &str, options: ExifWriteOptions) -> Vec<Cow<'static, str>> {
+  let mut args = vec![Cow::Owned(format!("-XMP-exif:DateTimeOriginal={date_str}"))];
+  if options.write_all_dates {
+    args.push(Cow::Owned(format!("-XMP-xmp:CreateDate={date_str}")));
+    args.push(Cow::Owned(format!("-XMP-xmp:ModifyDate={date_str}")));
+    args.push(Cow::Owned(format!("-XMP:DateCreated={date_str}")));
+  }
+  args
+}
+
+/// The exiftool arguments that write `date` to the IPTC date and time tags.
+/// IPTC stores the UTC offset with the time.
+fn iptc_date_write_args(
   date: &NaiveDateTime,
-  dry_run: bool,
+  utc_offset: Option<FixedOffset>,
+) -> Vec<Cow<'static, str>> {
+  let mut time_str = date.format("%H:%M:%S").to_string();
+  if let Some(utc_offset) = utc_offset {
+    time_str.push_str(&utc_offset.to_string());
+  }
+  vec![
+    Cow::Owned(format!("-IPTC:DateCreated={}", date.format("%Y:%m:%d"))),
+    Cow::Owned(format!("-IPTC:TimeCreated={time_str}")),
+  ]
+}
+
+/// The exiftool arguments that write a naive local `date` to the EXIF tags.
+fn exif_date_write_args(date_str: &str, options: ExifWriteOptions) -> Vec<Cow<'static, str>> {
+  if options.write_all_dates {
+    // AllDates is a shortcut for DateTimeOriginal, CreateDate and ModifyDate.
+    vec![
+      Cow::Owned(format!("-AllDates={date_str}")),
+      Cow::Owned(format!("-XMP:DateCreated={date_str}")),
+    ]
+  } else {
+    vec![Cow::Owned(format!("-DateTimeOriginal={date_str}"))]
+  }
+}
+
+/// The exiftool arguments that write the milliseconds of `date`, if they are known, so burst photos keep their order.
+fn subsec_write_args(
+  date: &ConfidentNaiveDateTime,
+  options: ExifWriteOptions,
+) -> Vec<Cow<'static, str>> {
+  if date.confidence < DateConfidence::Millisecond {
+    return Vec::new();
+  }
+  let subsec = date.date.format("%3f");
+  if options.write_all_dates {
+    vec![
+      Cow::Owned(format!("-SubSecTimeOriginal={subsec}")),
+      Cow::Owned(format!("-SubSecTimeDigitized={subsec}")),
+      Cow::Owned(format!("-SubSecTime={subsec}")),
+    ]
+  } else {
+    vec![Cow::Owned(format!("-SubSecTimeOriginal={subsec}"))]
+  }
+}
+
+/// The exiftool arguments that write the UTC offset of the date, so other tools do not have to guess the time zone.
+fn offset_write_args(utc_offset: FixedOffset, options: ExifWriteOptions) -> Vec<Cow<'static, str>> {
+  let mut args = vec![
+    Cow::Owned(format!("-OffsetTimeOriginal={utc_offset}")),
+    Cow::Owned(format!("-OffsetTimeDigitized={utc_offset}")),
+  ];
+  if options.write_all_dates {
+    args.push(Cow::Owned(format!("-OffsetTime={utc_offset}")));
+  }
+  args
+}
+
+/// The exiftool arguments that write a naive local `date` to the QuickTime tags of a video.
+/// QuickTime dates are stored in UTC, so `QuickTimeUTC` makes exiftool convert from the local time zone.
+fn quicktime_date_write_args(date_str: &str, options: ExifWriteOptions) -> Vec<Cow<'static, str>> {
+  let mut args = vec![
+    Cow::Borrowed("-api"),
+    Cow::Borrowed("QuickTimeUTC"),
+    Cow::Owned(format!("-QuickTime:CreateDate={date_str}")),
+    Cow::Owned(format!("-QuickTime:MediaCreateDate={date_str}")),
+  ];
+  if options.write_all_dates {
+    args.push(Cow::Owned(format!("-QuickTime:ModifyDate={date_str}")));
+    args.push(Cow::Owned(format!("-QuickTime:TrackCreateDate={date_str}")));
+    args.push(Cow::Owned(format!("-XMP:DateCreated={date_str}")));
+  }
+  args
+}
+
+/// The exiftool arguments that write `date_str` to `file_path`, without the file path itself.
+pub fn date_write_args(
+  file_path: &Path,
+  date: &ConfidentNaiveDateTime,
+  source: &str,
+  options: ExifWriteOptions,
+) -> Vec<Cow<'static, str>> {
+  let date_str = date.date.format("%Y-%m-%d %H:%M:%S").to_string();
+  let mut args = Vec::new();
+  if options.ignore_minor_exif_errors {
+    args.push(Cow::Borrowed("-m"));
+  }
+  if backups().is_none() {
+    args.push(Cow::Borrowed("-overwrite_original"));
+  }
+  if options.preserve_modified_time {
+    args.push(Cow::Borrowed("-P"));
+  }
+  let utc_offset = date.offset.or(options.default_utc_offset);
+  if is_quicktime_video(file_path) {
+    // With a UTC offset, exiftool converts the date to UTC with it instead of the local time zone.
+    let date_str = match utc_offset {
+      Some(utc_offset) => format!("{date_str}{utc_offset}"),
+      None => date_str,
+    };
+    args.extend(quicktime_date_write_args(&date_str, options));
+  } else if is_xmp_only_format(file_path) {
+    let mut date_str = date_str;
+    if date.confidence >= DateConfidence::Millisecond {
+      date_str.push_str(&date.date.format("%.3f").to_string());
+    }
+    if let Some(utc_offset) = utc_offset {
+      date_str.push_str(&utc_offset.to_string());
+    }
+    args.extend(xmp_date_write_args(&date_str, options));
+  } else {
+    args.extend(exif_date_write_args(&date_str, options));
+    args.extend(subsec_write_args(date, options));
+    if let Some(utc_offset) = utc_offset {
+      args.extend(offset_write_args(utc_offset, options));
+    }
+    // XMP sidecars have no IPTC block.
+    let is_sidecar = file_path
+      .extension()
+      .is_some_and(|ext| ext.eq_ignore_ascii_case("xmp"));
+    if options.write_iptc && !is_sidecar {
+      args.extend(iptc_date_write_args(&date.date, utc_offset));
+    }
+  }
+  if options.write_provenance {
+    let value = date_source_value(source, date.confidence, Local::now().naive_local());
+    args.push(Cow::Owned(format!("-{DATE_SOURCE_TAG}={value}")));
+  }
+  args
+}
+
+/// The tags that the exiftool arguments `args` write, with `AllDates` expanded.
+#[must_use]
+pub fn written_tags(args: &[Cow<'_, str>]) -> Vec<String> {
+  let mut tags = Vec::new();
+  for arg in args {
+    let Some((tag, _)) = arg
+      .strip_prefix('-')
+      .and_then(|assignment| assignment.split_once('='))
+    else {
+      continue;
+    };
+    if tag == "AllDates" {
+      tags.extend(["DateTimeOriginal", "CreateDate", "ModifyDate"].map(String::from));
+    } else {
+      tags.push(tag.to_string());
+    }
+  }
+  tags
+}
+
+/// Parses the values of `tags` printed by exiftool with `-f -s3`, one per line in the order of `tags`.
+/// Missing tags are printed as `-`.
+fn parse_tag_values(
+  tags: &[String],
+  exiftool_stdout: &str,
+) -> anyhow::Result<BTreeMap<String, Option<String>>> {
+  let values = exiftool_stdout.lines().collect::<Vec<_>>();
+  if values.len() != tags.len() {
+    anyhow::bail!(
+      "Expected {} tag values, but exiftool printed:\n{exiftool_stdout}",
+      tags.len()
+    );
+  }
+  Ok(
+    tags
+      .iter()
+      .zip(values)
+      .map(|(tag, value)| (tag.clone(), (value != "-").then(|| value.to_string())))
+      .collect(),
+  )
+}
+
+/// Reads the current values of `tags`, so they can be recorded in the undo journal before they are written.
+/// Values are kept as printed by exiftool, which accepts them back unchanged.
+fn get_tag_values(
+  file_path: &Path,
+  tags: &[String],
   ignore_minor_exif_errors: bool,
+) -> Result<BTreeMap<String, Option<String>>, ErrorWithFilePath> {
+  // XMP sidecars that do not exist yet have none of the tags.
+  if !file_path.exists() {
+    return Ok(tags.iter().map(|tag| (tag.clone(), None)).collect());
+  }
+
+  let mut args = Vec::new();
+  if ignore_minor_exif_errors {
+    args.push(Cow::Borrowed("-m"));
+  }
+  if tags.iter().any(|tag| tag.starts_with("QuickTime:")) {
+    // Read the QuickTime dates with their UTC offset, like they are written back.
+    args.push(Cow::Borrowed("-api"));
+    args.push(Cow::Borrowed("QuickTimeUTC"));
+  }
+  args.push(Cow::Borrowed("-f"));
+  args.push(Cow::Borrowed("-s3"));
+  args.extend(tags.iter().map(|tag| Cow::Owned(format!("-{tag}"))));
+  args.push(file_path.to_string_lossy());
+
+  let exiftool_output = execute(&args)
+    .context("Failed to execute exiftool to get the tags to be written")
+    .map_err(ErrorWithFilePath::from_source(file_path))?;
+  parse_tag_values(tags, exiftool_output.stdout.trim_end())
+    .map_err(ErrorWithFilePath::from_source(file_path))
+}
+
+/// Sets the EXIF date of `file_path`.
+/// `source` describes where the date came from, e.g. `filename:whatsapp`, and is recorded if provenance tags are written.
+pub fn set_exif_date(
+  file_path: &Path,
+  date: &ConfidentNaiveDateTime,
+  source: &str,
+  options: ExifWriteOptions,
 ) -> Result<(), ErrorWithFilePath> {
-  if dry_run {
+  if options.dry_run {
     info!(
       file_path = %file_path.display(),
       "Would set EXIF date to {}",
-      date.format("%Y-%m-%d %H:%M:%S"),
+      date.date.format("%Y-%m-%d %H:%M:%S"),
     );
     return Ok(());
   }
 
-  let date_str = date.format("%Y-%m-%d %H:%M:%S").to_string();
-
-  EXIFTOOL.with_borrow_mut(|et| {
-    let mut args = Vec::new();
-    if ignore_minor_exif_errors {
-      args.push(Cow::Borrowed("-m"));
+  #[cfg(feature = "native-jpeg-write")]
+  if exiftool_disabled() {
+    // Only `DateTimeOriginal` is written natively.
+    if let Some(undo_journal) = undo_journal() {
+      let previous_date =
+        get_exif_dates(file_path, options.ignore_minor_exif_errors)?.date_time_original;
+      undo_journal.record_exif_date(file_path, previous_date)?;
     }
-    args.push(Cow::Borrowed("-overwrite_original"));
-    args.push(Cow::Owned(format!("-DateTimeOriginal={date_str}")));
-    args.push(file_path.to_string_lossy());
+    return write_date_time_original_natively(file_path, &date.date, options)
+      .map_err(ErrorWithFilePath::from_source(file_path));
+  }
 
-    let exiftool_output = et
-      .execute(&args)
-      .context("Failed to execute exiftool to set EXIF date")
-      .map_err(ErrorWithFilePath::from_source(file_path))?;
-    let exiftool_stdout = exiftool_output.stdout.trim();
-    let exiftool_stderr = exiftool_output.stderr.trim();
+  let date_str = date.date.format("%Y-%m-%d %H:%M:%S").to_string();
 
-    if exiftool_stdout.contains("1 image files updated") {
-      Ok(())
-    } else {
-      Err(ErrorWithFilePath::new(
+  let mut args = date_write_args(file_path, date, source, options);
+  if let Some(undo_journal) = undo_journal() {
+    let previous_values = get_tag_values(
+      file_path,
+      &written_tags(&args),
+      options.ignore_minor_exif_errors,
+    )?;
+    undo_journal.record_exif_tags(file_path, previous_values)?;
+  }
+  args.push(file_path.to_string_lossy());
+
+  let pending_backup = backups().map(|backups| backups.prepare(file_path));
+  let exiftool_output = execute_write(file_path, &args)
+    .context("Failed to execute exiftool to set EXIF date")
+    .map_err(ErrorWithFilePath::from_source(file_path))?;
+  let exiftool_stdout = exiftool_output.stdout.trim();
+  let exiftool_stderr = exiftool_output.stderr.trim();
+
+  // XMP sidecars that do not exist yet are created.
+  if exiftool_stdout.contains("1 image files updated")
+    || exiftool_stdout.contains("1 image files created")
+  {
+    pending_backup.map_or(Ok(()), PendingBackup::finish)
+  } else {
+    Err(ErrorWithFilePath::new(
+      file_path,
+      anyhow::anyhow!(
+        "Failed to set EXIF date to {date_str}. exiftool stderr:\n{exiftool_stderr}\nstdout:\n{exiftool_stdout}"
+      ),
+    ))
+  }
+}
+
+/// The `-if` condition that holds if none of the tags of [`ExifDates`] are present.
+#[must_use]
+fn no_exif_dates_condition() -> String {
+  let tags = EXIF_DATE_READ_TAGS
+    .iter()
+    .chain(&GPS_POSITION_READ_TAGS)
+    .map(|tag| format!("${}", tag.trim_start_matches('-').trim_end_matches('#')))
+    .collect::<Vec<_>>()
+    .join(" or ");
+  format!("not ({tags})")
+}
+
+/// Sets the EXIF date only if the file has none of the date tags that are read for [`ExifDates`].
+/// This writes the date in a single exiftool call, instead of reading the dates first.
+/// Returns whether the date was written.
+pub fn set_exif_date_if_missing(
+  file_path: &Path,
+  date: &ConfidentNaiveDateTime,
+  source: &str,
+  options: ExifWriteOptions,
+) -> Result<bool, ErrorWithFilePath> {
+  // Without exiftool the dates are read first and written unconditionally.
+  if exiftool_disabled() {
+    return Ok(false);
+  }
+
+  let date_str = date.date.format("%Y-%m-%d %H:%M:%S").to_string();
+
+  let mut args = vec![Cow::Borrowed("-if"), Cow::Owned(no_exif_dates_condition())];
+  args.extend(date_write_args(file_path, date, source, options));
+
+  // Whether the file is written is only known afterwards, so the previous values are read now and recorded then.
+  // The condition only guarantees that the date tags are missing, not the UTC offset, sub-second and IPTC tags.
+  let previous_values = undo_journal()
+    .map(|_| {
+      get_tag_values(
         file_path,
-        anyhow::anyhow!(
-          "Failed to set EXIF date to {date_str}. exiftool stderr:\n{exiftool_stderr}\nstdout:\n{exiftool_stdout}"
-        ),
-      ))
+        &written_tags(&args),
+        options.ignore_minor_exif_errors,
+      )
+    })
+    .transpose()?;
+  let previous_modified_time =
+    undo_journal().and_then(|_| std::fs::metadata(file_path).ok()?.modified().ok());
+  args.push(file_path.to_string_lossy());
+  let pending_backup = backups().map(|backups| backups.prepare(file_path));
+  let exiftool_output = execute_write(file_path, &args)
+    .context("Failed to execute exiftool to conditionally set EXIF date")
+    .map_err(ErrorWithFilePath::from_source(file_path))?;
+  let exiftool_stdout = exiftool_output.stdout.trim();
+  let exiftool_stderr = exiftool_output.stderr.trim();
+
+  if exiftool_stdout.contains("1 image files updated") {
+    pending_backup.map_or(Ok(()), PendingBackup::finish)?;
+    if let (Some(undo_journal), Some(previous_values)) = (undo_journal(), previous_values) {
+      undo_journal.record_exif_tags_at(
+        file_path,
+        previous_values,
+        previous_modified_time
+          .map(|modified_time| DateTime::<Utc>::from(modified_time).naive_utc()),
+      )?;
     }
-  })
+    Ok(true)
+  } else if exiftool_stdout.contains("1 files failed condition") {
+    Ok(false)
+  } else {
+    Err(ErrorWithFilePath::new(
+      file_path,
+      anyhow::anyhow!(
+        "Failed to conditionally set EXIF date to {date_str}. exiftool stderr:\n{exiftool_stderr}\nstdout:\n{exiftool_stdout}"
+      ),
+    ))
+  }
+}
+
+/// Writes back the `DateTimeOriginal` recorded in the undo journal, or removes it if the file had none.
+pub fn restore_date_time_original(
+  file_path: &Path,
+  date: Option<&NaiveDateTime>,
+  dry_run: bool,
+) -> Result<(), ErrorWithFilePath> {
+  #[cfg(feature = "native-jpeg-write")]
+  if !dry_run
+    && exiftool_disabled()
+    && let Some(date) = date
+  {
+    return write_date_time_original_natively(file_path, date, ExifWriteOptions::default())
+      .map_err(ErrorWithFilePath::from_source(file_path));
+  }
+  let tags = BTreeMap::from([(
+    String::from("DateTimeOriginal"),
+    date.map(|date| date.format("%Y-%m-%d %H:%M:%S").to_string()),
+  )]);
+  restore_exif_tags(file_path, &tags, dry_run)
+}
+
+/// The exiftool arguments that set `tags` back to their recorded values and remove the tags recorded as missing.
+#[must_use]
+pub fn restore_args<'a>(
+  file_path: &'a Path,
+  tags: &BTreeMap<String, Option<String>>,
+) -> Vec<Cow<'a, str>> {
+  let mut args = vec![Cow::Borrowed("-m"), Cow::Borrowed("-overwrite_original")];
+  if tags.keys().any(|tag| tag.starts_with("QuickTime:")) {
+    // The QuickTime dates were read with their UTC offset.
+    args.push(Cow::Borrowed("-api"));
+    args.push(Cow::Borrowed("QuickTimeUTC"));
+  }
+  args.extend(
+    tags
+      .iter()
+      .map(|(tag, value)| Cow::Owned(format!("-{tag}={}", value.as_deref().unwrap_or_default()))),
+  );
+  args.push(file_path.to_string_lossy());
+  args
+}
+
+/// Writes back the tag values recorded in the undo journal, or removes the tags the file did not have.
+pub fn restore_exif_tags(
+  file_path: &Path,
+  tags: &BTreeMap<String, Option<String>>,
+  dry_run: bool,
+) -> Result<(), ErrorWithFilePath> {
+  if dry_run {
+    for (tag, value) in tags {
+      if let Some(value) = value {
+        info!(
+          file_path = %file_path.display(),
+          "Would restore {tag} {value}",
+        );
+      } else {
+        info!(
+          file_path = %file_path.display(),
+          "Would remove {tag}",
+        );
+      }
+    }
+    return Ok(());
+  }
+  if exiftool_disabled() {
+    return Err(ErrorWithFilePath::new(
+      file_path,
+      anyhow::anyhow!("The previous tags can only be restored with exiftool"),
+    ));
+  }
+
+  let args = restore_args(file_path, tags);
+  let exiftool_output = execute_write(file_path, &args)
+    .context("Failed to execute exiftool to restore the previous tags")
+    .map_err(ErrorWithFilePath::from_source(file_path))?;
+  let exiftool_stdout = exiftool_output.stdout.trim();
+  let exiftool_stderr = exiftool_output.stderr.trim();
+
+  // Removing tags that are already missing leaves the file unchanged.
+  if exiftool_stdout.contains("1 image files updated")
+    || exiftool_stdout.contains("1 image files unchanged")
+  {
+    Ok(())
+  } else {
+    Err(ErrorWithFilePath::new(
+      file_path,
+      anyhow::anyhow!(
+        "Failed to restore the previous tags. exiftool stderr:\n{exiftool_stderr}\nstdout:\n{exiftool_stdout}"
+      ),
+    ))
+  }
 }
 
 pub fn repair_exif_errors(file_path: &Path, dry_run: bool) -> Result<(), ErrorWithFilePath> {
@@ -294,42 +1268,48 @@ pub fn repair_exif_errors(file_path: &Path, dry_run: bool) -> Result<(), ErrorWi
     );
     return Ok(());
   }
+  if exiftool_disabled() {
+    return Err(ErrorWithFilePath::new(
+      file_path,
+      anyhow::anyhow!("EXIF errors can only be repaired with exiftool"),
+    ));
+  }
 
-  EXIFTOOL.with_borrow_mut(|et| {
-    let args = [
-      Cow::Borrowed("-m"),
-      Cow::Borrowed("-overwrite_original"),
-      Cow::Borrowed("-exif:all="),
-      Cow::Borrowed("-tagsfromfile"),
-      Cow::Borrowed("@"),
-      Cow::Borrowed("-all:all"),
-      Cow::Borrowed("-unsafe"),
-      Cow::Borrowed("-icc_profile"),
-      file_path.to_string_lossy(),
-    ];
-
-    let exiftool_output = et
-      .execute(&args)
-      .context("Failed to execute exiftool to repair EXIF errors")
-      .map_err(ErrorWithFilePath::from_source(file_path))?;
-    let exiftool_stdout = exiftool_output.stdout.trim();
-    let exiftool_stderr = exiftool_output.stderr.trim();
+  let mut args = vec![Cow::Borrowed("-m")];
+  if backups().is_none() {
+    args.push(Cow::Borrowed("-overwrite_original"));
+  }
+  args.extend([
+    Cow::Borrowed("-exif:all="),
+    Cow::Borrowed("-tagsfromfile"),
+    Cow::Borrowed("@"),
+    Cow::Borrowed("-all:all"),
+    Cow::Borrowed("-unsafe"),
+    Cow::Borrowed("-icc_profile"),
+    file_path.to_string_lossy(),
+  ]);
 
-    if exiftool_stdout.contains("1 image files updated") {
-      info!(
-        file_path = %file_path.display(),
-        "Successfully repaired EXIF errors",
-      );
-      Ok(())
-    } else {
-      Err(ErrorWithFilePath::new(
-        file_path,
-        anyhow::anyhow!(
-          "Failed to repair EXIF errors. exiftool stderr:\n{exiftool_stderr}\nstdout:\n{exiftool_stdout}"
-        ),
-      ))
-    }
-  })
+  let pending_backup = backups().map(|backups| backups.prepare(file_path));
+  let exiftool_output = execute_write(file_path, &args)
+    .context("Failed to execute exiftool to repair EXIF errors")
+    .map_err(ErrorWithFilePath::from_source(file_path))?;
+  let exiftool_stdout = exiftool_output.stdout.trim();
+  let exiftool_stderr = exiftool_output.stderr.trim();
+
+  if exiftool_stdout.contains("1 image files updated") {
+    info!(
+      file_path = %file_path.display(),
+      "Successfully repaired EXIF errors",
+    );
+    pending_backup.map_or(Ok(()), PendingBackup::finish)
+  } else {
+    Err(ErrorWithFilePath::new(
+      file_path,
+      anyhow::anyhow!(
+        "Failed to repair EXIF errors. exiftool stderr:\n{exiftool_stderr}\nstdout:\n{exiftool_stdout}"
+      ),
+    ))
+  }
 }
 
 pub fn wrap_with_exiftool_repair<R>(
@@ -356,30 +1336,57 @@ pub fn wrap_with_exiftool_repair<R>(
 }
 
 fn exiftool_writable_file_extensions_internal() -> anyhow::Result<BTreeSet<String>> {
+  #[cfg(feature = "native-jpeg-write")]
+  if exiftool_disabled() {
+    return Ok(NATIVE_WRITE_EXTENSIONS.map(str::to_string).into());
+  }
+
   // run exiftool to get the list of writable file extensions
-  EXIFTOOL.with_borrow_mut(|et| {
-    let exiftool_output = et.execute(&["-listwf"])?;
-    let exiftool_stdout = exiftool_output.stdout.trim();
-
-    let mut extensions = BTreeSet::new();
-    for line_str in exiftool_stdout.lines() {
-      if line_str.starts_with("Writable file extensions:") {
-        continue;
-      }
-      for extension in line_str.split_whitespace() {
-        extensions.insert(extension.to_string());
-      }
+  let exiftool_output = execute(&["-listwf"])?;
+  let exiftool_stdout = exiftool_output.stdout.trim();
+
+  let mut extensions = BTreeSet::new();
+  for line_str in exiftool_stdout.lines() {
+    if line_str.starts_with("Writable file extensions:") {
+      continue;
     }
-    // We remove file types that are supported by exiftool but make no sense for our program
-    extensions.remove("PDF"); // PDF files don't support the DateTimeOriginal
-    extensions.remove("PSC");
-    Ok(extensions)
-  })
+    for extension in line_str.split_whitespace() {
+      extensions.insert(extension.to_string());
+    }
+  }
+  // We remove file types that are supported by exiftool but make no sense for our program
+  extensions.remove("PDF"); // PDF files don't support the DateTimeOriginal
+  extensions.remove("PSC");
+  Ok(extensions)
 }
 
-pub fn exiftool_writable_file_extensions() -> anyhow::Result<&'static BTreeSet<String>> {
-  static WRITABLE_EXTENSIONS: OnceLock<BTreeSet<String>> = OnceLock::new();
+static WRITABLE_EXTENSIONS: OnceLock<BTreeSet<String>> = OnceLock::new();
+
+/// Image formats whose write support depends on the exiftool version and build, with their extensions.
+pub const MODERN_IMAGE_FORMATS: [(&str, &[&str]); 3] = [
+  ("HEIC/HEIF", &["HEIC", "HEIF", "HIF"]),
+  ("AVIF", &["AVIF"]),
+  ("WebP", &["WEBP"]),
+];
 
+/// Whether the installed exiftool can write each of the [`MODERN_IMAGE_FORMATS`].
+/// Files of formats it cannot write are skipped like any other unsupported file.
+pub fn modern_image_format_support() -> anyhow::Result<Vec<(&'static str, bool)>> {
+  let writable_extensions = exiftool_writable_file_extensions()?;
+  Ok(
+    MODERN_IMAGE_FORMATS
+      .iter()
+      .map(|&(format, extensions)| {
+        let writable = extensions
+          .iter()
+          .any(|extension| writable_extensions.contains(*extension));
+        (format, writable)
+      })
+      .collect(),
+  )
+}
+
+pub fn exiftool_writable_file_extensions() -> anyhow::Result<&'static BTreeSet<String>> {
   // TODO: use get_or_init once it is stabilized: https://github.com/rust-lang/rust/issues/109737
   //WRITABLE_EXTENSIONS.get_or_try_init(exiftool_writable_file_extensions_internal)
 
@@ -392,3 +1399,190 @@ pub fn exiftool_writable_file_extensions() -> anyhow::Result<&'static BTreeSet<S
 
   Ok(WRITABLE_EXTENSIONS.get().unwrap())
 }
+
+/// Lets the tests process common image files without asking exiftool, which may not be installed.
+#[cfg(test)]
+pub fn assume_writable_file_extensions() {
+  let _ = WRITABLE_EXTENSIONS.set(
+    ["JPG", "JPEG", "PNG", "HEIC", "MP4"]
+      .map(str::to_string)
+      .into(),
+  );
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn exif_dates_json() {
+    let dates = parse_exif_dates_json(
+      r#"[{
+        "SourceFile": "/a/IMG_0001.jpg",
+        "DateTimeOriginal": "2019-08-18 14:30:00",
+        "ModifyDate": "0000:00:00 00:00:00",
+        "GPSLatitude": 48.2082,
+        "GPSLongitude": -16.3738
+      }, {
+        "SourceFile": "/a/VID_0001.mp4",
+        "CreateDate": "2019-08-18 12:30:00"
+      }, {
+        "SourceFile": "/a/Screenshot.png",
+        "CreationTime": "Sun, 18 Aug 2019 14:30:00 +0200"
+      }, {
+        "SourceFile": "/a/broken.jpg",
+        "Error": "File format error"
+      }, {
+        "SourceFile": "/a/invalid.jpg",
+        "DateTimeOriginal": "2019:08:18"
+      }]"#,
+    )
+    .unwrap();
+    assert_eq!(dates.len(), 3);
+    let image_dates = dates[Path::new("/a/IMG_0001.jpg")];
+    assert!(image_dates.date_time_original.is_some());
+    assert_eq!(image_dates.modify_date, None);
+    assert_eq!(image_dates.gps_position(), Some((48.2082, -16.3738)));
+    assert!(dates[Path::new("/a/VID_0001.mp4")].create_date.is_some());
+    let screenshot_dates = dates[Path::new("/a/Screenshot.png")];
+    assert_eq!(
+      screenshot_dates.original(),
+      Some((
+        "PNG:CreationTime",
+        NaiveDateTime::parse_from_str("2019-08-18 14:30:00", "%Y-%m-%d %H:%M:%S").unwrap()
+      ))
+    );
+    assert!(parse_exif_dates_json("").unwrap().is_empty());
+  }
+
+  #[test]
+  fn png_creation_times() {
+    let date = NaiveDateTime::parse_from_str("2019-08-18 14:30:00", "%Y-%m-%d %H:%M:%S").ok();
+    assert_eq!(
+      parse_png_creation_time("Sun, 18 Aug 2019 14:30:00 +0200"),
+      date
+    );
+    assert_eq!(parse_png_creation_time("2019-08-18T14:30:00+02:00"), date);
+    assert_eq!(parse_png_creation_time("2019-08-18T14:30:00"), date);
+    assert_eq!(parse_png_creation_time("yesterday"), None);
+  }
+
+  #[test]
+  fn exiftool_version() {
+    assert_eq!(parse_exiftool_version("12.76\n"), Some((12, 76)));
+    assert_eq!(parse_exiftool_version("10.80"), Some((10, 80)));
+    assert!(parse_exiftool_version("10.08") < Some(MIN_EXIFTOOL_VERSION));
+    assert_eq!(parse_exiftool_version("exiftool"), None);
+  }
+
+  #[test]
+  fn date_write_args() {
+    let options = ExifWriteOptions::default();
+    assert!(is_quicktime_video(Path::new("/a/VID_20190818.mp4")));
+    assert!(!is_quicktime_video(Path::new("/a/IMG_20190818.jpg")));
+
+    let args = quicktime_date_write_args("2019-08-18 14:30:00", options);
+    assert_eq!(&args[..2], ["-api", "QuickTimeUTC"]);
+    assert!(args.contains(&Cow::Borrowed("-QuickTime:CreateDate=2019-08-18 14:30:00")));
+    assert!(args.contains(&Cow::Borrowed(
+      "-QuickTime:MediaCreateDate=2019-08-18 14:30:00"
+    )));
+
+    assert_eq!(
+      exif_date_write_args("2019-08-18 14:30:00", options),
+      ["-DateTimeOriginal=2019-08-18 14:30:00"]
+    );
+
+    let date = ConfidentNaiveDateTime::new(
+      NaiveDateTime::parse_from_str("2019-08-18 14:30:00.025", "%Y-%m-%d %H:%M:%S%.f").unwrap(),
+      DateConfidence::Millisecond,
+    );
+    assert_eq!(
+      subsec_write_args(&date, options),
+      ["-SubSecTimeOriginal=025"]
+    );
+    assert!(
+      subsec_write_args(
+        &ConfidentNaiveDateTime::new(date.date, DateConfidence::Second),
+        options
+      )
+      .is_empty()
+    );
+
+    assert_eq!(
+      offset_write_args(FixedOffset::east_opt(7200).unwrap(), options),
+      ["-OffsetTimeOriginal=+02:00", "-OffsetTimeDigitized=+02:00"]
+    );
+
+    assert_eq!(
+      iptc_date_write_args(
+        &NaiveDateTime::parse_from_str("2019-08-18 14:30:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+        FixedOffset::east_opt(7200)
+      ),
+      [
+        "-IPTC:DateCreated=2019:08:18",
+        "-IPTC:TimeCreated=14:30:00+02:00"
+      ]
+    );
+
+    assert!(is_xmp_only_format(Path::new("/a/IMG_20190818.webp")));
+    let date = ConfidentNaiveDateTime::new(
+      NaiveDateTime::parse_from_str("2019-08-18 14:30:00.250", "%Y-%m-%d %H:%M:%S%.3f").unwrap(),
+      DateConfidence::Millisecond,
+    )
+    .with_offset(FixedOffset::east_opt(7200).unwrap());
+    let args = super::date_write_args(Path::new("/a/IMG_20190818.webp"), &date, "test", options);
+    assert!(args.contains(&Cow::Borrowed(
+      "-XMP-exif:DateTimeOriginal=2019-08-18 14:30:00.250+02:00"
+    )));
+    assert!(!args.iter().any(|arg| arg.starts_with("-DateTimeOriginal")
+      || arg.starts_with("-OffsetTime")
+      || arg.starts_with("-SubSec")));
+
+    assert_eq!(
+      no_exif_dates_condition(),
+      "not ($DateTimeOriginal or $CreateDate or $MediaCreateDate or $XMP:DateCreated or $PNG:CreationTime or $ModifyDate or $GPSDateTime or $XMP-idf:DateSource or $Composite:GPSLatitude or $Composite:GPSLongitude)"
+    );
+  }
+
+  #[test]
+  fn tag_values() {
+    let tags = ["DateTimeOriginal", "OffsetTimeOriginal", "IPTC:TimeCreated"].map(String::from);
+    let values = parse_tag_values(&tags, "2019:08:18 14:30:00\n-\n14:30:00+02:00").unwrap();
+    assert_eq!(
+      values,
+      BTreeMap::from([
+        (
+          String::from("DateTimeOriginal"),
+          Some(String::from("2019:08:18 14:30:00"))
+        ),
+        (String::from("OffsetTimeOriginal"), None),
+        (
+          String::from("IPTC:TimeCreated"),
+          Some(String::from("14:30:00+02:00"))
+        ),
+      ])
+    );
+    assert!(parse_tag_values(&tags, "2019:08:18 14:30:00").is_err());
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn forced_abort_kills_workers() {
+    use std::os::unix::process::ExitStatusExt as _;
+
+    // A hung exiftool is stood in for by a process that never exits on its own.
+    let mut hung_worker = Command::new("sleep").arg("60").spawn().unwrap();
+    EXIFTOOL_WORKER_PIDS
+      .lock()
+      .unwrap()
+      .insert(hung_worker.id());
+    kill_exiftool_workers();
+    let exit_status = hung_worker.wait().unwrap();
+    EXIFTOOL_WORKER_PIDS
+      .lock()
+      .unwrap()
+      .remove(&hung_worker.id());
+    assert_eq!(exit_status.signal(), Some(libc::SIGKILL));
+  }
+}
@@ -0,0 +1,111 @@
+use alloc::collections::BTreeMap;
+use core::cmp::Reverse;
+use std::{path::Path, sync::Mutex};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct ExtensionCounts {
+  checked: usize,
+  updated: usize,
+  errors: usize,
+}
+
+/// Tallies the checked, updated and failed files per file extension, e.g. to see that all failures are HEICs.
+pub struct ExtensionStats {
+  counts: Mutex<BTreeMap<String, ExtensionCounts>>,
+}
+
+impl ExtensionStats {
+  #[must_use]
+  pub const fn new() -> Self {
+    Self {
+      counts: Mutex::new(BTreeMap::new()),
+    }
+  }
+
+  fn update(&self, file_path: &Path, update: impl FnOnce(&mut ExtensionCounts)) {
+    let extension = file_path
+      .extension()
+      .map(|ext| ext.to_string_lossy().to_ascii_uppercase())
+      .unwrap_or_default();
+    let mut counts = self
+      .counts
+      .lock()
+      .expect("Extension stats lock should not be poisoned");
+    update(counts.entry(extension).or_default());
+  }
+
+  pub fn record_checked(&self, file_path: &Path) {
+    self.update(file_path, |counts| counts.checked += 1);
+  }
+
+  /// The EXIF date or modified time of the file was changed.
+  pub fn record_updated(&self, file_path: &Path) {
+    self.update(file_path, |counts| counts.updated += 1);
+  }
+
+  pub fn record_error(&self, file_path: &Path) {
+    self.update(file_path, |counts| counts.errors += 1);
+  }
+
+  /// The lines of a table with one row per extension, most checked files first.
+  #[must_use]
+  pub fn table(&self) -> Vec<String> {
+    let mut counts = self
+      .counts
+      .lock()
+      .expect("Extension stats lock should not be poisoned")
+      .iter()
+      .map(|(extension, counts)| {
+        let extension = if extension.is_empty() {
+          "(none)"
+        } else {
+          extension.as_str()
+        };
+        (extension.to_string(), *counts)
+      })
+      .collect::<Vec<_>>();
+    counts.sort_by_key(|(_, counts)| Reverse(counts.checked));
+
+    let extension_width = counts
+      .iter()
+      .map(|(extension, _)| extension.len())
+      .chain(["Extension".len()])
+      .max()
+      .unwrap_or_default();
+    let mut lines = vec![format!(
+      "{:<extension_width$}  {:>7}  {:>7}  {:>6}",
+      "Extension", "Checked", "Updated", "Errors"
+    )];
+    lines.extend(counts.iter().map(|(extension, counts)| {
+      format!(
+        "{extension:<extension_width$}  {:>7}  {:>7}  {:>6}",
+        counts.checked, counts.updated, counts.errors
+      )
+    }));
+    lines
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn table() {
+    let stats = ExtensionStats::new();
+    for file_name in ["a.jpg", "b.JPG", "c.heic", "notes"] {
+      stats.record_checked(Path::new(file_name));
+    }
+    stats.record_updated(Path::new("a.jpg"));
+    stats.record_error(Path::new("c.heic"));
+    assert_eq!(
+      stats.table(),
+      [
+        "Extension  Checked  Updated  Errors",
+        "JPG              2        1       0",
+        "(none)           1        0       0",
+        "HEIC             1        0       1",
+      ]
+    );
+  }
+}
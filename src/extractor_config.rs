@@ -0,0 +1,208 @@
+use alloc::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{Context as _, bail};
+use serde::Deserialize;
+
+use crate::date_extractors::{
+  DEFAULT_CUSTOM_EXTRACTOR_PRIORITY, DateConfidence, DateExtractorRegistry, RegexDateExtractor,
+};
+
+/// The extractor config file.
+///
+/// Example:
+/// ```toml
+/// [[extractor]]
+/// name = "scans"
+/// regex = '^scan_\d+_(?P<year>\d{4})(?P<month>\d{2})\.'
+/// confidence = "Month"
+///
+/// # Lower priorities are tried first.
+/// [priorities]
+/// whatsapp = 15
+/// scans = 95
+///
+/// [confidence]
+/// whatsapp = "Hour"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExtractorConfig {
+  #[serde(default, rename = "extractor")]
+  pub extractors: Vec<CustomExtractorConfig>,
+  /// Priority overrides of built-in and custom extractors by name.
+  #[serde(default)]
+  pub priorities: BTreeMap<String, i32>,
+  /// Confidence overrides of built-in and custom extractors by name.
+  #[serde(default, rename = "confidence")]
+  pub confidence_overrides: BTreeMap<String, DateConfidence>,
+}
+
+/// A user-defined regex extractor, see [`RegexDateExtractor`].
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomExtractorConfig {
+  pub name: String,
+  pub regex: String,
+  pub confidence: DateConfidence,
+  #[serde(default)]
+  pub priority: Option<i32>,
+}
+
+impl ExtractorConfig {
+  pub fn load(config_path: &Path) -> anyhow::Result<Self> {
+    let config = std::fs::read_to_string(config_path).with_context(|| {
+      format!(
+        "\"{}\": Failed to read extractor config",
+        config_path.display()
+      )
+    })?;
+    Self::parse(&config).with_context(|| {
+      format!(
+        "\"{}\": Failed to parse extractor config",
+        config_path.display()
+      )
+    })
+  }
+
+  pub fn parse(config: &str) -> anyhow::Result<Self> {
+    Ok(toml::from_str(config)?)
+  }
+
+  /// Compiles the custom extractors, adds them to the registry and applies the priority and confidence overrides.
+  pub fn apply(&self, registry: &mut DateExtractorRegistry) -> anyhow::Result<()> {
+    for extractor in &self.extractors {
+      if registry.contains(&extractor.name) {
+        bail!(
+          "A date extractor named \"{}\" already exists",
+          extractor.name
+        );
+      }
+      registry.register(Box::new(RegexDateExtractor::new(
+        extractor.name.clone(),
+        extractor
+          .priority
+          .unwrap_or(DEFAULT_CUSTOM_EXTRACTOR_PRIORITY),
+        &extractor.regex,
+        extractor.confidence,
+      )?));
+    }
+    for (name, priority) in &self.priorities {
+      registry
+        .set_priority(name, *priority)
+        .context("Invalid priority override")?;
+    }
+    for (name, confidence) in &self.confidence_overrides {
+      registry
+        .set_confidence(name, *confidence)
+        .context("Invalid confidence override")?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use chrono::NaiveDateTime;
+
+  use super::*;
+  use crate::date_extractors::{ConfidentNaiveDateTime, DateRangePolicy};
+
+  #[test]
+  fn custom_extractors() {
+    let config = ExtractorConfig::parse(
+      r#"
+        [[extractor]]
+        name = "scans"
+        regex = '^scan_\d+_(?P<year>\d{4})(?P<month>\d{2})\.'
+        confidence = "Month"
+
+        [[extractor]]
+        name = "backups"
+        regex = '^bak-(?P<year>\d{4})-(?P<month>\d{2})-(?P<day>\d{2})'
+        confidence = "Day"
+        priority = 200
+      "#,
+    )
+    .unwrap();
+    let mut registry = DateExtractorRegistry::with_builtin_extractors(DateRangePolicy::default());
+    config.apply(&mut registry).unwrap();
+
+    assert_eq!(
+      registry.extract(
+        Path::new("/a/scan_3_200104.tif"),
+        "scan_3_200104.tif",
+        NaiveDateTime::MAX,
+        false,
+      ),
+      Some(ConfidentNaiveDateTime::new(
+        NaiveDateTime::parse_from_str("20010401000000", "%Y%m%d%H%M%S").unwrap(),
+        DateConfidence::Month,
+      ))
+    );
+
+    // Registering the same extractors twice is rejected.
+    assert!(config.apply(&mut registry).is_err());
+  }
+
+  #[test]
+  fn priority_overrides() {
+    let config = ExtractorConfig::parse(
+      r#"
+        [[extractor]]
+        name = "scans"
+        regex = '^scan_(?P<year>\d{4})'
+        confidence = "Year"
+
+        [priorities]
+        scans = 95
+        whatsapp = 15
+      "#,
+    )
+    .unwrap();
+    let mut registry = DateExtractorRegistry::with_builtin_extractors(DateRangePolicy::default());
+    config.apply(&mut registry).unwrap();
+    let priorities = registry.priorities().collect::<Vec<_>>();
+    assert!(priorities.contains(&("scans", 95)));
+    assert!(priorities.contains(&("whatsapp", 15)));
+
+    let config = ExtractorConfig::parse("[priorities]\nunknown = 1").unwrap();
+    assert!(config.apply(&mut registry).is_err());
+  }
+
+  #[test]
+  fn confidence_overrides() {
+    let config = ExtractorConfig::parse("[confidence]\nwhatsapp = \"Hour\"").unwrap();
+    let mut registry = DateExtractorRegistry::with_builtin_extractors(DateRangePolicy::default());
+    config.apply(&mut registry).unwrap();
+    assert_eq!(
+      registry
+        .extract(
+          Path::new("/a/IMG-20190818-WA0001.jpg"),
+          "IMG-20190818-WA0001.jpg",
+          NaiveDateTime::MAX,
+          false,
+        )
+        .map(|ret| ret.confidence),
+      Some(DateConfidence::Hour)
+    );
+
+    assert!(ExtractorConfig::parse("[confidence]\nwhatsapp = \"Fortnight\"").is_err());
+  }
+
+  #[test]
+  fn invalid_config() {
+    assert!(
+      ExtractorConfig::parse(
+        r#"
+          [[extractor]]
+          name = "scans"
+          regex = '(?P<year>\d{4})'
+          confidence = "Fortnight"
+        "#
+      )
+      .is_err()
+    );
+    assert!(ExtractorConfig::parse("[[extractor]]\nname = \"scans\"").is_err());
+  }
+}
@@ -0,0 +1,65 @@
+use std::{
+  ffi::OsString,
+  io::{self, Read as _},
+  path::{Path, PathBuf},
+};
+
+use anyhow::Context as _;
+
+/// Reads a NUL-separated list of paths, as written by `find -print0`, from a file or from standard input if the path is `-`.
+pub fn read_file_list(list_path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+  let mut bytes = Vec::new();
+  if list_path == Path::new("-") {
+    io::stdin()
+      .lock()
+      .read_to_end(&mut bytes)
+      .context("Failed to read the file list from standard input")?;
+  } else {
+    bytes = std::fs::read(list_path)
+      .with_context(|| format!("\"{}\": Failed to read the file list", list_path.display()))?;
+  }
+  Ok(parse_file_list(&bytes))
+}
+
+/// Splits a NUL-separated list of paths, skipping empty entries such as the one after a trailing NUL.
+#[must_use]
+fn parse_file_list(bytes: &[u8]) -> Vec<PathBuf> {
+  bytes
+    .split(|byte| *byte == 0)
+    .filter(|entry| !entry.is_empty())
+    .map(|entry| PathBuf::from(os_string_from_bytes(entry)))
+    .collect()
+}
+
+#[cfg(unix)]
+fn os_string_from_bytes(bytes: &[u8]) -> OsString {
+  use std::os::unix::ffi::OsStrExt as _;
+
+  std::ffi::OsStr::from_bytes(bytes).to_os_string()
+}
+
+#[cfg(not(unix))]
+fn os_string_from_bytes(bytes: &[u8]) -> OsString {
+  OsString::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn nul_separated_paths() {
+    assert_eq!(
+      parse_file_list(b"./a b/IMG_1234.JPG\0./c\nd.png\0"),
+      vec![
+        PathBuf::from("./a b/IMG_1234.JPG"),
+        PathBuf::from("./c\nd.png")
+      ]
+    );
+    assert_eq!(
+      parse_file_list(b"\0photo.jpg\0\0"),
+      vec![PathBuf::from("photo.jpg")]
+    );
+    assert!(parse_file_list(b"").is_empty());
+  }
+}
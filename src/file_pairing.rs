@@ -0,0 +1,352 @@
+use alloc::collections::BTreeMap;
+use core::sync::atomic::Ordering;
+use std::{
+  path::{Path, PathBuf},
+  sync::Mutex,
+};
+
+use anyhow::Context as _;
+use chrono::SubsecRound as _;
+use tracing::{error, info};
+
+use crate::{
+  ProcessState,
+  date_extractors::{ConfidentNaiveDateTime, DateConfidence},
+  errors::ErrorWithFilePath,
+  exiftool::{set_exif_date, wrap_with_exiftool_repair},
+  xmp_sidecar::{date_write_target, find_xmp_sidecar},
+};
+
+/// A kind of file pair whose members must share the same date.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PairKind {
+  /// iPhone Live Photos: a photo and a short video with the same stem.
+  LivePhoto,
+  /// A RAW file and the JPEG the camera recorded alongside it.
+  RawJpeg,
+}
+
+/// Extensions of camera RAW formats.
+const RAW_EXTENSIONS: [&str; 20] = [
+  "3FR", "ARW", "CR2", "CR3", "CRW", "DCR", "DNG", "ERF", "IIQ", "K25", "KDC", "MRW", "NEF", "NRW",
+  "ORF", "PEF", "RAF", "RW2", "SRW", "X3F",
+];
+
+/// Whether `file_path` is a camera RAW file.
+#[must_use]
+pub fn is_raw_file(file_path: &Path) -> bool {
+  file_path.extension().is_some_and(|ext| {
+    RAW_EXTENSIONS
+      .iter()
+      .any(|raw_ext| ext.eq_ignore_ascii_case(raw_ext))
+  })
+}
+
+impl PairKind {
+  /// Extensions of the members that may provide the date, in order of preference for ties.
+  #[must_use]
+  const fn source_extensions(self) -> &'static [&'static str] {
+    match self {
+      Self::LivePhoto => &["HEIC", "HEIF", "JPG", "JPEG", "MOV"],
+      Self::RawJpeg => &["JPG", "JPEG"],
+    }
+  }
+
+  /// Extensions of the members that receive the date.
+  #[must_use]
+  const fn target_extensions(self) -> &'static [&'static str] {
+    match self {
+      Self::LivePhoto => &["HEIC", "HEIF", "JPG", "JPEG", "MOV"],
+      Self::RawJpeg => &RAW_EXTENSIONS,
+    }
+  }
+
+  /// The confidence a source date needs to be propagated.
+  #[must_use]
+  const fn min_source_confidence(self) -> DateConfidence {
+    match self {
+      Self::LivePhoto => DateConfidence::None,
+      Self::RawJpeg => DateConfidence::Day,
+    }
+  }
+
+  /// Whether the date is written to the XMP sidecar of a target instead of the target itself, if it has one.
+  #[must_use]
+  const fn prefers_xmp_sidecar(self) -> bool {
+    match self {
+      Self::LivePhoto => false,
+      Self::RawJpeg => true,
+    }
+  }
+
+  #[must_use]
+  const fn name(self) -> &'static str {
+    match self {
+      Self::LivePhoto => "Live Photo",
+      Self::RawJpeg => "RAW+JPEG",
+    }
+  }
+}
+
+struct PairMember {
+  file_path: PathBuf,
+  extension: String,
+  date: Option<ConfidentNaiveDateTime>,
+}
+
+/// Collects the resolved dates of media files during the per-file pass,
+/// so that the members of file pairs can be forced to the same date afterwards.
+pub struct FilePairs {
+  kinds: Vec<PairKind>,
+  /// Files grouped by their folder and their lowercase stem.
+  groups: Mutex<BTreeMap<(PathBuf, String), Vec<PairMember>>>,
+}
+
+impl FilePairs {
+  #[must_use]
+  pub const fn new(kinds: Vec<PairKind>) -> Self {
+    Self {
+      kinds,
+      groups: Mutex::new(BTreeMap::new()),
+    }
+  }
+
+  /// Records the resolved date of a media file, or its lack of one.
+  pub fn record(&self, file_path: &Path, date: Option<ConfidentNaiveDateTime>) {
+    let Some(extension) = file_path
+      .extension()
+      .and_then(|ext| ext.to_str())
+      .map(str::to_ascii_uppercase)
+    else {
+      return;
+    };
+    if !self.kinds.iter().any(|kind| {
+      kind.source_extensions().contains(&extension.as_str())
+        || kind.target_extensions().contains(&extension.as_str())
+    }) {
+      return;
+    }
+    let Some(file_stem) = file_path.file_stem() else {
+      return;
+    };
+    let folder_path = file_path
+      .parent()
+      .map(Path::to_path_buf)
+      .unwrap_or_default();
+
+    self
+      .groups
+      .lock()
+      .expect("File pairs lock should not be poisoned")
+      .entry((folder_path, file_stem.to_string_lossy().to_lowercase()))
+      .or_default()
+      .push(PairMember {
+        file_path: file_path.to_path_buf(),
+        extension,
+        date,
+      });
+  }
+
+  /// Determines the date every pair member must be set to.
+  /// This consumes the recorded files.
+  #[must_use]
+  fn resolve(&self) -> Vec<(PairKind, PathBuf, ConfidentNaiveDateTime)> {
+    let groups = core::mem::take(
+      &mut *self
+        .groups
+        .lock()
+        .expect("File pairs lock should not be poisoned"),
+    );
+
+    let mut updates = Vec::new();
+    for members in groups.values() {
+      for &kind in &self.kinds {
+        let extension_rank = |member: &PairMember| {
+          kind
+            .source_extensions()
+            .iter()
+            .position(|ext| *ext == member.extension)
+        };
+        // The most confident source wins, ties are broken by the extension preference.
+        let Some((source, source_date)) = members
+          .iter()
+          .filter_map(|member| Some((extension_rank(member)?, member, member.date?)))
+          .filter(|(_, _, date)| date.confidence >= kind.min_source_confidence())
+          .max_by(|(rank_a, _, date_a), (rank_b, _, date_b)| {
+            date_a
+              .confidence
+              .cmp(&date_b.confidence)
+              .then(rank_b.cmp(rank_a))
+          })
+          .map(|(_, member, date)| (member, date))
+        else {
+          continue;
+        };
+
+        let targets = members.iter().filter(|member| {
+          !core::ptr::eq(*member, source)
+            && kind
+              .target_extensions()
+              .contains(&member.extension.as_str())
+        });
+        for target in targets {
+          let is_same_date = target
+            .date
+            .is_some_and(|date| date.date.trunc_subsecs(0) == source_date.date.trunc_subsecs(0));
+          if !is_same_date {
+            updates.push((kind, target.file_path.clone(), source_date));
+          }
+        }
+      }
+    }
+    updates
+  }
+}
+
+/// Runs the pairing pass and writes the shared date to every pair member that deviates from it.
+pub fn apply_file_pairing(file_pairs: &FilePairs, process_state: &ProcessState) {
+  for (kind, file_path, date) in file_pairs.resolve() {
+    if process_state.should_exit.load(Ordering::Relaxed) {
+      return;
+    }
+
+    let file_path = if kind.prefers_xmp_sidecar()
+      && let Some(sidecar_path) = find_xmp_sidecar(&file_path)
+    {
+      sidecar_path
+    } else {
+      date_write_target(&file_path, process_state.options.sidecar_for_raw)
+    };
+
+    if !process_state.passes_min_confidence(&file_path, &date) {
+      continue;
+    }
+
+    info!(
+      file_path = %file_path.display(),
+      "Setting EXIF date to the date of its {} pair {}",
+      kind.name(),
+      date,
+    );
+
+    let set_exif_date_result = wrap_with_exiftool_repair(
+      &file_path,
+      process_state.options.repair_exif_errors,
+      process_state.options.dry_run,
+      || {
+        set_exif_date(
+          &file_path,
+          &date,
+          &format!("pair:{}", kind.name()),
+          process_state.options.exif_write_options(),
+        )
+      },
+    )
+    .with_context(|| {
+      format!(
+        "Failed to set EXIF date to the {} pair date {date}",
+        kind.name()
+      )
+    });
+
+    match set_exif_date_result {
+      Ok(()) => {
+        process_state
+          .stat_pairs_synced
+          .fetch_add(1, Ordering::Relaxed);
+      },
+      Err(e) => {
+        let e = ErrorWithFilePath::new(&file_path, e);
+        error!(
+          file_path = %file_path.display(),
+          source = ?e,
+          "Failed to process file",
+        );
+        process_state
+          .stat_files_errors
+          .fetch_add(1, Ordering::Relaxed);
+        process_state.record_error(&e.file_path, &e.source);
+      },
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use chrono::NaiveDateTime;
+
+  use super::*;
+
+  fn date(date: &str, confidence: DateConfidence) -> ConfidentNaiveDateTime {
+    ConfidentNaiveDateTime::new(
+      NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S").unwrap(),
+      confidence,
+    )
+  }
+
+  #[test]
+  fn live_photo_pairs() {
+    let file_pairs = FilePairs::new(vec![PairKind::LivePhoto]);
+    let photo_date = date("2021-06-01 12:00:00", DateConfidence::Second);
+    file_pairs.record(Path::new("/a/IMG_0001.HEIC"), Some(photo_date));
+    file_pairs.record(
+      Path::new("/a/IMG_0001.MOV"),
+      Some(date("2021-06-03 00:00:00", DateConfidence::Day)),
+    );
+    // Already in sync.
+    file_pairs.record(Path::new("/a/IMG_0002.HEIC"), Some(photo_date));
+    file_pairs.record(Path::new("/a/IMG_0002.mov"), Some(photo_date));
+    // The video is more confident than the photo.
+    file_pairs.record(
+      Path::new("/a/IMG_0003.JPG"),
+      Some(date("2021-01-01 00:00:00", DateConfidence::Year)),
+    );
+    file_pairs.record(Path::new("/a/IMG_0003.MOV"), Some(photo_date));
+    // Not a pair.
+    file_pairs.record(Path::new("/a/IMG_0004.MOV"), None);
+
+    let mut updates = file_pairs.resolve();
+    updates.sort_by(|a, b| a.1.cmp(&b.1));
+    assert_eq!(
+      updates,
+      vec![
+        (
+          PairKind::LivePhoto,
+          PathBuf::from("/a/IMG_0001.MOV"),
+          photo_date
+        ),
+        (
+          PairKind::LivePhoto,
+          PathBuf::from("/a/IMG_0003.JPG"),
+          photo_date
+        ),
+      ]
+    );
+  }
+
+  #[test]
+  fn raw_jpeg_pairs() {
+    let file_pairs = FilePairs::new(vec![PairKind::RawJpeg]);
+    let jpeg_date = date("2021-06-01 12:00:00", DateConfidence::Second);
+    file_pairs.record(Path::new("/a/IMG_1234.JPG"), Some(jpeg_date));
+    file_pairs.record(Path::new("/a/IMG_1234.CR2"), None);
+    // The JPEG date is not confident enough.
+    file_pairs.record(
+      Path::new("/a/IMG_1235.JPG"),
+      Some(date("2021-01-01 00:00:00", DateConfidence::Year)),
+    );
+    file_pairs.record(Path::new("/a/IMG_1235.CR2"), None);
+    // The RAW never provides the date.
+    file_pairs.record(Path::new("/a/IMG_1236.JPG"), None);
+    file_pairs.record(Path::new("/a/IMG_1236.NEF"), Some(jpeg_date));
+
+    assert_eq!(
+      file_pairs.resolve(),
+      vec![(
+        PairKind::RawJpeg,
+        PathBuf::from("/a/IMG_1234.CR2"),
+        jpeg_date
+      )]
+    );
+  }
+}
@@ -0,0 +1,142 @@
+use alloc::collections::BTreeMap;
+use core::{str::FromStr, sync::atomic::Ordering};
+use std::{
+  path::{Path, PathBuf},
+  sync::Mutex,
+};
+
+use chrono::NaiveDateTime;
+use tracing::error;
+
+use crate::{ProcessState, errors::ErrorWithFilePath, get_modified_time, set_modified_time};
+
+/// Which of the dates of the files in a folder becomes its modified time.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum FolderMtimePolicy {
+  #[default]
+  Newest,
+  Oldest,
+}
+
+impl FromStr for FolderMtimePolicy {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "newest" => Ok(Self::Newest),
+      "oldest" => Ok(Self::Oldest),
+      _ => Err(anyhow::anyhow!("Unknown folder modified time policy: {s}")),
+    }
+  }
+}
+
+/// Collects the modified times matching the resolved dates of the files in each folder during the per-file pass,
+/// so that the folders can be set to them afterwards.
+pub struct FolderDates {
+  policy: FolderMtimePolicy,
+  /// The newest or oldest modified time per folder, in UTC.
+  modified_times: Mutex<BTreeMap<PathBuf, NaiveDateTime>>,
+}
+
+impl FolderDates {
+  #[must_use]
+  pub const fn new(policy: FolderMtimePolicy) -> Self {
+    Self {
+      policy,
+      modified_times: Mutex::new(BTreeMap::new()),
+    }
+  }
+
+  /// Records the modified time matching the resolved date of a file for its folder.
+  pub fn record(&self, file_path: &Path, modified_time: NaiveDateTime) {
+    let Some(folder_path) = file_path.parent() else {
+      return;
+    };
+    let policy = self.policy;
+    self
+      .modified_times
+      .lock()
+      .expect("Folder dates lock should not be poisoned")
+      .entry(folder_path.to_path_buf())
+      .and_modify(|folder_time| {
+        *folder_time = match policy {
+          FolderMtimePolicy::Newest => (*folder_time).max(modified_time),
+          FolderMtimePolicy::Oldest => (*folder_time).min(modified_time),
+        };
+      })
+      .or_insert(modified_time);
+  }
+
+  /// The modified time of each folder. This consumes the recorded times.
+  #[must_use]
+  fn take(&self) -> BTreeMap<PathBuf, NaiveDateTime> {
+    core::mem::take(
+      &mut *self
+        .modified_times
+        .lock()
+        .expect("Folder dates lock should not be poisoned"),
+    )
+  }
+}
+
+/// Sets the modified time of every folder with dated files to the newest or oldest of their dates.
+/// This runs after all files are written, which changes the modified time of their folders.
+pub fn apply_folder_mtimes(folder_dates: &FolderDates, process_state: &ProcessState) {
+  for (folder_path, modified_time) in folder_dates.take() {
+    if process_state.should_exit.load(Ordering::Relaxed) {
+      return;
+    }
+
+    if get_modified_time(&folder_path).is_ok_and(|folder_modified_time| {
+      process_state.mtimes_match(&folder_modified_time, &modified_time)
+    }) {
+      continue;
+    }
+
+    match set_modified_time(&folder_path, &modified_time, process_state) {
+      Ok(()) => {
+        process_state
+          .stat_folder_mtimes_updated
+          .fetch_add(1, Ordering::Relaxed);
+      },
+      Err(e) => {
+        let e = ErrorWithFilePath::new(&folder_path, e);
+        error!(
+          file_path = %folder_path.display(),
+          source = ?e,
+          "Failed to set folder modified time",
+        );
+        process_state
+          .stat_files_errors
+          .fetch_add(1, Ordering::Relaxed);
+        process_state.record_error(&e.file_path, &e.source);
+      },
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn folder_dates() {
+    let date = |date: &str| NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S").unwrap();
+    for (policy, expected) in [
+      (FolderMtimePolicy::Newest, "2019-08-20 10:00:00"),
+      (FolderMtimePolicy::Oldest, "2019-08-18 14:30:00"),
+    ] {
+      let folder_dates = FolderDates::new(policy);
+      folder_dates.record(Path::new("/photos/a.jpg"), date("2019-08-18 14:30:00"));
+      folder_dates.record(Path::new("/photos/b.jpg"), date("2019-08-20 10:00:00"));
+      folder_dates.record(Path::new("/photos/2018/c.jpg"), date("2018-01-01 12:00:00"));
+      let modified_times = folder_dates.take();
+      assert_eq!(modified_times[Path::new("/photos")], date(expected));
+      assert_eq!(
+        modified_times[Path::new("/photos/2018")],
+        date("2018-01-01 12:00:00")
+      );
+      assert!(folder_dates.take().is_empty());
+    }
+  }
+}
@@ -0,0 +1,235 @@
+use alloc::sync::Arc;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::Context as _;
+use regex::Regex;
+
+/// Name of the files that exclude paths in their folder and below from processing.
+pub const IGNORE_FILE_NAME: &str = ".imagedatefixerignore";
+
+/// A single pattern of an ignore file.
+#[derive(Debug)]
+struct IgnoreRule {
+  regex: Regex,
+  /// `!pattern`: re-includes paths ignored by an earlier rule.
+  negated: bool,
+  /// `pattern/`: only matches folders.
+  folder_only: bool,
+  /// Patterns with a slash match the path relative to the ignore file, others match the name at any depth.
+  anchored: bool,
+}
+
+/// The rules of one ignore file, which uses a subset of the gitignore syntax:
+/// `#` comments, `!` negation, a trailing `/` for folders, a leading `/` to anchor the pattern and `*`, `**`, `?` and `[...]` wildcards.
+///
+/// Example:
+/// ```text
+/// # Exported thumbnails
+/// *.thumb.jpg
+/// /scans/raw/
+/// !scans/raw/keep.jpg
+/// ```
+#[derive(Debug)]
+pub struct IgnoreFile {
+  folder_path: PathBuf,
+  rules: Vec<IgnoreRule>,
+}
+
+impl IgnoreFile {
+  /// Loads the ignore file of a folder, if it has one.
+  pub fn load(folder_path: &Path) -> anyhow::Result<Option<Self>> {
+    let ignore_file_path = folder_path.join(IGNORE_FILE_NAME);
+    if !ignore_file_path.is_file() {
+      return Ok(None);
+    }
+    let content = std::fs::read_to_string(&ignore_file_path).with_context(|| {
+      format!(
+        "\"{}\": Failed to read the ignore file",
+        ignore_file_path.display()
+      )
+    })?;
+    Self::parse(folder_path, &content)
+      .with_context(|| {
+        format!(
+          "\"{}\": Failed to parse the ignore file",
+          ignore_file_path.display()
+        )
+      })
+      .map(Some)
+  }
+
+  pub fn parse(folder_path: &Path, content: &str) -> anyhow::Result<Self> {
+    let mut rules = Vec::new();
+    for line in content.lines() {
+      let line = line.trim_end();
+      if line.is_empty() || line.starts_with('#') {
+        continue;
+      }
+      let (negated, pattern) = match line.strip_prefix('!') {
+        Some(pattern) => (true, pattern),
+        None => (false, line),
+      };
+      let (folder_only, pattern) = match pattern.strip_suffix('/') {
+        Some(pattern) => (true, pattern),
+        None => (false, pattern),
+      };
+      let anchored = pattern.contains('/');
+      let pattern = pattern.trim_start_matches('/');
+      if pattern.is_empty() {
+        continue;
+      }
+      rules.push(IgnoreRule {
+        regex: Regex::new(&format!("^{}$", glob_to_regex(pattern)))
+          .with_context(|| format!("Invalid pattern: {line}"))?,
+        negated,
+        folder_only,
+        anchored,
+      });
+    }
+    Ok(Self {
+      folder_path: folder_path.to_path_buf(),
+      rules,
+    })
+  }
+
+  /// Whether the last rule matching `path` ignores or re-includes it, `None` if no rule matches.
+  fn matches(&self, path: &Path, is_folder: bool) -> Option<bool> {
+    let relative_path = path.strip_prefix(&self.folder_path).ok()?;
+    let relative_path = relative_path
+      .components()
+      .filter_map(|component| match component {
+        Component::Normal(name) => Some(name.to_string_lossy()),
+        _ => None,
+      })
+      .collect::<Vec<_>>();
+    let file_name = relative_path.last()?;
+    let relative_path = relative_path.join("/");
+
+    self
+      .rules
+      .iter()
+      .rev()
+      .find(|rule| {
+        (!rule.folder_only || is_folder)
+          && if rule.anchored {
+            rule.regex.is_match(&relative_path)
+          } else {
+            rule.regex.is_match(file_name)
+          }
+      })
+      .map(|rule| !rule.negated)
+  }
+}
+
+/// The ignore files of a folder and its ancestors, passed down the directory walker.
+#[derive(Debug, Default, Clone)]
+pub struct IgnoreFiles(Vec<Arc<IgnoreFile>>);
+
+impl IgnoreFiles {
+  pub fn push(&mut self, ignore_file: IgnoreFile) {
+    self.0.push(Arc::new(ignore_file));
+  }
+
+  /// Whether `path` is ignored. Deeper ignore files take precedence over the ones of ancestor folders.
+  #[must_use]
+  pub fn is_ignored(&self, path: &Path, is_folder: bool) -> bool {
+    self
+      .0
+      .iter()
+      .rev()
+      .find_map(|ignore_file| ignore_file.matches(path, is_folder))
+      .unwrap_or(false)
+  }
+}
+
+/// Translates a gitignore-style glob into a regex.
+fn glob_to_regex(glob: &str) -> String {
+  let mut regex = String::new();
+  let mut chars = glob.chars().peekable();
+  while let Some(c) = chars.next() {
+    match c {
+      '*' if chars.peek() == Some(&'*') => {
+        chars.next();
+        if chars.peek() == Some(&'/') {
+          chars.next();
+          regex.push_str("(?:.*/)?");
+        } else {
+          regex.push_str(".*");
+        }
+      },
+      '*' => regex.push_str("[^/]*"),
+      '?' => regex.push_str("[^/]"),
+      '[' => {
+        let rest = chars.clone().collect::<String>();
+        match rest.find(']') {
+          Some(end) if end > 0 => {
+            let class = &rest[..end];
+            // Skip the class and the closing bracket.
+            for _ in 0..=class.chars().count() {
+              chars.next();
+            }
+            let (negation, class) = match class.strip_prefix('!') {
+              Some(class) => ("^", class),
+              None => ("", class),
+            };
+            regex.push('[');
+            regex.push_str(negation);
+            regex.push_str(&class.replace('\\', r"\\").replace('[', r"\["));
+            regex.push(']');
+          },
+          _ => regex.push_str(r"\["),
+        }
+      },
+      '\\' => {
+        if let Some(escaped) = chars.next() {
+          regex.push_str(&regex::escape(&escaped.to_string()));
+        }
+      },
+      _ => regex.push_str(&regex::escape(&c.to_string())),
+    }
+  }
+  regex
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn ignore_rules() {
+    let ignore_file = IgnoreFile::parse(
+      Path::new("/photos"),
+      "# comment\n\n*.thumb.jpg\n/scans/raw/\n!scans/raw/keep.jpg\ncache/\nIMG_[0-9]?.JPG\ndocs/**/*.png\n",
+    )
+    .unwrap();
+    let is_ignored = |path: &str, is_folder: bool| {
+      ignore_file
+        .matches(Path::new(path), is_folder)
+        .unwrap_or(false)
+    };
+
+    assert!(is_ignored("/photos/a/b/IMG_1.thumb.jpg", false));
+    assert!(!is_ignored("/photos/a/b/IMG_1.jpg", false));
+    assert!(is_ignored("/photos/scans/raw", true));
+    assert!(!is_ignored("/photos/scans/raw", false));
+    assert!(!is_ignored("/photos/old/scans/raw", true));
+    assert!(!is_ignored("/photos/scans/raw/keep.jpg", false));
+    assert!(is_ignored("/photos/2020/cache", true));
+    assert!(is_ignored("/photos/IMG_12.JPG", false));
+    assert!(!is_ignored("/photos/IMG_123.JPG", false));
+    assert!(is_ignored("/photos/docs/a.png", false));
+    assert!(is_ignored("/photos/docs/a/b/c.png", false));
+    assert!(!is_ignored("/elsewhere/IMG_1.thumb.jpg", false));
+  }
+
+  #[test]
+  fn nested_ignore_files() {
+    let mut ignore_files = IgnoreFiles::default();
+    ignore_files.push(IgnoreFile::parse(Path::new("/photos"), "*.png\n").unwrap());
+    ignore_files.push(IgnoreFile::parse(Path::new("/photos/screenshots"), "!*.png\n").unwrap());
+
+    assert!(ignore_files.is_ignored(Path::new("/photos/a.png"), false));
+    assert!(!ignore_files.is_ignored(Path::new("/photos/screenshots/a.png"), false));
+    assert!(!ignore_files.is_ignored(Path::new("/photos/a.jpg"), false));
+  }
+}
@@ -0,0 +1,157 @@
+use core::str::FromStr;
+use std::{
+  ffi::OsString,
+  fs::File,
+  io::{self, Write},
+  path::{Path, PathBuf},
+};
+
+use anyhow::Context as _;
+use chrono::{DateTime, Local, NaiveDate};
+
+use crate::backups::parse_size;
+
+/// How many rotated log files are kept next to the current one, as `<log file>.1` (newest) to `<log file>.5`.
+const KEPT_LOG_FILES: usize = 5;
+
+/// When the log file is rotated.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum LogRotation {
+  /// On the first event of a new day.
+  Daily,
+  /// Before the log file would grow beyond this many bytes.
+  Size(u64),
+}
+
+impl FromStr for LogRotation {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    if s == "daily" {
+      Ok(Self::Daily)
+    } else {
+      Ok(Self::Size(parse_size(s)?))
+    }
+  }
+}
+
+/// A log file that is appended to and rotated by size or day, so long runs keep a persistent record.
+pub struct RotatingLogFile {
+  path: PathBuf,
+  rotation: LogRotation,
+  file: File,
+  size: u64,
+  /// The local date of the last write, for daily rotation.
+  date: NaiveDate,
+}
+
+fn open_log_file(path: &Path) -> io::Result<File> {
+  File::options().create(true).append(true).open(path)
+}
+
+/// `<log file>.<index>`
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+  let mut rotated_path = OsString::from(path.as_os_str());
+  rotated_path.push(format!(".{index}"));
+  PathBuf::from(rotated_path)
+}
+
+impl RotatingLogFile {
+  pub fn open(path: &Path, rotation: LogRotation) -> anyhow::Result<Self> {
+    let file = open_log_file(path)
+      .with_context(|| format!("\"{}\": Failed to open the log file", path.display()))?;
+    let metadata = file.metadata()?;
+    // A log file left by an earlier run is rotated on the first event if it is from another day.
+    let date = metadata
+      .modified()
+      .map_or_else(|_| Local::now(), DateTime::<Local>::from)
+      .date_naive();
+    Ok(Self {
+      path: path.to_path_buf(),
+      rotation,
+      file,
+      size: metadata.len(),
+      date,
+    })
+  }
+
+  fn needs_rotation(&self, write_size: usize, today: NaiveDate) -> bool {
+    match self.rotation {
+      LogRotation::Daily => today != self.date,
+      LogRotation::Size(max_size) => {
+        self.size > 0 && self.size.saturating_add(write_size as u64) > max_size
+      },
+    }
+  }
+
+  fn rotate(&mut self) -> io::Result<()> {
+    for index in (1..KEPT_LOG_FILES).rev() {
+      let older_path = rotated_path(&self.path, index);
+      if older_path.exists() {
+        std::fs::rename(&older_path, rotated_path(&self.path, index + 1))?;
+      }
+    }
+    std::fs::rename(&self.path, rotated_path(&self.path, 1))?;
+    self.file = open_log_file(&self.path)?;
+    self.size = 0;
+    Ok(())
+  }
+}
+
+impl Write for RotatingLogFile {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    let today = Local::now().date_naive();
+    if self.needs_rotation(buf.len(), today) {
+      self.rotate()?;
+    }
+    self.date = today;
+    let written = self.file.write(buf)?;
+    self.size += written as u64;
+    Ok(written)
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    self.file.flush()
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn rotation() {
+    let folder_path = std::env::temp_dir().join(format!(
+      "image-date-fixer-log-file-test-{}",
+      std::process::id()
+    ));
+    std::fs::create_dir_all(&folder_path).unwrap();
+    let log_path = folder_path.join("image-date-fixer.log");
+
+    let mut log_file = RotatingLogFile::open(&log_path, LogRotation::Size(10)).unwrap();
+    for line in ["first\n", "second\n", "third\n"] {
+      log_file.write_all(line.as_bytes()).unwrap();
+    }
+    assert_eq!(std::fs::read_to_string(&log_path).unwrap(), "third\n");
+    assert_eq!(
+      std::fs::read_to_string(rotated_path(&log_path, 1)).unwrap(),
+      "second\n"
+    );
+    assert_eq!(
+      std::fs::read_to_string(rotated_path(&log_path, 2)).unwrap(),
+      "first\n"
+    );
+
+    let yesterday = log_file.date.pred_opt().unwrap();
+    log_file.rotation = LogRotation::Daily;
+    assert!(!log_file.needs_rotation(100, log_file.date));
+    log_file.date = yesterday;
+    assert!(log_file.needs_rotation(0, Local::now().date_naive()));
+    std::fs::remove_dir_all(&folder_path).unwrap();
+
+    assert_eq!(
+      LogRotation::from_str("10MiB").unwrap(),
+      LogRotation::Size(10 * 1024 * 1024)
+    );
+  }
+}
@@ -1,14 +1,117 @@
-use core::fmt::{self, Write as _};
-use std::io;
+use core::{
+  fmt::{self, Write as _},
+  str::FromStr,
+};
+use std::{
+  io::{self, IsTerminal as _},
+  sync::Mutex,
+};
 
-use tracing::{Event, Level, Subscriber, field::Visit, level_filters::LevelFilter};
+use serde_json::{Map, Value};
+use tracing::{Event, Level, Subscriber, field::Visit, level_filters::LevelFilter, span::Record};
 use tracing_subscriber::{
-  EnvFilter,
-  fmt::{FmtContext, FormatEvent, FormatFields, format::Writer},
+  EnvFilter, Layer, Registry,
+  field::RecordFields,
+  fmt::{FmtContext, FormatEvent, FormatFields, FormattedFields, MakeWriter, format::Writer},
+  layer::SubscriberExt as _,
   registry::LookupSpan,
+  util::SubscriberInitExt as _,
 };
 
-struct CustomFormatter;
+use crate::{
+  log_file::RotatingLogFile,
+  progress::log_writer,
+  system_log::{LogTarget, SystemLogLayer},
+};
+
+/// How log events are written.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum LogFormat {
+  /// Colored, human readable lines.
+  #[default]
+  Pretty,
+  /// One short line per event with the time, level, file name and message.
+  Compact,
+  /// The default format of tracing, including the span context.
+  Full,
+  /// One JSON object per line, e.g. for Loki or Elasticsearch.
+  Json,
+}
+
+impl FromStr for LogFormat {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "pretty" => Ok(Self::Pretty),
+      "compact" => Ok(Self::Compact),
+      "full" => Ok(Self::Full),
+      "json" => Ok(Self::Json),
+      _ => Err(anyhow::anyhow!("Unknown log format: {s}")),
+    }
+  }
+}
+
+/// Whether the log on stdout is colored.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum ColorChoice {
+  /// Only if stdout is a terminal and `NO_COLOR` is not set.
+  #[default]
+  Auto,
+  Always,
+  Never,
+}
+
+impl FromStr for ColorChoice {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "auto" => Ok(Self::Auto),
+      "always" => Ok(Self::Always),
+      "never" => Ok(Self::Never),
+      _ => Err(anyhow::anyhow!("Unknown color choice: {s}")),
+    }
+  }
+}
+
+impl ColorChoice {
+  /// See <https://no-color.org>, an explicit `--color always` still wins over `NO_COLOR`.
+  const fn use_colors(self, no_color: bool, is_terminal: bool) -> bool {
+    match self {
+      Self::Auto => !no_color && is_terminal,
+      Self::Always => true,
+      Self::Never => false,
+    }
+  }
+}
+
+/// The level selected by `-q` or `-v`, `-vv` and so on, `None` without either.
+#[must_use]
+pub const fn verbosity_level(quiet: bool, verbosity: u8) -> Option<Level> {
+  match (quiet, verbosity) {
+    (true, _) => Some(Level::ERROR),
+    (false, 0) => None,
+    (false, 1) => Some(Level::DEBUG),
+    (false, _) => Some(Level::TRACE),
+  }
+}
+
+/// The ANSI color and the padded name of a level.
+const fn level_style(level: Level) -> (&'static str, &'static str) {
+  match level {
+    Level::TRACE => ("\x1b[35m", "TRACE"), // Purple
+    Level::DEBUG => ("\x1b[34m", "DEBUG"), // Blue
+    Level::INFO => ("\x1b[32m", "INFO "),  // Green (padded for alignment)
+    Level::WARN => ("\x1b[33m", "WARN "),  // Yellow
+    Level::ERROR => ("\x1b[31m", "ERROR"), // Red
+  }
+}
+
+struct CustomFormatter {
+  /// Whether the level is colored with ANSI escape codes.
+  ansi: bool,
+}
 
 impl<S, N> FormatEvent<S, N> for CustomFormatter
 where
@@ -30,16 +133,13 @@ where
     write!(writer, "{}  ", now.format("%Y-%m-%dT%H:%M:%S.%fZ"))?;
 
     // Render Level with Colors
-    let level = *event.metadata().level();
-    let (color_start, level_str) = match level {
-      Level::TRACE => ("\x1b[35m", "TRACE"), // Purple
-      Level::DEBUG => ("\x1b[34m", "DEBUG"), // Blue
-      Level::INFO => ("\x1b[32m", "INFO "),  // Green (padded for alignment)
-      Level::WARN => ("\x1b[33m", "WARN "),  // Yellow
-      Level::ERROR => ("\x1b[31m", "ERROR"), // Red
-    };
-    // Reset color
-    write!(writer, "{color_start}{level_str} \x1b[0m")?;
+    let (color_start, level_str) = level_style(*event.metadata().level());
+    if self.ansi {
+      // Reset color
+      write!(writer, "{color_start}{level_str} \x1b[0m")?;
+    } else {
+      write!(writer, "{level_str} ")?;
+    }
 
     // Render Target
     write!(writer, "{}: ", event.metadata().target())?;
@@ -63,11 +163,53 @@ where
   }
 }
 
+/// Writes one short line per event, e.g. `21:30:44 I IMG_0001.JPG: Setting EXIF date ...`.
+/// Other fields than the message are left out.
+struct CompactFormatter {
+  /// Whether the level is colored with ANSI escape codes.
+  ansi: bool,
+}
+
+impl<S, N> FormatEvent<S, N> for CompactFormatter
+where
+  S: Subscriber + for<'a> LookupSpan<'a>,
+  N: for<'a> FormatFields<'a> + 'static,
+{
+  fn format_event(
+    &self,
+    _ctx: &FmtContext<'_, S, N>,
+    mut writer: Writer<'_>,
+    event: &Event<'_>,
+  ) -> fmt::Result {
+    let mut visitor = PathVisitor::default();
+    event.record(&mut visitor);
+
+    write!(writer, "{} ", chrono::Local::now().format("%H:%M:%S"))?;
+    let (color_start, level_str) = level_style(*event.metadata().level());
+    let level_letter = &level_str[..1];
+    if self.ansi {
+      write!(writer, "{color_start}{level_letter}\x1b[0m ")?;
+    } else {
+      write!(writer, "{level_letter} ")?;
+    }
+    if let Some(path) = &visitor.file_path {
+      let file_name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+      write!(writer, "{file_name}: ")?;
+    }
+    if let Some(msg) = visitor.message {
+      write!(writer, "{msg}")?;
+    }
+    writeln!(writer)
+  }
+}
+
+/// Splits the fields of an event into the file path, the message and the remaining fields.
 #[derive(Default)]
-struct PathVisitor {
-  file_path: Option<String>,
-  message: Option<String>,
-  others: String,
+pub struct PathVisitor {
+  pub file_path: Option<String>,
+  pub message: Option<String>,
+  /// Formatted as ` name=value` each.
+  pub others: String,
 }
 
 impl Visit for PathVisitor {
@@ -84,19 +226,259 @@ impl Visit for PathVisitor {
   }
 }
 
-pub fn setup_logging(log_level: Option<Level>) {
-  let logging_builder = tracing_subscriber::fmt::fmt()
-    .with_writer(io::stdout)
-    .event_format(CustomFormatter);
-  if let Some(level) = log_level {
-    logging_builder.with_max_level(level).init();
+/// Writes every event as a JSON object with `timestamp`, `level`, `target`, `file_path`, `message`,
+/// the remaining `fields` and the `spans` it is in, outermost first.
+struct JsonFormatter;
+
+impl<S, N> FormatEvent<S, N> for JsonFormatter
+where
+  S: Subscriber + for<'a> LookupSpan<'a>,
+  N: for<'a> FormatFields<'a> + 'static,
+{
+  fn format_event(
+    &self,
+    ctx: &FmtContext<'_, S, N>,
+    mut writer: Writer<'_>,
+    event: &Event<'_>,
+  ) -> fmt::Result {
+    let mut visitor = JsonVisitor::default();
+    event.record(&mut visitor);
+
+    // The span fields are stored as JSON by `JsonFields`.
+    let spans = ctx
+      .event_scope()
+      .into_iter()
+      .flat_map(tracing_subscriber::registry::Scope::from_root)
+      .map(|span| {
+        let mut fields = span
+          .extensions()
+          .get::<FormattedFields<N>>()
+          .and_then(|fields| serde_json::from_str::<Map<String, Value>>(&fields.fields).ok())
+          .unwrap_or_default();
+        fields.insert(String::from("name"), span.name().into());
+        Value::Object(fields)
+      })
+      .collect::<Vec<_>>();
+
+    let line = serde_json::json!({
+      "timestamp": chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S.%fZ").to_string(),
+      "level": event.metadata().level().as_str(),
+      "target": event.metadata().target(),
+      "file_path": visitor.file_path,
+      "message": visitor.message,
+      "fields": visitor.fields,
+      "spans": spans,
+    });
+    writeln!(writer, "{line}")
+  }
+}
+
+#[derive(Default)]
+struct JsonVisitor {
+  file_path: Option<String>,
+  message: Option<String>,
+  fields: Map<String, Value>,
+}
+
+impl JsonVisitor {
+  fn record_value(&mut self, field: &tracing::field::Field, value: Value) {
+    self.fields.insert(field.name().to_string(), value);
+  }
+
+  /// All fields, including the file path and message.
+  fn into_fields(self) -> Map<String, Value> {
+    let mut fields = self.fields;
+    if let Some(file_path) = self.file_path {
+      fields.insert(String::from("file_path"), file_path.into());
+    }
+    if let Some(message) = self.message {
+      fields.insert(String::from("message"), message.into());
+    }
+    fields
+  }
+}
+
+/// Stores the fields of spans as a JSON object for `JsonFormatter`.
+struct JsonFields;
+
+impl<'writer> FormatFields<'writer> for JsonFields {
+  fn format_fields<R: RecordFields>(&self, mut writer: Writer<'writer>, fields: R) -> fmt::Result {
+    let mut visitor = JsonVisitor::default();
+    fields.record(&mut visitor);
+    write!(writer, "{}", Value::Object(visitor.into_fields()))
+  }
+
+  fn add_fields(
+    &self,
+    current: &'writer mut FormattedFields<Self>,
+    fields: &Record<'_>,
+  ) -> fmt::Result {
+    let mut visitor = JsonVisitor::default();
+    fields.record(&mut visitor);
+    let mut merged_fields =
+      serde_json::from_str::<Map<String, Value>>(&current.fields).unwrap_or_default();
+    merged_fields.extend(visitor.into_fields());
+    current.fields = Value::Object(merged_fields).to_string();
+    Ok(())
+  }
+}
+
+impl Visit for JsonVisitor {
+  fn record_i64(&mut self, field: &tracing::field::Field, value: i64) {
+    self.record_value(field, value.into());
+  }
+
+  fn record_u64(&mut self, field: &tracing::field::Field, value: u64) {
+    self.record_value(field, value.into());
+  }
+
+  fn record_f64(&mut self, field: &tracing::field::Field, value: f64) {
+    self.record_value(field, value.into());
+  }
+
+  fn record_bool(&mut self, field: &tracing::field::Field, value: bool) {
+    self.record_value(field, value.into());
+  }
+
+  fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+    match field.name() {
+      "file_path" => self.file_path = Some(value.to_string()),
+      "message" => self.message = Some(value.to_string()),
+      _ => self.record_value(field, value.into()),
+    }
+  }
+
+  fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn fmt::Debug) {
+    let value = format!("{value:?}");
+    match field.name() {
+      "file_path" => self.file_path = Some(value),
+      "message" => self.message = Some(value),
+      _ => self.record_value(field, value.into()),
+    }
+  }
+}
+
+fn format_layer<W>(
+  log_format: LogFormat,
+  ansi: bool,
+  writer: W,
+) -> Box<dyn Layer<Registry> + Send + Sync>
+where
+  W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+  let layer = tracing_subscriber::fmt::layer().with_writer(writer);
+  match log_format {
+    LogFormat::Pretty => layer.event_format(CustomFormatter { ansi }).boxed(),
+    LogFormat::Compact => layer.event_format(CompactFormatter { ansi }).boxed(),
+    // Colors need the `ansi` feature of tracing-subscriber.
+    LogFormat::Full => layer.boxed(),
+    LogFormat::Json => layer
+      .fmt_fields(JsonFields)
+      .event_format(JsonFormatter)
+      .boxed(),
+  }
+}
+
+/// Logs to stdout, or journald or syslog, and with `log_file` to that file as well. The log file is never colored.
+pub fn setup_logging(
+  log_level: Option<Level>,
+  log_format: LogFormat,
+  color_choice: ColorChoice,
+  log_target: LogTarget,
+  log_file: Option<RotatingLogFile>,
+) -> anyhow::Result<()> {
+  let mut layers = if log_target == LogTarget::Stdout {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|no_color| !no_color.is_empty());
+    let ansi = color_choice.use_colors(no_color, io::stdout().is_terminal());
+    vec![format_layer(log_format, ansi, log_writer)]
   } else {
-    logging_builder
-      .with_env_filter(
-        EnvFilter::builder()
-          .with_default_directive(LevelFilter::INFO.into())
-          .from_env_lossy(),
-      )
-      .init();
+    vec![SystemLogLayer::connect(log_target)?.boxed()]
+  };
+  if let Some(log_file) = log_file {
+    layers.push(format_layer(log_format, false, Mutex::new(log_file)));
+  }
+  let env_filter = log_level.is_none().then(|| {
+    EnvFilter::builder()
+      .with_default_directive(LevelFilter::INFO.into())
+      .from_env_lossy()
+  });
+  tracing_subscriber::registry()
+    .with(layers)
+    .with(log_level.map(LevelFilter::from_level))
+    .with(env_filter)
+    .init();
+  Ok(())
+}
+
+#[cfg(test)]
+mod test {
+  use alloc::sync::Arc;
+  use std::sync::Mutex;
+
+  use tracing::info;
+
+  use super::*;
+
+  #[test]
+  fn json_lines() {
+    let output = Arc::new(Mutex::new(Vec::new()));
+    let writer_output = Arc::clone(&output);
+    let subscriber = tracing_subscriber::fmt::fmt()
+      .with_writer(move || WriterGuard(Arc::clone(&writer_output)))
+      .fmt_fields(JsonFields)
+      .event_format(JsonFormatter)
+      .finish();
+    tracing::subscriber::with_default(subscriber, || {
+      let _span = tracing::info_span!("directory", path = %"/photos").entered();
+      info!(
+        file_path = %"/photos/IMG_1.jpg",
+        tag = "DateTimeOriginal",
+        retries = 2,
+        "Set EXIF date",
+      );
+    });
+
+    let output = String::from_utf8(output.lock().unwrap().clone()).unwrap();
+    let line: Value = serde_json::from_str(output.trim_end()).unwrap();
+    assert_eq!(line["level"], "INFO");
+    assert_eq!(line["file_path"], "/photos/IMG_1.jpg");
+    assert_eq!(line["message"], "Set EXIF date");
+    assert_eq!(
+      line["fields"],
+      serde_json::json!({ "tag": "DateTimeOriginal", "retries": 2 })
+    );
+    assert_eq!(
+      line["spans"],
+      serde_json::json!([{ "name": "directory", "path": "/photos" }])
+    );
+  }
+
+  #[test]
+  fn colors() {
+    assert!(ColorChoice::Auto.use_colors(false, true));
+    assert!(!ColorChoice::Auto.use_colors(true, true));
+    assert!(!ColorChoice::Auto.use_colors(false, false));
+    assert!(ColorChoice::Always.use_colors(true, false));
+    assert!(!ColorChoice::Never.use_colors(false, true));
+  }
+
+  #[test]
+  fn verbosity() {
+    assert_eq!(verbosity_level(false, 0), None);
+    assert_eq!(verbosity_level(true, 0), Some(Level::ERROR));
+    assert_eq!(verbosity_level(false, 1), Some(Level::DEBUG));
+    assert_eq!(verbosity_level(false, 3), Some(Level::TRACE));
+  }
+
+  struct WriterGuard(Arc<Mutex<Vec<u8>>>);
+
+  impl io::Write for WriterGuard {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+      self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+      Ok(())
+    }
   }
 }
@@ -2,45 +2,148 @@
 
 extern crate alloc;
 
+mod backups;
+mod busy_retry;
+mod change_plan;
+mod change_report;
+mod checkpoint;
+mod companion_files;
+mod config_file;
+mod corroboration;
+mod creation_time;
 mod date_extractors;
+mod date_shift;
+mod error_summary;
 mod errors;
+mod event_stream;
+mod exif_confidence;
 mod exiftool;
+mod extension_stats;
+mod extractor_config;
+mod file_list;
+mod file_pairing;
+mod folder_mtimes;
+mod ignore_files;
+mod log_file;
 mod logging;
+mod metrics;
+#[cfg(feature = "native-exif-read")]
+mod native_exif;
+mod notify;
+mod organize;
+mod parse_command;
+mod plan_review;
 mod pretty_duration;
+mod progress;
+mod provenance;
+mod retry;
+#[cfg(feature = "tui")]
+mod review_screen;
+mod run_lock;
+mod sibling_interpolation;
+mod system_log;
+#[cfg(unix)]
+mod systemd_notify;
+mod text_dates;
 mod tie_command_to_self;
+mod timezone;
+mod undo_journal;
+mod unresolved_files;
+mod verification;
+mod xmp_sidecar;
+mod year_histogram;
 
-use alloc::{collections::BTreeSet, sync::Arc};
+use alloc::{
+  collections::{BTreeMap, BTreeSet},
+  sync::Arc,
+};
 use core::{
+  net::SocketAddr,
   str::FromStr as _,
   sync::atomic::{AtomicBool, AtomicUsize, Ordering},
 };
 use std::{
-  io::{self, Write as _},
+  fs::Metadata,
+  io::{self, IsTerminal as _, Write as _},
   path::{Path, PathBuf},
   process::exit,
+  sync::Mutex,
+  time::Instant,
 };
 
 use anyhow::{Context as _, bail};
-use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Utc};
+use chrono::{
+  DateTime, Datelike, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime, Offset as _,
+  SubsecRound as _, TimeDelta, TimeZone as _, Utc,
+};
 use clap::{Arg, ArgAction, command, value_parser};
-use jwalk::WalkDir;
+use jwalk::WalkDirGeneric;
 use rayon::prelude::*;
-use tracing::{Level, error, info, trace, warn};
+use tracing::{Level, debug, error, info, info_span, trace, warn};
 
-use date_extractors::{ConfidentNaiveDateTime, DateConfidence, get_date_for_file};
+use date_extractors::{
+  ConfidentNaiveDateTime, DEFAULT_CUSTOM_EXTRACTOR_PRIORITY, DEFAULT_MIN_YEAR, DateConfidence,
+  DateExtractorRegistry, DateRangePolicy, RegexDateExtractor, date_extractor_registry,
+  install_date_extractor_registry,
+};
+#[cfg(feature = "native-jpeg-write")]
+use exiftool::disable_exiftool;
 use exiftool::{
-  exiftool_writable_file_extensions, get_exif_date, has_exiftool, set_exif_date,
-  wrap_with_exiftool_repair,
+  ExifDates, ExifDatesCache, ExifWriteOptions, MIN_EXIFTOOL_VERSION, exiftool_version,
+  exiftool_writable_file_extensions, get_exif_dates, get_exif_text_values, install_exiftool_pool,
+  kill_exiftool_workers, modern_image_format_support, set_exif_date, set_exif_date_if_missing,
+  set_exiftool_path, wrap_with_exiftool_repair,
 };
 
-use crate::{errors::ErrorWithFilePath, logging::setup_logging};
+use crate::{
+  backups::{BackupMode, backups, install_backups, parse_size, pretty_size},
+  busy_retry::{is_busy_io_error, retry_while_busy},
+  change_plan::{ChangeList, apply_change_plan},
+  change_report::{write_csv_report, write_json_report},
+  checkpoint::Checkpoint,
+  companion_files::{find_primary_file, sync_companion_files},
+  config_file::args_with_config_file,
+  corroboration::{ReviewList, is_corroborated},
+  creation_time::{CAN_SET_CREATION_TIME, get_creation_time, sync_creation_time},
+  date_shift::{DateShift, shift_file},
+  error_summary::ErrorSummary,
+  errors::ErrorWithFilePath,
+  event_stream::{FileEvent, emit_event, install_event_stream},
+  exif_confidence::ExifConfidencePolicy,
+  extension_stats::ExtensionStats,
+  extractor_config::ExtractorConfig,
+  file_list::read_file_list,
+  file_pairing::{FilePairs, PairKind, apply_file_pairing},
+  folder_mtimes::{FolderDates, FolderMtimePolicy, apply_folder_mtimes},
+  ignore_files::{IgnoreFile, IgnoreFiles},
+  log_file::{LogRotation, RotatingLogFile},
+  logging::{ColorChoice, LogFormat, setup_logging, verbosity_level},
+  metrics::serve_metrics,
+  notify::{install_notify_url, notify_completion, notify_failure},
+  organize::{OrganizeMode, Organizer, apply_organize},
+  parse_command::{run_explain_command, run_parse_command},
+  plan_review::{ReviewGrouping, run_review_command},
+  progress::{Progress, ProgressDisplay},
+  provenance::install_provenance_config,
+  retry::{FailedFiles, retry_failed_files},
+  run_lock::{LOCK_FILE_NAME, RunLock, lock_folder_paths},
+  sibling_interpolation::{SiblingDates, apply_sibling_interpolation},
+  system_log::LogTarget,
+  text_dates::extract_date_from_text,
+  timezone::{local_date_at, parse_utc_offset, utc_offset_at, utc_offset_from_gps},
+  undo_journal::{install_undo_journal, run_undo_command, undo_journal},
+  unresolved_files::{DATE_LIKE_DIGIT_COUNT, UnresolvedFiles},
+  verification::{FileDates, Verification},
+  xmp_sidecar::{date_write_target, find_xmp_sidecar, get_xmp_sidecar_date},
+  year_histogram::YearHistogram,
+};
 
 fn set_modified_time(
   file_path: &Path,
   date: &NaiveDateTime,
   process_state: &ProcessState,
 ) -> anyhow::Result<()> {
-  if process_state.dry_run {
+  if process_state.options.dry_run {
     info!(
       file_path = %file_path.display(),
       "Would set modified time to {}",
@@ -49,8 +152,16 @@ fn set_modified_time(
     return Ok(());
   }
 
+  if let Some(undo_journal) = undo_journal() {
+    undo_journal.record_modified_time(file_path)?;
+  }
+
   // TODO: https://doc.rust-lang.org/std/fs/fn.set_times.html once it is stabilized.
-  let file = std::fs::File::open(file_path);
+  let file = retry_while_busy(
+    file_path,
+    || std::fs::File::open(file_path),
+    |result| result.as_ref().is_err_and(is_busy_io_error),
+  );
   let file = match file {
     Ok(file) => file,
     Err(e) => {
@@ -63,12 +174,48 @@ fn set_modified_time(
   };
 
   let date_time = DateTime::<Utc>::from_naive_utc_and_offset(*date, Utc);
-  file
-    .set_modified(date_time.into())
-    .context("Failed to set modified time")?;
+  retry_while_busy(
+    file_path,
+    || file.set_modified(date_time.into()),
+    |result| result.as_ref().is_err_and(is_busy_io_error),
+  )
+  .context("Failed to set modified time")?;
   Ok(())
 }
 
+/// The modified time (in UTC) of the moment an EXIF date was taken.
+/// The EXIF date is a wall time in its own UTC offset, `--timezone` or else the local time zone.
+#[must_use]
+fn modified_time_from_exif_date(
+  date: &ConfidentNaiveDateTime,
+  default_utc_offset: Option<FixedOffset>,
+) -> Option<NaiveDateTime> {
+  match date.offset.or(default_utc_offset) {
+    Some(utc_offset) => {
+      Some(date.date - TimeDelta::seconds(i64::from(utc_offset.local_minus_utc())))
+    },
+    None => Local
+      .from_local_datetime(&date.date)
+      .earliest()
+      .map(|date| date.naive_utc()),
+  }
+}
+
+/// The wall time of a modified time (in UTC) in `--timezone` or else the local time zone.
+#[must_use]
+fn local_date_from_modified_time(
+  modified_time: &NaiveDateTime,
+  default_utc_offset: Option<FixedOffset>,
+) -> ConfidentNaiveDateTime {
+  let utc_offset =
+    default_utc_offset.unwrap_or_else(|| Local.offset_from_utc_datetime(modified_time).fix());
+  ConfidentNaiveDateTime::new(
+    *modified_time + TimeDelta::seconds(i64::from(utc_offset.local_minus_utc())),
+    DateConfidence::Second,
+  )
+  .with_offset(utc_offset)
+}
+
 fn get_modified_time(file_path: &Path) -> Result<NaiveDateTime, ErrorWithFilePath> {
   let metadata = std::fs::metadata(file_path)
     .map_err(|e| ErrorWithFilePath::new(file_path, e).context("Failed to get metadata for file"))?;
@@ -81,72 +228,639 @@ fn get_modified_time(file_path: &Path) -> Result<NaiveDateTime, ErrorWithFilePat
   Ok(modified_date_time.naive_utc())
 }
 
-struct ProcessState {
+/// Settings of a processing run that are derived from the command line.
+#[expect(
+  clippy::struct_excessive_bools,
+  reason = "Each bool mirrors an independent command line flag, grouping them would only add indirection."
+)]
+struct ProcessOptions {
   excluded_files: BTreeSet<PathBuf>,
+  /// Only files with these (uppercase) extensions are processed.
+  only_extensions: Option<BTreeSet<String>>,
+  /// Smaller files, usually thumbnails, are skipped.
+  min_size: Option<u64>,
+  /// Larger files, usually videos, are skipped.
+  max_size: Option<u64>,
+  /// Files last modified before this (UTC) are skipped.
+  modified_since: Option<NaiveDateTime>,
+  /// Files last modified at or after this (UTC) are skipped.
+  modified_before: Option<NaiveDateTime>,
+  /// How often files that failed are retried at the end of the per-file pass.
+  retries: u32,
+  /// Files modified less than this long before they are checked may still be written, e.g. by Syncthing or rsync, and are skipped.
+  skip_recent: Option<TimeDelta>,
   skip_hidden_files: bool,
-  should_exit: AtomicBool,
-  start_time: NaiveDateTime,
+  /// Folders containing a `.nomedia` file are skipped, like Android galleries do.
+  respect_nomedia: bool,
+  /// Folders on other file systems than their top level directory are skipped.
+  one_file_system: bool,
   dry_run: bool,
+  /// The changes are collected for `--plan` instead of being written, which implies a dry run.
+  write_plan: bool,
+  /// The changes are collected for `--report-csv` or `--report-json`.
+  report_changes: bool,
+  /// The files without any date are collected for `--unresolved-out`.
+  collect_unresolved_files: bool,
+  /// `--progress` was given and stdout is a terminal.
+  show_progress: bool,
+  /// Set by the `verify` subcommand, which only reports files whose dates disagree by more than this.
+  verify_tolerance: Option<TimeDelta>,
   modified_times_future_threshold: NaiveDateTime,
+  /// Modified times before this are clamped to it.
+  min_valid_mtime: NaiveDateTime,
+  /// Modified times closer than this are considered equal, as e.g. FAT only stores every other second.
+  mtime_granularity: TimeDelta,
   exif_dates_future_threshold: NaiveDateTime,
+  exif_confidence_policy: ExifConfidencePolicy,
+  /// Guessed dates below this confidence are reported but not written.
+  min_confidence: DateConfidence,
+  /// How many confidence levels a guess must beat an existing EXIF date by to overwrite it.
+  overwrite_margin: u8,
+  /// Existing EXIF dates are never overwritten with guessed dates.
+  only_fill_missing: bool,
+  /// Existing EXIF dates are overwritten with dates derived from the file path regardless of their confidence.
+  prefer_filename: bool,
+  /// Existing EXIF dates that differ from the guessed date by at most this much are not overwritten.
+  min_delta: TimeDelta,
+  write_all_dates: bool,
+  preserve_modified_time: bool,
+  /// The modified time of every media file is set to its resolved EXIF date.
+  sync_mtime_from_exif: bool,
+  /// The creation time of every media file is set to its resolved EXIF date, which Windows always does.
+  sync_birthtime: bool,
+  /// Files without any date get their modified or creation time as the date, at this confidence.
+  mtime_fallback_confidence: Option<DateConfidence>,
+  write_provenance: bool,
+  /// The UTC offset written for dates whose source does not state one.
+  default_utc_offset: Option<FixedOffset>,
+  write_iptc: bool,
+  /// Dates for RAW files are written to their XMP sidecar instead of the RAW file.
+  sidecar_for_raw: bool,
+  /// Guessed dates are only written if the modified time is within this window of them.
+  corroboration_window: Option<TimeDelta>,
   ignore_minor_exif_errors: bool,
   repair_exif_errors: bool,
+  folder_date_depth: usize,
+  /// Dates mentioned in the EXIF description and comment tags are used if the file has no date tag.
+  mine_text_tags: bool,
+  interpolate_sibling_dates: bool,
+  /// Folders get the newest or oldest date of their files as modified time after all files are processed.
+  folder_mtime_policy: Option<FolderMtimePolicy>,
+  sync_live_photos: bool,
+  sync_raw_jpeg_pairs: bool,
+  /// Set by the `organize` subcommand, which puts the media files into `YYYY/MM/` folders below it.
+  organize_target: Option<PathBuf>,
+  organize_mode: OrganizeMode,
+  /// Set by the `shift` subcommand, which shifts the existing EXIF dates by it instead of processing the files.
+  date_shift: Option<DateShift>,
+  /// The modified times are shifted along with the EXIF dates.
+  shift_modified_time: bool,
+}
+
+impl ProcessOptions {
+  const fn exif_write_options(&self) -> ExifWriteOptions {
+    ExifWriteOptions {
+      dry_run: self.dry_run,
+      ignore_minor_exif_errors: self.ignore_minor_exif_errors,
+      write_all_dates: self.write_all_dates,
+      preserve_modified_time: self.preserve_modified_time,
+      write_provenance: self.write_provenance,
+      default_utc_offset: self.default_utc_offset,
+      write_iptc: self.write_iptc,
+    }
+  }
+}
+
+struct ProcessState {
+  options: ProcessOptions,
+  should_exit: AtomicBool,
+  start_time: NaiveDateTime,
+  /// Only collected if sibling interpolation is enabled.
+  sibling_dates: Option<SiblingDates>,
+  /// Only collected if any kind of file pair is synced.
+  file_pairs: Option<FilePairs>,
+  /// Only collected in corroboration mode.
+  review_list: Option<ReviewList>,
+  /// Only collected with `--plan` or a change report.
+  change_list: Option<ChangeList>,
+  /// Only collected by the `verify` subcommand.
+  verification: Option<Verification>,
+  /// Only collected with `--unresolved-out`.
+  unresolved_files: Option<UnresolvedFiles>,
+  /// Only tracked with `--progress`.
+  progress: Option<Progress>,
+  /// Only written with `--checkpoint` or `--resume`.
+  checkpoint: Option<Checkpoint>,
+  /// Only collected with `--retries`.
+  failed_files: Option<FailedFiles>,
+  /// Only collected by the `organize` subcommand.
+  organizer: Option<Organizer>,
+  /// Only collected with `--fix-folder-mtimes`.
+  folder_dates: Option<FolderDates>,
+  /// EXIF dates read per directory ahead of processing the files.
+  exif_dates_cache: ExifDatesCache,
+  /// The dates guessed from the paths while deciding which files to read ahead, so they are not guessed again.
+  guessed_dates:
+    Mutex<BTreeMap<PathBuf, Option<(&'static str, &'static str, ConfidentNaiveDateTime)>>>,
+  /// Device and inode of the processed files with more than one hardlink.
+  #[cfg(unix)]
+  processed_hardlinks: Mutex<BTreeSet<(u64, u64)>>,
 
   stat_folders_checked: AtomicUsize,
   stat_folders_skipped: AtomicUsize,
+  stat_nomedia_folders_skipped: AtomicUsize,
+  stat_recent_files_skipped: AtomicUsize,
+  stat_files_fixed_by_retry: AtomicUsize,
   stat_files_checked: AtomicUsize,
   stat_media_files_checked: AtomicUsize,
   stat_files_skipped: AtomicUsize,
   stat_files_errors: AtomicUsize,
   stat_exif_updated: AtomicUsize,
   stat_exif_overwritten: AtomicUsize,
+  stat_exif_interpolated: AtomicUsize,
   stat_modified_time_updated: AtomicUsize,
+  stat_folder_mtimes_updated: AtomicUsize,
+  stat_companion_files_synced: AtomicUsize,
+  stat_pairs_synced: AtomicUsize,
+  stat_files_organized: AtomicUsize,
+  stat_below_min_confidence: AtomicUsize,
+  stat_exif_close_enough: AtomicUsize,
+  /// How often each date extractor provided the guessed date, in the order the extractors are tried.
+  stat_extractor_hits: Vec<(String, AtomicUsize)>,
+  stat_extensions: ExtensionStats,
+  stat_exif_years: YearHistogram,
+  error_summary: ErrorSummary,
 }
 
 impl ProcessState {
   #[must_use]
-  fn new(
-    excluded_files: BTreeSet<PathBuf>,
-    skip_hidden_files: bool,
-    dry_run: bool,
-    modified_times_future_threshold: NaiveDateTime,
-    exif_dates_future_threshold: NaiveDateTime,
-    ignore_minor_exif_errors: bool,
-    repair_exif_errors: bool,
-  ) -> Self {
+  fn new(options: ProcessOptions) -> Self {
+    let mut pair_kinds = Vec::new();
+    if options.sync_live_photos {
+      pair_kinds.push(PairKind::LivePhoto);
+    }
+    if options.sync_raw_jpeg_pairs {
+      pair_kinds.push(PairKind::RawJpeg);
+    }
+
     Self {
-      excluded_files,
-      skip_hidden_files,
+      sibling_dates: options.interpolate_sibling_dates.then(SiblingDates::new),
+      review_list: options.corroboration_window.map(|_| ReviewList::new()),
+      change_list: (options.write_plan || options.report_changes).then(ChangeList::new),
+      verification: options.verify_tolerance.map(Verification::new),
+      unresolved_files: options.collect_unresolved_files.then(UnresolvedFiles::new),
+      progress: options.show_progress.then(Progress::new),
+      checkpoint: None,
+      // A retry after the EXIF date was written would shift it again, so shifts are not retried.
+      failed_files: (options.retries > 0 && options.date_shift.is_none()).then(FailedFiles::new),
+      organizer: options
+        .organize_target
+        .clone()
+        .map(|target_root| Organizer::new(target_root, options.organize_mode)),
+      folder_dates: options.folder_mtime_policy.map(FolderDates::new),
+      exif_dates_cache: ExifDatesCache::new(),
+      guessed_dates: Mutex::new(BTreeMap::new()),
+      #[cfg(unix)]
+      processed_hardlinks: Mutex::new(BTreeSet::new()),
+      file_pairs: (!pair_kinds.is_empty()).then(|| FilePairs::new(pair_kinds)),
+      options,
       should_exit: AtomicBool::new(false),
       start_time: Local::now().naive_utc(),
-      dry_run,
-      modified_times_future_threshold,
-      exif_dates_future_threshold,
-      ignore_minor_exif_errors,
-      repair_exif_errors,
 
       stat_folders_checked: AtomicUsize::new(0),
       stat_folders_skipped: AtomicUsize::new(0),
+      stat_nomedia_folders_skipped: AtomicUsize::new(0),
+      stat_recent_files_skipped: AtomicUsize::new(0),
+      stat_files_fixed_by_retry: AtomicUsize::new(0),
       stat_files_checked: AtomicUsize::new(0),
       stat_media_files_checked: AtomicUsize::new(0),
       stat_files_skipped: AtomicUsize::new(0),
       stat_files_errors: AtomicUsize::new(0),
       stat_exif_updated: AtomicUsize::new(0),
       stat_exif_overwritten: AtomicUsize::new(0),
+      stat_exif_interpolated: AtomicUsize::new(0),
       stat_modified_time_updated: AtomicUsize::new(0),
+      stat_folder_mtimes_updated: AtomicUsize::new(0),
+      stat_companion_files_synced: AtomicUsize::new(0),
+      stat_pairs_synced: AtomicUsize::new(0),
+      stat_files_organized: AtomicUsize::new(0),
+      stat_below_min_confidence: AtomicUsize::new(0),
+      stat_exif_close_enough: AtomicUsize::new(0),
+      stat_extractor_hits: date_extractor_registry()
+        .names()
+        .map(|name| (name.to_string(), AtomicUsize::new(0)))
+        .collect(),
+      stat_extensions: ExtensionStats::new(),
+      stat_exif_years: YearHistogram::new(),
+      error_summary: ErrorSummary::new(),
+    }
+  }
+
+  fn record_extractor_hit(&self, extractor_name: &str) {
+    if let Some((_, hits)) = self
+      .stat_extractor_hits
+      .iter()
+      .find(|(name, _)| name == extractor_name)
+    {
+      hits.fetch_add(1, Ordering::Relaxed);
+    }
+  }
+
+  /// Whether the folder is skipped because it contains a `.nomedia` file.
+  fn is_nomedia_folder(&self, folder_path: &Path) -> bool {
+    if !self.options.respect_nomedia || !folder_path.join(NOMEDIA_FILE_NAME).exists() {
+      return false;
+    }
+    debug!(
+      file_path = %folder_path.display(),
+      "Skipping folder with a {NOMEDIA_FILE_NAME} file",
+    );
+    self
+      .stat_nomedia_folders_skipped
+      .fetch_add(1, Ordering::Relaxed);
+    true
+  }
+
+  /// Whether `file_path` passes `--only-extensions`.
+  fn has_wanted_extension(&self, file_path: &Path) -> bool {
+    let Some(only_extensions) = &self.options.only_extensions else {
+      return true;
+    };
+    file_path
+      .extension()
+      .and_then(|ext| ext.to_str())
+      .is_some_and(|ext| only_extensions.contains(&ext.to_ascii_uppercase()))
+  }
+
+  /// Whether a file of `size` bytes passes `--min-size` and `--max-size`.
+  fn has_wanted_size(&self, size: u64) -> bool {
+    self
+      .options
+      .min_size
+      .is_none_or(|min_size| size >= min_size)
+      && self
+        .options
+        .max_size
+        .is_none_or(|max_size| size <= max_size)
+  }
+
+  /// Whether a file last modified at `modified_time` passes `--modified-since` and `--modified-before`.
+  fn has_wanted_modified_time(&self, modified_time: NaiveDateTime) -> bool {
+    self
+      .options
+      .modified_since
+      .is_none_or(|since| modified_time >= since)
+      && self
+        .options
+        .modified_before
+        .is_none_or(|before| modified_time < before)
+  }
+
+  /// Whether a file last modified at `modified_time` is within `--skip-recent` of now.
+  fn is_recently_modified(&self, modified_time: NaiveDateTime) -> bool {
+    self
+      .options
+      .skip_recent
+      .is_some_and(|skip_recent| Local::now().naive_utc() - modified_time < skip_recent)
+  }
+
+  /// Why `file_path` is not processed, `None` if it is.
+  /// Hardlinks to already processed files are only known while processing, see [`Self::is_processed_hardlink`].
+  fn skip_reason(&self, file_path: &Path, metadata: Option<&Metadata>) -> Option<SkipReason> {
+    if file_path.file_name() == Some(LOCK_FILE_NAME.as_ref()) {
+      return Some(SkipReason::LockFile);
+    }
+    if self
+      .checkpoint
+      .as_ref()
+      .is_some_and(|checkpoint| checkpoint.is_finished(file_path))
+    {
+      return Some(SkipReason::AlreadyProcessed);
+    }
+    if find_primary_file(file_path).is_some() {
+      return Some(SkipReason::CompanionFile);
+    }
+    if !self.has_wanted_extension(file_path) {
+      return Some(SkipReason::Extension);
     }
+    if metadata.is_some_and(|metadata| !self.has_wanted_size(metadata.len())) {
+      return Some(SkipReason::Size);
+    }
+    let modified_time = metadata
+      .and_then(|metadata| metadata.modified().ok())
+      .map(|modified_time| DateTime::<Utc>::from(modified_time).naive_utc());
+    if modified_time.is_some_and(|modified_time| !self.has_wanted_modified_time(modified_time)) {
+      return Some(SkipReason::ModifiedTime);
+    }
+    if modified_time.is_some_and(|modified_time| self.is_recently_modified(modified_time)) {
+      return Some(SkipReason::RecentlyModified);
+    }
+    None
+  }
+
+  /// Whether the EXIF dates of `file_path` are read ahead with the other files of its directory.
+  /// Only the files that are processed are read, so the cache does not fill up with files that are skipped.
+  /// Files whose guessed date can be written with a single conditional write are not read either,
+  /// they are only read if the write finds a date already. The guess is kept for [`Self::take_guessed_date`].
+  fn should_prefetch(
+    &self,
+    file_path: &Path,
+    metadata: Option<&Metadata>,
+    exiftool_writable_file_extensions: &BTreeSet<String>,
+  ) -> bool {
+    if is_excluded(file_path, &self.options.excluded_files)
+      || self.skip_reason(file_path, metadata).is_some()
+      || !file_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| exiftool_writable_file_extensions.contains(&ext.to_ascii_uppercase()))
+    {
+      return false;
+    }
+    // Shifted files are always read and never get a guessed date.
+    if self.options.date_shift.is_some() {
+      return true;
+    }
+    let guess = self.guess_date_from_path(file_path);
+    self
+      .guessed_dates
+      .lock()
+      .expect("guessed dates lock should not be poisoned")
+      .insert(file_path.to_path_buf(), guess);
+    guess
+      .is_none_or(|(_, _, guessed_date)| !self.can_write_without_reading(file_path, &guessed_date))
+  }
+
+  /// [`Self::guess_date_from_path`], reusing the guess made while reading ahead.
+  fn take_guessed_date(
+    &self,
+    file_path: &Path,
+  ) -> Option<(&'static str, &'static str, ConfidentNaiveDateTime)> {
+    self
+      .guessed_dates
+      .lock()
+      .expect("guessed dates lock should not be poisoned")
+      .remove(file_path)
+      .unwrap_or_else(|| self.guess_date_from_path(file_path))
+  }
+
+  /// Drops what was read ahead for a file that is skipped after all.
+  fn forget_read_ahead(&self, file_path: &Path) {
+    let _ = self.exif_dates_cache.take(file_path);
+    self
+      .guessed_dates
+      .lock()
+      .expect("guessed dates lock should not be poisoned")
+      .remove(file_path);
+  }
+
+  /// The date guessed from the file name or else the closest ancestor folder carrying one,
+  /// along with where it was found (`filename` or `folder`) and the name of the extractor.
+  fn guess_date_from_path(
+    &self,
+    file_path: &Path,
+  ) -> Option<(&'static str, &'static str, ConfidentNaiveDateTime)> {
+    let file_name = file_path.file_name()?.to_string_lossy();
+    let registry = date_extractor_registry();
+    registry
+      .extract_with_name(file_path, &file_name, self.start_time, false)
+      .map(|(extractor_name, date)| ("filename", extractor_name, date))
+      .or_else(|| {
+        // Walk up the ancestor folders until one of them carries a date.
+        file_path
+          .ancestors()
+          .skip(1)
+          .take(self.options.folder_date_depth)
+          .find_map(|folder_path| {
+            let folder_name = folder_path.file_name()?.to_string_lossy();
+            registry
+              .extract_with_name(folder_path, &folder_name, self.start_time, true)
+              .map(|(extractor_name, date)| ("folder", extractor_name, date))
+          })
+      })
+  }
+
+  /// Whether two modified times are equal within the file system granularity.
+  fn mtimes_match(&self, a: &NaiveDateTime, b: &NaiveDateTime) -> bool {
+    (*a - *b).abs() < self.options.mtime_granularity
+  }
+
+  /// Whether the modified time `a` is at or before `b`, allowing for the file system granularity.
+  fn is_mtime_at_or_before(&self, a: &NaiveDateTime, b: &NaiveDateTime) -> bool {
+    a <= b || self.mtimes_match(a, b)
+  }
+
+  /// The modified time `--sync-mtime-from-exif` sets for a file with the EXIF date `resolved_date`,
+  /// `None` if it is not set or the modified time already matches.
+  fn synced_modified_time(
+    &self,
+    resolved_date: &ConfidentNaiveDateTime,
+    exif_date_written: bool,
+    original_file_modified_time: Option<NaiveDateTime>,
+  ) -> Option<NaiveDateTime> {
+    if !self.options.sync_mtime_from_exif {
+      return None;
+    }
+    let exif_modified_time =
+      modified_time_from_exif_date(resolved_date, self.options.default_utc_offset)?;
+    // Writing the EXIF date changed the modified time, unless it was preserved.
+    let exif_date_changed_mtime = exif_date_written && !self.options.preserve_modified_time;
+    let mtime_differs = original_file_modified_time.is_none_or(|original_file_modified_time| {
+      !self.mtimes_match(&original_file_modified_time, &exif_modified_time)
+    });
+    (exif_date_changed_mtime || mtime_differs).then_some(exif_modified_time)
+  }
+
+  /// The date `--mtime-as-fallback` gives a file without any date, from the earlier plausible one of its modified and creation time,
+  /// along with which of them it is.
+  fn file_time_fallback_date(
+    &self,
+    modified_time: Option<NaiveDateTime>,
+    creation_time: Option<NaiveDateTime>,
+  ) -> Option<(ConfidentNaiveDateTime, &'static str)> {
+    let mtime_fallback_confidence = self.options.mtime_fallback_confidence?;
+    let (file_time, file_time_source) = [
+      modified_time.map(|modified_time| (modified_time, "mtime")),
+      creation_time.map(|creation_time| (creation_time, "birthtime")),
+    ]
+    .into_iter()
+    .flatten()
+    .filter(|(file_time, _)| {
+      self.is_mtime_at_or_before(&self.options.min_valid_mtime, file_time)
+        && self.is_mtime_at_or_before(file_time, &self.options.modified_times_future_threshold)
+    })
+    .min()?;
+    let mut file_time_date =
+      local_date_from_modified_time(&file_time, self.options.default_utc_offset);
+    file_time_date.confidence = mtime_fallback_confidence;
+    Some((file_time_date, file_time_source))
+  }
+
+  /// Whether `file_path` is a hardlink to a file that was already processed through another path.
+  /// Otherwise the file is recorded as processed.
+  #[cfg(unix)]
+  fn is_processed_hardlink(&self, metadata: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt as _;
+
+    if metadata.nlink() <= 1 {
+      return false;
+    }
+    !self
+      .processed_hardlinks
+      .lock()
+      .expect("Processed hardlinks lock should not be poisoned")
+      .insert((metadata.dev(), metadata.ino()))
+  }
+
+  #[cfg(not(unix))]
+  const fn is_processed_hardlink(&self, _metadata: &Metadata) -> bool {
+    false
+  }
+
+  /// Whether the folder is skipped by `--one-file-system` because it is on another device than `root_device_id`.
+  fn is_other_file_system(&self, folder_path: &Path, root_device_id: Option<u64>) -> bool {
+    let Some(root_device_id) = root_device_id else {
+      return false;
+    };
+    if device_id(folder_path).is_none_or(|device_id| device_id == root_device_id) {
+      return false;
+    }
+    debug!(
+      file_path = %folder_path.display(),
+      "Skipping folder on another file system",
+    );
+    self.stat_folders_skipped.fetch_add(1, Ordering::Relaxed);
+    true
+  }
+
+  /// Whether `date` would be written to `file_path` if it has no EXIF dates, without any side effects.
+  /// Only then can it be written with a conditional write before the EXIF dates are read.
+  fn can_write_without_reading(&self, file_path: &Path, date: &ConfidentNaiveDateTime) -> bool {
+    !self.options.dry_run
+      && self.options.corroboration_window.is_none()
+      && !self.options.mine_text_tags
+      && date.confidence >= self.options.min_confidence
+      && date_write_target(file_path, self.options.sidecar_for_raw) == file_path
+  }
+
+  /// Whether a guessed date may replace the existing EXIF date,
+  /// depending on `--only-fill-missing`, `--prefer-filename` and `--overwrite-margin`.
+  fn may_overwrite_exif_date(
+    &self,
+    original_exif_date: &ConfidentNaiveDateTime,
+    guessed_date: &ConfidentNaiveDateTime,
+    guess_from_path: bool,
+  ) -> bool {
+    !self.options.only_fill_missing
+      && ((self.options.prefer_filename && guess_from_path)
+        || guessed_date
+          .confidence
+          .levels_above(original_exif_date.confidence)
+          >= i16::from(self.options.overwrite_margin))
+  }
+
+  /// Whether a guessed date is within `--min-delta` of the existing EXIF date, so rewriting it is not worth it.
+  fn is_close_enough(
+    &self,
+    original_exif_date: &ConfidentNaiveDateTime,
+    guessed_date: &ConfidentNaiveDateTime,
+  ) -> bool {
+    // The EXIF date is written with second precision, so a sub-second difference alone is no reason to overwrite it.
+    let difference =
+      (guessed_date.date.trunc_subsecs(0) - original_exif_date.date.trunc_subsecs(0)).abs();
+    difference <= self.options.min_delta
+  }
+
+  /// Whether a guessed date may be written to `file_path`.
+  /// Dates below `--min-confidence` are reported instead.
+  fn passes_min_confidence(&self, file_path: &Path, date: &ConfidentNaiveDateTime) -> bool {
+    if date.confidence >= self.options.min_confidence {
+      return true;
+    }
+    info!(
+      file_path = %file_path.display(),
+      "Not writing guessed date {} (confidence: {:?}) because it is below the minimum confidence {:?}",
+      date,
+      date.confidence,
+      self.options.min_confidence,
+    );
+    self
+      .stat_below_min_confidence
+      .fetch_add(1, Ordering::Relaxed);
+    false
+  }
+
+  /// Whether the modified time of the file corroborates a guessed date in corroboration mode.
+  /// Uncorroborated dates are added to the review list instead.
+  fn passes_corroboration(
+    &self,
+    file_path: &Path,
+    date: &ConfidentNaiveDateTime,
+    modified_time: Option<NaiveDateTime>,
+  ) -> bool {
+    let (Some(window), Some(review_list)) = (self.options.corroboration_window, &self.review_list)
+    else {
+      return true;
+    };
+    if modified_time.is_some_and(|modified_time| is_corroborated(date, &modified_time, window)) {
+      return true;
+    }
+    info!(
+      file_path = %file_path.display(),
+      "Not writing guessed date {} because the modified time does not corroborate it, adding it to the review list",
+      date,
+    );
+    review_list.record(file_path, *date, modified_time);
+    false
+  }
+
+  /// The main counters, e.g. for the `--notify-url` payload.
+  fn stats_json(&self) -> serde_json::Value {
+    let duration_seconds = (Local::now().naive_utc() - self.start_time).num_seconds();
+    serde_json::json!({
+      "folders_checked": self.stat_folders_checked.load(Ordering::Relaxed),
+      "files_checked": self.stat_files_checked.load(Ordering::Relaxed),
+      "media_files_checked": self.stat_media_files_checked.load(Ordering::Relaxed),
+      "files_skipped": self.stat_files_skipped.load(Ordering::Relaxed),
+      "files_errors": self.stat_files_errors.load(Ordering::Relaxed),
+      "exif_dates_updated": self.stat_exif_updated.load(Ordering::Relaxed),
+      "exif_dates_overwritten": self.stat_exif_overwritten.load(Ordering::Relaxed),
+      "modified_times_updated": self.stat_modified_time_updated.load(Ordering::Relaxed),
+      "files_organized": self.stat_files_organized.load(Ordering::Relaxed),
+      "duration_seconds": duration_seconds,
+    })
+  }
+
+  fn record_skipped(&self, file_path: &Path, reason: &str) {
+    self.stat_files_skipped.fetch_add(1, Ordering::Relaxed);
+    emit_event(&FileEvent::skipped(file_path, reason));
+  }
+
+  /// Does not count the error, as a file with several errors is counted once.
+  fn record_error(&self, file_path: &Path, error: &anyhow::Error) {
+    self.error_summary.record_error(file_path, error);
+    emit_event(&FileEvent::error(file_path, error));
   }
 
   fn pretty_print_stats(&self) -> Result<(), io::Error> {
     let folders_checked = self.stat_folders_checked.load(Ordering::Relaxed);
     let folders_skipped = self.stat_folders_skipped.load(Ordering::Relaxed);
+    let nomedia_folders_skipped = self.stat_nomedia_folders_skipped.load(Ordering::Relaxed);
+    let recent_files_skipped = self.stat_recent_files_skipped.load(Ordering::Relaxed);
     let files_checked = self.stat_files_checked.load(Ordering::Relaxed);
     let media_files_checked = self.stat_media_files_checked.load(Ordering::Relaxed);
     let files_skipped = self.stat_files_skipped.load(Ordering::Relaxed);
     let files_errors = self.stat_files_errors.load(Ordering::Relaxed);
     let exif_updated = self.stat_exif_updated.load(Ordering::Relaxed);
     let exif_overwritten = self.stat_exif_overwritten.load(Ordering::Relaxed);
+    let exif_interpolated = self.stat_exif_interpolated.load(Ordering::Relaxed);
     let modified_time_updated = self.stat_modified_time_updated.load(Ordering::Relaxed);
+    let folder_mtimes_updated = self.stat_folder_mtimes_updated.load(Ordering::Relaxed);
+    let companion_files_synced = self.stat_companion_files_synced.load(Ordering::Relaxed);
+    let pairs_synced = self.stat_pairs_synced.load(Ordering::Relaxed);
+    let files_organized = self.stat_files_organized.load(Ordering::Relaxed);
+    let below_min_confidence = self.stat_below_min_confidence.load(Ordering::Relaxed);
+    let exif_close_enough = self.stat_exif_close_enough.load(Ordering::Relaxed);
 
     // Acquire a lock on standard output for buffered writing
     let mut stdout = io::stdout().lock();
@@ -154,16 +868,118 @@ impl ProcessState {
     writeln!(&mut stdout, "Statistics:")?;
     writeln!(&mut stdout, "  Folders checked: {folders_checked}")?;
     writeln!(&mut stdout, "  Folders skipped: {folders_skipped}")?;
+    if self.options.respect_nomedia {
+      writeln!(
+        &mut stdout,
+        "  Folders skipped because of {NOMEDIA_FILE_NAME}: {nomedia_folders_skipped}"
+      )?;
+    }
     writeln!(&mut stdout, "  Files checked: {files_checked}")?;
     writeln!(&mut stdout, "  Media files checked: {media_files_checked}")?;
     writeln!(&mut stdout, "  Files skipped: {files_skipped}")?;
+    if self.options.skip_recent.is_some() {
+      writeln!(
+        &mut stdout,
+        "  Files skipped because they were modified recently: {recent_files_skipped}"
+      )?;
+    }
     writeln!(&mut stdout, "  Files with errors: {files_errors}")?;
+    if self.options.retries > 0 {
+      writeln!(
+        &mut stdout,
+        "  Files processed on retry: {}",
+        self.stat_files_fixed_by_retry.load(Ordering::Relaxed)
+      )?;
+    }
     writeln!(&mut stdout, "  EXIF dates updated: {exif_updated}")?;
     writeln!(&mut stdout, "  EXIF dates overwritten: {exif_overwritten}")?;
+    if self.sibling_dates.is_some() {
+      writeln!(
+        &mut stdout,
+        "  EXIF dates interpolated from siblings: {exif_interpolated}"
+      )?;
+    }
     writeln!(
       &mut stdout,
       "  Modified times updated: {modified_time_updated}"
     )?;
+    if self.folder_dates.is_some() {
+      writeln!(
+        &mut stdout,
+        "  Folder modified times updated: {folder_mtimes_updated}"
+      )?;
+    }
+    writeln!(
+      &mut stdout,
+      "  Companion files synced: {companion_files_synced}"
+    )?;
+    if self.file_pairs.is_some() {
+      writeln!(&mut stdout, "  Paired files synced: {pairs_synced}")?;
+    }
+    if self.organizer.is_some() {
+      writeln!(&mut stdout, "  Files organized: {files_organized}")?;
+    }
+    if let Some(backups) = backups() {
+      writeln!(
+        &mut stdout,
+        "  Backups kept: {} ({})",
+        backups.count(),
+        pretty_size(backups.bytes())
+      )?;
+    }
+    if self.options.min_delta > TimeDelta::zero() {
+      writeln!(
+        &mut stdout,
+        "  EXIF dates close enough to the guessed date: {exif_close_enough}"
+      )?;
+    }
+    if let Some(review_list) = &self.review_list {
+      writeln!(
+        &mut stdout,
+        "  Guessed dates awaiting review: {}",
+        review_list.len()
+      )?;
+    }
+    if let Some(change_list) = &self.change_list
+      && self.options.write_plan
+    {
+      writeln!(&mut stdout, "  Planned changes: {}", change_list.len())?;
+    }
+    if let Some(unresolved_files) = &self.unresolved_files {
+      writeln!(
+        &mut stdout,
+        "  Media files without any date: {}",
+        unresolved_files.len()
+      )?;
+    }
+    if let Some(verification) = &self.verification {
+      writeln!(
+        &mut stdout,
+        "  Files with disagreeing dates: {}",
+        verification.len()
+      )?;
+    }
+    if self.options.min_confidence > DateConfidence::None {
+      writeln!(
+        &mut stdout,
+        "  Guessed dates below minimum confidence: {below_min_confidence}"
+      )?;
+    }
+    writeln!(&mut stdout, "  Dates guessed per extractor:")?;
+    for (name, hits) in &self.stat_extractor_hits {
+      writeln!(&mut stdout, "    {name}: {}", hits.load(Ordering::Relaxed))?;
+    }
+    writeln!(&mut stdout, "  Files per extension:")?;
+    for line in self.stat_extensions.table() {
+      writeln!(&mut stdout, "    {line}")?;
+    }
+    let year_lines = self.stat_exif_years.lines();
+    if !year_lines.is_empty() {
+      writeln!(&mut stdout, "  EXIF dates written per year:")?;
+      for line in year_lines {
+        writeln!(&mut stdout, "    {line}")?;
+      }
+    }
 
     let std_duration = (Local::now().naive_utc() - self.start_time).to_std();
     if let Ok(std_duration) = std_duration {
@@ -174,26 +990,51 @@ impl ProcessState {
       )?;
     }
 
+    let error_lines = self.error_summary.lines();
+    if !error_lines.is_empty() {
+      writeln!(&mut stdout, "Errors by cause:")?;
+      for line in error_lines {
+        writeln!(&mut stdout, "  {line}")?;
+      }
+    }
+
     Ok(())
   }
 }
 
+/// The milliseconds since `start`, for the timing fields of the log.
+fn elapsed_ms(start: Instant) -> u64 {
+  u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX)
+}
+
 fn check_dir_recursive(root_dir: &Path, process_state: &Arc<ProcessState>) {
   if process_state.should_exit.load(Ordering::Relaxed) {
     return;
   }
 
-  if is_excluded(root_dir, &process_state.excluded_files) {
+  if is_excluded(root_dir, &process_state.options.excluded_files) {
     process_state
       .stat_folders_skipped
       .fetch_add(1, Ordering::Relaxed);
     return;
   }
+  if process_state.is_nomedia_folder(root_dir) {
+    return;
+  }
+  let root_device_id = if process_state.options.one_file_system {
+    device_id(root_dir)
+  } else {
+    None
+  };
 
   process_state
     .stat_folders_checked
     .fetch_add(1, Ordering::Relaxed);
 
+  // Events inherit the directory context from the spans, e.g. in the `spans` of `--log-format json`.
+  let top_level_span = info_span!("top_level_directory", path = %root_dir.display());
+  let _top_level_guard = top_level_span.enter();
+  let start = Instant::now();
   info!(
     file_path = %root_dir.display(),
     "Processing top level directory",
@@ -201,18 +1042,104 @@ fn check_dir_recursive(root_dir: &Path, process_state: &Arc<ProcessState>) {
 
   let entries = {
     let process_state = process_state.clone();
-    WalkDir::new(root_dir)
-      .skip_hidden(process_state.skip_hidden_files)
-      .process_read_dir(move |_depth, _path, _read_dir_state, children| {
+    let top_level_span = top_level_span.clone();
+    // The ignore files of the folder being read and its ancestors are passed down to the subfolders.
+    WalkDirGeneric::<(IgnoreFiles, ())>::new(root_dir)
+      .skip_hidden(process_state.options.skip_hidden_files)
+      .process_read_dir(move |depth, path, ignore_files, children| {
+        // The root entry itself is read from its parent without a depth.
+        let _directory_guard = depth.is_some().then(|| {
+          info_span!(parent: &top_level_span, "directory", path = %path.display()).entered()
+        });
+        let read_start = Instant::now();
+        if let Some(progress) = &process_state.progress
+          && depth.is_some()
+        {
+          progress.set_current_folder(path);
+        }
+        // The root entry itself is processed without a depth.
+        if depth.is_some() {
+          match IgnoreFile::load(path) {
+            Ok(Some(ignore_file)) => ignore_files.push(ignore_file),
+            Ok(None) => {},
+            Err(e) => {
+              error!(
+                file_path = %path.display(),
+                "{e:#}",
+              );
+              process_state
+                .stat_files_errors
+                .fetch_add(1, Ordering::Relaxed);
+              process_state.record_error(path, &e);
+            },
+          }
+        }
+        children.retain(|child| {
+          let Ok(child) = child else {
+            return true;
+          };
+          let child_path = child.path();
+          let is_folder = child.file_type.is_dir();
+          if !ignore_files.is_ignored(&child_path, is_folder) {
+            return true;
+          }
+          trace!(
+            file_path = %child_path.display(),
+            "Skipping ignored path",
+          );
+          if is_folder {
+            process_state
+              .stat_folders_skipped
+              .fetch_add(1, Ordering::Relaxed);
+          } else {
+            process_state.record_skipped(&child_path, "ignored");
+          }
+          false
+        });
+
         // Filter out excluded directories
         for child in children.iter_mut().flatten() {
-          if is_excluded(&child.path(), &process_state.excluded_files) {
+          if is_excluded(&child.path(), &process_state.options.excluded_files) {
             child.read_children_path = None;
             process_state
               .stat_folders_skipped
               .fetch_add(1, Ordering::Relaxed);
+          } else if child.read_children_path.is_some()
+            && (process_state.is_nomedia_folder(&child.path())
+              || process_state.is_other_file_system(&child.path(), root_device_id))
+          {
+            child.read_children_path = None;
           }
         }
+
+        // Read the EXIF dates of all media files in the directory at once.
+        let Ok(exiftool_writable_file_extensions) = exiftool_writable_file_extensions() else {
+          return;
+        };
+        let media_file_paths = children
+          .iter()
+          .flatten()
+          .filter(|child| child.file_type.is_file())
+          .filter(|child| {
+            process_state.should_prefetch(
+              &child.path(),
+              child.metadata().ok().as_ref(),
+              exiftool_writable_file_extensions,
+            )
+          })
+          .map(jwalk::DirEntry::path)
+          .collect::<Vec<_>>();
+        process_state.exif_dates_cache.prefetch(
+          &media_file_paths,
+          process_state.options.ignore_minor_exif_errors,
+        );
+        if depth.is_some() {
+          debug!(
+            duration_ms = elapsed_ms(read_start),
+            media_files = media_file_paths.len(),
+            "Read directory",
+          );
+        }
       })
       .into_iter()
   };
@@ -221,6 +1148,8 @@ fn check_dir_recursive(root_dir: &Path, process_state: &Arc<ProcessState>) {
     if process_state.should_exit.load(Ordering::Relaxed) {
       return Err(());
     }
+    // Spans are per thread, so the worker threads enter it themselves.
+    let _top_level_guard = top_level_span.enter();
 
     let entry = match entry_result {
       Ok(entry) => entry,
@@ -237,15 +1166,14 @@ fn check_dir_recursive(root_dir: &Path, process_state: &Arc<ProcessState>) {
         process_state
           .stat_files_errors
           .fetch_add(1, Ordering::Relaxed);
+        process_state.record_error(Path::new(&file_path), &e.into());
         return Ok(());
       },
     };
 
     let path = entry.path();
-    if is_excluded(&path, &process_state.excluded_files) {
-      process_state
-        .stat_files_skipped
-        .fetch_add(1, Ordering::Relaxed);
+    if is_excluded(&path, &process_state.options.excluded_files) {
+      process_state.record_skipped(&path, "excluded");
       return Ok(());
     }
 
@@ -262,11 +1190,19 @@ fn check_dir_recursive(root_dir: &Path, process_state: &Arc<ProcessState>) {
         "Processing directory",
       );
     } else if file_type.is_file() {
+      let _directory_guard = path
+        .parent()
+        .map(|folder_path| info_span!("directory", path = %folder_path.display()).entered());
       check_file(&path, process_state);
     } else {
-      process_state
-        .stat_files_skipped
-        .fetch_add(1, Ordering::Relaxed);
+      process_state.record_skipped(
+        &path,
+        if file_type.is_symlink() {
+          "symbolic link"
+        } else {
+          "not a regular file"
+        },
+      );
       if file_type.is_symlink() {
         warn!(
           file_path = %path.display(),
@@ -283,37 +1219,104 @@ fn check_dir_recursive(root_dir: &Path, process_state: &Arc<ProcessState>) {
 
     Ok(())
   });
+
+  info!(
+    file_path = %root_dir.display(),
+    duration_ms = elapsed_ms(start),
+    "Finished top level directory",
+  );
+}
+
+/// Whether the device ID of a file can be read to stay on one file system with `--one-file-system`.
+const CAN_DETECT_FILE_SYSTEMS: bool = cfg!(unix);
+
+/// The ID of the device (file system) the file is on.
+#[cfg(unix)]
+fn device_id(file_path: &Path) -> Option<u64> {
+  use std::os::unix::fs::MetadataExt as _;
+
+  std::fs::metadata(file_path)
+    .ok()
+    .map(|metadata| metadata.dev())
+}
+
+#[cfg(not(unix))]
+const fn device_id(_file_path: &Path) -> Option<u64> {
+  None
+}
+
+/// Android marks folders that galleries should ignore, e.g. app caches, with a file of this name.
+const NOMEDIA_FILE_NAME: &str = ".nomedia";
+
+/// The exit code of a run aborted with a second Ctrl+C, as a shell reports for a process killed by SIGINT.
+const ABORT_EXIT_CODE: i32 = 130;
+
+#[derive(Debug, PartialEq, Eq)]
+enum CtrlCAction {
+  /// Stop scheduling new files, but finish the ones in progress.
+  Finish,
+  /// Kill the exiftool workers and exit right away.
+  Abort,
+}
+
+/// The first Ctrl+C finishes the run gracefully, any further one aborts it.
+fn ctrl_c_action(should_exit: &AtomicBool) -> CtrlCAction {
+  if should_exit.swap(true, Ordering::Relaxed) {
+    CtrlCAction::Abort
+  } else {
+    CtrlCAction::Finish
+  }
+}
+
+fn install_ctrl_c_handler(process_state: Arc<ProcessState>) {
+  ctrlc::set_handler(move || match ctrl_c_action(&process_state.should_exit) {
+    CtrlCAction::Finish => println!(
+      "\nReceived Ctrl+C! Finishing the files in progress, then exiting... Press Ctrl+C again to abort."
+    ),
+    CtrlCAction::Abort => {
+      println!("\nReceived Ctrl+C again! Aborting...");
+      kill_exiftool_workers();
+      exit(ABORT_EXIT_CODE);
+    },
+  })
+  .expect("Error setting Ctrl+C handler");
 }
 
-const OLD_MODIFIED_TIME_THRESHOLD: NaiveDateTime = NaiveDateTime::new(
+/// Modified times before this are considered invalid and clamped to it, unless `--min-valid-mtime` is given.
+const DEFAULT_MIN_VALID_MTIME: NaiveDateTime = NaiveDateTime::new(
   NaiveDate::from_ymd_opt(1970, 1, 2).unwrap(),
   NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
 );
 
-#[must_use]
-fn get_confidence_of_naive(naive: &NaiveDateTime) -> DateConfidence {
-  if *naive == OLD_MODIFIED_TIME_THRESHOLD {
-    return DateConfidence::None;
-  }
-  if naive.second() != 0 {
-    return DateConfidence::Second;
-  }
-  if naive.minute() != 0 {
-    return DateConfidence::Minute;
-  }
-  if naive.hour() != 0 {
-    return DateConfidence::Hour;
-  }
-  if naive.day() != 1 {
-    return DateConfidence::Day;
-  }
-  if naive.month() != 1 {
-    return DateConfidence::Month;
+/// Parses a `--modified-since` or `--modified-before` bound, either a local date or a duration before now.
+fn parse_modified_time_bound(bound: &str) -> anyhow::Result<NaiveDateTime> {
+  if let Ok(duration) = pretty_duration::parse_duration(bound) {
+    let duration = TimeDelta::from_std(duration)?;
+    return Utc::now()
+      .naive_utc()
+      .checked_sub_signed(duration)
+      .ok_or_else(|| anyhow::anyhow!("Duration too long: {bound}"));
   }
-  if naive.year() % 10 != 0 {
-    return DateConfidence::Year;
-  }
-  DateConfidence::Decade
+  let date = parse_cli_date(bound).map_err(|_| {
+    anyhow::anyhow!("Invalid date or duration: {bound}, expected e.g. 2024-01-31 or 7d")
+  })?;
+  Local
+    .from_local_datetime(&date)
+    .earliest()
+    .map(|date| date.naive_utc())
+    .ok_or_else(|| anyhow::anyhow!("Invalid local date: {bound}"))
+}
+
+/// Parses a date given on the command line, e.g. `1950-01-01` or `1950-01-01 12:00:00`.
+fn parse_cli_date(date: &str) -> anyhow::Result<NaiveDateTime> {
+  NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S")
+    .or_else(|_| NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S"))
+    .or_else(|_| {
+      NaiveDate::parse_from_str(date, "%Y-%m-%d").map(|date| date.and_time(NaiveTime::MIN))
+    })
+    .map_err(|_| {
+      anyhow::anyhow!("Invalid date: {date}, expected YYYY-MM-DD or YYYY-MM-DD HH:MM:SS")
+    })
 }
 
 #[must_use]
@@ -321,19 +1324,101 @@ fn is_excluded(path: &Path, excluded: &BTreeSet<PathBuf>) -> bool {
   path.ancestors().any(|ancestor| excluded.contains(ancestor))
 }
 
-macro_rules! dyn_event {
-    ($lvl:ident, $($arg:tt)+) => {
-        match $lvl {
-            ::tracing::Level::TRACE => ::tracing::trace!($($arg)+),
-            ::tracing::Level::DEBUG => ::tracing::debug!($($arg)+),
-            ::tracing::Level::INFO => ::tracing::info!($($arg)+),
-            ::tracing::Level::WARN => ::tracing::warn!($($arg)+),
-            ::tracing::Level::ERROR => ::tracing::error!($($arg)+),
-        }
-    };
+/// Why a file is left alone without being processed.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum SkipReason {
+  LockFile,
+  /// Processed before the run was interrupted, see `--resume`.
+  AlreadyProcessed,
+  /// Companion files are synced together with their primary file.
+  CompanionFile,
+  Extension,
+  Size,
+  ModifiedTime,
+  RecentlyModified,
+}
+
+impl SkipReason {
+  /// The reason of the `skipped` event.
+  const fn name(self) -> &'static str {
+    match self {
+      Self::LockFile => "lock file",
+      Self::AlreadyProcessed => "already processed",
+      Self::CompanionFile => "companion file",
+      Self::Extension => "extension",
+      Self::Size => "size",
+      Self::ModifiedTime => "modified time",
+      Self::RecentlyModified => "recently modified",
+    }
+  }
+
+  const fn description(self) -> &'static str {
+    match self {
+      Self::LockFile => "lock file of a run",
+      Self::AlreadyProcessed => "file processed before the run was interrupted",
+      Self::CompanionFile => "companion file, it is synced with its primary file",
+      Self::Extension => "file without one of the extensions of --only-extensions",
+      Self::Size => "file outside of --min-size and --max-size",
+      Self::ModifiedTime => "file modified outside of --modified-since and --modified-before",
+      Self::RecentlyModified => "file modified within --skip-recent, it may still be written",
+    }
+  }
+
+  const fn log_level(self) -> Level {
+    match self {
+      Self::RecentlyModified => Level::DEBUG,
+      _ => Level::TRACE,
+    }
+  }
+}
+
+macro_rules! dyn_event {
+    ($lvl:ident, $($arg:tt)+) => {
+        match $lvl {
+            ::tracing::Level::TRACE => ::tracing::trace!($($arg)+),
+            ::tracing::Level::DEBUG => ::tracing::debug!($($arg)+),
+            ::tracing::Level::INFO => ::tracing::info!($($arg)+),
+            ::tracing::Level::WARN => ::tracing::warn!($($arg)+),
+            ::tracing::Level::ERROR => ::tracing::error!($($arg)+),
+        }
+    };
 }
 
 fn check_file(file_path: &Path, process_state: &Arc<ProcessState>) {
+  let metadata = std::fs::metadata(file_path).ok();
+  if let Some(skip_reason) = process_state.skip_reason(file_path, metadata.as_ref()) {
+    let log_level = skip_reason.log_level();
+    dyn_event!(
+      log_level,
+      file_path = %file_path.display(),
+      "Skipping {}",
+      skip_reason.description(),
+    );
+    if skip_reason == SkipReason::RecentlyModified {
+      process_state
+        .stat_recent_files_skipped
+        .fetch_add(1, Ordering::Relaxed);
+    }
+    // The file may have changed since its directory was read, so its dates may have been read ahead.
+    process_state.forget_read_ahead(file_path);
+    process_state.record_skipped(file_path, skip_reason.name());
+    return;
+  }
+
+  // Libraries deduplicated with hardlinks would otherwise have the same file written once per link.
+  if let Some(metadata) = &metadata
+    && process_state.is_processed_hardlink(metadata)
+  {
+    debug!(
+      file_path = %file_path.display(),
+      "Skipping hardlink to an already processed file",
+    );
+    // All links in one directory are read ahead.
+    process_state.forget_read_ahead(file_path);
+    process_state.record_skipped(file_path, "hardlink");
+    return;
+  }
+
   trace!(
     file_path = %file_path.display(),
     "Processing file",
@@ -341,25 +1426,55 @@ fn check_file(file_path: &Path, process_state: &Arc<ProcessState>) {
   process_state
     .stat_files_checked
     .fetch_add(1, Ordering::Relaxed);
-  if let Err(errors) = process_file(file_path, process_state) {
-    if errors.len() == 1 {
-      error!(
-        file_path = %file_path.display(),
-        source = ?errors[0],
-        "Failed to process file",
-      );
-    } else {
-      error!(
+  process_state.stat_extensions.record_checked(file_path);
+  match process_file(file_path, process_state) {
+    Ok(()) => {
+      if let Some(checkpoint) = &process_state.checkpoint {
+        checkpoint.record(file_path);
+      }
+    },
+    Err(errors) => {
+      if let Some(failed_files) = &process_state.failed_files {
+        warn!(
           file_path = %file_path.display(),
-          error_count = errors.len(),
           errors = ?errors,
-          "Failed to process file",
-      );
-    }
+          "Failed to process file, retrying it at the end",
+        );
+        failed_files.record(file_path, errors);
+      } else {
+        report_file_errors(file_path, &errors, process_state);
+      }
+    },
+  }
+}
 
-    process_state
-      .stat_files_errors
-      .fetch_add(1, Ordering::Relaxed);
+/// Logs and counts the errors of a file that failed for good.
+fn report_file_errors(
+  file_path: &Path,
+  errors: &[ErrorWithFilePath],
+  process_state: &ProcessState,
+) {
+  if errors.len() == 1 {
+    error!(
+      file_path = %file_path.display(),
+      source = ?errors[0],
+      "Failed to process file",
+    );
+  } else {
+    error!(
+        file_path = %file_path.display(),
+        error_count = errors.len(),
+        errors = ?errors,
+        "Failed to process file",
+    );
+  }
+
+  process_state
+    .stat_files_errors
+    .fetch_add(1, Ordering::Relaxed);
+  process_state.stat_extensions.record_error(file_path);
+  for e in errors {
+    process_state.record_error(&e.file_path, &e.source);
   }
 }
 
@@ -367,10 +1482,21 @@ fn process_file(
   file_path: &Path,
   process_state: &ProcessState,
 ) -> Result<(), Vec<ErrorWithFilePath>> {
+  if let Some(date_shift) = &process_state.options.date_shift {
+    return shift_file(
+      file_path,
+      date_shift,
+      process_state.options.shift_modified_time,
+      process_state,
+    )
+    .map_err(|e| vec![e]);
+  }
+
   let mut errors = Vec::<ErrorWithFilePath>::new();
 
   let mut new_file_modified_time = None;
   let mut new_exif_date = None;
+  let mut new_exif_source = "";
 
   let original_file_modified_time = match get_modified_time(file_path) {
     Ok(modified_time) => Some(modified_time),
@@ -381,10 +1507,21 @@ fn process_file(
   };
   let mut original_exif_date = None;
   let mut guessed_date = None;
+  // Where the guessed date came from, recorded with `--write-provenance`.
+  let mut guessed_source = String::new();
+  // Whether the guessed date was already written by a conditional write.
+  let mut exif_date_written = false;
+  // Whether the guessed date was derived from the file path, rather than from the sidecar or GPS.
+  let mut guess_from_path = false;
+  // The date derived from the file path, compared to the other dates by the `verify` subcommand.
+  let mut file_name_date = None;
 
   if let Some(original_file_modified_time) = original_file_modified_time {
     // check if the original modified time is in the future
-    if original_file_modified_time > process_state.modified_times_future_threshold {
+    if !process_state.is_mtime_at_or_before(
+      &original_file_modified_time,
+      &process_state.options.modified_times_future_threshold,
+    ) {
       info!(
         file_path = %file_path.display(),
         "File has a modified time in the future: {}",
@@ -392,14 +1529,18 @@ fn process_file(
       );
       new_file_modified_time = Some(process_state.start_time);
     }
-    // check if the original modified time is before 1970-01-02
-    else if original_file_modified_time < OLD_MODIFIED_TIME_THRESHOLD {
+    // check if the original modified time is before the minimum valid modified time
+    else if !process_state.is_mtime_at_or_before(
+      &process_state.options.min_valid_mtime,
+      &original_file_modified_time,
+    ) {
       info!(
         file_path = %file_path.display(),
-        "File has a modified time before 1970-01-02: {}.",
+        "File has a modified time before {}: {}.",
+        process_state.options.min_valid_mtime.format("%Y-%m-%d %H:%M:%S"),
         original_file_modified_time.format("%Y-%m-%d %H:%M:%S"),
       );
-      new_file_modified_time = Some(OLD_MODIFIED_TIME_THRESHOLD);
+      new_file_modified_time = Some(process_state.options.min_valid_mtime);
     }
   }
 
@@ -416,26 +1557,23 @@ fn process_file(
       return Err(errors);
     },
   };
-  if file_extension.is_some_and(|ext| exiftool_writable_file_extensions.contains(&ext)) {
+  let is_media_file =
+    file_extension.is_some_and(|ext| exiftool_writable_file_extensions.contains(&ext));
+  if is_media_file {
     // We are dealing with a file type that exiftool can write to.
     process_state
       .stat_media_files_checked
       .fetch_add(1, Ordering::Relaxed);
 
-    // guess the date from the file path
-    let file_name = file_path
-      .file_name()
-      .expect("File name should be present")
-      .to_string_lossy();
-    guessed_date =
-      get_date_for_file(file_path, &file_name, process_state.start_time).or_else(|| {
-        let folder_path = file_path.parent()?;
-        let folder_name = folder_path
-          .file_name()
-          .expect("Folder name should be present")
-          .to_string_lossy();
-        get_date_for_file(folder_path, &folder_name, process_state.start_time)
-      });
+    let registry = date_extractor_registry();
+    let guess = process_state.take_guessed_date(file_path);
+    if let Some((kind, extractor_name, _)) = guess {
+      process_state.record_extractor_hit(extractor_name);
+      guessed_source = format!("{kind}:{extractor_name}");
+    }
+    guessed_date = guess.map(|(_, _, date)| date);
+    guess_from_path = guessed_date.is_some();
+    file_name_date = guessed_date;
 
     if let Some(guessed_date) = guessed_date {
       trace!(
@@ -446,32 +1584,191 @@ fn process_file(
       );
     }
 
+    // an XMP sidecar date competes with the guessed date
+    if let Some(sidecar_path) = find_xmp_sidecar(file_path) {
+      match get_xmp_sidecar_date(&sidecar_path) {
+        Ok(Some(sidecar_date)) if sidecar_date.date <= process_state.start_time => {
+          trace!(
+            file_path = %file_path.display(),
+            "XMP sidecar date: {}",
+            sidecar_date,
+          );
+          // The sidecar is explicit metadata, so it wins ties against the file name.
+          if guessed_date
+            .is_none_or(|guessed_date| sidecar_date.confidence >= guessed_date.confidence)
+          {
+            guessed_date = Some(sidecar_date);
+            guessed_source = String::from("xmp-sidecar");
+            guess_from_path = false;
+          }
+        },
+        Ok(_) => {},
+        Err(e) => errors.push(e),
+      }
+    }
+
+    // Files without any date tag get the guessed date in a single conditional write, saving the read.
+    let mut exif_dates = process_state.exif_dates_cache.take(file_path);
+    if exif_dates.is_none()
+      && let Some(guessed_date) = guessed_date
+      && process_state.can_write_without_reading(file_path, &guessed_date)
+    {
+      match set_exif_date_if_missing(
+        file_path,
+        &guessed_date,
+        &guessed_source,
+        process_state.options.exif_write_options(),
+      ) {
+        Ok(true) => {
+          exif_date_written = true;
+          exif_dates = Some(ExifDates::default());
+        },
+        Ok(false) => {},
+        Err(e) => debug!(
+          file_path = %file_path.display(),
+          "Conditional EXIF date write failed, reading the EXIF dates first: {e}",
+        ),
+      }
+    }
+
     // get the original exif date and its confidence
-    let get_exif_date_result = wrap_with_exiftool_repair(
-      file_path,
-      process_state.repair_exif_errors,
-      process_state.dry_run,
-      || get_exif_date(file_path, process_state.ignore_minor_exif_errors),
-    );
-    original_exif_date = match get_exif_date_result {
-      // successfully got an exif date
-      Ok(Some(date)) => Some(ConfidentNaiveDateTime::new(
-        date,
-        get_confidence_of_naive(&date),
-      )),
-      // The file has no EXIF date set.
-      Ok(None) => None,
-      Err(e) => {
-        // Failed to get EXIF date, likely due to EXIF errors.
-        errors.push(e);
-        None
-      },
+    let get_exif_dates_result = match exif_dates {
+      Some(exif_dates) => Ok(exif_dates),
+      None => wrap_with_exiftool_repair(
+        file_path,
+        process_state.options.repair_exif_errors,
+        process_state.options.dry_run,
+        || get_exif_dates(file_path, process_state.options.ignore_minor_exif_errors),
+      ),
     };
+    let exif_dates = get_exif_dates_result.unwrap_or_else(|e| {
+      // Failed to get EXIF date, likely due to EXIF errors.
+      errors.push(e);
+      ExifDates::default()
+    });
+    // None if the file has no EXIF date set
+    original_exif_date = process_state
+      .options
+      .exif_confidence_policy
+      .apply(&exif_dates);
+    // Dates written with `--write-provenance` are exactly as confident as recorded, so reruns leave them alone unless a better guess turns up.
+    if let Some(recorded_confidence) = exif_dates.date_source_confidence
+      && let Some(original_exif_date) = &mut original_exif_date
+    {
+      original_exif_date.confidence = recorded_confidence;
+    }
+
+    // Scanning software sometimes only mentions the capture date in a description or comment.
+    if process_state.options.mine_text_tags && original_exif_date.is_none() {
+      match get_exif_text_values(file_path, process_state.options.ignore_minor_exif_errors) {
+        Ok(text_values) => {
+          if let Some((extractor_name, text_date)) = text_values
+            .iter()
+            .filter_map(|text| extract_date_from_text(registry, text, process_state.start_time))
+            .max_by_key(|(_, date)| date.confidence)
+            && guessed_date
+              .is_none_or(|guessed_date| text_date.confidence > guessed_date.confidence)
+          {
+            trace!(
+              file_path = %file_path.display(),
+              "Date found in EXIF text tags: {}",
+              text_date,
+            );
+            guessed_date = Some(text_date);
+            guessed_source = format!("text:{extractor_name}");
+            guess_from_path = false;
+          }
+        },
+        Err(e) => errors.push(e),
+      }
+    }
+
+    // The time zone at the GPS position turns the guess into the local time where the photo was taken.
+    let gps_position = exif_dates.gps_position();
+    if let Some(gps_position) = gps_position
+      && let Some(guessed_date) = &mut guessed_date
+    {
+      match guessed_date.offset {
+        // e.g. Unix timestamps, which are in UTC
+        Some(utc_offset) => {
+          let utc_date =
+            guessed_date.date - TimeDelta::seconds(i64::from(utc_offset.local_minus_utc()));
+          if let Some((local_date, local_utc_offset)) = local_date_at(gps_position, &utc_date) {
+            guessed_date.date = local_date;
+            guessed_date.offset = Some(local_utc_offset);
+          }
+        },
+        None => {
+          guessed_date.offset = utc_offset_at(gps_position, &guessed_date.date);
+        },
+      }
+    }
+
+    // The GPS date is written by the GPS receiver and rarely wrong, so it validates the guess and serves as a fallback.
+    if let Some(gps_date) = exif_dates.gps_date_time
+      && gps_date <= process_state.start_time
+    {
+      if let Some(contradicted_date) = guessed_date
+        && !is_corroborated(&contradicted_date, &gps_date, TimeDelta::days(1))
+      {
+        info!(
+          file_path = %file_path.display(),
+          "Ignoring guessed date {} because it contradicts the GPS date {} (UTC)",
+          contradicted_date,
+          gps_date.format("%Y-%m-%d %H:%M:%S"),
+        );
+        guessed_date = None;
+      }
+      // A guessed time of day that agrees with the GPS date reveals the UTC offset.
+      if let Some(guessed_date) = &mut guessed_date
+        && guessed_date.offset.is_none()
+        && guessed_date.confidence >= DateConfidence::Minute
+        && let Some(utc_offset) = utc_offset_from_gps(&guessed_date.date, &gps_date)
+      {
+        *guessed_date = guessed_date.with_offset(utc_offset);
+      }
+      let gps_date = if let Some((local_date, utc_offset)) =
+        gps_position.and_then(|gps_position| local_date_at(gps_position, &gps_date))
+      {
+        // The time zone at the GPS position is known, so the date is exact.
+        ConfidentNaiveDateTime::new(local_date, DateConfidence::Second).with_offset(utc_offset)
+      } else {
+        // The GPS date is in UTC, converted to `--timezone` if given.
+        // The local time zone is unknown, so only the day is trusted.
+        let utc_offset = process_state
+          .options
+          .default_utc_offset
+          .unwrap_or_else(|| FixedOffset::east_opt(0).expect("UTC is a valid offset"));
+        ConfidentNaiveDateTime::new(
+          gps_date + TimeDelta::seconds(i64::from(utc_offset.local_minus_utc())),
+          DateConfidence::Day,
+        )
+        .with_offset(utc_offset)
+      };
+      if guessed_date.is_none_or(|guessed_date| gps_date.confidence >= guessed_date.confidence) {
+        guessed_date = Some(gps_date);
+        guessed_source = String::from("gps");
+        guess_from_path = false;
+      }
+    }
+
+    // As a last resort, the modified or creation time is used if it is plausible.
+    // The creation time is the earlier one if the file was edited in place, the modified time if it was copied.
+    if process_state.options.mtime_fallback_confidence.is_some()
+      && original_exif_date.is_none()
+      && guessed_date.is_none()
+      && let Some((file_time_date, file_time_source)) = process_state
+        .file_time_fallback_date(original_file_modified_time, get_creation_time(file_path))
+    {
+      guessed_date = Some(file_time_date);
+      guessed_source = String::from(file_time_source);
+    }
 
     if let Some(original_exif_date) = original_exif_date {
       trace!(
         file_path = %file_path.display(),
-        "Original EXIF date: {} (confidence: {:?})",
+        "Original EXIF date from {}: {} (confidence: {:?})",
+        exif_dates.original().map_or("", |(tag, _)| tag),
         original_exif_date.date.format("%Y-%m-%d %H:%M:%S"),
         original_exif_date.confidence,
       );
@@ -480,7 +1777,7 @@ fn process_file(
 
   // fix future exif dates
   if let Some(original_exif_date) = original_exif_date
-    && original_exif_date.date > process_state.exif_dates_future_threshold
+    && original_exif_date.date > process_state.options.exif_dates_future_threshold
   {
     info!(
       file_path = %file_path.display(),
@@ -491,17 +1788,39 @@ fn process_file(
       process_state.start_time,
       DateConfidence::None,
     ));
+    new_exif_source = "future-exif-date";
   }
 
   if let Some(original_exif_date) = original_exif_date {
     if let Some(guessed_date) = guessed_date
-      && guessed_date.confidence > original_exif_date.confidence
-      && guessed_date.date != original_exif_date.date
+      && process_state.may_overwrite_exif_date(&original_exif_date, &guessed_date, guess_from_path)
     {
-      new_exif_date = Some(guessed_date);
+      if process_state.is_close_enough(&original_exif_date, &guessed_date) {
+        if guessed_date.date.trunc_subsecs(0) != original_exif_date.date.trunc_subsecs(0) {
+          debug!(
+            file_path = %file_path.display(),
+            "Not overwriting EXIF date {} with guessed date {} because they are close enough",
+            original_exif_date,
+            guessed_date,
+          );
+          process_state
+            .stat_exif_close_enough
+            .fetch_add(1, Ordering::Relaxed);
+        }
+      } else if process_state.passes_min_confidence(file_path, &guessed_date)
+        && process_state.passes_corroboration(file_path, &guessed_date, original_file_modified_time)
+      {
+        new_exif_date = Some(guessed_date);
+        new_exif_source = &guessed_source;
+      }
     }
   } else {
-    new_exif_date = new_exif_date.or(guessed_date);
+    new_exif_date = guessed_date
+      .filter(|guessed_date| process_state.passes_min_confidence(file_path, guessed_date))
+      .filter(|guessed_date| {
+        process_state.passes_corroboration(file_path, guessed_date, original_file_modified_time)
+      });
+    new_exif_source = &guessed_source;
   }
 
   if original_exif_date.is_none() && new_exif_date.is_none() && guessed_date.is_none() {
@@ -512,7 +1831,10 @@ fn process_file(
       .chars()
       .filter(char::is_ascii_digit)
       .count();
-    let log_level = if digit_count_in_file_name > 4 {
+    if let Some(unresolved_files) = &process_state.unresolved_files {
+      unresolved_files.record(file_path, digit_count_in_file_name);
+    }
+    let log_level = if digit_count_in_file_name > DATE_LIKE_DIGIT_COUNT {
       Level::DEBUG
     } else {
       Level::TRACE
@@ -525,6 +1847,7 @@ fn process_file(
   }
 
   // overwrite or set the EXIF date
+  let mut exif_date_write_failed = false;
   if let Some(new_exif_date) = new_exif_date {
     if let Some(original_exif_date) = original_exif_date {
       info!(
@@ -545,19 +1868,31 @@ fn process_file(
     }
 
     // write the new exif date
-    let set_exif_date_result = wrap_with_exiftool_repair(
-      file_path,
-      process_state.repair_exif_errors,
-      process_state.dry_run,
-      || {
-        set_exif_date(
-          file_path,
-          &new_exif_date.date,
-          process_state.dry_run,
-          process_state.ignore_minor_exif_errors,
-        )
-      },
-    )
+    let write_path = date_write_target(file_path, process_state.options.sidecar_for_raw);
+    if write_path != file_path {
+      debug!(
+        file_path = %file_path.display(),
+        "Writing the EXIF date to the XMP sidecar \"{}\"",
+        write_path.display(),
+      );
+    }
+    let set_exif_date_result = if exif_date_written {
+      Ok(())
+    } else {
+      wrap_with_exiftool_repair(
+        &write_path,
+        process_state.options.repair_exif_errors,
+        process_state.options.dry_run,
+        || {
+          set_exif_date(
+            &write_path,
+            &new_exif_date,
+            new_exif_source,
+            process_state.options.exif_write_options(),
+          )
+        },
+      )
+    }
     .with_context(|| {
       if let Some(original_exif_date) = original_exif_date {
         format!(
@@ -570,6 +1905,17 @@ fn process_file(
     match set_exif_date_result {
       // update the statistics
       Ok(()) => {
+        process_state.stat_exif_years.record(&new_exif_date.date);
+        emit_event(&if let Some(original_exif_date) = &original_exif_date {
+          FileEvent::overwritten(
+            file_path,
+            &original_exif_date.date,
+            &new_exif_date.date,
+            new_exif_source,
+          )
+        } else {
+          FileEvent::guessed(file_path, &new_exif_date.date, new_exif_source)
+        });
         if original_exif_date.is_some() {
           process_state
             .stat_exif_overwritten
@@ -581,19 +1927,121 @@ fn process_file(
         }
       },
       Err(e) => {
+        exif_date_write_failed = true;
         errors.push(ErrorWithFilePath::new(file_path, e));
       },
     }
   }
 
+  if is_media_file && let Some(resolved_date) = new_exif_date.or(original_exif_date) {
+    errors.extend(sync_companion_files(
+      file_path,
+      &resolved_date,
+      process_state,
+    ));
+  }
+
+  if is_media_file && let Some(file_pairs) = &process_state.file_pairs {
+    file_pairs.record(file_path, new_exif_date.or(original_exif_date));
+  }
+
+  if is_media_file
+    && let Some(folder_dates) = &process_state.folder_dates
+    && let Some(resolved_date) = new_exif_date.or(original_exif_date)
+    && let Some(modified_time) =
+      modified_time_from_exif_date(&resolved_date, process_state.options.default_utc_offset)
+  {
+    folder_dates.record(file_path, modified_time);
+  }
+
+  if is_media_file && let Some(verification) = &process_state.verification {
+    let to_utc = |date: ConfidentNaiveDateTime| {
+      modified_time_from_exif_date(&date, process_state.options.default_utc_offset)
+        .map(|utc_date| ConfidentNaiveDateTime::new(utc_date, date.confidence))
+    };
+    verification.check(
+      file_path,
+      FileDates {
+        file_name_date: file_name_date.and_then(to_utc),
+        exif_date: original_exif_date.and_then(to_utc),
+        modified_time: original_file_modified_time,
+      },
+    );
+  }
+
+  if is_media_file
+    && let Some(organizer) = &process_state.organizer
+    && let Some(resolved_date) = new_exif_date.or(original_exif_date)
+  {
+    organizer.record(file_path, resolved_date.date);
+  }
+
+  if is_media_file && let Some(sibling_dates) = &process_state.sibling_dates {
+    sibling_dates.record(
+      file_path,
+      new_exif_date.or(original_exif_date).map(|date| date.date),
+    );
+  }
+
+  // File managers sort by modified time, so it follows the capture date.
+  if is_media_file
+    && let Some(resolved_date) = new_exif_date.or(original_exif_date)
+    && let Some(synced_modified_time) = process_state.synced_modified_time(
+      &resolved_date,
+      new_exif_date.is_some(),
+      original_file_modified_time,
+    )
+  {
+    new_file_modified_time = Some(synced_modified_time);
+  }
+
   // overwrite the modified time
+  let mut modified_time_write_failed = false;
   if let Some(new_file_modified_time) = new_file_modified_time {
     if let Err(e) = set_modified_time(file_path, &new_file_modified_time, process_state) {
+      modified_time_write_failed = true;
       errors.push(ErrorWithFilePath::new(file_path, e));
+    } else {
+      process_state
+        .stat_modified_time_updated
+        .fetch_add(1, Ordering::Relaxed);
     }
-    process_state
-      .stat_modified_time_updated
-      .fetch_add(1, Ordering::Relaxed);
+  }
+
+  // Only the changes that were actually made are reported.
+  let reported_exif_date = new_exif_date.filter(|_| !exif_date_write_failed);
+  let reported_modified_time = new_file_modified_time.filter(|_| !modified_time_write_failed);
+  if reported_exif_date.is_some() || reported_modified_time.is_some() {
+    process_state.stat_extensions.record_updated(file_path);
+  }
+  if let Some(change_list) = &process_state.change_list
+    && (reported_exif_date.is_some() || reported_modified_time.is_some())
+  {
+    change_list.record(
+      file_path,
+      original_exif_date.as_ref(),
+      reported_exif_date
+        .as_ref()
+        .map(|date| (date, new_exif_source)),
+      original_file_modified_time.as_ref(),
+      reported_modified_time.as_ref(),
+    );
+  }
+
+  // Windows and macOS apps sort by creation time, so it follows the capture date too.
+  if (cfg!(windows) || process_state.options.sync_birthtime)
+    && is_media_file
+    && let Some(resolved_date) = new_exif_date.or(original_exif_date)
+    && let Some(creation_time) =
+      modified_time_from_exif_date(&resolved_date, process_state.options.default_utc_offset)
+    && let Err(e) = sync_creation_time(
+      file_path,
+      &creation_time,
+      process_state.options.mtime_granularity,
+      process_state.options.dry_run,
+    )
+  {
+    errors.push(ErrorWithFilePath::new(file_path, e));
   }
 
   if errors.is_empty() {
@@ -603,13 +2051,181 @@ fn process_file(
   }
 }
 
+/// Starts `--exiftool-jobs` exiftool workers, or one per CPU, and warns about the image formats they can not write.
+fn start_exiftool_workers(matches: &clap::ArgMatches) -> anyhow::Result<()> {
+  // Before the format probe below, which is the first exiftool command.
+  install_exiftool_pool(
+    matches
+      .get_one::<u32>("exiftool-jobs")
+      .map(|exiftool_jobs| usize::try_from(*exiftool_jobs))
+      .transpose()?,
+  )?;
+  if let Ok(format_support) = modern_image_format_support() {
+    for (format, writable) in format_support {
+      if writable {
+        debug!("exiftool can write {format} files");
+      } else {
+        warn!(
+          "exiftool can not write {format} files, they are skipped. Update exiftool to fix them."
+        );
+      }
+    }
+  }
+  Ok(())
+}
+
 #[must_use]
 fn new_argparser() -> clap::Command {
   command!()
   .about("Extracts possible timestamp information from filenames and sets EXIF and modified times accordingly.")
+  .after_help("As a Type=notify systemd service, e.g. started by a timer, it reports readiness, its progress as the status and watchdog pings via sd_notify.")
+  .subcommand(
+    clap::Command::new("parse")
+    .about("Run the date extractors on a single file name or path and print the result without touching the disk")
+    .arg(
+      Arg::new("input")
+      .help("File name or path to parse")
+      .required(true)
+      .value_name("name or path"),
+    ),
+  )
+  .subcommand(
+    clap::Command::new("apply")
+    .about("Execute the changes of a plan written with --plan, skipping files modified since")
+    .arg(
+      Arg::new("plan")
+      .help("Plan to apply")
+      .required(true)
+      .value_name("file")
+      .value_parser(value_parser!(PathBuf)),
+    ),
+  )
+  .subcommand(
+    clap::Command::new("review")
+    .about("Approve or reject the changes of a plan written with --plan group by group, keeping only the approved ones. In a terminal, the groups are shown in an interactive table")
+    .arg(
+      Arg::new("plan")
+      .help("Plan to review")
+      .required(true)
+      .value_name("file")
+      .value_parser(value_parser!(PathBuf)),
+    )
+    .arg(
+      Arg::new("group-by")
+      .long("group-by")
+      .help("Group the changes by the extractor that found the date, its confidence or the folder of the file")
+      .value_parser(["extractor", "confidence", "directory"])
+      .default_value("extractor"),
+    )
+    .arg(
+      Arg::new("output")
+      .long("output")
+      .help("File to write the approved changes to, instead of overwriting the plan")
+      .value_name("file")
+      .value_parser(value_parser!(PathBuf)),
+    ),
+  )
+  .subcommand(
+    clap::Command::new("verify")
+    .about("Report media files whose file name date, EXIF date and modified time disagree, without writing anything")
+    .arg(
+      Arg::new("files")
+      .help("Files or directories to verify, in addition to the ones given before the subcommand")
+      .num_args(1..)
+      .value_name("files")
+      .value_parser(value_parser!(PathBuf)),
+    )
+    .arg(
+      Arg::new("tolerance")
+      .long("tolerance")
+      .help("How far dates may disagree beyond the time span covered by their confidence, e.g. 2h or 1d")
+      .value_name("duration")
+      .value_parser(pretty_duration::parse_duration)
+      .default_value("1d"),
+    )
+    .arg(
+      Arg::new("report")
+      .long("report")
+      .help("File to write the files with disagreeing dates to (tab separated)")
+      .value_name("file")
+      .value_parser(value_parser!(PathBuf)),
+    ),
+  )
+  .subcommand(
+    clap::Command::new("organize")
+    .about("Fix the dates of the media files like a normal run, then put them into YYYY/MM/ folders of their dates")
+    .arg(
+      Arg::new("files")
+      .help("Files or directories to organize, in addition to the ones given before the subcommand")
+      .num_args(1..)
+      .value_name("files")
+      .value_parser(value_parser!(PathBuf)),
+    )
+    .arg(
+      Arg::new("target")
+      .long("target")
+      .help("Folder to create the date folders in")
+      .required(true)
+      .value_name("directory")
+      .value_parser(value_parser!(PathBuf)),
+    )
+    .arg(
+      Arg::new("mode")
+      .long("mode")
+      .help("Whether the files are moved, hardlinked or copied into the date folders. Companion files and XMP sidecars follow their media file")
+      .value_parser(["move", "hardlink", "copy"])
+      .default_value("move"),
+    ),
+  )
+  .subcommand(
+    clap::Command::new("shift")
+    .about("Shift the existing EXIF dates of the media files by a fixed offset, e.g. to correct a camera clock that was set wrong")
+    .arg(
+      Arg::new("files")
+      .help("Files or directories to shift, in addition to the ones given before the subcommand")
+      .num_args(1..)
+      .value_name("files")
+      .value_parser(value_parser!(PathBuf)),
+    )
+    .arg(
+      Arg::new("shift")
+      .long("shift")
+      .help("Signed offset to add to the EXIF dates, in years (y), months (mo), weeks (w), days (d), hours (h), minutes (m) and seconds (s), e.g. \"+1y 3h\" or -30m")
+      .required(true)
+      .allow_hyphen_values(true)
+      .value_name("offset")
+      .value_parser(DateShift::from_str),
+    )
+    .arg(
+      Arg::new("mtime")
+      .long("mtime")
+      .help("Shift the modified times by the same offset")
+      .action(ArgAction::SetTrue),
+    ),
+  )
+  .subcommand(
+    clap::Command::new("undo")
+    .about("Restore the date tags and modified times recorded in an undo journal written with --journal")
+    .arg(
+      Arg::new("journal")
+      .long("journal")
+      .help("Undo journal to revert")
+      .required(true)
+      .value_name("file")
+      .value_parser(value_parser!(PathBuf)),
+    ),
+  )
+  .arg(
+    Arg::new("config")
+    .long("config")
+    .help("TOML file setting any of the options by their long name, e.g. dry-run = true, and the options of a subcommand in a table named after it, e.g. [organize]. Options given on the command line take precedence. Defaults to image-date-fixer/config.toml in the user config directory if it exists")
+    .value_name("file")
+    .value_parser(value_parser!(PathBuf)),
+  )
   .arg(
     Arg::new("flagged_files")
     .long("files")
+    .action(ArgAction::Append)
     .help("Files or directories to process")
     .num_args(1..)
     .value_name("files")
@@ -622,9 +2238,17 @@ fn new_argparser() -> clap::Command {
     .value_name("files")
     .value_parser(value_parser!(PathBuf)),
   )
+  .arg(
+    Arg::new("files-from")
+    .long("files-from")
+    .help("Process the NUL-separated files listed in this file, or on stdin if it is -, e.g. from find -print0. Listed directories are not walked")
+    .value_name("file")
+    .value_parser(value_parser!(PathBuf)),
+  )
   .arg(
     Arg::new("exclude-files")
     .long("exclude-files")
+    .action(ArgAction::Append)
     .help("Files or directories to exclude")
     .num_args(1..)
     .value_name("excluded files")
@@ -633,10 +2257,106 @@ fn new_argparser() -> clap::Command {
   .arg(
     Arg::new("log-level")
     .long("log-level")
-    .help("Log level")
+    .global(true)
+    .help("Log level, takes precedence over -q, -v and RUST_LOG")
     .value_name("log level")
     .value_parser(["TRACE", "DEBUG", "INFO", "WARNING", "ERROR"]),
   )
+  .arg(
+    Arg::new("quiet")
+    .short('q')
+    .long("quiet")
+    .global(true)
+    .help("Only log errors")
+    .conflicts_with("verbose")
+    .action(ArgAction::SetTrue),
+  )
+  .arg(
+    Arg::new("verbose")
+    .short('v')
+    .long("verbose")
+    .global(true)
+    .help("Log more details, -v logs DEBUG and -vv TRACE events. Takes precedence over RUST_LOG")
+    .action(ArgAction::Count),
+  )
+  .arg(
+    Arg::new("log-format")
+    .long("log-format")
+    .global(true)
+    .help("Write the log as colored text, as one short line per event, in the default format of tracing including the span context, or as one JSON object per line with the timestamp, level, file path, message and the other fields")
+    .value_parser(["pretty", "compact", "full", "json"])
+    .default_value("pretty"),
+  )
+  .arg(
+    Arg::new("color")
+    .long("color")
+    .global(true)
+    .help("Color the log on stdout. auto only colors it if stdout is a terminal and NO_COLOR is not set")
+    .value_parser(["auto", "always", "never"])
+    .default_value("auto"),
+  )
+  .arg(
+    Arg::new("log-target")
+    .long("log-target")
+    .global(true)
+    .help("Write the log to stdout, or to journald or syslog with proper priorities, e.g. when running as a service")
+    .value_parser(["stdout", "journald", "syslog"])
+    .default_value("stdout"),
+  )
+  .arg(
+    Arg::new("events-out")
+    .long("events-out")
+    .global(true)
+    .help("Write one JSON line per guessed or overwritten EXIF date, skipped file and error to this file or FIFO as it happens, e.g. for a wrapping UI")
+    .value_name("file")
+    .value_parser(value_parser!(PathBuf)),
+  )
+  .arg(
+    Arg::new("metrics-listen")
+    .long("metrics-listen")
+    .global(true)
+    .help("Serve the counters of the run in the Prometheus text format on http://<address>/metrics while it is running, e.g. 127.0.0.1:9187")
+    .value_name("address")
+    .value_parser(value_parser!(SocketAddr)),
+  )
+  .arg(
+    Arg::new("notify-url")
+    .long("notify-url")
+    .global(true)
+    .help("POST the statistics as JSON to this webhook (ntfy, Slack or Discord) when the run finishes or fails. Needs curl, which is checked at startup")
+    .value_name("url"),
+  )
+  .arg(
+    Arg::new("log-file")
+    .long("log-file")
+    .global(true)
+    .help("Also append the log to this file, without colors")
+    .value_name("file")
+    .value_parser(value_parser!(PathBuf)),
+  )
+  .arg(
+    Arg::new("log-rotation")
+    .long("log-rotation")
+    .global(true)
+    .help("Rotate the log file daily or when it would grow beyond this size, e.g. 10MiB. The last 5 log files are kept as <file>.1 to <file>.5")
+    .value_name("daily|size")
+    .value_parser(LogRotation::from_str)
+    .default_value("10MiB"),
+  )
+  .arg(
+    Arg::new("exiftool-path")
+    .long("exiftool-path")
+    .help("Path to the exiftool executable (defaults to exiftool in PATH)")
+    .value_name("path")
+    .value_parser(value_parser!(PathBuf)),
+  )
+  .arg(
+    Arg::new("exiftool-jobs")
+    .long("exiftool-jobs")
+    .help("Number of exiftool processes to run in parallel (defaults to the number of CPUs)")
+    .value_name("jobs")
+    .value_parser(value_parser!(u32).range(1..)),
+  )
   .arg(
     Arg::new("fix-future-modified-times")
     .long("fix-future-modified-times")
@@ -644,6 +2364,22 @@ fn new_argparser() -> clap::Command {
     .value_name("days into the future")
     .value_parser(value_parser!(u64)),
   )
+  .arg(
+    Arg::new("min-valid-mtime")
+    .long("min-valid-mtime")
+    .help("Modified times before this date are considered invalid and set to it, e.g. 1950-01-01 for archives of old scans")
+    .value_name("date")
+    .default_value("1970-01-02")
+    .value_parser(parse_cli_date),
+  )
+  .arg(
+    Arg::new("mtime-granularity")
+    .long("mtime-granularity")
+    .help("Modified times closer than this many seconds are considered equal, e.g. 2 for FAT formatted SD cards")
+    .value_name("seconds")
+    .default_value("1")
+    .value_parser(value_parser!(u32).range(1..)),
+  )
   .arg(
     Arg::new("fix-future-exif-dates")
     .long("fix-future-exif-dates")
@@ -669,12 +2405,37 @@ fn new_argparser() -> clap::Command {
     .help("Do not print final statistics")
     .action(ArgAction::SetTrue),
   )
+  .arg(
+    Arg::new("wait")
+    .long("wait")
+    .help(format!("Wait for other runs processing the same folders to finish instead of failing. Runs lock the folders they process with a {LOCK_FILE_NAME} file"))
+    .action(ArgAction::SetTrue),
+  )
+  .arg(
+    Arg::new("no-wait")
+    .long("no-wait")
+    .help("Fail if another run is processing the same folders (default)")
+    .overrides_with("wait")
+    .action(ArgAction::SetTrue),
+  )
   .arg(
     Arg::new("skip-hidden-files")
     .long("skip-hidden-files")
     .help("Skip hidden files")
     .action(ArgAction::SetTrue),
   )
+  .arg(
+    Arg::new("include-nomedia-folders")
+    .long("include-nomedia-folders")
+    .help("Also process folders containing a .nomedia file, which Android uses to hide e.g. app caches from galleries")
+    .action(ArgAction::SetTrue),
+  )
+  .arg(
+    Arg::new("one-file-system")
+    .long("one-file-system")
+    .help("Do not descend into folders on other file systems than the given directories, e.g. network shares or snapshots mounted inside them")
+    .action(ArgAction::SetTrue),
+  )
   .arg(
     Arg::new("ignore-minor-exif-errors")
     .long("ignore-minor-exif-errors")
@@ -687,24 +2448,599 @@ fn new_argparser() -> clap::Command {
     .help("Attempt to repair EXIF errors if exiftool fails to read or write EXIF data. This can fix some errors but may also cause data loss, so use with caution.")
     .action(ArgAction::SetTrue),
   )
-}
-
-fn main() -> anyhow::Result<()> {
-  let matches = new_argparser().get_matches();
-
-  // set the correct log level
-  let log_level = matches
-    .get_one::<String>("log-level")
-    .and_then(|level| Level::from_str(level).ok());
-  setup_logging(log_level);
+  .arg(
+    Arg::new("folder-date-depth")
+    .long("folder-date-depth")
+    .global(true)
+    .help("Number of ancestor folders to search for a date if the file name does not contain one")
+    .value_name("depth")
+    .default_value("1")
+    .value_parser(value_parser!(usize)),
+  )
+  .arg(
+    Arg::new("mine-text-tags")
+    .long("mine-text-tags")
+    .help("Look for dates in the EXIF description and comment tags of files without a date tag, e.g. \"Scanned 2003-05-12\"")
+    .action(ArgAction::SetTrue),
+  )
+  .arg(
+    Arg::new("date-range-policy")
+    .long("date-range-policy")
+    .global(true)
+    .help("Which date to use for files in folders named after a date range (e.g. \"2019-07-01 - 2019-07-14 Kroatien\")")
+    .value_name("policy")
+    .default_value("start")
+    .value_parser(["start", "midpoint", "end"]),
+  )
+  .arg(
+    Arg::new("extractor-config")
+    .long("extractor-config")
+    .global(true)
+    .help("TOML file declaring additional date extractors, each with a name, a regex with named groups (year, month, day, hour, minute, second, millisecond), a confidence and an optional priority. The [priorities] and [confidence] tables override the priority (lower is tried first) and the reported confidence of built-in and custom extractors by name.")
+    .value_name("file")
+    .value_parser(value_parser!(PathBuf)),
+  )
+  .arg(
+    Arg::new("filename-pattern")
+    .long("filename-pattern")
+    .global(true)
+    .help("strftime-style pattern matching the start of file names (e.g. \"backup_%Y%m%d_%H%M%S\"). Supports %Y, %m, %d, %H, %M, %S, %f (milliseconds) and %%. Can be specified multiple times.")
+    .value_name("pattern")
+    .action(ArgAction::Append),
+  )
+  .arg(
+    Arg::new("disable-extractor")
+    .long("disable-extractor")
+    .global(true)
+    .help("Disable the date extractor with the given name. Can be specified multiple times.")
+    .value_name("name")
+    .action(ArgAction::Append)
+    .conflicts_with("only-extractors"),
+  )
+  .arg(
+    Arg::new("only-extractors")
+    .long("only-extractors")
+    .global(true)
+    .help("Comma-separated names of the only date extractors to use")
+    .value_name("names")
+    .value_delimiter(','),
+  )
+  .arg(
+    Arg::new("only-extensions")
+    .long("only-extensions")
+    .help("Comma-separated file extensions to restrict processing to, e.g. jpg,heic,mp4")
+    .value_name("extensions")
+    .value_delimiter(','),
+  )
+  .arg(
+    Arg::new("min-size")
+    .long("min-size")
+    .help("Skip files smaller than this, e.g. 20K to skip thumbnails")
+    .value_name("size")
+    .value_parser(parse_size),
+  )
+  .arg(
+    Arg::new("max-size")
+    .long("max-size")
+    .help("Skip files larger than this, e.g. 100MiB to skip videos")
+    .value_name("size")
+    .value_parser(parse_size),
+  )
+  .arg(
+    Arg::new("modified-since")
+    .long("modified-since")
+    .help("Skip files last modified before this local date or duration ago, e.g. 2024-01-31 or 1d for nightly runs")
+    .value_name("date|duration")
+    .value_parser(parse_modified_time_bound),
+  )
+  .arg(
+    Arg::new("modified-before")
+    .long("modified-before")
+    .help("Skip files last modified at or after this local date or duration ago, e.g. 2024-01-31 or 1h to skip files still being copied")
+    .value_name("date|duration")
+    .value_parser(parse_modified_time_bound),
+  )
+  .arg(
+    Arg::new("retries")
+    .long("retries")
+    .help("Retry files that failed, e.g. because of a network file system hiccup, up to this many times at the end of the run. Only files that still fail are reported as errors")
+    .value_name("count")
+    .value_parser(value_parser!(u32)),
+  )
+  .arg(
+    Arg::new("skip-recent")
+    .long("skip-recent")
+    .help("Skip files modified less than this long ago, as they may still be written, e.g. 60s for Syncthing or rsync targets")
+    .value_name("duration")
+    .value_parser(pretty_duration::parse_duration),
+  )
+  .arg(
+    Arg::new("explain")
+    .long("explain")
+    .help("Explain for every date extractor which part of the given file name matched or where parsing failed, then exit without touching the disk")
+    .value_name("file"),
+  )
+  .arg(
+    Arg::new("min-year")
+    .long("min-year")
+    .global(true)
+    .help("Reject guessed dates before this year")
+    .value_name("year")
+    .default_value("1900")
+    .value_parser(value_parser!(i32)),
+  )
+  .arg(
+    Arg::new("max-year")
+    .long("max-year")
+    .global(true)
+    .help("Reject guessed dates after this year [default: next year]")
+    .value_name("year")
+    .value_parser(value_parser!(i32)),
+  )
+  .arg(
+    Arg::new("interpolate-sibling-dates")
+    .long("interpolate-sibling-dates")
+    .help("After processing, interpolate dates for date-less sequence-numbered files (e.g. DSC_0042.jpg) from their dated siblings")
+    .action(ArgAction::SetTrue),
+  )
+  .arg(
+    Arg::new("sync-live-photos")
+    .long("sync-live-photos")
+    .help("After processing, force the photo and video of Live Photos (e.g. IMG_0001.HEIC and IMG_0001.MOV) to the same date")
+    .action(ArgAction::SetTrue),
+  )
+  .arg(
+    Arg::new("sync-raw-jpeg-pairs")
+    .long("sync-raw-jpeg-pairs")
+    .help("After processing, apply confident JPEG dates to the RAW file with the same stem (or its XMP sidecar)")
+    .action(ArgAction::SetTrue),
+  )
+  .arg(
+    Arg::new("exif-confidence-policy")
+    .long("exif-confidence-policy")
+    .help("How confident existing EXIF dates are considered. \"placeholder\" trusts EXIF dates to the second unless they are known placeholder dates (e.g. 2000-01-01 00:00:00), \"precision\" derives the confidence from the least significant nonzero component, \"trust\" trusts every EXIF date, \"agreement\" is like \"placeholder\" but lowers the confidence the further CreateDate and ModifyDate diverge from DateTimeOriginal")
+    .value_name("policy")
+    .default_value("placeholder")
+    .value_parser(["placeholder", "precision", "trust", "agreement"]),
+  )
+  .arg(
+    Arg::new("min-confidence")
+    .long("min-confidence")
+    .help("Guessed dates below this confidence are reported but never written")
+    .value_name("confidence")
+    .default_value("none")
+    .value_parser(["none", "decade", "year", "month", "day", "hour", "minute", "second", "millisecond"]),
+  )
+  .arg(
+    Arg::new("overwrite-margin")
+    .long("overwrite-margin")
+    .help("Number of confidence levels a guessed date must exceed the confidence of an existing EXIF date by to overwrite it")
+    .value_name("levels")
+    .default_value("1")
+    .value_parser(value_parser!(u8).range(1..)),
+  )
+  .arg(
+    Arg::new("write-all-dates")
+    .long("write-all-dates")
+    .help("Write all date tags (DateTimeOriginal, CreateDate, ModifyDate and XMP:DateCreated) instead of only DateTimeOriginal")
+    .action(ArgAction::SetTrue),
+  )
+  .arg(
+    Arg::new("plan")
+    .long("plan")
+    .help("Write every change (path, old and new EXIF date and modified time, source and confidence) to this JSON file without touching any files, to be reviewed and executed with the apply subcommand")
+    .value_name("file")
+    .value_parser(value_parser!(PathBuf)),
+  )
+  .arg(
+    Arg::new("report-csv")
+    .long("report-csv")
+    .help("Write one row per changed file (or file that would be changed in a dry run) with the old and new EXIF date and modified time, the source and the confidence of the date to this CSV file")
+    .value_name("file")
+    .value_parser(value_parser!(PathBuf)),
+  )
+  .arg(
+    Arg::new("report-json")
+    .long("report-json")
+    .help("Write the same content as --report-csv as JSON with a schema version to this file")
+    .value_name("file")
+    .value_parser(value_parser!(PathBuf)),
+  )
+  .arg(
+    Arg::new("progress")
+    .long("progress")
+    .help("Show a progress line with the file counts, fixes, errors and current folder. Ignored if stdout is not a terminal")
+    .action(ArgAction::SetTrue),
+  )
+  .arg(
+    Arg::new("precount")
+    .long("precount")
+    .help("Count the files before processing them, so that --progress can show a progress bar and an ETA")
+    .requires("progress")
+    .action(ArgAction::SetTrue),
+  )
+  .arg(
+    Arg::new("checkpoint")
+    .long("checkpoint")
+    .help("Record the files that were processed without errors in this file, so an interrupted run can be continued with --resume. It is removed when the run finishes")
+    .value_name("file")
+    .value_parser(value_parser!(PathBuf)),
+  )
+  .arg(
+    Arg::new("resume")
+    .long("resume")
+    .help("Continue an interrupted run from the checkpoint written with --checkpoint, skipping the files it already processed")
+    .value_name("checkpoint")
+    .conflicts_with("checkpoint")
+    .value_parser(value_parser!(PathBuf)),
+  )
+  .arg(
+    Arg::new("unresolved-out")
+    .long("unresolved-out")
+    .help("Write the media files with neither an EXIF date nor a date guessed from their path to this file, with a hint whether their file name may contain an unrecognized date, to rename them manually")
+    .value_name("file")
+    .value_parser(value_parser!(PathBuf)),
+  )
+  .arg(
+    Arg::new("journal")
+    .long("journal")
+    .help("Append the previous values of the written date tags and the modified time of every changed file to this file, so the run can be reverted with the undo subcommand")
+    .value_name("file")
+    .value_parser(value_parser!(PathBuf)),
+  )
+  .arg(
+    Arg::new("backup-originals")
+    .long("backup-originals")
+    .help("Keep a copy of every file before its EXIF data is modified, next to it as <file>_original or in the given directory")
+    .value_name("backup directory")
+    .num_args(0..=1)
+    .require_equals(true)
+    .value_parser(value_parser!(PathBuf)),
+  )
+  .arg(
+    Arg::new("timezone")
+    .long("timezone")
+    .help("UTC offset of dates whose source does not state one, e.g. +02:00, written to OffsetTimeOriginal")
+    .value_name("offset")
+    .value_parser(parse_utc_offset),
+  )
+  .arg(
+    Arg::new("write-provenance")
+    .long("write-provenance")
+    .help("Record how each written date was determined in the XMP-idf:DateSource tag")
+    .action(ArgAction::SetTrue),
+  )
+  .arg(
+    Arg::new("write-iptc")
+    .long("write-iptc")
+    .help("Also write IPTC:DateCreated and IPTC:TimeCreated, which news and archive software reads instead of EXIF")
+    .action(ArgAction::SetTrue),
+  )
+  .arg(
+    Arg::new("preserve-mtime")
+    .long("preserve-mtime")
+    .help("Keep the file modification time when writing EXIF dates instead of setting it to the current time")
+    .action(ArgAction::SetTrue),
+  )
+  .arg(
+    Arg::new("mtime-as-fallback")
+    .long("mtime-as-fallback")
+    .help("Write the modified time to files without any EXIF or file name date, if it is plausible, at the given confidence")
+    .value_name("confidence")
+    .num_args(0..=1)
+    .require_equals(true)
+    .default_missing_value("year")
+    .value_parser(["none", "decade", "year", "month", "day", "hour", "minute", "second", "millisecond"]),
+  )
+  .arg(
+    Arg::new("sync-mtime-from-exif")
+    .long("sync-mtime-from-exif")
+    .help("Set the modified time of every media file to its EXIF date, so file managers sort by capture date")
+    .action(ArgAction::SetTrue),
+  )
+  .arg(
+    Arg::new("sync-birthtime")
+    .long("sync-birthtime")
+    .help("Set the creation (birth) time of every media file to its EXIF date on macOS, which is always done on Windows")
+    .action(ArgAction::SetTrue),
+  )
+  .arg(
+    Arg::new("fix-folder-mtimes")
+    .long("fix-folder-mtimes")
+    .help("After processing, set the modified time of every folder to the newest or oldest date of the media files directly inside it")
+    .value_name("newest|oldest")
+    .num_args(0..=1)
+    .require_equals(true)
+    .default_missing_value("newest")
+    .value_parser(["newest", "oldest"]),
+  )
+  .arg(
+    Arg::new("sidecar-for-raw")
+    .long("sidecar-for-raw")
+    .help("Never modify RAW files and write their dates to an XMP sidecar instead, which is created if necessary")
+    .action(ArgAction::SetTrue),
+  )
+  .arg(
+    Arg::new("min-delta")
+    .long("min-delta")
+    .help("Do not overwrite existing EXIF dates that differ from the guessed date by at most this many seconds")
+    .value_name("seconds")
+    .default_value("0")
+    .value_parser(value_parser!(u32)),
+  )
+  .arg(
+    Arg::new("only-fill-missing")
+    .long("only-fill-missing")
+    .help("Only set EXIF dates that are missing and never overwrite existing ones, regardless of their confidence")
+    .action(ArgAction::SetTrue),
+  )
+  .arg(
+    Arg::new("prefer-filename")
+    .long("prefer-filename")
+    .help("Overwrite existing EXIF dates with the date derived from the file name or folders whenever one is found, regardless of the confidence of the existing EXIF date")
+    .conflicts_with("only-fill-missing")
+    .action(ArgAction::SetTrue),
+  )
+  .arg(
+    Arg::new("corroborate-with-modified-time")
+    .long("corroborate-with-modified-time")
+    .help("Only write guessed dates if the modified time of the file is within this many days of the guess. Other guesses are added to the review list")
+    .value_name("days")
+    .value_parser(value_parser!(u32)),
+  )
+  .arg(
+    Arg::new("review-list")
+    .long("review-list")
+    .help("File to write guessed dates to that were not corroborated by the modified time (tab separated)")
+    .value_name("file")
+    .requires("corroborate-with-modified-time")
+    .value_parser(value_parser!(PathBuf)),
+  )
+}
+
+fn main() -> anyhow::Result<()> {
+  run().inspect_err(notify_failure)
+}
+
+fn run() -> anyhow::Result<()> {
+  let args = args_with_config_file(&new_argparser(), std::env::args_os().collect())?;
+  let matches = new_argparser().get_matches_from(args);
+
+  // set the correct log level
+  let log_level = matches
+    .get_one::<String>("log-level")
+    .and_then(|level| Level::from_str(level).ok())
+    .or_else(|| verbosity_level(matches.get_flag("quiet"), matches.get_count("verbose")));
+  let log_format = matches
+    .get_one::<String>("log-format")
+    .map(|log_format| LogFormat::from_str(log_format))
+    .transpose()?
+    .unwrap_or_default();
+  let log_file = matches
+    .get_one::<PathBuf>("log-file")
+    .map(|log_file_path| {
+      let log_rotation = *matches
+        .get_one::<LogRotation>("log-rotation")
+        .expect("The log rotation has a default value");
+      RotatingLogFile::open(log_file_path, log_rotation)
+    })
+    .transpose()?;
+  let color_choice = matches
+    .get_one::<String>("color")
+    .map(|color_choice| ColorChoice::from_str(color_choice))
+    .transpose()?
+    .unwrap_or_default();
+  let log_target = matches
+    .get_one::<String>("log-target")
+    .map(|log_target| LogTarget::from_str(log_target))
+    .transpose()?
+    .unwrap_or_default();
+  setup_logging(log_level, log_format, color_choice, log_target, log_file)?;
+  if let Some(events_path) = matches.get_one::<PathBuf>("events-out") {
+    install_event_stream(events_path)?;
+  }
+  if let Some(notify_url) = matches.get_one::<String>("notify-url") {
+    install_notify_url(notify_url)?;
+  }
+
+  let folder_date_depth = matches
+    .get_one::<usize>("folder-date-depth")
+    .copied()
+    .unwrap_or(1);
+  let mine_text_tags = matches
+    .get_one::<bool>("mine-text-tags")
+    .copied()
+    .unwrap_or(false);
+  let date_range_policy = matches
+    .get_one::<String>("date-range-policy")
+    .map(|policy| DateRangePolicy::from_str(policy))
+    .transpose()?
+    .unwrap_or_default();
+  let mut date_extractor_registry =
+    DateExtractorRegistry::with_builtin_extractors(date_range_policy);
+  if let Some(extractor_config_path) = matches.get_one::<PathBuf>("extractor-config") {
+    ExtractorConfig::load(extractor_config_path)?.apply(&mut date_extractor_registry)?;
+  }
+  for (index, pattern) in matches
+    .get_many::<String>("filename-pattern")
+    .unwrap_or_default()
+    .enumerate()
+  {
+    date_extractor_registry.register(Box::new(RegexDateExtractor::from_strftime(
+      format!("filename_pattern_{}", index + 1),
+      DEFAULT_CUSTOM_EXTRACTOR_PRIORITY,
+      pattern,
+    )?));
+  }
+  if let Some(disabled_extractors) = matches.get_many::<String>("disable-extractor") {
+    date_extractor_registry.disable(disabled_extractors.map(String::as_str))?;
+  }
+  if let Some(only_extractors) = matches.get_many::<String>("only-extractors") {
+    date_extractor_registry.retain_only(only_extractors.map(String::as_str))?;
+  }
+  let min_year = matches
+    .get_one::<i32>("min-year")
+    .copied()
+    .unwrap_or(DEFAULT_MIN_YEAR);
+  let max_year = matches
+    .get_one::<i32>("max-year")
+    .copied()
+    .unwrap_or_else(|| Local::now().year() + 1);
+  date_extractor_registry.set_plausible_years(min_year, max_year)?;
+  date_extractor_registry.validate_order()?;
+  debug!(
+    "Date extractors in the order they are tried: {}",
+    date_extractor_registry
+      .priorities()
+      .map(|(name, priority)| format!("{name} ({priority})"))
+      .collect::<Vec<_>>()
+      .join(", ")
+  );
+  install_date_extractor_registry(date_extractor_registry)?;
+
+  if let Some(input) = matches.get_one::<String>("explain") {
+    run_explain_command(input, folder_date_depth)?;
+    return Ok(());
+  }
+  if let Some(parse_matches) = matches.subcommand_matches("parse") {
+    let input = parse_matches
+      .get_one::<String>("input")
+      .expect("The input is required");
+    if !run_parse_command(input, folder_date_depth)? {
+      exit(1);
+    }
+    return Ok(());
+  }
+  if let Some(review_matches) = matches.subcommand_matches("review") {
+    let plan_path = review_matches
+      .get_one::<PathBuf>("plan")
+      .expect("The plan is required");
+    let grouping = review_matches
+      .get_one::<String>("group-by")
+      .map(|grouping| ReviewGrouping::from_str(grouping))
+      .transpose()?
+      .unwrap_or_default();
+    run_review_command(
+      plan_path,
+      grouping,
+      review_matches
+        .get_one::<PathBuf>("output")
+        .map(PathBuf::as_path),
+    )?;
+    return Ok(());
+  }
 
   let flagged_files = matches
     .get_many::<PathBuf>("flagged_files")
     .unwrap_or_default();
+  let folder_mtime_policy = matches
+    .get_one::<String>("fix-folder-mtimes")
+    .map(|policy| FolderMtimePolicy::from_str(policy))
+    .transpose()?;
+  let interpolate_sibling_dates = matches
+    .get_one::<bool>("interpolate-sibling-dates")
+    .copied()
+    .unwrap_or(false);
+  let sync_live_photos = matches
+    .get_one::<bool>("sync-live-photos")
+    .copied()
+    .unwrap_or(false);
+  let sync_raw_jpeg_pairs = matches
+    .get_one::<bool>("sync-raw-jpeg-pairs")
+    .copied()
+    .unwrap_or(false);
+  let exif_confidence_policy = matches
+    .get_one::<String>("exif-confidence-policy")
+    .map(|policy| ExifConfidencePolicy::from_str(policy))
+    .transpose()?
+    .unwrap_or_default();
+  let min_confidence = matches
+    .get_one::<String>("min-confidence")
+    .map(|confidence| DateConfidence::from_str(confidence))
+    .transpose()?
+    .unwrap_or(DateConfidence::None);
+  let write_all_dates = matches
+    .get_one::<bool>("write-all-dates")
+    .copied()
+    .unwrap_or(false);
+  let preserve_modified_time = matches
+    .get_one::<bool>("preserve-mtime")
+    .copied()
+    .unwrap_or(false);
+  let mtime_fallback_confidence = matches
+    .get_one::<String>("mtime-as-fallback")
+    .map(|confidence| DateConfidence::from_str(confidence))
+    .transpose()?;
+  let sync_mtime_from_exif = matches
+    .get_one::<bool>("sync-mtime-from-exif")
+    .copied()
+    .unwrap_or(false);
+  let sync_birthtime = matches
+    .get_one::<bool>("sync-birthtime")
+    .copied()
+    .unwrap_or(false);
+  if sync_birthtime && !CAN_SET_CREATION_TIME {
+    error!(
+      "--sync-birthtime is not supported on this platform, which can not set the creation time."
+    );
+    exit(1);
+  }
+  let default_utc_offset = matches.get_one::<FixedOffset>("timezone").copied();
+  let write_provenance = matches
+    .get_one::<bool>("write-provenance")
+    .copied()
+    .unwrap_or(false);
+  if write_provenance {
+    install_provenance_config()?;
+  }
+  let write_iptc = matches
+    .get_one::<bool>("write-iptc")
+    .copied()
+    .unwrap_or(false);
+  let sidecar_for_raw = matches
+    .get_one::<bool>("sidecar-for-raw")
+    .copied()
+    .unwrap_or(false);
+  let min_delta = matches
+    .get_one::<u32>("min-delta")
+    .map(|seconds| TimeDelta::seconds((*seconds).into()))
+    .unwrap_or_default();
+  let only_fill_missing = matches
+    .get_one::<bool>("only-fill-missing")
+    .copied()
+    .unwrap_or(false);
+  let prefer_filename = matches
+    .get_one::<bool>("prefer-filename")
+    .copied()
+    .unwrap_or(false);
+  let corroboration_window = matches
+    .get_one::<u32>("corroborate-with-modified-time")
+    .map(|days| TimeDelta::days((*days).into()));
+  let review_list_path = matches.get_one::<PathBuf>("review-list");
+  let overwrite_margin = matches
+    .get_one::<u8>("overwrite-margin")
+    .copied()
+    .unwrap_or(1);
   let positonal_files = matches
     .get_many::<PathBuf>("positional_files")
     .unwrap_or_default();
-  let files = flagged_files.chain(positonal_files);
+  let verify_matches = matches.subcommand_matches("verify");
+  let organize_matches = matches.subcommand_matches("organize");
+  let shift_matches = matches.subcommand_matches("shift");
+  let subcommand_files = verify_matches
+    .or(organize_matches)
+    .or(shift_matches)
+    .and_then(|subcommand_matches| subcommand_matches.get_many::<PathBuf>("files"))
+    .unwrap_or_default();
+  let files = flagged_files
+    .chain(positonal_files)
+    .chain(subcommand_files)
+    .collect::<Vec<_>>();
+  let listed_files = matches
+    .get_one::<PathBuf>("files-from")
+    .map(|list_path| read_file_list(list_path))
+    .transpose()?
+    .unwrap_or_default();
 
   let excluded_files_denorm = matches
     .get_many::<PathBuf>("exclude-files")
@@ -732,9 +3068,104 @@ fn main() -> anyhow::Result<()> {
     excluded_files.insert(absolute_path);
   }
 
-  if !has_exiftool() {
-    error!("exiftool is not installed. Make sure it is installed and in your PATH.");
-    exit(1);
+  if matches.contains_id("backup-originals") {
+    let backup_mode = match matches.get_one::<PathBuf>("backup-originals") {
+      Some(backup_dir) => {
+        // The backups must not be processed themselves.
+        excluded_files.insert(std::path::absolute(backup_dir).context(format!(
+          "\"{}\": Failed to get absolute path for the backup directory",
+          backup_dir.display()
+        ))?);
+        BackupMode::Directory(backup_dir.clone())
+      },
+      None => BackupMode::Beside,
+    };
+    install_backups(backup_mode);
+  }
+
+  let organize_target = organize_matches
+    .map(|organize_matches| -> anyhow::Result<PathBuf> {
+      let target_root = organize_matches
+        .get_one::<PathBuf>("target")
+        .expect("The target is required");
+      // The organized files must not be processed again.
+      excluded_files.insert(std::path::absolute(target_root).context(format!(
+        "\"{}\": Failed to get absolute path for the target directory",
+        target_root.display()
+      ))?);
+      Ok(target_root.clone())
+    })
+    .transpose()?;
+  let organize_mode = organize_matches
+    .and_then(|organize_matches| organize_matches.get_one::<String>("mode"))
+    .map(|mode| OrganizeMode::from_str(mode))
+    .transpose()?
+    .unwrap_or_default();
+
+  if let Some(exiftool_path) = matches.get_one::<PathBuf>("exiftool-path") {
+    set_exiftool_path(exiftool_path.clone());
+  }
+  match exiftool_version() {
+    Ok(version) if version < MIN_EXIFTOOL_VERSION => {
+      let e = anyhow::anyhow!(
+        "exiftool {}.{:02} is too old, at least {}.{:02} is required.",
+        version.0,
+        version.1,
+        MIN_EXIFTOOL_VERSION.0,
+        MIN_EXIFTOOL_VERSION.1,
+      );
+      error!("{e}");
+      notify_failure(&e);
+      exit(1);
+    },
+    Ok(version) => info!("Using exiftool {}.{:02}", version.0, version.1),
+    #[cfg(feature = "native-jpeg-write")]
+    Err(e) => {
+      warn!(
+        "exiftool is not installed, only DateTimeOriginal of JPEG files is read and written: {e:#}"
+      );
+      disable_exiftool();
+    },
+    #[cfg(not(feature = "native-jpeg-write"))]
+    Err(e) => {
+      let e = e.context(
+        "exiftool is not installed. Make sure it is installed and in your PATH or pass --exiftool-path",
+      );
+      error!("{e:#}");
+      notify_failure(&e);
+      exit(1);
+    },
+  }
+  start_exiftool_workers(&matches)?;
+
+  let plan_path = matches.get_one::<PathBuf>("plan");
+  let write_plan = plan_path.is_some();
+  let report_csv_path = matches.get_one::<PathBuf>("report-csv");
+  let report_json_path = matches.get_one::<PathBuf>("report-json");
+  let report_changes = report_csv_path.is_some() || report_json_path.is_some();
+  let unresolved_out_path = matches.get_one::<PathBuf>("unresolved-out");
+  // The progress line would garble redirected output.
+  let show_progress = matches.get_flag("progress") && io::stdout().is_terminal();
+  let verify_tolerance = verify_matches
+    .and_then(|verify_matches| verify_matches.get_one::<core::time::Duration>("tolerance"))
+    .map(|tolerance| TimeDelta::from_std(*tolerance))
+    .transpose()?;
+  let dry_run = matches.get_one::<bool>("dry-run").copied().unwrap_or(false)
+    || write_plan
+    || verify_tolerance.is_some();
+  if let Some(undo_matches) = matches.subcommand_matches("undo") {
+    let journal_path = undo_matches
+      .get_one::<PathBuf>("journal")
+      .expect("The journal is required");
+    if !run_undo_command(journal_path, dry_run)? {
+      exit(1);
+    }
+    return Ok(());
+  }
+  if let Some(journal_path) = matches.get_one::<PathBuf>("journal")
+    && !dry_run
+  {
+    install_undo_journal(journal_path)?;
   }
 
   let fix_future_modified_times_day_offset =
@@ -747,6 +3178,19 @@ fn main() -> anyhow::Result<()> {
     })
     .unwrap_or(NaiveDateTime::MAX);
 
+  let min_valid_mtime = matches
+    .get_one::<NaiveDateTime>("min-valid-mtime")
+    .copied()
+    .unwrap_or(DEFAULT_MIN_VALID_MTIME);
+
+  let mtime_granularity = TimeDelta::seconds(
+    matches
+      .get_one::<u32>("mtime-granularity")
+      .copied()
+      .unwrap_or(1)
+      .into(),
+  );
+
   let fix_future_exif_dates_day_offset = matches.get_one::<u64>("fix-future-exif-dates").copied();
   let exif_dates_future_threshold = fix_future_exif_dates_day_offset
     .and_then(|invalid_exif_dates_days| {
@@ -756,7 +3200,6 @@ fn main() -> anyhow::Result<()> {
     })
     .unwrap_or(NaiveDateTime::MAX);
 
-  let dry_run = matches.get_one::<bool>("dry-run").copied().unwrap_or(false);
   let print_supported_file_extensions = matches
     .get_one::<bool>("print-supported-file-extensions")
     .copied()
@@ -769,6 +3212,18 @@ fn main() -> anyhow::Result<()> {
     .get_one::<bool>("skip-hidden-files")
     .copied()
     .unwrap_or(false);
+  let respect_nomedia = !matches
+    .get_one::<bool>("include-nomedia-folders")
+    .copied()
+    .unwrap_or(false);
+  let one_file_system = matches
+    .get_one::<bool>("one-file-system")
+    .copied()
+    .unwrap_or(false);
+  if one_file_system && !CAN_DETECT_FILE_SYSTEMS {
+    error!("--one-file-system is not supported on this platform.");
+    exit(1);
+  }
   let ignore_minor_exif_errors = matches
     .get_one::<bool>("ignore-minor-exif-errors")
     .copied()
@@ -794,47 +3249,292 @@ fn main() -> anyhow::Result<()> {
       }
     }
     writeln!(&mut stdout, "\n")?;
+
+    writeln!(&mut stdout, "Modern image formats:")?;
+    for (format, writable) in modern_image_format_support()? {
+      let support = if writable {
+        "writable"
+      } else {
+        "not writable by the installed exiftool"
+      };
+      writeln!(&mut stdout, "  {format}: {support}")?;
+    }
+    writeln!(&mut stdout)?;
   }
 
-  let process_state = Arc::new(ProcessState::new(
-    excluded_files,
-    skip_hidden_files,
-    dry_run,
-    modified_times_future_threshold,
-    exif_dates_future_threshold,
-    ignore_minor_exif_errors,
-    repair_exif_errors,
-  ));
-
-  let ctrlc_process_state = process_state.clone();
-  ctrlc::set_handler(move || {
-    println!("\nReceived Ctrl+C! Exiting...");
-    ctrlc_process_state
-      .should_exit
-      .store(true, Ordering::Relaxed);
-  })
-  .expect("Error setting Ctrl+C handler");
+  let min_size = matches.get_one::<u64>("min-size").copied();
+  let max_size = matches.get_one::<u64>("max-size").copied();
+  let modified_since = matches.get_one::<NaiveDateTime>("modified-since").copied();
+  let modified_before = matches.get_one::<NaiveDateTime>("modified-before").copied();
+  let retries = matches.get_one::<u32>("retries").copied().unwrap_or(0);
+  if retries > 0 && shift_matches.is_some() {
+    warn!("Ignoring --retries, as a retry of a partially shifted file would shift it again");
+  }
+  let skip_recent = matches
+    .get_one::<core::time::Duration>("skip-recent")
+    .map(|skip_recent| TimeDelta::from_std(*skip_recent))
+    .transpose()?;
+  let only_extensions = matches
+    .get_many::<String>("only-extensions")
+    .map(|extensions| -> anyhow::Result<BTreeSet<String>> {
+      let writable_extensions = exiftool_writable_file_extensions()?;
+      let mut only_extensions = BTreeSet::new();
+      for extension in extensions {
+        let extension = extension
+          .trim()
+          .trim_start_matches('.')
+          .to_ascii_uppercase();
+        if writable_extensions.contains(&extension) {
+          only_extensions.insert(extension);
+        } else {
+          warn!(
+            "Ignoring the extension {extension} of --only-extensions, which exiftool can not write"
+          );
+        }
+      }
+      Ok(only_extensions)
+    })
+    .transpose()?;
+
+  let checkpoint = if let Some(resume_path) = matches.get_one::<PathBuf>("resume") {
+    Some(Checkpoint::open(resume_path, true)?)
+  } else {
+    matches
+      .get_one::<PathBuf>("checkpoint")
+      .map(|checkpoint_path| Checkpoint::open(checkpoint_path, false))
+      .transpose()?
+  };
+
+  let process_state = Arc::new(ProcessState {
+    checkpoint,
+    ..ProcessState::new(ProcessOptions {
+      excluded_files,
+      only_extensions,
+      min_size,
+      max_size,
+      modified_since,
+      modified_before,
+      skip_recent,
+      retries,
+      skip_hidden_files,
+      respect_nomedia,
+      one_file_system,
+      dry_run,
+      write_plan,
+      report_changes,
+      collect_unresolved_files: unresolved_out_path.is_some(),
+      show_progress,
+      verify_tolerance,
+      modified_times_future_threshold,
+      min_valid_mtime,
+      mtime_granularity,
+      exif_dates_future_threshold,
+      exif_confidence_policy,
+      min_confidence,
+      overwrite_margin,
+      only_fill_missing,
+      prefer_filename,
+      min_delta,
+      write_all_dates,
+      preserve_modified_time,
+      sync_mtime_from_exif,
+      sync_birthtime,
+      mtime_fallback_confidence,
+      write_provenance,
+      default_utc_offset,
+      write_iptc,
+      sidecar_for_raw,
+      corroboration_window,
+      ignore_minor_exif_errors,
+      repair_exif_errors,
+      folder_date_depth,
+      mine_text_tags,
+      interpolate_sibling_dates,
+      folder_mtime_policy,
+      sync_live_photos,
+      sync_raw_jpeg_pairs,
+      organize_target,
+      organize_mode,
+      date_shift: shift_matches
+        .and_then(|shift_matches| shift_matches.get_one::<DateShift>("shift"))
+        .copied(),
+      shift_modified_time: shift_matches
+        .and_then(|shift_matches| shift_matches.get_one::<bool>("mtime"))
+        .copied()
+        .unwrap_or(false),
+    })
+  });
+
+  if let Some(metrics_address) = matches.get_one::<SocketAddr>("metrics-listen") {
+    serve_metrics(*metrics_address, process_state.clone())?;
+  }
 
-  files.par_bridge().for_each(|file_path| {
+  install_ctrl_c_handler(process_state.clone());
+
+  #[cfg(unix)]
+  let systemd_notifier = systemd_notify::SystemdNotifier::start(&process_state);
+
+  if let Some(apply_matches) = matches.subcommand_matches("apply") {
+    let plan_path = apply_matches
+      .get_one::<PathBuf>("plan")
+      .expect("The plan is required");
+    apply_change_plan(plan_path, &process_state)?;
+    #[cfg(unix)]
+    if let Some(systemd_notifier) = systemd_notifier {
+      systemd_notifier.finish();
+    }
+    if print_stats {
+      process_state.pretty_print_stats()?;
+    }
+    let stat_files_errors = process_state.stat_files_errors.load(Ordering::Relaxed);
+    notify_completion(&process_state.stats_json(), stat_files_errors);
+    if stat_files_errors > 0 {
+      error!(
+        "Failed to apply the plan to {stat_files_errors} files. Check the logs for more details.",
+      );
+      exit(1);
+    }
+    return Ok(());
+  }
+
+  // Dry runs leave the files alone, so they do not need to keep other runs out.
+  let run_locks = if dry_run {
+    Vec::new()
+  } else {
+    let wait_for_lock = matches.get_flag("wait");
+    lock_folder_paths(
+      files
+        .iter()
+        .map(|file_path| file_path.as_path())
+        .chain(listed_files.iter().map(PathBuf::as_path)),
+    )
+    .iter()
+    .map(|folder_path| RunLock::acquire(folder_path, wait_for_lock))
+    .collect::<anyhow::Result<Vec<_>>>()?
+  };
+
+  let progress_display = process_state.progress.as_ref().map(|progress| {
+    if matches.get_flag("precount") {
+      progress.precount(&files, skip_hidden_files);
+    }
+    ProgressDisplay::start(process_state.clone())
+  });
+
+  files.par_iter().for_each(|file_path| {
     // check if the file is a directory
     if file_path.is_dir() {
       check_dir_recursive(file_path, &process_state);
     } else {
-      if is_excluded(file_path, &process_state.excluded_files) {
-        process_state
-          .stat_files_skipped
-          .fetch_add(1, Ordering::Relaxed);
+      if process_state.should_exit.load(Ordering::Relaxed) {
+        return;
+      }
+      if is_excluded(file_path, &process_state.options.excluded_files) {
+        process_state.record_skipped(file_path, "excluded");
         return;
       }
       check_file(file_path, &process_state);
     }
   });
 
+  // The listed files were already selected, so they bypass the directory walker.
+  listed_files.par_iter().for_each(|file_path| {
+    if process_state.should_exit.load(Ordering::Relaxed) {
+      return;
+    }
+    if file_path.is_dir() {
+      debug!(
+        file_path = %file_path.display(),
+        "Skipping directory listed in --files-from",
+      );
+      process_state
+        .stat_folders_skipped
+        .fetch_add(1, Ordering::Relaxed);
+    } else if is_excluded(file_path, &process_state.options.excluded_files) {
+      process_state.record_skipped(file_path, "excluded");
+    } else {
+      check_file(file_path, &process_state);
+    }
+  });
+
+  if let Some(failed_files) = &process_state.failed_files {
+    retry_failed_files(failed_files, process_state.options.retries, &process_state);
+  }
+
+  if let Some(sibling_dates) = &process_state.sibling_dates {
+    apply_sibling_interpolation(sibling_dates, &process_state);
+  }
+
+  if let Some(file_pairs) = &process_state.file_pairs {
+    apply_file_pairing(file_pairs, &process_state);
+  }
+
+  // Last, as writing the files changes the modified times of their folders.
+  if let Some(folder_dates) = &process_state.folder_dates {
+    apply_folder_mtimes(folder_dates, &process_state);
+  }
+
+  // The other passes expect the files where they were found.
+  if let Some(organizer) = &process_state.organizer {
+    apply_organize(organizer, &process_state);
+  }
+
+  // The files in progress were finished, so the reports and statistics below are complete for the processed files.
+  let interrupted = process_state.should_exit.load(Ordering::Relaxed);
+  if interrupted {
+    warn!("The run was interrupted, not all files were processed");
+  }
+  if let Some(checkpoint) = &process_state.checkpoint {
+    checkpoint.finish(interrupted)?;
+  }
+
+  if let Some(progress_display) = progress_display {
+    progress_display.finish();
+  }
+  #[cfg(unix)]
+  if let Some(systemd_notifier) = systemd_notifier {
+    systemd_notifier.finish();
+  }
+
+  if let Some(review_list) = &process_state.review_list
+    && let Some(review_list_path) = review_list_path
+  {
+    review_list.write(review_list_path)?;
+  }
+
+  if let Some(change_list) = &process_state.change_list {
+    let changes = change_list.sorted();
+    if let Some(plan_path) = plan_path {
+      change_plan::write_plan(&changes, plan_path)?;
+    }
+    if let Some(report_csv_path) = report_csv_path {
+      write_csv_report(&changes, report_csv_path)?;
+    }
+    if let Some(report_json_path) = report_json_path {
+      write_json_report(&changes, report_json_path)?;
+    }
+  }
+
+  if let Some(unresolved_files) = &process_state.unresolved_files
+    && let Some(unresolved_out_path) = unresolved_out_path
+  {
+    unresolved_files.write(unresolved_out_path)?;
+  }
+
+  if let Some(verification) = &process_state.verification
+    && let Some(report_path) =
+      verify_matches.and_then(|verify_matches| verify_matches.get_one::<PathBuf>("report"))
+  {
+    verification.write(report_path)?;
+  }
+
   if print_stats {
     process_state.pretty_print_stats()?;
   }
 
+  drop(run_locks);
+
   let stat_files_errors = process_state.stat_files_errors.load(Ordering::Relaxed);
+  notify_completion(&process_state.stats_json(), stat_files_errors);
   if stat_files_errors > 0 {
     error!(
       "Finished processing with {stat_files_errors} files that had errors. Check the logs for more details.",
@@ -844,3 +3544,814 @@ fn main() -> anyhow::Result<()> {
 
   Ok(())
 }
+
+#[cfg(test)]
+mod test {
+  use core::time::Duration;
+  use std::{fs::File, time::SystemTime};
+
+  use super::*;
+  use crate::{
+    change_plan::PlannedChange,
+    exiftool::{assume_writable_file_extensions, exiftool_pool_jobs, track_exiftool_worker},
+  };
+
+  /// A processing run with the defaults of the command line, changed by `configure`.
+  fn new_process_state(configure: impl FnOnce(&mut ProcessOptions)) -> ProcessState {
+    let mut options = ProcessOptions {
+      excluded_files: BTreeSet::new(),
+      only_extensions: None,
+      min_size: None,
+      max_size: None,
+      modified_since: None,
+      modified_before: None,
+      retries: 0,
+      skip_recent: None,
+      skip_hidden_files: false,
+      respect_nomedia: false,
+      one_file_system: false,
+      dry_run: false,
+      write_plan: false,
+      report_changes: false,
+      collect_unresolved_files: false,
+      show_progress: false,
+      verify_tolerance: None,
+      modified_times_future_threshold: NaiveDateTime::MAX,
+      min_valid_mtime: DEFAULT_MIN_VALID_MTIME,
+      mtime_granularity: TimeDelta::seconds(1),
+      exif_dates_future_threshold: NaiveDateTime::MAX,
+      exif_confidence_policy: ExifConfidencePolicy::default(),
+      min_confidence: DateConfidence::None,
+      overwrite_margin: 1,
+      only_fill_missing: false,
+      prefer_filename: false,
+      min_delta: TimeDelta::zero(),
+      write_all_dates: false,
+      preserve_modified_time: false,
+      sync_mtime_from_exif: false,
+      sync_birthtime: false,
+      mtime_fallback_confidence: None,
+      write_provenance: false,
+      default_utc_offset: None,
+      write_iptc: false,
+      sidecar_for_raw: false,
+      corroboration_window: None,
+      ignore_minor_exif_errors: false,
+      repair_exif_errors: false,
+      folder_date_depth: 1,
+      mine_text_tags: false,
+      interpolate_sibling_dates: false,
+      folder_mtime_policy: None,
+      sync_live_photos: false,
+      sync_raw_jpeg_pairs: false,
+      organize_target: None,
+      organize_mode: OrganizeMode::default(),
+      date_shift: None,
+      shift_modified_time: false,
+    };
+    configure(&mut options);
+    ProcessState::new(options)
+  }
+
+  /// A folder for the files of a test, removed by the test when it is done.
+  fn test_folder(name: &str) -> PathBuf {
+    let folder_path = std::env::temp_dir().join(format!(
+      "image-date-fixer-{name}-test-{}",
+      std::process::id()
+    ));
+    std::fs::create_dir_all(&folder_path).unwrap();
+    folder_path
+  }
+
+  /// A dry run that records the changes it would make, changed by `configure`.
+  fn new_dry_run_state(configure: impl FnOnce(&mut ProcessOptions)) -> Arc<ProcessState> {
+    assume_writable_file_extensions();
+    Arc::new(new_process_state(|options| {
+      options.dry_run = true;
+      options.report_changes = true;
+      configure(options);
+    }))
+  }
+
+  /// The change the run would make to `file_path`, `None` if it is left alone.
+  fn planned_change(process_state: &ProcessState, file_path: &Path) -> Option<PlannedChange> {
+    process_state
+      .change_list
+      .as_ref()
+      .expect("Dry runs of the tests record their changes")
+      .sorted()
+      .into_iter()
+      .find(|change| change.path == file_path)
+  }
+
+  /// Checks `file_path` as if it had the EXIF dates `exif_dates`, creating it if it does not exist,
+  /// and returns the EXIF date the run would write.
+  fn check_with_exif_dates(
+    process_state: &Arc<ProcessState>,
+    file_path: &Path,
+    exif_dates: ExifDates,
+  ) -> Option<String> {
+    if !file_path.exists() {
+      std::fs::write(file_path, b"").unwrap();
+    }
+    process_state.exif_dates_cache.insert(file_path, exif_dates);
+    check_file(file_path, process_state);
+    planned_change(process_state, file_path).and_then(|change| change.new_exif_date)
+  }
+
+  /// The EXIF dates of a file whose `DateTimeOriginal` is `date_time_original`.
+  fn exif_dates(date_time_original: &str) -> ExifDates {
+    ExifDates {
+      date_time_original: Some(
+        NaiveDateTime::parse_from_str(date_time_original, "%Y-%m-%d %H:%M:%S").unwrap(),
+      ),
+      ..ExifDates::default()
+    }
+  }
+
+  #[test]
+  fn min_confidence_gate() {
+    let folder_path = test_folder("min-confidence");
+    let second_path = folder_path.join("IMG_20190701_120000.jpg");
+    let day_path = folder_path.join("2019-07-01.jpg");
+
+    let process_state = new_dry_run_state(|_| {});
+    assert_eq!(
+      check_with_exif_dates(&process_state, &day_path, ExifDates::default()).as_deref(),
+      Some("2019-07-01 00:00:00")
+    );
+
+    let process_state = new_dry_run_state(|options| options.min_confidence = DateConfidence::Hour);
+    assert_eq!(
+      check_with_exif_dates(&process_state, &second_path, ExifDates::default()).as_deref(),
+      Some("2019-07-01 12:00:00")
+    );
+    assert_eq!(
+      check_with_exif_dates(&process_state, &day_path, ExifDates::default()),
+      None
+    );
+    assert_eq!(
+      process_state
+        .stat_below_min_confidence
+        .load(Ordering::Relaxed),
+      1
+    );
+    std::fs::remove_dir_all(&folder_path).unwrap();
+  }
+
+  #[test]
+  fn overwrite_margin_gate() {
+    let folder_path = test_folder("overwrite-margin");
+    let minute_path = folder_path.join("2019-07-02 12.30.jpg");
+    let second_path = folder_path.join("20190702_123005.jpg");
+    // Midnight is only trusted to the day with the precision policy.
+    let day_exif_dates = || exif_dates("2019-07-02 00:00:00");
+    let with_precision_policy = |options: &mut ProcessOptions| {
+      options.exif_confidence_policy = ExifConfidencePolicy::Precision;
+    };
+
+    let process_state = new_dry_run_state(with_precision_policy);
+    assert_eq!(
+      check_with_exif_dates(&process_state, &minute_path, day_exif_dates()).as_deref(),
+      Some("2019-07-02 12:30:00")
+    );
+
+    let process_state = new_dry_run_state(|options| {
+      with_precision_policy(options);
+      options.overwrite_margin = 3;
+    });
+    assert_eq!(
+      check_with_exif_dates(&process_state, &minute_path, day_exif_dates()),
+      None
+    );
+    assert_eq!(
+      check_with_exif_dates(&process_state, &second_path, day_exif_dates()).as_deref(),
+      Some("2019-07-02 12:30:05")
+    );
+    std::fs::remove_dir_all(&folder_path).unwrap();
+  }
+
+  #[test]
+  fn only_fill_missing_gate() {
+    let folder_path = test_folder("only-fill-missing");
+    let file_path = folder_path.join("IMG_20190701_123000.jpg");
+    // January 1st at midnight is only trusted to the year with the precision policy.
+    let year_exif_dates = || exif_dates("2019-01-01 00:00:00");
+
+    let process_state = new_dry_run_state(|options| {
+      options.exif_confidence_policy = ExifConfidencePolicy::Precision;
+    });
+    assert_eq!(
+      check_with_exif_dates(&process_state, &file_path, year_exif_dates()).as_deref(),
+      Some("2019-07-01 12:30:00")
+    );
+
+    let process_state = new_dry_run_state(|options| {
+      options.exif_confidence_policy = ExifConfidencePolicy::Precision;
+      options.only_fill_missing = true;
+      options.prefer_filename = true;
+    });
+    assert_eq!(
+      check_with_exif_dates(&process_state, &file_path, year_exif_dates()),
+      None
+    );
+    // Missing dates are still filled in.
+    assert_eq!(
+      check_with_exif_dates(&process_state, &file_path, ExifDates::default()).as_deref(),
+      Some("2019-07-01 12:30:00")
+    );
+    std::fs::remove_dir_all(&folder_path).unwrap();
+  }
+
+  #[test]
+  fn prefer_filename_gate() {
+    let folder_path = test_folder("prefer-filename");
+    let file_path = folder_path.join("2019-07-01.jpg");
+    let undated_file_path = folder_path.join("DSC_0001.jpg");
+    let exif_dates_with_gps = || ExifDates {
+      gps_date_time: NaiveDateTime::parse_from_str("2019-07-01 12:00:00", "%Y-%m-%d %H:%M:%S").ok(),
+      ..exif_dates("2023-05-04 10:00:00")
+    };
+
+    let process_state = new_dry_run_state(|_| {});
+    assert_eq!(
+      check_with_exif_dates(
+        &process_state,
+        &file_path,
+        exif_dates("2023-05-04 10:00:00")
+      ),
+      None
+    );
+
+    let process_state = new_dry_run_state(|options| options.prefer_filename = true);
+    assert_eq!(
+      check_with_exif_dates(
+        &process_state,
+        &file_path,
+        exif_dates("2023-05-04 10:00:00")
+      )
+      .as_deref(),
+      Some("2019-07-01 00:00:00")
+    );
+    // The GPS date is not a file name date.
+    assert_eq!(
+      check_with_exif_dates(&process_state, &undated_file_path, exif_dates_with_gps()),
+      None
+    );
+    std::fs::remove_dir_all(&folder_path).unwrap();
+  }
+
+  #[test]
+  fn min_delta_gate() {
+    let folder_path = test_folder("min-delta");
+    let close_path = folder_path.join("20190702_123005.jpg");
+    let far_path = folder_path.join("20190702_123006.jpg");
+    // Only trusted to the minute with the precision policy, so the file names are more precise.
+    let minute_exif_dates = || exif_dates("2019-07-02 12:30:00");
+
+    let process_state = new_dry_run_state(|options| {
+      options.exif_confidence_policy = ExifConfidencePolicy::Precision;
+    });
+    assert_eq!(
+      check_with_exif_dates(&process_state, &close_path, minute_exif_dates()).as_deref(),
+      Some("2019-07-02 12:30:05")
+    );
+
+    let process_state = new_dry_run_state(|options| {
+      options.exif_confidence_policy = ExifConfidencePolicy::Precision;
+      options.min_delta = TimeDelta::seconds(5);
+    });
+    assert_eq!(
+      check_with_exif_dates(&process_state, &close_path, minute_exif_dates()),
+      None
+    );
+    assert_eq!(
+      check_with_exif_dates(&process_state, &far_path, minute_exif_dates()).as_deref(),
+      Some("2019-07-02 12:30:06")
+    );
+    assert_eq!(
+      process_state.stat_exif_close_enough.load(Ordering::Relaxed),
+      1
+    );
+    std::fs::remove_dir_all(&folder_path).unwrap();
+  }
+
+  /// Sets the modified time of `file_path` to `modified_time` (in UTC).
+  fn set_test_modified_time(file_path: &Path, modified_time: &str) {
+    let modified_time = NaiveDateTime::parse_from_str(modified_time, "%Y-%m-%d %H:%M:%S")
+      .unwrap()
+      .and_utc();
+    File::options()
+      .write(true)
+      .open(file_path)
+      .unwrap()
+      .set_modified(modified_time.into())
+      .unwrap();
+  }
+
+  #[test]
+  fn sync_mtime_from_exif_gate() {
+    let folder_path = test_folder("sync-mtime-from-exif");
+    let file_path = folder_path.join("DSC_0001.jpg");
+    std::fs::write(&file_path, b"").unwrap();
+    let planned_modified_time = |process_state: &Arc<ProcessState>| {
+      check_with_exif_dates(process_state, &file_path, exif_dates("2019-07-01 12:30:00"));
+      planned_change(process_state, &file_path).and_then(|change| change.new_modified_time)
+    };
+
+    let process_state = new_dry_run_state(|_| {});
+    assert_eq!(planned_modified_time(&process_state), None);
+
+    let with_sync = |options: &mut ProcessOptions| {
+      options.sync_mtime_from_exif = true;
+      options.default_utc_offset = FixedOffset::east_opt(0);
+    };
+    assert_eq!(
+      planned_modified_time(&new_dry_run_state(with_sync)).as_deref(),
+      Some("2019-07-01 12:30:00")
+    );
+    // Modified times that already match are left alone.
+    set_test_modified_time(&file_path, "2019-07-01 12:30:00");
+    assert_eq!(planned_modified_time(&new_dry_run_state(with_sync)), None);
+    std::fs::remove_dir_all(&folder_path).unwrap();
+  }
+
+  #[test]
+  fn mtime_fallback_gate() {
+    let folder_path = test_folder("mtime-fallback");
+    let file_path = folder_path.join("DSC_0001.jpg");
+    let epoch_file_path = folder_path.join("DSC_0002.jpg");
+    std::fs::write(&file_path, b"").unwrap();
+    std::fs::write(&epoch_file_path, b"").unwrap();
+    // Earlier than the creation time, which is the time the file was written.
+    set_test_modified_time(&file_path, "2019-07-01 12:30:00");
+    set_test_modified_time(&epoch_file_path, "1970-01-01 00:00:00");
+
+    let process_state = new_dry_run_state(|_| {});
+    assert_eq!(
+      check_with_exif_dates(&process_state, &file_path, ExifDates::default()),
+      None
+    );
+
+    let process_state = new_dry_run_state(|options| {
+      options.mtime_fallback_confidence = Some(DateConfidence::Year);
+      options.default_utc_offset = FixedOffset::east_opt(0);
+    });
+    assert_eq!(
+      check_with_exif_dates(&process_state, &file_path, ExifDates::default()).as_deref(),
+      Some("2019-07-01 12:30:00")
+    );
+    let change = planned_change(&process_state, &file_path).unwrap();
+    assert_eq!(change.source.as_deref(), Some("mtime"));
+    assert_eq!(change.confidence, Some(DateConfidence::Year));
+    // The modified times of 1970 are not plausible, so only the creation time is left, if there is one.
+    check_with_exif_dates(&process_state, &epoch_file_path, ExifDates::default());
+    assert_eq!(
+      planned_change(&process_state, &epoch_file_path).and_then(|change| change.source),
+      get_creation_time(&epoch_file_path).map(|_| String::from("birthtime"))
+    );
+    std::fs::remove_dir_all(&folder_path).unwrap();
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn hardlink_gate() {
+    let folder_path = test_folder("hardlink");
+    let file_path = folder_path.join("IMG_20190701_120000.jpg");
+    let link_path = folder_path.join("IMG_20190701_120000 (link).jpg");
+    let other_file_path = folder_path.join("IMG_20190701_120001.jpg");
+    std::fs::write(&file_path, b"").unwrap();
+    std::fs::hard_link(&file_path, &link_path).unwrap();
+
+    let process_state = new_dry_run_state(|_| {});
+    assert!(check_with_exif_dates(&process_state, &file_path, ExifDates::default()).is_some());
+    // The file was already written through its other link.
+    assert_eq!(
+      check_with_exif_dates(&process_state, &link_path, ExifDates::default()),
+      None
+    );
+    assert!(
+      check_with_exif_dates(&process_state, &other_file_path, ExifDates::default()).is_some()
+    );
+    assert_eq!(process_state.stat_files_skipped.load(Ordering::Relaxed), 1);
+    std::fs::remove_dir_all(&folder_path).unwrap();
+  }
+
+  #[test]
+  fn only_extensions_gate() {
+    let folder_path = test_folder("only-extensions");
+    let jpeg_path = folder_path.join("IMG_20190701_120000.jpg");
+    let heic_path = folder_path.join("IMG_20190701_120001.HEIC");
+    let png_path = folder_path.join("IMG_20190701_120002.png");
+
+    let process_state = new_dry_run_state(|_| {});
+    assert!(check_with_exif_dates(&process_state, &png_path, ExifDates::default()).is_some());
+
+    let process_state = new_dry_run_state(|options| {
+      options.only_extensions = Some(BTreeSet::from([String::from("JPG"), String::from("HEIC")]));
+    });
+    assert!(check_with_exif_dates(&process_state, &jpeg_path, ExifDates::default()).is_some());
+    assert!(check_with_exif_dates(&process_state, &heic_path, ExifDates::default()).is_some());
+    assert_eq!(
+      check_with_exif_dates(&process_state, &png_path, ExifDates::default()),
+      None
+    );
+    assert_eq!(process_state.stat_files_skipped.load(Ordering::Relaxed), 1);
+    std::fs::remove_dir_all(&folder_path).unwrap();
+  }
+
+  #[test]
+  fn size_gate() {
+    let folder_path = test_folder("size");
+    let thumbnail_path = folder_path.join("IMG_20190701_120000.jpg");
+    let photo_path = folder_path.join("IMG_20190701_120001.jpg");
+    let video_path = folder_path.join("IMG_20190701_120002.jpg");
+    std::fs::write(&thumbnail_path, [0; 999]).unwrap();
+    std::fs::write(&photo_path, [0; 2000]).unwrap();
+    std::fs::write(&video_path, [0; 2001]).unwrap();
+
+    let process_state = new_dry_run_state(|_| {});
+    assert!(check_with_exif_dates(&process_state, &thumbnail_path, ExifDates::default()).is_some());
+
+    let process_state = new_dry_run_state(|options| {
+      options.min_size = Some(1000);
+      options.max_size = Some(2000);
+    });
+    assert_eq!(
+      check_with_exif_dates(&process_state, &thumbnail_path, ExifDates::default()),
+      None
+    );
+    assert!(check_with_exif_dates(&process_state, &photo_path, ExifDates::default()).is_some());
+    assert_eq!(
+      check_with_exif_dates(&process_state, &video_path, ExifDates::default()),
+      None
+    );
+    assert_eq!(process_state.stat_files_skipped.load(Ordering::Relaxed), 2);
+    std::fs::remove_dir_all(&folder_path).unwrap();
+  }
+
+  #[test]
+  fn nomedia_gate() {
+    let folder_path = test_folder("nomedia");
+    let cache_path = folder_path.join("cache");
+    std::fs::create_dir_all(&cache_path).unwrap();
+    std::fs::write(cache_path.join(NOMEDIA_FILE_NAME), b"").unwrap();
+    let file_path = folder_path.join("IMG_20190701_120000.jpg");
+    let cached_file_path = cache_path.join("IMG_20190701_120001.jpg");
+    std::fs::write(&file_path, b"").unwrap();
+    std::fs::write(&cached_file_path, b"").unwrap();
+    let walk = |process_state: &Arc<ProcessState>| {
+      for file_path in [&file_path, &cached_file_path] {
+        process_state
+          .exif_dates_cache
+          .insert(file_path, ExifDates::default());
+      }
+      check_dir_recursive(&folder_path, process_state);
+    };
+
+    let process_state = new_dry_run_state(|_| {});
+    walk(&process_state);
+    assert!(planned_change(&process_state, &cached_file_path).is_some());
+
+    let process_state = new_dry_run_state(|options| options.respect_nomedia = true);
+    walk(&process_state);
+    assert!(planned_change(&process_state, &file_path).is_some());
+    assert!(planned_change(&process_state, &cached_file_path).is_none());
+    assert_eq!(
+      process_state
+        .stat_nomedia_folders_skipped
+        .load(Ordering::Relaxed),
+      1
+    );
+    std::fs::remove_dir_all(&folder_path).unwrap();
+  }
+
+  /// `/dev/shm` is a tmpfs mounted below the devtmpfs of `/dev`, so walking `/dev` crosses into another file system.
+  #[cfg(target_os = "linux")]
+  #[test]
+  fn one_file_system_gate() {
+    let dev_path = Path::new("/dev");
+    let folder_path = Path::new("/dev/shm").join(format!(
+      "image-date-fixer-one-file-system-test-{}",
+      std::process::id()
+    ));
+    assert_ne!(device_id(dev_path), device_id(Path::new("/dev/shm")));
+    std::fs::create_dir_all(&folder_path).unwrap();
+    let file_path = folder_path.join("IMG_20190701_120000.jpg");
+    std::fs::write(&file_path, b"").unwrap();
+    let walk = |root_path: &Path, process_state: &Arc<ProcessState>| {
+      process_state
+        .exif_dates_cache
+        .insert(&file_path, ExifDates::default());
+      check_dir_recursive(root_path, process_state);
+    };
+
+    let process_state = new_dry_run_state(|options| options.one_file_system = true);
+    walk(dev_path, &process_state);
+    assert!(planned_change(&process_state, &file_path).is_none());
+    assert!(process_state.stat_folders_skipped.load(Ordering::Relaxed) >= 1);
+    // The top level directory decides the file system, not the root of the whole tree.
+    let process_state = new_dry_run_state(|options| options.one_file_system = true);
+    walk(&folder_path, &process_state);
+    assert!(planned_change(&process_state, &file_path).is_some());
+    std::fs::remove_dir_all(&folder_path).unwrap();
+  }
+
+  #[test]
+  fn organize_keeps_existing_companions() {
+    let folder_path = test_folder("organize-companions");
+    let source_path = folder_path.join("camera");
+    let target_root = folder_path.join("sorted");
+    let date_folder_path = target_root.join("2019").join("07");
+    std::fs::create_dir_all(&source_path).unwrap();
+    std::fs::create_dir_all(&date_folder_path).unwrap();
+    let file_path = source_path.join("IMG_0001.JPG");
+    std::fs::write(&file_path, "photo").unwrap();
+    std::fs::write(source_path.join("IMG_0001.AAE"), "new edit").unwrap();
+    // Organized before, from another camera with the same numbering.
+    std::fs::write(date_folder_path.join("IMG_0001.AAE"), "old edit").unwrap();
+
+    let process_state = new_process_state(|options| options.organize_target = Some(target_root));
+    let organizer = process_state.organizer.as_ref().unwrap();
+    organizer.record(
+      &file_path,
+      NaiveDateTime::parse_from_str("2019-07-01 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+    );
+    apply_organize(organizer, &process_state);
+    let read = |file_name: &str| std::fs::read_to_string(date_folder_path.join(file_name)).unwrap();
+    assert_eq!(read("IMG_0001.AAE"), "old edit");
+    assert_eq!(read("IMG_0001 (1).JPG"), "photo");
+    assert_eq!(read("IMG_0001 (1).AAE"), "new edit");
+    assert!(!date_folder_path.join("IMG_0001.JPG").exists());
+    assert_eq!(process_state.stat_files_errors.load(Ordering::Relaxed), 0);
+    std::fs::remove_dir_all(&folder_path).unwrap();
+  }
+
+  #[test]
+  fn skipped_files_are_not_prefetched() {
+    let folder_path = std::env::temp_dir().join(format!(
+      "image-date-fixer-prefetch-test-{}",
+      std::process::id()
+    ));
+    std::fs::create_dir_all(&folder_path).unwrap();
+    let file_path = folder_path.join("IMG_1.jpg");
+    let thumbnail_path = folder_path.join("IMG_1_thumb.jpg");
+    std::fs::write(&file_path, [0; 100]).unwrap();
+    std::fs::write(&thumbnail_path, [0; 10]).unwrap();
+    let exiftool_writable_file_extensions = BTreeSet::from([String::from("JPG")]);
+
+    let process_state = Arc::new(new_process_state(|options| options.min_size = Some(50)));
+    let should_prefetch = |file_path: &Path| {
+      process_state.should_prefetch(
+        file_path,
+        std::fs::metadata(file_path).ok().as_ref(),
+        &exiftool_writable_file_extensions,
+      )
+    };
+    assert!(should_prefetch(&file_path));
+    assert!(!should_prefetch(&thumbnail_path));
+    assert!(!should_prefetch(&folder_path.join(LOCK_FILE_NAME)));
+
+    // Dates read ahead for a file that is skipped after all are dropped.
+    process_state
+      .exif_dates_cache
+      .insert(&thumbnail_path, ExifDates::default());
+    check_file(&thumbnail_path, &process_state);
+    assert!(
+      process_state
+        .exif_dates_cache
+        .take(&thumbnail_path)
+        .is_none()
+    );
+    assert_eq!(process_state.stat_files_skipped.load(Ordering::Relaxed), 1);
+    std::fs::remove_dir_all(&folder_path).unwrap();
+  }
+
+  #[test]
+  fn conditional_writes_are_not_prefetched() {
+    let exiftool_writable_file_extensions = BTreeSet::from([String::from("JPG")]);
+    let dated_file_path = Path::new("/photos/IMG_20190818_130841.jpg");
+    let undated_file_path = Path::new("/photos/DSC_0001.jpg");
+
+    // The guessed date is written with one exiftool call that skips files with a date, without reading them first.
+    let process_state = new_process_state(|_| {});
+    assert!(!process_state.should_prefetch(
+      dated_file_path,
+      None,
+      &exiftool_writable_file_extensions
+    ));
+    assert!(process_state.should_prefetch(
+      undated_file_path,
+      None,
+      &exiftool_writable_file_extensions
+    ));
+
+    // Dry runs never write, so the dates are read.
+    let process_state = new_process_state(|options| options.dry_run = true);
+    assert!(process_state.should_prefetch(
+      dated_file_path,
+      None,
+      &exiftool_writable_file_extensions
+    ));
+  }
+
+  #[test]
+  fn guessed_dates_are_reused() {
+    let folder_path = test_folder("guessed-dates");
+    let file_path = folder_path.join("IMG_20190701_120000.jpg");
+    std::fs::write(&file_path, b"").unwrap();
+    let exiftool_writable_file_extensions = BTreeSet::from([String::from("JPG")]);
+    let guessed_date_count =
+      |process_state: &ProcessState| process_state.guessed_dates.lock().unwrap().len();
+
+    let process_state = new_dry_run_state(|_| {});
+    assert!(process_state.should_prefetch(&file_path, None, &exiftool_writable_file_extensions));
+    assert_eq!(guessed_date_count(&process_state), 1);
+    assert_eq!(
+      check_with_exif_dates(&process_state, &file_path, ExifDates::default()).as_deref(),
+      Some("2019-07-01 12:00:00")
+    );
+    assert_eq!(guessed_date_count(&process_state), 0);
+    std::fs::remove_dir_all(&folder_path).unwrap();
+  }
+
+  #[test]
+  fn skip_recent_gate() {
+    let folder_path = test_folder("skip-recent");
+    let new_file_path = folder_path.join("IMG_20190818_143000.jpg");
+    let old_file_path = folder_path.join("IMG_20190818_143001.jpg");
+    std::fs::write(&new_file_path, b"").unwrap();
+    std::fs::write(&old_file_path, b"").unwrap();
+    File::options()
+      .write(true)
+      .open(&old_file_path)
+      .unwrap()
+      .set_modified(SystemTime::now() - Duration::from_hours(1))
+      .unwrap();
+
+    let process_state = new_dry_run_state(|_| {});
+    assert!(check_with_exif_dates(&process_state, &new_file_path, ExifDates::default()).is_some());
+
+    // Files that may still be written to are deferred, counted separately and left untouched.
+    let process_state = new_dry_run_state(|options| {
+      options.skip_recent = Some(TimeDelta::seconds(60));
+    });
+    assert_eq!(
+      check_with_exif_dates(&process_state, &new_file_path, ExifDates::default()),
+      None
+    );
+    assert!(check_with_exif_dates(&process_state, &old_file_path, ExifDates::default()).is_some());
+    assert_eq!(
+      process_state
+        .stat_recent_files_skipped
+        .load(Ordering::Relaxed),
+      1
+    );
+    std::fs::remove_dir_all(&folder_path).unwrap();
+  }
+
+  #[test]
+  fn ctrl_c_gate() {
+    let process_state = new_process_state(|_| {});
+    assert_eq!(
+      ctrl_c_action(&process_state.should_exit),
+      CtrlCAction::Finish
+    );
+    assert!(process_state.should_exit.load(Ordering::Relaxed));
+    assert_eq!(
+      ctrl_c_action(&process_state.should_exit),
+      CtrlCAction::Abort
+    );
+    assert_eq!(
+      ctrl_c_action(&process_state.should_exit),
+      CtrlCAction::Abort
+    );
+  }
+
+  #[test]
+  fn shifts_are_not_retried() {
+    let folder_path = test_folder("shift-retries");
+    let file_path = folder_path.join("IMG_20190701_120000.jpg");
+    std::fs::write(&file_path, b"").unwrap();
+    assume_writable_file_extensions();
+
+    // The write fails, as there is neither an exiftool nor a valid JPEG to write to.
+    let process_state = Arc::new(new_process_state(|options| {
+      options.retries = 1;
+    }));
+    process_state
+      .exif_dates_cache
+      .insert(&file_path, ExifDates::default());
+    check_file(&file_path, &process_state);
+    assert_eq!(process_state.stat_files_errors.load(Ordering::Relaxed), 0);
+    let failed_files = process_state.failed_files.as_ref().unwrap();
+    retry_failed_files(failed_files, 0, &process_state);
+    assert_eq!(process_state.stat_files_errors.load(Ordering::Relaxed), 1);
+
+    // A failed shift may already have written the shifted date, so it is reported instead of retried.
+    let process_state = Arc::new(new_process_state(|options| {
+      options.retries = 1;
+      options.date_shift = Some(DateShift::from_str("+1h").unwrap());
+    }));
+    assert!(process_state.failed_files.is_none());
+    process_state
+      .exif_dates_cache
+      .insert(&file_path, exif_dates("2019-07-01 12:00:00"));
+    check_file(&file_path, &process_state);
+    assert_eq!(process_state.stat_files_errors.load(Ordering::Relaxed), 1);
+    std::fs::remove_dir_all(&folder_path).unwrap();
+  }
+
+  /// Set for the copy of the test binary that [`ctrl_c_abort`] runs and interrupts.
+  const CTRL_C_CHILD_VAR: &str = "IMAGE_DATE_FIXER_CTRL_C_CHILD";
+
+  #[cfg(target_os = "linux")]
+  #[test]
+  fn ctrl_c_abort() {
+    use std::{
+      io::{BufRead as _, BufReader},
+      process::{Command, Stdio},
+    };
+
+    let interrupt = || {
+      let status = Command::new("kill")
+        .args(["-INT", &std::process::id().to_string()])
+        .status()
+        .unwrap();
+      assert!(status.success());
+    };
+    if std::env::var_os(CTRL_C_CHILD_VAR).is_some() {
+      install_ctrl_c_handler(Arc::new(new_process_state(|_| {})));
+      // A hung exiftool is stood in for by a process that never exits on its own.
+      #[expect(
+        clippy::zombie_processes,
+        reason = "The second Ctrl+C kills the worker and exits before it could be waited on."
+      )]
+      let hung_worker = Command::new("sleep").arg("60").spawn().unwrap();
+      track_exiftool_worker(hung_worker.id());
+      println!("hung worker {}", hung_worker.id());
+      interrupt();
+      std::thread::sleep(Duration::from_millis(500));
+      interrupt();
+      std::thread::sleep(Duration::from_secs(10));
+      panic!("The second Ctrl+C should have aborted the run");
+    }
+
+    let mut child = Command::new(std::env::current_exe().unwrap())
+      .args([
+        "--exact",
+        "test::ctrl_c_abort",
+        "--nocapture",
+        "--test-threads",
+        "1",
+      ])
+      .env(CTRL_C_CHILD_VAR, "1")
+      .stdout(Stdio::piped())
+      .spawn()
+      .unwrap();
+    // Kept open, so the child can still print when it is interrupted.
+    let mut child_lines = BufReader::new(child.stdout.take().unwrap()).lines();
+    let hung_worker_pid = child_lines
+      .by_ref()
+      .map_while(Result::ok)
+      // The test harness prints the name of the test on the same line.
+      .find_map(|line| {
+        line
+          .split_once("hung worker ")
+          .map(|(_, pid)| pid.parse::<u32>().unwrap())
+      })
+      .expect("The child should start the hung worker");
+    assert_eq!(child.wait().unwrap().code(), Some(ABORT_EXIT_CODE));
+    drop(child_lines);
+
+    // The killed worker is gone, or a zombie if nothing reaps the orphan.
+    // The signal is delivered asynchronously, so the worker may take a moment to die.
+    let is_killed = || {
+      let worker_state = std::fs::read_to_string(format!("/proc/{hung_worker_pid}/stat"))
+        .ok()
+        .and_then(|stat| {
+          stat
+            .rsplit_once(") ")
+            .and_then(|(_, fields)| fields.chars().next())
+        });
+      matches!(worker_state, None | Some('Z'))
+    };
+    let mut attempts = 0;
+    while !is_killed() && attempts < 50 {
+      std::thread::sleep(Duration::from_millis(100));
+      attempts += 1;
+    }
+    assert!(is_killed());
+  }
+
+  #[test]
+  fn exiftool_jobs() {
+    let matches = new_argparser()
+      .try_get_matches_from(["image-date-fixer", "--exiftool-jobs", "3", "."])
+      .unwrap();
+    start_exiftool_workers(&matches).unwrap();
+    // The format probe runs on the workers, so they must be started with --exiftool-jobs before it.
+    assert_eq!(exiftool_pool_jobs(), Some(3));
+    assert!(install_exiftool_pool(None).is_err());
+  }
+}
@@ -0,0 +1,248 @@
+use alloc::sync::Arc;
+use core::{fmt::Write as _, net::SocketAddr, sync::atomic::Ordering, time::Duration};
+use std::{
+  io::{self, BufRead as _, BufReader, Write as _},
+  net::{TcpListener, TcpStream},
+};
+
+use anyhow::Context as _;
+use chrono::Utc;
+use tracing::{debug, info};
+
+use crate::ProcessState;
+
+const METRIC_PREFIX: &str = "image_date_fixer_";
+/// Connections are served one at a time, so a client that stalls must not block the scrapes after it.
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy)]
+enum MetricKind {
+  Counter,
+  Gauge,
+}
+
+struct Metric {
+  name: &'static str,
+  help: &'static str,
+  kind: MetricKind,
+  value: i64,
+}
+
+fn counter(name: &'static str, help: &'static str, value: usize) -> Metric {
+  Metric {
+    name,
+    help,
+    kind: MetricKind::Counter,
+    value: i64::try_from(value).unwrap_or(i64::MAX),
+  }
+}
+
+fn process_metrics(process_state: &ProcessState) -> Vec<Metric> {
+  let start_time = process_state.start_time.and_utc();
+  vec![
+    counter(
+      "folders_checked_total",
+      "Folders checked.",
+      process_state.stat_folders_checked.load(Ordering::Relaxed),
+    ),
+    counter(
+      "folders_skipped_total",
+      "Folders skipped.",
+      process_state.stat_folders_skipped.load(Ordering::Relaxed),
+    ),
+    counter(
+      "files_checked_total",
+      "Files checked.",
+      process_state.stat_files_checked.load(Ordering::Relaxed),
+    ),
+    counter(
+      "media_files_checked_total",
+      "Media files checked.",
+      process_state
+        .stat_media_files_checked
+        .load(Ordering::Relaxed),
+    ),
+    counter(
+      "files_skipped_total",
+      "Files skipped.",
+      process_state.stat_files_skipped.load(Ordering::Relaxed),
+    ),
+    counter(
+      "files_errors_total",
+      "Files with errors.",
+      process_state.stat_files_errors.load(Ordering::Relaxed),
+    ),
+    counter(
+      "exif_dates_updated_total",
+      "EXIF dates written to files without one.",
+      process_state.stat_exif_updated.load(Ordering::Relaxed),
+    ),
+    counter(
+      "exif_dates_overwritten_total",
+      "Wrong EXIF dates overwritten.",
+      process_state.stat_exif_overwritten.load(Ordering::Relaxed),
+    ),
+    counter(
+      "exif_dates_interpolated_total",
+      "EXIF dates interpolated from siblings.",
+      process_state.stat_exif_interpolated.load(Ordering::Relaxed),
+    ),
+    counter(
+      "modified_times_updated_total",
+      "Modified times updated.",
+      process_state
+        .stat_modified_time_updated
+        .load(Ordering::Relaxed),
+    ),
+    counter(
+      "folder_modified_times_updated_total",
+      "Folder modified times updated.",
+      process_state
+        .stat_folder_mtimes_updated
+        .load(Ordering::Relaxed),
+    ),
+    counter(
+      "companion_files_synced_total",
+      "Companion files synced.",
+      process_state
+        .stat_companion_files_synced
+        .load(Ordering::Relaxed),
+    ),
+    counter(
+      "files_organized_total",
+      "Files organized.",
+      process_state.stat_files_organized.load(Ordering::Relaxed),
+    ),
+    Metric {
+      name: "run_start_time_seconds",
+      help: "Start of the run as a Unix timestamp.",
+      kind: MetricKind::Gauge,
+      value: start_time.timestamp(),
+    },
+    Metric {
+      name: "run_duration_seconds",
+      help: "Seconds since the start of the run.",
+      kind: MetricKind::Gauge,
+      value: (Utc::now() - start_time).num_seconds(),
+    },
+  ]
+}
+
+/// The Prometheus text exposition format.
+fn render_metrics(metrics: &[Metric]) -> String {
+  let mut text = String::new();
+  for metric in metrics {
+    let kind = match metric.kind {
+      MetricKind::Counter => "counter",
+      MetricKind::Gauge => "gauge",
+    };
+    let _ = writeln!(
+      text,
+      "# HELP {METRIC_PREFIX}{name} {help}\n\
+       # TYPE {METRIC_PREFIX}{name} {kind}\n\
+       {METRIC_PREFIX}{name} {value}",
+      name = metric.name,
+      help = metric.help,
+      value = metric.value,
+    );
+  }
+  text
+}
+
+/// Reads the request line and skips the headers, failing after the [`CONNECTION_TIMEOUT`].
+fn read_request_line(stream: &TcpStream) -> io::Result<String> {
+  stream.set_read_timeout(Some(CONNECTION_TIMEOUT))?;
+  stream.set_write_timeout(Some(CONNECTION_TIMEOUT))?;
+  let mut reader = BufReader::new(stream);
+  let mut request_line = String::new();
+  reader.read_line(&mut request_line)?;
+  // The headers are not needed.
+  let mut header_line = String::new();
+  while reader.read_line(&mut header_line)? > 2 {
+    header_line.clear();
+  }
+  Ok(request_line)
+}
+
+fn handle_connection(stream: &TcpStream, process_state: &ProcessState) -> io::Result<()> {
+  let request_line = read_request_line(stream)?;
+  let mut parts = request_line.split_whitespace();
+  let response = match (parts.next(), parts.next()) {
+    (Some("GET"), Some("/metrics")) => {
+      let body = render_metrics(&process_metrics(process_state));
+      format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+      )
+    },
+    _ => String::from("HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"),
+  };
+  (&*stream).write_all(response.as_bytes())
+}
+
+/// Serves the counters of the run on `http://<address>/metrics` until the process exits.
+pub fn serve_metrics(address: SocketAddr, process_state: Arc<ProcessState>) -> anyhow::Result<()> {
+  let listener = TcpListener::bind(address)
+    .with_context(|| format!("Failed to listen for metrics on {address}"))?;
+  info!("Serving metrics on http://{address}/metrics");
+  std::thread::spawn(move || {
+    for stream in listener.incoming() {
+      if let Err(e) = stream.and_then(|stream| handle_connection(&stream, &process_state)) {
+        debug!("Failed to serve metrics: {e}");
+      }
+    }
+  });
+  Ok(())
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn exposition_format() {
+    let metrics = [
+      counter("files_checked_total", "Files checked.", 12),
+      Metric {
+        name: "run_duration_seconds",
+        help: "Seconds since the start of the run.",
+        kind: MetricKind::Gauge,
+        value: 3,
+      },
+    ];
+    assert_eq!(
+      render_metrics(&metrics),
+      "# HELP image_date_fixer_files_checked_total Files checked.\n\
+       # TYPE image_date_fixer_files_checked_total counter\n\
+       image_date_fixer_files_checked_total 12\n\
+       # HELP image_date_fixer_run_duration_seconds Seconds since the start of the run.\n\
+       # TYPE image_date_fixer_run_duration_seconds gauge\n\
+       image_date_fixer_run_duration_seconds 3\n"
+    );
+  }
+
+  #[test]
+  fn stalled_connections_time_out() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (stream, _) = listener.accept().unwrap();
+
+    // A client that never finishes its request is dropped instead of blocking the next one.
+    client.write_all(b"GET /metrics HTTP/1.1\r\n").unwrap();
+    let error = read_request_line(&stream).unwrap_err();
+    assert!(matches!(
+      error.kind(),
+      io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    ));
+
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (stream, _) = listener.accept().unwrap();
+    client
+      .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+      .unwrap();
+    assert_eq!(
+      read_request_line(&stream).unwrap(),
+      "GET /metrics HTTP/1.1\r\n"
+    );
+  }
+}
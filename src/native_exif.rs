@@ -0,0 +1,530 @@
+use std::{fs::File, io::BufReader, path::Path};
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use exif::{In, Tag, Value};
+
+#[cfg(feature = "native-jpeg-write")]
+use std::{
+  fs::Metadata,
+  io::{Seek as _, SeekFrom, Write as _},
+  path::PathBuf,
+  time::SystemTime,
+};
+
+#[cfg(feature = "native-jpeg-write")]
+use anyhow::Context as _;
+
+use crate::exiftool::ExifDates;
+#[cfg(feature = "native-jpeg-write")]
+use crate::{backups::backups, exiftool::ExifWriteOptions};
+
+/// Extensions of files whose EXIF data can be read without exiftool.
+const NATIVE_EXIF_EXTENSIONS: [&str; 6] = ["JPG", "JPEG", "TIF", "TIFF", "HEIC", "HEIF"];
+
+#[must_use]
+pub fn can_read_natively(file_path: &Path) -> bool {
+  file_path.extension().is_some_and(|ext| {
+    NATIVE_EXIF_EXTENSIONS
+      .iter()
+      .any(|native_ext| ext.eq_ignore_ascii_case(native_ext))
+  })
+}
+
+/// Reads the EXIF dates without exiftool.
+/// XMP tags are not read, so files without any EXIF date return None and should be read with exiftool instead.
+#[must_use]
+pub fn read_exif_dates_natively(file_path: &Path) -> Option<ExifDates> {
+  if !can_read_natively(file_path) {
+    return None;
+  }
+  let mut reader = BufReader::new(File::open(file_path).ok()?);
+  let exif = exif::Reader::new().read_from_container(&mut reader).ok()?;
+
+  let dates = ExifDates {
+    date_time_original: ascii_date(&exif, Tag::DateTimeOriginal),
+    create_date: ascii_date(&exif, Tag::DateTimeDigitized),
+    modify_date: ascii_date(&exif, Tag::DateTime),
+    gps_date_time: gps_date_time(&exif),
+    gps_latitude: gps_coordinate(&exif, Tag::GPSLatitude, Tag::GPSLatitudeRef, b'S'),
+    gps_longitude: gps_coordinate(&exif, Tag::GPSLongitude, Tag::GPSLongitudeRef, b'W'),
+    ..ExifDates::default()
+  };
+  dates.original().is_some().then_some(dates)
+}
+
+#[must_use]
+fn ascii_value(exif: &exif::Exif, tag: Tag) -> Option<&[u8]> {
+  match &exif.get_field(tag, In::PRIMARY)?.value {
+    Value::Ascii(values) => values.first().map(Vec::as_slice),
+    _ => None,
+  }
+}
+
+#[must_use]
+fn ascii_date(exif: &exif::Exif, tag: Tag) -> Option<NaiveDateTime> {
+  let date = exif::DateTime::from_ascii(ascii_value(exif, tag)?).ok()?;
+  Some(NaiveDateTime::new(
+    NaiveDate::from_ymd_opt(
+      i32::from(date.year),
+      u32::from(date.month),
+      u32::from(date.day),
+    )?,
+    NaiveTime::from_hms_opt(
+      u32::from(date.hour),
+      u32::from(date.minute),
+      u32::from(date.second),
+    )?,
+  ))
+}
+
+#[must_use]
+fn rationals(exif: &exif::Exif, tag: Tag) -> Option<&[exif::Rational]> {
+  match &exif.get_field(tag, In::PRIMARY)?.value {
+    Value::Rational(values) if values.len() == 3 => Some(values),
+    _ => None,
+  }
+}
+
+/// Combines `GPSDateStamp` and `GPSTimeStamp` like exiftool's `GPSDateTime`, in UTC.
+#[must_use]
+fn gps_date_time(exif: &exif::Exif) -> Option<NaiveDateTime> {
+  let date = core::str::from_utf8(ascii_value(exif, Tag::GPSDateStamp)?).ok()?;
+  let date = NaiveDate::parse_from_str(date, "%Y:%m:%d").ok()?;
+  let time = rationals(exif, Tag::GPSTimeStamp)?
+    .iter()
+    .map(|value| value.num.checked_div(value.denom))
+    .collect::<Option<Vec<_>>>()?;
+  Some(NaiveDateTime::new(
+    date,
+    NaiveTime::from_hms_opt(time[0], time[1], time[2])?,
+  ))
+}
+
+/// A signed coordinate in degrees, negative if the reference is `negative_ref`.
+#[must_use]
+fn gps_coordinate(exif: &exif::Exif, tag: Tag, ref_tag: Tag, negative_ref: u8) -> Option<f64> {
+  let [degrees, minutes, seconds] = rationals(exif, tag)? else {
+    return None;
+  };
+  let coordinate = degrees.to_f64() + minutes.to_f64() / 60.0 + seconds.to_f64() / 3600.0;
+  if ascii_value(exif, ref_tag)?.first() == Some(&negative_ref) {
+    Some(-coordinate)
+  } else {
+    Some(coordinate)
+  }
+}
+
+/// The `Exif\0\0` header that starts the EXIF APP1 segment of a JPEG.
+#[cfg(feature = "native-jpeg-write")]
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+
+/// Where the EXIF data of a JPEG is.
+#[cfg(feature = "native-jpeg-write")]
+#[derive(Debug, PartialEq, Eq)]
+enum JpegExif {
+  /// The byte range of the TIFF data in the APP1 segment.
+  Existing(core::ops::Range<usize>),
+  /// There is no EXIF data, a new APP1 segment belongs at this position.
+  Missing { insert_at: usize },
+}
+
+#[cfg(feature = "native-jpeg-write")]
+fn find_jpeg_exif(jpeg: &[u8]) -> anyhow::Result<JpegExif> {
+  anyhow::ensure!(jpeg.starts_with(&[0xFF, 0xD8]), "Not a JPEG file");
+  let mut position = 2;
+  // The EXIF segment belongs after the JFIF APP0 segment, which has to come first.
+  let mut insert_at = 2;
+  loop {
+    let marker = jpeg
+      .get(position..position + 4)
+      .context("Unexpected end of the JPEG file")?;
+    anyhow::ensure!(marker[0] == 0xFF, "Invalid JPEG segment marker");
+    // The image data starts at SOS, no metadata follows.
+    if marker[1] == 0xDA || marker[1] == 0xD9 {
+      return Ok(JpegExif::Missing { insert_at });
+    }
+    let segment_end = position + 2 + usize::from(u16::from_be_bytes([marker[2], marker[3]]));
+    let data = jpeg
+      .get(position + 4..segment_end)
+      .context("Unexpected end of the JPEG file")?;
+    if marker[1] == 0xE1 && data.starts_with(EXIF_HEADER) {
+      return Ok(JpegExif::Existing(
+        position + 4 + EXIF_HEADER.len()..segment_end,
+      ));
+    }
+    if marker[1] == 0xE0 {
+      insert_at = segment_end;
+    }
+    position = segment_end;
+  }
+}
+
+/// Reads the integers of TIFF data in its byte order.
+#[cfg(feature = "native-jpeg-write")]
+struct Tiff<'a> {
+  data: &'a [u8],
+  little_endian: bool,
+}
+
+#[cfg(feature = "native-jpeg-write")]
+impl<'a> Tiff<'a> {
+  fn new(data: &'a [u8]) -> Option<Self> {
+    let little_endian = match data.get(0..2)? {
+      b"II" => true,
+      b"MM" => false,
+      _ => return None,
+    };
+    Some(Self {
+      data,
+      little_endian,
+    })
+  }
+
+  fn u16_at(&self, offset: usize) -> Option<u16> {
+    let bytes = self.data.get(offset..offset + 2)?.try_into().ok()?;
+    Some(if self.little_endian {
+      u16::from_le_bytes(bytes)
+    } else {
+      u16::from_be_bytes(bytes)
+    })
+  }
+
+  fn offset_at(&self, offset: usize) -> Option<usize> {
+    let bytes = self.data.get(offset..offset + 4)?.try_into().ok()?;
+    usize::try_from(if self.little_endian {
+      u32::from_le_bytes(bytes)
+    } else {
+      u32::from_be_bytes(bytes)
+    })
+    .ok()
+  }
+
+  /// The offset of the 12 byte entry of `tag` in the IFD at `ifd_offset`.
+  fn find_entry(&self, ifd_offset: usize, tag: u16) -> Option<usize> {
+    let entry_count = usize::from(self.u16_at(ifd_offset)?);
+    (0..entry_count)
+      .map(|index| ifd_offset + 2 + index * 12)
+      .find(|&entry| self.u16_at(entry) == Some(tag))
+  }
+
+  /// The offset of the `DateTimeOriginal` value, if it is a 20 byte ASCII string that can be replaced in place.
+  fn date_time_original_offset(&self) -> Option<usize> {
+    const EXIF_IFD_POINTER: u16 = 0x8769;
+    const DATE_TIME_ORIGINAL: u16 = 0x9003;
+    const ASCII: u16 = 2;
+
+    let ifd0 = self.offset_at(4)?;
+    let exif_ifd = self.offset_at(self.find_entry(ifd0, EXIF_IFD_POINTER)? + 8)?;
+    let entry = self.find_entry(exif_ifd, DATE_TIME_ORIGINAL)?;
+    if self.u16_at(entry + 2)? != ASCII || self.offset_at(entry + 4)? != 20 {
+      return None;
+    }
+    let offset = self.offset_at(entry + 8)?;
+    (offset + 20 <= self.data.len()).then_some(offset)
+  }
+}
+
+/// Sets `DateTimeOriginal` in the EXIF data of `jpeg`.
+/// An existing value is replaced in place, JPEGs without EXIF data get a new EXIF segment.
+/// Other layouts need exiftool.
+#[cfg(feature = "native-jpeg-write")]
+fn set_date_time_original_in_jpeg(jpeg: &[u8], date: &NaiveDateTime) -> anyhow::Result<Vec<u8>> {
+  let date_str = date.format("%Y:%m:%d %H:%M:%S").to_string();
+  let mut jpeg = jpeg.to_vec();
+  match find_jpeg_exif(&jpeg)? {
+    JpegExif::Existing(tiff_range) => {
+      let offset = Tiff::new(&jpeg[tiff_range.clone()])
+        .and_then(|tiff| tiff.date_time_original_offset())
+        .context("The EXIF data has no DateTimeOriginal that can be replaced without exiftool")?;
+      let value_start = tiff_range.start + offset;
+      jpeg[value_start..value_start + date_str.len()].copy_from_slice(date_str.as_bytes());
+    },
+    JpegExif::Missing { insert_at } => {
+      let field = exif::Field {
+        tag: Tag::DateTimeOriginal,
+        ifd_num: In::PRIMARY,
+        value: Value::Ascii(vec![date_str.into_bytes()]),
+      };
+      let mut writer = exif::experimental::Writer::new();
+      writer.push_field(&field);
+      let mut tiff = std::io::Cursor::new(Vec::new());
+      writer
+        .write(&mut tiff, false)
+        .context("Failed to create the EXIF data")?;
+      let tiff = tiff.into_inner();
+
+      let segment_length = u16::try_from(2 + EXIF_HEADER.len() + tiff.len())?;
+      let mut segment = vec![0xFF, 0xE1];
+      segment.extend(segment_length.to_be_bytes());
+      segment.extend(EXIF_HEADER);
+      segment.extend(tiff);
+      jpeg.splice(insert_at..insert_at, segment);
+    },
+  }
+  Ok(jpeg)
+}
+
+/// Sets `DateTimeOriginal` of a JPEG without exiftool.
+/// Only used when exiftool is not installed, so the other tags of [`ExifWriteOptions`] are not written.
+#[cfg(feature = "native-jpeg-write")]
+pub fn write_date_time_original_natively(
+  file_path: &Path,
+  date: &NaiveDateTime,
+  options: ExifWriteOptions,
+) -> anyhow::Result<()> {
+  anyhow::ensure!(
+    can_write_natively(file_path),
+    "Only the dates of JPEG files can be written without exiftool"
+  );
+  let original_jpeg = std::fs::read(file_path).context("Failed to read the file")?;
+  let metadata = std::fs::metadata(file_path)?;
+  let jpeg = set_date_time_original_in_jpeg(&original_jpeg, date)?;
+
+  let pending_backup = backups().map(|backups| backups.prepare(file_path));
+  if let Some(pending_backup) = &pending_backup {
+    pending_backup.copy_original()?;
+  }
+
+  // Like exiftool, the modified time is that of the write unless it is preserved.
+  let preserved_modified_time = options
+    .preserve_modified_time
+    .then(|| metadata.modified())
+    .transpose()?;
+  if jpeg.len() == original_jpeg.len() {
+    overwrite_changed_bytes(file_path, &original_jpeg, &jpeg, preserved_modified_time)?;
+  } else {
+    replace_file(file_path, &jpeg, &metadata, preserved_modified_time)?;
+  }
+
+  if let Some(pending_backup) = pending_backup {
+    pending_backup.finish()?;
+  }
+  Ok(())
+}
+
+/// Writes the bytes of `jpeg` that differ from `original_jpeg` in place,
+/// which keeps the file itself with its permissions, owner, extended attributes and hardlinks.
+#[cfg(feature = "native-jpeg-write")]
+fn overwrite_changed_bytes(
+  file_path: &Path,
+  original_jpeg: &[u8],
+  jpeg: &[u8],
+  preserved_modified_time: Option<SystemTime>,
+) -> anyhow::Result<()> {
+  let differs = |(original_byte, byte): (&u8, &u8)| original_byte != byte;
+  let Some(start) = original_jpeg.iter().zip(jpeg).position(differs) else {
+    return Ok(());
+  };
+  let end = jpeg.len()
+    - original_jpeg
+      .iter()
+      .rev()
+      .zip(jpeg.iter().rev())
+      .position(differs)
+      .unwrap_or_default();
+  let mut file = File::options()
+    .write(true)
+    .open(file_path)
+    .context("Failed to open the file")?;
+  file.seek(SeekFrom::Start(u64::try_from(start)?))?;
+  file
+    .write_all(&jpeg[start..end])
+    .context("Failed to write the date")?;
+  if let Some(modified_time) = preserved_modified_time {
+    file.set_modified(modified_time)?;
+  }
+  Ok(())
+}
+
+/// Replaces the file with `jpeg` through a temporary file with the same permissions, so the original is never left half written.
+/// The replaced file is a new file, so hardlinks to the original keep the original content.
+#[cfg(feature = "native-jpeg-write")]
+fn replace_file(
+  file_path: &Path,
+  jpeg: &[u8],
+  metadata: &Metadata,
+  preserved_modified_time: Option<SystemTime>,
+) -> anyhow::Result<()> {
+  let mut temporary_path = file_path.as_os_str().to_owned();
+  temporary_path.push(".image-date-fixer-tmp");
+  let temporary_path = PathBuf::from(temporary_path);
+  let result = (|| -> anyhow::Result<()> {
+    let mut file =
+      File::create_new(&temporary_path).context("Failed to create the temporary file")?;
+    file
+      .write_all(jpeg)
+      .context("Failed to write the temporary file")?;
+    file.set_permissions(metadata.permissions())?;
+    if let Some(modified_time) = preserved_modified_time {
+      file.set_modified(modified_time)?;
+    }
+    drop(file);
+    std::fs::rename(&temporary_path, file_path).context("Failed to replace the file")
+  })();
+  if result.is_err() {
+    let _ = std::fs::remove_file(&temporary_path);
+  }
+  result
+}
+
+/// Extensions of files whose date can be written without exiftool.
+#[cfg(feature = "native-jpeg-write")]
+pub const NATIVE_WRITE_EXTENSIONS: [&str; 2] = ["JPG", "JPEG"];
+
+#[cfg(feature = "native-jpeg-write")]
+#[must_use]
+fn can_write_natively(file_path: &Path) -> bool {
+  file_path.extension().is_some_and(|ext| {
+    NATIVE_WRITE_EXTENSIONS
+      .iter()
+      .any(|native_ext| ext.eq_ignore_ascii_case(native_ext))
+  })
+}
+
+#[cfg(test)]
+mod test {
+  use std::io::Cursor;
+
+  use exif::{Field, Rational, experimental::Writer};
+
+  use super::*;
+
+  fn field(tag: Tag, value: Value) -> Field {
+    Field {
+      tag,
+      ifd_num: In::PRIMARY,
+      value,
+    }
+  }
+
+  fn ascii(value: &str) -> Value {
+    Value::Ascii(vec![value.as_bytes().to_vec()])
+  }
+
+  fn rational(values: [u32; 3]) -> Value {
+    Value::Rational(values.map(|num| Rational { num, denom: 1 }).to_vec())
+  }
+
+  #[test]
+  fn native_read() {
+    let fields = [
+      field(Tag::DateTimeOriginal, ascii("2019:08:18 14:30:00")),
+      field(Tag::DateTime, ascii("2020:01:01 10:00:00")),
+      field(Tag::GPSDateStamp, ascii("2019:08:18")),
+      field(Tag::GPSTimeStamp, rational([12, 30, 0])),
+      field(Tag::GPSLatitude, rational([48, 12, 0])),
+      field(Tag::GPSLatitudeRef, ascii("N")),
+      field(Tag::GPSLongitude, rational([16, 30, 0])),
+      field(Tag::GPSLongitudeRef, ascii("W")),
+    ];
+    let mut writer = Writer::new();
+    for field in &fields {
+      writer.push_field(field);
+    }
+    let mut tiff = Cursor::new(Vec::new());
+    writer.write(&mut tiff, false).unwrap();
+
+    let file_path = std::env::temp_dir().join("image-date-fixer-native-read.tif");
+    std::fs::write(&file_path, tiff.into_inner()).unwrap();
+    let dates = read_exif_dates_natively(&file_path).unwrap();
+    std::fs::remove_file(&file_path).unwrap();
+
+    let date = |date: &str| NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S").unwrap();
+    assert_eq!(dates.date_time_original, Some(date("2019-08-18 14:30:00")));
+    assert_eq!(dates.modify_date, Some(date("2020-01-01 10:00:00")));
+    assert_eq!(dates.create_date, None);
+    assert_eq!(dates.gps_date_time, Some(date("2019-08-18 12:30:00")));
+    assert_eq!(dates.gps_position(), Some((48.2, -16.5)));
+
+    assert!(!can_read_natively(Path::new("/a/VID_0001.mp4")));
+    assert_eq!(read_exif_dates_natively(Path::new("/a/missing.jpg")), None);
+  }
+  #[cfg(feature = "native-jpeg-write")]
+  #[test]
+  fn native_jpeg_write() {
+    let read_date_time_original = |jpeg: &[u8]| {
+      let exif = exif::Reader::new()
+        .read_from_container(&mut Cursor::new(jpeg))
+        .unwrap();
+      ascii_date(&exif, Tag::DateTimeOriginal)
+    };
+    let date = |date: &str| NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S").unwrap();
+
+    let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10];
+    jpeg.extend(b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0");
+    let jfif_end = jpeg.len();
+    jpeg.extend([0xFF, 0xDA, 0x00, 0x02, 0x12, 0x34, 0xFF, 0xD9]);
+    assert_eq!(
+      find_jpeg_exif(&jpeg).unwrap(),
+      JpegExif::Missing {
+        insert_at: jfif_end
+      }
+    );
+
+    // A new EXIF segment is inserted after the JFIF segment.
+    let jpeg = set_date_time_original_in_jpeg(&jpeg, &date("2019-08-18 14:30:00")).unwrap();
+    assert!(matches!(
+      find_jpeg_exif(&jpeg).unwrap(),
+      JpegExif::Existing(_)
+    ));
+    assert_eq!(
+      read_date_time_original(&jpeg),
+      Some(date("2019-08-18 14:30:00"))
+    );
+    assert!(jpeg.ends_with(&[0xFF, 0xDA, 0x00, 0x02, 0x12, 0x34, 0xFF, 0xD9]));
+
+    // The existing value is replaced in place.
+    let rewritten = set_date_time_original_in_jpeg(&jpeg, &date("2020-01-02 03:04:05")).unwrap();
+    assert_eq!(rewritten.len(), jpeg.len());
+    assert_eq!(
+      read_date_time_original(&rewritten),
+      Some(date("2020-01-02 03:04:05"))
+    );
+
+    assert!(set_date_time_original_in_jpeg(b"GIF89a", &date("2020-01-02 03:04:05")).is_err());
+  }
+
+  #[cfg(all(unix, feature = "native-jpeg-write"))]
+  #[test]
+  fn native_jpeg_write_keeps_file() {
+    use std::os::unix::fs::{MetadataExt as _, PermissionsExt as _};
+
+    let date = |date: &str| NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S").unwrap();
+    let folder_path = std::env::temp_dir().join(format!(
+      "image-date-fixer-native-write-test-{}",
+      std::process::id()
+    ));
+    std::fs::create_dir_all(&folder_path).unwrap();
+    let file_path = folder_path.join("IMG_1.jpg");
+    let mut jpeg = vec![0xFF, 0xD8];
+    jpeg.extend([0xFF, 0xDA, 0x00, 0x02, 0x12, 0x34, 0xFF, 0xD9]);
+    std::fs::write(&file_path, jpeg).unwrap();
+    std::fs::set_permissions(&file_path, std::fs::Permissions::from_mode(0o640)).unwrap();
+    let options = ExifWriteOptions::default();
+
+    // A new EXIF segment replaces the file.
+    write_date_time_original_natively(&file_path, &date("2019-08-18 14:30:00"), options).unwrap();
+    let metadata = std::fs::metadata(&file_path).unwrap();
+    assert_eq!(metadata.permissions().mode() & 0o777, 0o640);
+
+    // Changing the date again keeps the file and its hardlinks.
+    let link_path = folder_path.join("IMG_1 (link).jpg");
+    std::fs::hard_link(&file_path, &link_path).unwrap();
+    write_date_time_original_natively(&file_path, &date("2020-01-02 03:04:05"), options).unwrap();
+    assert_eq!(std::fs::metadata(&file_path).unwrap().ino(), metadata.ino());
+    assert_eq!(
+      std::fs::read(&link_path).unwrap(),
+      std::fs::read(&file_path).unwrap()
+    );
+    assert_eq!(
+      read_exif_dates_natively(&link_path)
+        .unwrap()
+        .date_time_original,
+      Some(date("2020-01-02 03:04:05"))
+    );
+
+    let entries = std::fs::read_dir(&folder_path).unwrap().count();
+    assert_eq!(entries, 2, "No temporary file is left behind");
+    std::fs::remove_dir_all(&folder_path).unwrap();
+  }
+}
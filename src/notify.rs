@@ -0,0 +1,137 @@
+use std::{
+  io::Write as _,
+  process::{Command, Stdio},
+  sync::OnceLock,
+};
+
+use anyhow::Context as _;
+use serde_json::Value;
+use tracing::{error, info};
+
+static NOTIFY_URL: OnceLock<String> = OnceLock::new();
+
+/// Whether `program` can be run, checked with its `--version`.
+fn is_runnable(program: &str) -> bool {
+  Command::new(program)
+    .arg("--version")
+    .stdin(Stdio::null())
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .status()
+    .is_ok_and(|status| status.success())
+}
+
+/// Posts the outcome of the run to `url` when it finishes or fails.
+/// Fails if curl is missing, so the run does not end without its notification.
+pub fn install_notify_url(url: &str) -> anyhow::Result<()> {
+  anyhow::ensure!(
+    is_runnable("curl"),
+    "--notify-url needs curl, but it could not be run. Install curl or drop --notify-url"
+  );
+  let _ = NOTIFY_URL.set(url.to_string());
+  Ok(())
+}
+
+/// The summary is sent as `text` for Slack and as `content` for Discord. ntfy shows the whole body.
+fn payload(outcome: &str, summary: &str, stats: Option<&Value>) -> Value {
+  serde_json::json!({
+    "text": summary,
+    "content": summary,
+    "status": outcome,
+    "stats": stats,
+  })
+}
+
+/// Posts with curl, which is commonly installed and handles HTTPS.
+fn post_json(url: &str, body: &str) -> anyhow::Result<()> {
+  let mut child = Command::new("curl")
+    .args([
+      "--silent",
+      "--show-error",
+      "--fail",
+      "--max-time",
+      "30",
+      "--header",
+      "Content-Type: application/json",
+      "--data-binary",
+      "@-",
+      url,
+    ])
+    .stdin(Stdio::piped())
+    .stdout(Stdio::null())
+    .stderr(Stdio::piped())
+    .spawn()
+    .context("Failed to run curl")?;
+  child
+    .stdin
+    .take()
+    .expect("The stdin of curl should be piped")
+    .write_all(body.as_bytes())?;
+  let output = child.wait_with_output()?;
+  if !output.status.success() {
+    anyhow::bail!(
+      "curl failed with {}: {}",
+      output.status,
+      String::from_utf8_lossy(&output.stderr).trim()
+    );
+  }
+  Ok(())
+}
+
+fn notify(payload: &Value) {
+  let Some(url) = NOTIFY_URL.get() else {
+    return;
+  };
+  match post_json(url, &payload.to_string()) {
+    Ok(()) => info!("Sent the notification to {url}"),
+    Err(e) => error!("Failed to send the notification to {url}: {e:#}"),
+  }
+}
+
+/// Sends the statistics of a finished run to the `--notify-url`.
+pub fn notify_completion(stats: &Value, files_errors: usize) {
+  let summary = format!(
+    "image-date-fixer finished: {} files checked, {} EXIF dates and {} modified times fixed, {files_errors} files with errors",
+    stats["files_checked"],
+    stats["exif_dates_updated"].as_u64().unwrap_or_default()
+      + stats["exif_dates_overwritten"].as_u64().unwrap_or_default(),
+    stats["modified_times_updated"],
+  );
+  let outcome = if files_errors > 0 {
+    "errors"
+  } else {
+    "success"
+  };
+  notify(&payload(outcome, &summary, Some(stats)));
+}
+
+/// Sends a fatal error to the `--notify-url`.
+pub fn notify_failure(e: &anyhow::Error) {
+  notify(&payload(
+    "failed",
+    &format!("image-date-fixer failed: {e:#}"),
+    None,
+  ));
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn payloads() {
+    let payload = payload(
+      "success",
+      "image-date-fixer finished",
+      Some(&serde_json::json!({ "files_checked": 3 })),
+    );
+    assert_eq!(payload["text"], "image-date-fixer finished");
+    assert_eq!(payload["content"], payload["text"]);
+    assert_eq!(payload["stats"]["files_checked"], 3);
+  }
+
+  #[test]
+  fn missing_programs() {
+    assert!(!is_runnable("image-date-fixer-missing-program"));
+  }
+}
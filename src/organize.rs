@@ -0,0 +1,326 @@
+use alloc::collections::BTreeSet;
+use core::{str::FromStr, sync::atomic::Ordering};
+use std::{
+  ffi::OsString,
+  fs::File,
+  io,
+  path::{Path, PathBuf},
+  sync::Mutex,
+};
+
+use anyhow::Context as _;
+use chrono::{Datelike as _, NaiveDateTime};
+use tracing::{error, info};
+
+use crate::{
+  ProcessState, companion_files::find_companion_files, errors::ErrorWithFilePath,
+  xmp_sidecar::find_xmp_sidecar,
+};
+
+/// How files are put into the date folders.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum OrganizeMode {
+  #[default]
+  Move,
+  Hardlink,
+  /// The copies keep the modified time of the originals.
+  Copy,
+}
+
+impl OrganizeMode {
+  #[must_use]
+  pub const fn as_str(self) -> &'static str {
+    match self {
+      Self::Move => "move",
+      Self::Hardlink => "hardlink",
+      Self::Copy => "copy",
+    }
+  }
+}
+
+impl FromStr for OrganizeMode {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "move" => Ok(Self::Move),
+      "hardlink" => Ok(Self::Hardlink),
+      "copy" => Ok(Self::Copy),
+      _ => Err(anyhow::anyhow!("Unknown organize mode: {s}")),
+    }
+  }
+}
+
+/// Collects the resolved dates of the media files during the per-file pass,
+/// so that they can be put into `YYYY/MM/` folders under the target folder afterwards.
+pub struct Organizer {
+  target_root: PathBuf,
+  mode: OrganizeMode,
+  dates: Mutex<Vec<(PathBuf, NaiveDateTime)>>,
+}
+
+impl Organizer {
+  #[must_use]
+  pub const fn new(target_root: PathBuf, mode: OrganizeMode) -> Self {
+    Self {
+      target_root,
+      mode,
+      dates: Mutex::new(Vec::new()),
+    }
+  }
+
+  pub fn record(&self, file_path: &Path, date: NaiveDateTime) {
+    self
+      .dates
+      .lock()
+      .expect("Organizer lock should not be poisoned")
+      .push((file_path.to_path_buf(), date));
+  }
+
+  /// The recorded dates sorted by file path. This consumes the recorded dates.
+  #[must_use]
+  fn take(&self) -> Vec<(PathBuf, NaiveDateTime)> {
+    let mut dates = core::mem::take(
+      &mut *self
+        .dates
+        .lock()
+        .expect("Organizer lock should not be poisoned"),
+    );
+    dates.sort();
+    dates
+  }
+
+  #[must_use]
+  fn target_folder(&self, date: &NaiveDateTime) -> PathBuf {
+    self
+      .target_root
+      .join(format!("{:04}", date.year()))
+      .join(format!("{:02}", date.month()))
+  }
+}
+
+/// `file_name` in `folder_path`, with ` (1)`, ` (2)`, ... appended to its stem if that name is taken,
+/// or one of its `related_names` renamed along with it (see [`renamed_companion`]) is.
+#[must_use]
+fn free_target_path(folder_path: &Path, file_name: &Path, related_names: &[&Path]) -> PathBuf {
+  let stem = file_name.file_stem().unwrap_or_default().to_string_lossy();
+  let extension = file_name
+    .extension()
+    .map(|ext| format!(".{}", ext.to_string_lossy()))
+    .unwrap_or_default();
+  core::iter::once(stem.to_string())
+    .chain((1..=u32::MAX).map(|index| format!("{stem} ({index})")))
+    .find(|new_stem| {
+      !folder_path.join(format!("{new_stem}{extension}")).exists()
+        && related_names.iter().all(|related_name| {
+          !folder_path
+            .join(renamed_companion(related_name, &stem, new_stem))
+            .exists()
+        })
+    })
+    .map(|new_stem| folder_path.join(format!("{new_stem}{extension}")))
+    .expect("There should be a free file name")
+}
+
+/// The name of a companion or sidecar file after its primary file was renamed from `old_stem` to `new_stem`.
+#[must_use]
+fn renamed_companion(companion_name: &Path, old_stem: &str, new_stem: &str) -> OsString {
+  let companion_name = companion_name.to_string_lossy();
+  match companion_name.strip_prefix(old_stem) {
+    Some(rest) => OsString::from(format!("{new_stem}{rest}")),
+    None => OsString::from(companion_name.as_ref()),
+  }
+}
+
+fn transfer_file(source_path: &Path, target_path: &Path, mode: OrganizeMode) -> anyhow::Result<()> {
+  match mode {
+    OrganizeMode::Move => match std::fs::rename(source_path, target_path) {
+      Ok(()) => Ok(()),
+      // Moving to another file system needs a copy.
+      Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+        copy_file(source_path, target_path)?;
+        std::fs::remove_file(source_path).context("Failed to remove the moved file")
+      },
+      Err(e) => Err(e).context("Failed to move file"),
+    },
+    OrganizeMode::Hardlink => {
+      std::fs::hard_link(source_path, target_path).context("Failed to create hardlink")
+    },
+    OrganizeMode::Copy => copy_file(source_path, target_path),
+  }
+}
+
+fn copy_file(source_path: &Path, target_path: &Path) -> anyhow::Result<()> {
+  std::fs::copy(source_path, target_path).context("Failed to copy file")?;
+  let modified_time = std::fs::metadata(source_path)
+    .and_then(|metadata| metadata.modified())
+    .context("Failed to get modified time")?;
+  File::options()
+    .write(true)
+    .open(target_path)
+    .and_then(|file| file.set_modified(modified_time))
+    .context("Failed to set the modified time of the copy")
+}
+
+/// Puts a media file and its companion and sidecar files into the date folder.
+/// The companion and sidecar files are added to `related_files`.
+/// Returns whether the file was organized, `false` if it already is in its date folder.
+fn organize_file(
+  file_path: &Path,
+  target_folder: &Path,
+  process_state: &ProcessState,
+  mode: OrganizeMode,
+  related_files: &mut BTreeSet<PathBuf>,
+) -> Result<bool, ErrorWithFilePath> {
+  if file_path.parent() == Some(target_folder) {
+    return Ok(false);
+  }
+  let file_name = Path::new(file_path.file_name().expect("File name should be present"));
+  let file_related_files = find_companion_files(file_path)
+    .into_iter()
+    .chain(find_xmp_sidecar(file_path))
+    .collect::<Vec<_>>();
+  related_files.extend(file_related_files.iter().cloned());
+  // The related files must not overwrite files in the date folder either.
+  let related_names = file_related_files
+    .iter()
+    .filter_map(|related_path| related_path.file_name().map(Path::new))
+    .collect::<Vec<_>>();
+  let target_path = free_target_path(target_folder, file_name, &related_names);
+
+  if process_state.options.dry_run {
+    info!(
+      file_path = %file_path.display(),
+      "Would {} file to \"{}\"",
+      mode.as_str(),
+      target_path.display(),
+    );
+    return Ok(true);
+  }
+
+  std::fs::create_dir_all(target_folder)
+    .with_context(|| {
+      format!(
+        "\"{}\": Failed to create the date folder",
+        target_folder.display()
+      )
+    })
+    .map_err(ErrorWithFilePath::from_source(file_path))?;
+  transfer_file(file_path, &target_path, mode)
+    .map_err(ErrorWithFilePath::from_source(file_path))?;
+  info!(
+    file_path = %file_path.display(),
+    "Organized file to \"{}\"",
+    target_path.display(),
+  );
+
+  // Companion and sidecar files follow their primary file, renamed along with it.
+  let old_stem = file_name.file_stem().unwrap_or_default().to_string_lossy();
+  let new_stem = target_path
+    .file_stem()
+    .unwrap_or_default()
+    .to_string_lossy();
+  for related_path in file_related_files {
+    let Some(related_name) = related_path.file_name() else {
+      continue;
+    };
+    let related_target_path = target_folder.join(renamed_companion(
+      Path::new(related_name),
+      &old_stem,
+      &new_stem,
+    ));
+    transfer_file(&related_path, &related_target_path, mode)
+      .map_err(ErrorWithFilePath::from_source(&related_path))?;
+  }
+  Ok(true)
+}
+
+/// Moves, hardlinks or copies every media file with a resolved date into the `YYYY/MM/` folder of that date.
+/// This runs after all other passes, which expect the files where they were found.
+pub fn apply_organize(organizer: &Organizer, process_state: &ProcessState) {
+  // XMP sidecars are media files themselves, but follow their primary file.
+  let mut related_files = BTreeSet::new();
+  for (file_path, date) in organizer.take() {
+    if process_state.should_exit.load(Ordering::Relaxed) {
+      return;
+    }
+    if related_files.contains(&file_path) {
+      continue;
+    }
+
+    let target_folder = organizer.target_folder(&date);
+    match organize_file(
+      &file_path,
+      &target_folder,
+      process_state,
+      organizer.mode,
+      &mut related_files,
+    ) {
+      Ok(true) => {
+        process_state
+          .stat_files_organized
+          .fetch_add(1, Ordering::Relaxed);
+      },
+      Ok(false) => {},
+      Err(e) => {
+        error!(
+          file_path = %file_path.display(),
+          source = ?e,
+          "Failed to organize file",
+        );
+        process_state
+          .stat_files_errors
+          .fetch_add(1, Ordering::Relaxed);
+        process_state.record_error(&e.file_path, &e.source);
+      },
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn target_paths() {
+    let organizer = Organizer::new(PathBuf::from("/sorted"), OrganizeMode::Move);
+    let date = NaiveDateTime::parse_from_str("2019-08-18 14:30:00", "%Y-%m-%d %H:%M:%S").unwrap();
+    assert_eq!(organizer.target_folder(&date), Path::new("/sorted/2019/08"));
+
+    let folder_path = std::env::temp_dir().join(format!(
+      "image-date-fixer-organize-test-{}",
+      std::process::id()
+    ));
+    std::fs::create_dir_all(&folder_path).unwrap();
+    std::fs::write(folder_path.join("IMG_0001.JPG"), "").unwrap();
+    std::fs::write(folder_path.join("IMG_0001 (1).JPG"), "").unwrap();
+    std::fs::write(folder_path.join("IMG_0003.AAE"), "").unwrap();
+    assert_eq!(
+      free_target_path(&folder_path, Path::new("IMG_0002.JPG"), &[]),
+      folder_path.join("IMG_0002.JPG")
+    );
+    assert_eq!(
+      free_target_path(&folder_path, Path::new("IMG_0001.JPG"), &[]),
+      folder_path.join("IMG_0001 (2).JPG")
+    );
+    // A companion that is already in the date folder is not overwritten.
+    assert_eq!(
+      free_target_path(
+        &folder_path,
+        Path::new("IMG_0003.JPG"),
+        &[Path::new("IMG_0003.AAE"), Path::new("IMG_0003.JPG.xmp")]
+      ),
+      folder_path.join("IMG_0003 (1).JPG")
+    );
+    std::fs::remove_dir_all(&folder_path).unwrap();
+
+    assert_eq!(
+      renamed_companion(Path::new("IMG_0001.CR2.xmp"), "IMG_0001", "IMG_0001 (2)"),
+      "IMG_0001 (2).CR2.xmp"
+    );
+    assert_eq!(
+      renamed_companion(Path::new("IMG_0001.AAE"), "IMG_0001", "IMG_0001"),
+      "IMG_0001.AAE"
+    );
+  }
+}
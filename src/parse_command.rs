@@ -0,0 +1,189 @@
+use std::{
+  io::{self, Write as _},
+  path::Path,
+};
+
+use ariadne::{Color, Config, IndexType, Label, Report, ReportKind, Source};
+use chrono::{Local, NaiveDateTime};
+
+use crate::date_extractors::{
+  ConfidentNaiveDateTime, DateConfidence, Explanation, date_extractor_registry,
+};
+
+/// Where in the input a date was found.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseResult<'a> {
+  pub extractor_name: &'a str,
+  /// The ancestor folder the date was taken from, `None` if it was taken from the file name.
+  pub folder_name: Option<String>,
+  pub date: ConfidentNaiveDateTime,
+}
+
+/// Runs the extractor chain on a file name or path the same way it is run when processing files,
+/// including the search of up to `folder_date_depth` ancestor folders.
+#[must_use]
+pub fn parse_file_name(
+  input: &str,
+  folder_date_depth: usize,
+  current_time: NaiveDateTime,
+) -> Option<ParseResult<'static>> {
+  let registry = date_extractor_registry();
+  let file_path = Path::new(input);
+  let file_name = file_path.file_name()?.to_string_lossy();
+
+  if let Some((extractor_name, date)) =
+    registry.extract_with_name(file_path, &file_name, current_time, false)
+  {
+    return Some(ParseResult {
+      extractor_name,
+      folder_name: None,
+      date,
+    });
+  }
+
+  file_path
+    .ancestors()
+    .skip(1)
+    .take(folder_date_depth)
+    .find_map(|folder_path| {
+      let folder_name = folder_path.file_name()?.to_string_lossy();
+      let (extractor_name, date) =
+        registry.extract_with_name(folder_path, &folder_name, current_time, true)?;
+      Some(ParseResult {
+        extractor_name,
+        folder_name: Some(folder_name.into_owned()),
+        date,
+      })
+    })
+}
+
+/// Implements the `parse` subcommand.
+/// Returns whether a date was found.
+pub fn run_parse_command(input: &str, folder_date_depth: usize) -> anyhow::Result<bool> {
+  let result = parse_file_name(input, folder_date_depth, Local::now().naive_utc());
+
+  let mut stdout = io::stdout().lock();
+  writeln!(&mut stdout, "Input:      {input}")?;
+  let Some(result) = result else {
+    writeln!(&mut stdout, "No extractor matched")?;
+    return Ok(false);
+  };
+  match &result.folder_name {
+    Some(folder_name) => writeln!(
+      &mut stdout,
+      "Extractor:  {} (folder \"{folder_name}\")",
+      result.extractor_name
+    )?,
+    None => writeln!(&mut stdout, "Extractor:  {}", result.extractor_name)?,
+  }
+  let date_format = if result.date.confidence >= DateConfidence::Millisecond {
+    "%Y-%m-%d %H:%M:%S%.3f"
+  } else {
+    "%Y-%m-%d %H:%M:%S"
+  };
+  writeln!(
+    &mut stdout,
+    "Date:       {}",
+    result.date.date.format(date_format)
+  )?;
+  writeln!(&mut stdout, "Confidence: {:?}", result.date.confidence)?;
+  Ok(true)
+}
+
+/// Renders the explanation of every extractor as an ariadne report over `name`.
+/// `selected` is the name of the extractor whose date is used.
+fn print_explanations(
+  name: &str,
+  explanations: &[(&str, Explanation)],
+  selected: Option<&str>,
+) -> anyhow::Result<()> {
+  for (extractor_name, explanation) in explanations {
+    let kind = if selected == Some(*extractor_name) {
+      ReportKind::Custom("Selected", Color::Green)
+    } else if explanation.result.is_some() {
+      ReportKind::Custom("Candidate", Color::Cyan)
+    } else {
+      ReportKind::Custom("No match", Color::Yellow)
+    };
+    let mut report = Report::build(
+      kind,
+      (*extractor_name, explanation.span.clone().unwrap_or(0..0)),
+    )
+    .with_config(Config::default().with_index_type(IndexType::Byte))
+    .with_message(format!("{extractor_name}: {}", explanation.message));
+    if let Some(span) = &explanation.span {
+      report = report
+        .with_label(Label::new((*extractor_name, span.clone())).with_message(&explanation.message));
+    }
+    report
+      .finish()
+      .print((*extractor_name, Source::from(name)))?;
+  }
+  Ok(())
+}
+
+/// Implements `--explain`.
+/// Prints for every extractor which part of the file name matched or where parsing failed,
+/// followed by the explanations for the ancestor folders that are searched if the file name carries no date.
+pub fn run_explain_command(input: &str, folder_date_depth: usize) -> anyhow::Result<()> {
+  let registry = date_extractor_registry();
+  let current_time = Local::now().naive_utc();
+  let file_path = Path::new(input);
+  let Some(file_name) = file_path.file_name() else {
+    anyhow::bail!("\"{input}\": The input has no file name");
+  };
+  let file_name = file_name.to_string_lossy();
+
+  println!("File name \"{file_name}\":");
+  print_explanations(
+    &file_name,
+    &registry.explain(file_path, &file_name, current_time, false),
+    registry
+      .extract_with_name(file_path, &file_name, current_time, false)
+      .map(|(extractor_name, _)| extractor_name),
+  )?;
+
+  for folder_path in file_path.ancestors().skip(1).take(folder_date_depth) {
+    let Some(folder_name) = folder_path.file_name() else {
+      continue;
+    };
+    let folder_name = folder_name.to_string_lossy();
+    println!("\nFolder \"{folder_name}\":");
+    print_explanations(
+      &folder_name,
+      &registry.explain(folder_path, &folder_name, current_time, true),
+      registry
+        .extract_with_name(folder_path, &folder_name, current_time, true)
+        .map(|(extractor_name, _)| extractor_name),
+    )?;
+  }
+
+  println!();
+  run_parse_command(input, folder_date_depth)?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn parse_file_names() {
+    let result = parse_file_name("IMG-20190818-WA0001.jpg", 1, NaiveDateTime::MAX).unwrap();
+    assert_eq!(result.extractor_name, "whatsapp");
+    assert_eq!(result.folder_name, None);
+    assert_eq!(result.date.confidence, DateConfidence::Day);
+
+    let result = parse_file_name("/a/2019/07/DSC_0001.jpg", 1, NaiveDateTime::MAX).unwrap();
+    assert_eq!(result.extractor_name, "folder_hierarchy");
+    assert_eq!(result.folder_name, None);
+
+    let result = parse_file_name("/a/Urlaub 2019/x/DSC_0001.jpg", 2, NaiveDateTime::MAX).unwrap();
+    assert_eq!(result.extractor_name, "year_in_folder_name");
+    assert_eq!(result.folder_name.as_deref(), Some("Urlaub 2019"));
+    assert_eq!(
+      parse_file_name("/a/Urlaub 2019/x/DSC_0001.jpg", 1, NaiveDateTime::MAX),
+      None
+    );
+  }
+}
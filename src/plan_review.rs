@@ -0,0 +1,284 @@
+use alloc::collections::BTreeMap;
+use core::str::FromStr;
+#[cfg(feature = "tui")]
+use std::io::IsTerminal as _;
+use std::{
+  io::{self, BufRead, Write},
+  path::Path,
+};
+
+use crate::change_plan::{PlannedChange, read_plan, write_plan};
+#[cfg(feature = "tui")]
+use crate::review_screen::review_in_terminal;
+
+/// How many changes of a group are shown before asking for a decision.
+const SAMPLE_SIZE: usize = 5;
+
+/// What the changes of a plan are grouped by for review.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum ReviewGrouping {
+  /// The source of the new EXIF date, e.g. `filename:whatsapp`.
+  #[default]
+  Extractor,
+  Confidence,
+  /// The folder of the file.
+  Directory,
+}
+
+impl FromStr for ReviewGrouping {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "extractor" => Ok(Self::Extractor),
+      "confidence" => Ok(Self::Confidence),
+      "directory" => Ok(Self::Directory),
+      _ => Err(anyhow::anyhow!("Unknown review grouping: {s}")),
+    }
+  }
+}
+
+impl ReviewGrouping {
+  #[must_use]
+  pub fn group_of(self, change: &PlannedChange) -> String {
+    const MODIFIED_TIME_ONLY: &str = "(modified time only)";
+    match self {
+      Self::Extractor => change
+        .source
+        .clone()
+        .unwrap_or_else(|| String::from(MODIFIED_TIME_ONLY)),
+      Self::Confidence => change.confidence.map_or_else(
+        || String::from(MODIFIED_TIME_ONLY),
+        |confidence| format!("{confidence:?}"),
+      ),
+      Self::Directory => change
+        .path
+        .parent()
+        .map(|folder_path| folder_path.display().to_string())
+        .unwrap_or_default(),
+    }
+  }
+}
+
+fn group_changes(
+  changes: Vec<PlannedChange>,
+  grouping: ReviewGrouping,
+) -> BTreeMap<String, Vec<PlannedChange>> {
+  let mut groups = BTreeMap::<String, Vec<PlannedChange>>::new();
+  for change in changes {
+    groups
+      .entry(grouping.group_of(&change))
+      .or_default()
+      .push(change);
+  }
+  groups
+}
+
+#[must_use]
+pub fn describe_change(change: &PlannedChange) -> String {
+  let mut lines = vec![change.path.display().to_string()];
+  if let Some(new_exif_date) = &change.new_exif_date {
+    lines.push(format!(
+      "EXIF date: {} -> {new_exif_date}",
+      change.old_exif_date.as_deref().unwrap_or("none"),
+    ));
+  }
+  if let Some(new_modified_time) = &change.new_modified_time {
+    lines.push(format!(
+      "Modified time: {} -> {new_modified_time}",
+      change.old_modified_time.as_deref().unwrap_or("unknown"),
+    ));
+  }
+  lines.join("\n      ")
+}
+
+/// Asks whether to approve each group of changes, showing a sample of each.
+/// Returns the approved changes. Groups left undecided when quitting are rejected.
+fn review_groups(
+  groups: BTreeMap<String, Vec<PlannedChange>>,
+  input: &mut impl BufRead,
+  output: &mut impl Write,
+) -> io::Result<Vec<PlannedChange>> {
+  let group_width = groups.keys().map(String::len).max().unwrap_or_default();
+  writeln!(output, "Planned changes:")?;
+  for (group, changes) in &groups {
+    writeln!(output, "  {group:<group_width$}  {}", changes.len())?;
+  }
+
+  let group_count = groups.len();
+  let mut approved = Vec::new();
+  'groups: for (index, (group, changes)) in groups.into_iter().enumerate() {
+    writeln!(
+      output,
+      "\n[{}/{group_count}] {group}: {} changes",
+      index + 1,
+      changes.len()
+    )?;
+    for change in changes.iter().take(SAMPLE_SIZE) {
+      writeln!(output, "    {}", describe_change(change))?;
+    }
+    if changes.len() > SAMPLE_SIZE {
+      writeln!(output, "    ... and {} more", changes.len() - SAMPLE_SIZE)?;
+    }
+
+    loop {
+      write!(
+        output,
+        "Approve these changes? [y]es, [n]o, [l]ist all, [q]uit: "
+      )?;
+      output.flush()?;
+      let mut answer = String::new();
+      if input.read_line(&mut answer)? == 0 {
+        break 'groups;
+      }
+      match answer.trim() {
+        "y" | "yes" => {
+          approved.extend(changes);
+          break;
+        },
+        "n" | "no" => break,
+        "l" | "list" => {
+          for change in &changes {
+            writeln!(output, "    {}", describe_change(change))?;
+          }
+        },
+        "q" | "quit" => break 'groups,
+        _ => writeln!(output, "Please answer y, n, l or q.")?,
+      }
+    }
+  }
+  Ok(approved)
+}
+
+/// Implements the `review` subcommand, which keeps only the approved groups of changes of a plan written with `--plan`.
+/// In a terminal, the changes are shown in an interactive table, otherwise each group is asked for in turn.
+pub fn run_review_command(
+  plan_path: &Path,
+  grouping: ReviewGrouping,
+  output_path: Option<&Path>,
+) -> anyhow::Result<()> {
+  let changes = read_plan(plan_path)?;
+  let change_count = changes.len();
+  let mut stdout = io::stdout().lock();
+  if change_count == 0 {
+    writeln!(&mut stdout, "The plan has no changes to review.")?;
+    return Ok(());
+  }
+
+  #[cfg(feature = "tui")]
+  if io::stdin().is_terminal() && stdout.is_terminal() {
+    drop(stdout);
+    let Some(approved) = review_in_terminal(changes, grouping)? else {
+      writeln!(
+        io::stdout().lock(),
+        "Review aborted, the plan was not changed."
+      )?;
+      return Ok(());
+    };
+    return finish_review(approved, change_count, plan_path, output_path);
+  }
+
+  let approved = review_groups(
+    group_changes(changes, grouping),
+    &mut io::stdin().lock(),
+    &mut stdout,
+  )?;
+  drop(stdout);
+  finish_review(approved, change_count, plan_path, output_path)
+}
+
+/// Writes the approved changes and tells how to apply them.
+fn finish_review(
+  mut approved: Vec<PlannedChange>,
+  change_count: usize,
+  plan_path: &Path,
+  output_path: Option<&Path>,
+) -> anyhow::Result<()> {
+  let mut stdout = io::stdout().lock();
+  approved.sort_by(|a, b| a.path.cmp(&b.path));
+  let output_path = output_path.unwrap_or(plan_path);
+  write_plan(&approved, output_path)?;
+
+  writeln!(
+    &mut stdout,
+    "\nChanges approved: {}, rejected: {}",
+    approved.len(),
+    change_count - approved.len()
+  )?;
+  writeln!(
+    &mut stdout,
+    "Apply them with: image-date-fixer apply \"{}\"",
+    output_path.display()
+  )?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod test {
+  use std::path::PathBuf;
+
+  use super::*;
+  use crate::date_extractors::DateConfidence;
+
+  fn change(path: &str, source: Option<&str>) -> PlannedChange {
+    PlannedChange {
+      path: PathBuf::from(path),
+      old_exif_date: None,
+      new_exif_date: source.map(|_| String::from("2019-08-18 00:00:00")),
+      new_exif_offset: None,
+      old_modified_time: Some(String::from("2099-01-01 00:00:00")),
+      new_modified_time: Some(String::from("2019-08-18 00:00:00")),
+      source: source.map(String::from),
+      confidence: source.map(|_| DateConfidence::Day),
+    }
+  }
+
+  #[test]
+  fn review() {
+    let changes = vec![
+      change("/photos/IMG-20190818-WA0001.jpg", Some("filename:whatsapp")),
+      change(
+        "/photos/2019/IMG-20190818-WA0002.jpg",
+        Some("filename:whatsapp"),
+      ),
+      change(
+        "/photos/2019/08/DSC_0001.jpg",
+        Some("folder:folder_hierarchy"),
+      ),
+      change("/photos/notes.txt", None),
+    ];
+    let groups = group_changes(changes, ReviewGrouping::Extractor);
+    assert_eq!(
+      groups.keys().collect::<Vec<_>>(),
+      [
+        "(modified time only)",
+        "filename:whatsapp",
+        "folder:folder_hierarchy"
+      ]
+    );
+
+    // Rejects the first group, lists and approves the second and quits before the third.
+    let mut input = io::Cursor::new("n\nmaybe\nl\ny\nq\n");
+    let mut output = Vec::new();
+    let approved = review_groups(groups, &mut input, &mut output).unwrap();
+    assert_eq!(
+      approved
+        .iter()
+        .map(|change| change.path.to_str().unwrap())
+        .collect::<Vec<_>>(),
+      [
+        "/photos/IMG-20190818-WA0001.jpg",
+        "/photos/2019/IMG-20190818-WA0002.jpg"
+      ]
+    );
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("Please answer y, n, l or q."));
+    assert!(output.contains("EXIF date: none -> 2019-08-18 00:00:00"));
+
+    let groups = group_changes(
+      vec![change("/photos/a.jpg", Some("gps"))],
+      ReviewGrouping::Directory,
+    );
+    assert_eq!(groups.keys().collect::<Vec<_>>(), ["/photos"]);
+  }
+}
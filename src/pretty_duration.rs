@@ -74,3 +74,64 @@ pub fn pretty_duration(duration: Duration) -> String {
 
   result
 }
+
+/// Parses a duration like `90s`, `12h` or `1w 2d` as given on the command line.
+pub fn parse_duration(duration: &str) -> anyhow::Result<Duration> {
+  const UNITS: [(&str, u64); 5] = [
+    ("s", 1),
+    ("m", 60),
+    ("h", 60 * 60),
+    ("d", 24 * 60 * 60),
+    ("w", 7 * 24 * 60 * 60),
+  ];
+
+  let invalid = || anyhow::anyhow!("Invalid duration: {duration}, expected e.g. 30m, 12h or 7d");
+
+  let mut rest = duration.trim();
+  if rest.is_empty() {
+    return Err(invalid());
+  }
+  let mut secs = 0_u64;
+  while !rest.is_empty() {
+    let digits_end = rest
+      .find(|c: char| !c.is_ascii_digit())
+      .ok_or_else(invalid)?;
+    let value = rest[..digits_end].parse::<u64>().map_err(|_| invalid())?;
+    rest = &rest[digits_end..];
+    let unit_end = rest
+      .find(|c: char| !c.is_ascii_alphabetic())
+      .unwrap_or(rest.len());
+    let (_, unit_secs) = UNITS
+      .iter()
+      .find(|(unit, _)| rest[..unit_end].eq_ignore_ascii_case(unit))
+      .ok_or_else(invalid)?;
+    secs = value
+      .checked_mul(*unit_secs)
+      .and_then(|value_secs| secs.checked_add(value_secs))
+      .ok_or_else(invalid)?;
+    rest = rest[unit_end..].trim_start();
+  }
+
+  Ok(Duration::from_secs(secs))
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn durations() {
+    assert_eq!(pretty_duration(Duration::from_secs(90)), "1m 30s");
+    assert_eq!(parse_duration("90s").unwrap(), Duration::from_secs(90));
+    assert_eq!(parse_duration("12h").unwrap(), Duration::from_hours(12));
+    assert_eq!(
+      parse_duration("1w 2d").unwrap(),
+      Duration::from_hours(9 * 24)
+    );
+    assert_eq!(parse_duration("1D12H").unwrap(), Duration::from_hours(36));
+    assert!(parse_duration("").is_err());
+    assert!(parse_duration("12").is_err());
+    assert!(parse_duration("5y").is_err());
+    assert!(parse_duration("d").is_err());
+  }
+}
@@ -0,0 +1,241 @@
+use alloc::sync::Arc;
+use core::{
+  sync::atomic::{AtomicBool, Ordering},
+  time::Duration,
+};
+use std::{
+  io::{self, Write},
+  path::{Path, PathBuf},
+  sync::Mutex,
+  thread::JoinHandle,
+};
+
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use jwalk::WalkDir;
+
+use crate::ProcessState;
+
+/// How often the counters are copied to the progress bar, which redraws itself at most as often.
+const UPDATE_INTERVAL: Duration = Duration::from_millis(200);
+/// Longer current folders are shortened from the start.
+const MAX_FOLDER_WIDTH: usize = 50;
+const COUNTER_TEMPLATE: &str = "{pos} files  {msg}  {prefix}";
+const BAR_TEMPLATE: &str = "[{bar:20}] {pos}/{len} files  {msg}  ETA {eta}  {prefix}";
+
+/// The progress bar while it is shown, so the log can hide it while writing an event.
+static SHOWN_PROGRESS_BAR: Mutex<Option<ProgressBar>> = Mutex::new(None);
+
+/// Writes one log event to stdout, with the progress bar hidden while it is written.
+/// The event is buffered, so the bar is only hidden once per event.
+pub struct LogWriter {
+  buffer: Vec<u8>,
+}
+
+impl Write for LogWriter {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    self.buffer.extend_from_slice(buf);
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    Ok(())
+  }
+}
+
+impl Drop for LogWriter {
+  fn drop(&mut self) {
+    let write_event = || {
+      let mut stdout = io::stdout().lock();
+      let _ = stdout.write_all(&self.buffer);
+      let _ = stdout.flush();
+    };
+    let progress_bar = SHOWN_PROGRESS_BAR
+      .lock()
+      .expect("Progress bar lock should not be poisoned")
+      .clone();
+    match progress_bar {
+      Some(progress_bar) => progress_bar.suspend(write_event),
+      None => write_event(),
+    }
+  }
+}
+
+/// The stdout writer of the log.
+#[must_use]
+pub const fn log_writer() -> LogWriter {
+  LogWriter { buffer: Vec::new() }
+}
+
+/// The progress of a run for `--progress`, next to the counters of `ProcessState`.
+pub struct Progress {
+  progress_bar: ProgressBar,
+}
+
+impl Progress {
+  #[must_use]
+  pub fn new() -> Self {
+    let progress_bar = ProgressBar::with_draw_target(None, ProgressDrawTarget::hidden())
+      .with_style(
+        ProgressStyle::with_template(COUNTER_TEMPLATE)
+          .expect("The progress template should be valid"),
+      );
+    Self { progress_bar }
+  }
+
+  pub fn set_current_folder(&self, folder_path: &Path) {
+    self.progress_bar.set_prefix(shorten_path(
+      &folder_path.display().to_string(),
+      MAX_FOLDER_WIDTH,
+    ));
+  }
+
+  /// Counts the files below the given files and folders, so the progress has a bar and an ETA.
+  pub fn precount(&self, file_paths: &[&PathBuf], skip_hidden_files: bool) {
+    let total_files = file_paths
+      .iter()
+      .map(|file_path| {
+        if file_path.is_dir() {
+          WalkDir::new(file_path)
+            .skip_hidden(skip_hidden_files)
+            .into_iter()
+            .flatten()
+            .filter(|entry| entry.file_type().is_file())
+            .count()
+        } else {
+          1
+        }
+      })
+      .sum::<usize>();
+    self.progress_bar.set_length(total_files as u64);
+    self.progress_bar.set_style(
+      ProgressStyle::with_template(BAR_TEMPLATE)
+        .expect("The progress template should be valid")
+        .progress_chars("#-"),
+    );
+  }
+}
+
+/// The counters shown next to the file count.
+#[derive(Debug, Default, Clone, Copy)]
+struct ProgressCounts {
+  exif_dates_fixed: usize,
+  modified_times_fixed: usize,
+  errors: usize,
+}
+
+impl ProgressCounts {
+  fn message(self) -> String {
+    format!(
+      "EXIF fixed: {}  mtimes fixed: {}  errors: {}",
+      self.exif_dates_fixed, self.modified_times_fixed, self.errors
+    )
+  }
+}
+
+/// Shortens a path from the start to at most `max_width` characters.
+fn shorten_path(path: &str, max_width: usize) -> String {
+  let char_count = path.chars().count();
+  if char_count <= max_width {
+    return path.to_string();
+  }
+  let kept = path
+    .chars()
+    .skip(char_count - max_width + 3)
+    .collect::<String>();
+  format!("...{kept}")
+}
+
+fn update_progress(process_state: &ProcessState, progress: &Progress) {
+  let files_done = process_state.stat_files_checked.load(Ordering::Relaxed)
+    + process_state.stat_files_skipped.load(Ordering::Relaxed);
+  let counts = ProgressCounts {
+    exif_dates_fixed: process_state.stat_exif_updated.load(Ordering::Relaxed)
+      + process_state.stat_exif_overwritten.load(Ordering::Relaxed),
+    modified_times_fixed: process_state
+      .stat_modified_time_updated
+      .load(Ordering::Relaxed),
+    errors: process_state.stat_files_errors.load(Ordering::Relaxed),
+  };
+  progress.progress_bar.set_position(files_done as u64);
+  progress.progress_bar.set_message(counts.message());
+}
+
+/// Shows the progress bar and keeps it up to date in the background until it is finished.
+pub struct ProgressDisplay {
+  progress_bar: ProgressBar,
+  stop: Arc<AtomicBool>,
+  thread: JoinHandle<()>,
+}
+
+impl ProgressDisplay {
+  #[must_use]
+  pub fn start(process_state: Arc<ProcessState>) -> Self {
+    let progress_bar = process_state
+      .progress
+      .as_ref()
+      .map_or_else(ProgressBar::hidden, |progress| {
+        progress.progress_bar.clone()
+      });
+    progress_bar.set_draw_target(ProgressDrawTarget::stdout());
+    *SHOWN_PROGRESS_BAR
+      .lock()
+      .expect("Progress bar lock should not be poisoned") = Some(progress_bar.clone());
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = Arc::clone(&stop);
+    let thread = std::thread::spawn(move || {
+      let Some(progress) = &process_state.progress else {
+        return;
+      };
+      while !thread_stop.load(Ordering::Relaxed) {
+        update_progress(&process_state, progress);
+        std::thread::park_timeout(UPDATE_INTERVAL);
+      }
+    });
+    Self {
+      progress_bar,
+      stop,
+      thread,
+    }
+  }
+
+  /// Stops updating and removes the progress bar, e.g. before the statistics are printed.
+  pub fn finish(self) {
+    self.stop.store(true, Ordering::Relaxed);
+    self.thread.thread().unpark();
+    let _ = self.thread.join();
+    SHOWN_PROGRESS_BAR
+      .lock()
+      .expect("Progress bar lock should not be poisoned")
+      .take();
+    self.progress_bar.finish_and_clear();
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn progress_lines() {
+    let counts = ProgressCounts {
+      exif_dates_fixed: 12,
+      modified_times_fixed: 3,
+      errors: 1,
+    };
+    assert_eq!(
+      counts.message(),
+      "EXIF fixed: 12  mtimes fixed: 3  errors: 1"
+    );
+    assert_eq!(shorten_path("/photos/2019/08/18", 10), "...9/08/18");
+
+    // Hidden until it is shown, so precounting does not draw anything yet.
+    let progress = Progress::new();
+    progress.precount(
+      &[&std::env::temp_dir().join("image-date-fixer-no-such-file")],
+      false,
+    );
+    assert_eq!(progress.progress_bar.length(), Some(1));
+    assert!(progress.progress_bar.is_hidden());
+  }
+}
@@ -0,0 +1,97 @@
+use std::{
+  fs,
+  path::{Path, PathBuf},
+  sync::OnceLock,
+};
+
+use anyhow::Context as _;
+use chrono::NaiveDateTime;
+
+use crate::date_extractors::DateConfidence;
+
+/// Defines the `XMP-idf` namespace with the `DateSource` tag, which exiftool can only write if it knows the namespace.
+const EXIFTOOL_CONFIG: &str = r"%Image::ExifTool::UserDefined = (
+  'Image::ExifTool::XMP::Main' => {
+    idf => {
+      SubDirectory => { TagTable => 'Image::ExifTool::UserDefined::idf' },
+    },
+  },
+);
+%Image::ExifTool::UserDefined::idf = (
+  GROUPS => { 0 => 'XMP', 1 => 'XMP-idf', 2 => 'Image' },
+  NAMESPACE => { 'idf' => 'https://github.com/raldone01/image-date-fixer/ns/1.0/' },
+  WRITABLE => 'string',
+  DateSource => { },
+);
+1;
+";
+
+/// The tag that records how a written date was determined.
+pub const DATE_SOURCE_TAG: &str = "XMP-idf:DateSource";
+
+static EXIFTOOL_CONFIG_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// Writes the exiftool config that defines [`DATE_SOURCE_TAG`], so exiftool processes started from now on can write it.
+pub fn install_provenance_config() -> anyhow::Result<()> {
+  // Concurrent runs write the same contents, so the file can be shared.
+  let config_path = std::env::temp_dir().join("image-date-fixer.ExifTool_config");
+  fs::write(&config_path, EXIFTOOL_CONFIG).with_context(|| {
+    format!(
+      "\"{}\": Failed to write the exiftool config",
+      config_path.display()
+    )
+  })?;
+  let _ = EXIFTOOL_CONFIG_PATH.set(config_path);
+  Ok(())
+}
+
+/// The exiftool config to pass with `-config`, if provenance tags are written.
+#[must_use]
+pub fn provenance_config_path() -> Option<&'static Path> {
+  EXIFTOOL_CONFIG_PATH.get().map(PathBuf::as_path)
+}
+
+/// The value of [`DATE_SOURCE_TAG`], e.g. `filename:whatsapp, confidence=Day, fixedAt=2024-05-01T12:00:00`.
+#[must_use]
+pub fn date_source_value(
+  source: &str,
+  confidence: DateConfidence,
+  fixed_at: NaiveDateTime,
+) -> String {
+  format!(
+    "{source}, confidence={confidence:?}, fixedAt={}",
+    fixed_at.format("%Y-%m-%dT%H:%M:%S")
+  )
+}
+
+/// The confidence recorded in a [`DATE_SOURCE_TAG`] value.
+#[must_use]
+pub fn parse_date_source_confidence(value: &str) -> Option<DateConfidence> {
+  value
+    .split(", ")
+    .find_map(|field| field.strip_prefix("confidence="))?
+    .to_ascii_lowercase()
+    .parse()
+    .ok()
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn date_source() {
+    let fixed_at =
+      NaiveDateTime::parse_from_str("2024-05-01 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+    let value = date_source_value("filename:whatsapp", DateConfidence::Day, fixed_at);
+    assert_eq!(
+      value,
+      "filename:whatsapp, confidence=Day, fixedAt=2024-05-01T12:00:00"
+    );
+    assert_eq!(
+      parse_date_source_confidence(&value),
+      Some(DateConfidence::Day)
+    );
+    assert_eq!(parse_date_source_confidence("filename:whatsapp"), None);
+  }
+}
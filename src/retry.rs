@@ -0,0 +1,112 @@
+use core::{sync::atomic::Ordering, time::Duration};
+use std::{
+  path::{Path, PathBuf},
+  sync::Mutex,
+};
+
+use tracing::{info, warn};
+
+use crate::{ProcessState, errors::ErrorWithFilePath, process_file, report_file_errors};
+
+/// The wait before the first retry, doubled for every further retry, so transient errors like NFS hiccups can clear up.
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Files that failed in the per-file pass with their errors, to be retried with `--retries` before the errors are reported.
+pub struct FailedFiles {
+  failures: Mutex<Vec<(PathBuf, Vec<ErrorWithFilePath>)>>,
+}
+
+impl FailedFiles {
+  #[must_use]
+  pub const fn new() -> Self {
+    Self {
+      failures: Mutex::new(Vec::new()),
+    }
+  }
+
+  pub fn record(&self, file_path: &Path, errors: Vec<ErrorWithFilePath>) {
+    self
+      .failures
+      .lock()
+      .expect("Failed files lock should not be poisoned")
+      .push((file_path.to_path_buf(), errors));
+  }
+
+  fn take(&self) -> Vec<(PathBuf, Vec<ErrorWithFilePath>)> {
+    core::mem::take(
+      &mut *self
+        .failures
+        .lock()
+        .expect("Failed files lock should not be poisoned"),
+    )
+  }
+}
+
+fn retry_delay(attempt: u32) -> Duration {
+  FIRST_RETRY_DELAY.saturating_mul(1 << attempt.saturating_sub(1).min(6))
+}
+
+/// Retries the failed files up to `retries` times and reports the errors of the files that still fail.
+pub fn retry_failed_files(failed_files: &FailedFiles, retries: u32, process_state: &ProcessState) {
+  for attempt in 1..=retries {
+    if process_state.should_exit.load(Ordering::Relaxed) {
+      break;
+    }
+    let failures = failed_files.take();
+    if failures.is_empty() {
+      break;
+    }
+    let delay = retry_delay(attempt);
+    info!(
+      "Retrying {} failed files in {}s (attempt {attempt} of {retries})",
+      failures.len(),
+      delay.as_secs()
+    );
+    std::thread::sleep(delay);
+
+    for (file_path, errors) in failures {
+      // The errors of the files that were not retried are reported as they are.
+      if process_state.should_exit.load(Ordering::Relaxed) {
+        failed_files.record(&file_path, errors);
+        continue;
+      }
+      match process_file(&file_path, process_state) {
+        Ok(()) => {
+          info!(
+            file_path = %file_path.display(),
+            "Processed file on retry",
+          );
+          process_state
+            .stat_files_fixed_by_retry
+            .fetch_add(1, Ordering::Relaxed);
+          if let Some(checkpoint) = &process_state.checkpoint {
+            checkpoint.record(&file_path);
+          }
+        },
+        Err(errors) => {
+          warn!(
+            file_path = %file_path.display(),
+            "Failed to process file again",
+          );
+          failed_files.record(&file_path, errors);
+        },
+      }
+    }
+  }
+
+  for (file_path, errors) in failed_files.take() {
+    report_file_errors(&file_path, &errors, process_state);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn retry_delays() {
+    assert_eq!(retry_delay(1), Duration::from_secs(1));
+    assert_eq!(retry_delay(3), Duration::from_secs(4));
+    assert_eq!(retry_delay(100), Duration::from_secs(64));
+  }
+}
@@ -0,0 +1,401 @@
+use alloc::collections::BTreeMap;
+use core::fmt::Write as _;
+use std::io;
+
+use anyhow::Context as _;
+use ratatui::{
+  DefaultTerminal, Frame,
+  crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+  layout::{Constraint, Layout},
+  style::{Color, Style, Stylize as _},
+  text::{Line, Text},
+  widgets::{Block, Paragraph, Row, Table, TableState},
+};
+
+use crate::{
+  change_plan::PlannedChange,
+  plan_review::{ReviewGrouping, describe_change},
+};
+
+/// The next grouping when cycling through them with `g`.
+const fn next_grouping(grouping: ReviewGrouping) -> ReviewGrouping {
+  match grouping {
+    ReviewGrouping::Extractor => ReviewGrouping::Confidence,
+    ReviewGrouping::Confidence => ReviewGrouping::Directory,
+    ReviewGrouping::Directory => ReviewGrouping::Extractor,
+  }
+}
+
+const fn grouping_name(grouping: ReviewGrouping) -> &'static str {
+  match grouping {
+    ReviewGrouping::Extractor => "extractor",
+    ReviewGrouping::Confidence => "confidence",
+    ReviewGrouping::Directory => "directory",
+  }
+}
+
+/// The keys of the screen, shown at its bottom.
+const HELP: &str = "↑/↓ select  y approve  n reject  Y/N approve/reject all listed  g group by  / filter  q save and quit  Ctrl+C abort";
+
+/// What a key press asks the screen to do next.
+#[derive(Debug, PartialEq, Eq)]
+enum KeyOutcome {
+  Continue,
+  /// Write the approved changes.
+  Save,
+  /// Leave the plan unchanged.
+  Abort,
+}
+
+/// A table of the groups of changes of a plan, whose changes can be approved or rejected group by group.
+/// Decisions are kept per change, so they survive regrouping and filtering.
+struct ReviewScreen {
+  changes: Vec<PlannedChange>,
+  /// `Some(true)` if the change was approved, `Some(false)` if it was rejected.
+  decisions: Vec<Option<bool>>,
+  grouping: ReviewGrouping,
+  /// Only groups whose name contains the filter, ignoring case, are listed.
+  filter: String,
+  editing_filter: bool,
+  /// The listed groups with the indices of their changes.
+  groups: Vec<(String, Vec<usize>)>,
+  table_state: TableState,
+}
+
+impl ReviewScreen {
+  fn new(changes: Vec<PlannedChange>, grouping: ReviewGrouping) -> Self {
+    let mut screen = Self {
+      decisions: vec![None; changes.len()],
+      changes,
+      grouping,
+      filter: String::new(),
+      editing_filter: false,
+      groups: Vec::new(),
+      table_state: TableState::new(),
+    };
+    screen.regroup();
+    screen
+  }
+
+  /// Lists the groups matching the filter again, after the grouping or the filter changed.
+  fn regroup(&mut self) {
+    let filter = self.filter.to_lowercase();
+    let mut groups = BTreeMap::<String, Vec<usize>>::new();
+    for (index, change) in self.changes.iter().enumerate() {
+      let group = self.grouping.group_of(change);
+      if group.to_lowercase().contains(&filter) {
+        groups.entry(group).or_default().push(index);
+      }
+    }
+    self.groups = groups.into_iter().collect();
+    let selected = self
+      .table_state
+      .selected()
+      .unwrap_or_default()
+      .min(self.groups.len().saturating_sub(1));
+    self
+      .table_state
+      .select((!self.groups.is_empty()).then_some(selected));
+  }
+
+  fn decide_selected(&mut self, approve: bool) {
+    let Some((_, indices)) = self
+      .table_state
+      .selected()
+      .and_then(|selected| self.groups.get(selected))
+    else {
+      return;
+    };
+    for &index in indices {
+      self.decisions[index] = Some(approve);
+    }
+    self.table_state.select_next();
+  }
+
+  fn decide_listed(&mut self, approve: bool) {
+    for (_, indices) in &self.groups {
+      for &index in indices {
+        self.decisions[index] = Some(approve);
+      }
+    }
+  }
+
+  fn handle_key(&mut self, key: KeyEvent) -> KeyOutcome {
+    if key.kind != KeyEventKind::Press {
+      return KeyOutcome::Continue;
+    }
+    if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+      return KeyOutcome::Abort;
+    }
+    if self.editing_filter {
+      match key.code {
+        KeyCode::Enter => self.editing_filter = false,
+        KeyCode::Esc => {
+          self.editing_filter = false;
+          self.filter.clear();
+        },
+        KeyCode::Backspace => {
+          self.filter.pop();
+        },
+        KeyCode::Char(c) => self.filter.push(c),
+        _ => return KeyOutcome::Continue,
+      }
+      self.regroup();
+      return KeyOutcome::Continue;
+    }
+    match key.code {
+      KeyCode::Up | KeyCode::Char('k') => self.table_state.select_previous(),
+      KeyCode::Down | KeyCode::Char('j') => self.table_state.select_next(),
+      KeyCode::Char('y') => self.decide_selected(true),
+      KeyCode::Char('n') => self.decide_selected(false),
+      KeyCode::Char('Y') => self.decide_listed(true),
+      KeyCode::Char('N') => self.decide_listed(false),
+      KeyCode::Char('g') => {
+        self.grouping = next_grouping(self.grouping);
+        self.regroup();
+      },
+      KeyCode::Char('/') => self.editing_filter = true,
+      KeyCode::Esc if !self.filter.is_empty() => {
+        self.filter.clear();
+        self.regroup();
+      },
+      KeyCode::Char('q') => return KeyOutcome::Save,
+      _ => {},
+    }
+    KeyOutcome::Continue
+  }
+
+  fn group_status(&self, indices: &[usize]) -> &'static str {
+    let approved = indices
+      .iter()
+      .filter(|&&index| self.decisions[index] == Some(true))
+      .count();
+    let rejected = indices
+      .iter()
+      .filter(|&&index| self.decisions[index] == Some(false))
+      .count();
+    if approved == indices.len() {
+      "approved"
+    } else if rejected == indices.len() {
+      "rejected"
+    } else if approved + rejected == 0 {
+      ""
+    } else {
+      "mixed"
+    }
+  }
+
+  fn render(&mut self, frame: &mut Frame<'_>) {
+    let [table_area, changes_area, help_area] = Layout::vertical([
+      Constraint::Min(5),
+      Constraint::Percentage(40),
+      Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let rows = self
+      .groups
+      .iter()
+      .map(|(group, indices)| {
+        let status = self.group_status(indices);
+        let style = match status {
+          "approved" => Style::new().fg(Color::Green),
+          "rejected" => Style::new().fg(Color::Red),
+          "mixed" => Style::new().fg(Color::Yellow),
+          _ => Style::new(),
+        };
+        Row::new([status.to_string(), indices.len().to_string(), group.clone()]).style(style)
+      })
+      .collect::<Vec<_>>();
+    let mut title = format!(" Planned changes by {} ", grouping_name(self.grouping));
+    if self.editing_filter || !self.filter.is_empty() {
+      let _ = write!(title, "(filter: {}) ", self.filter);
+    }
+    let table = Table::new(
+      rows,
+      [
+        Constraint::Length(8),
+        Constraint::Length(8),
+        Constraint::Fill(1),
+      ],
+    )
+    .header(Row::new(["Status", "Changes", "Group"]).bold())
+    .block(Block::bordered().title(title))
+    .row_highlight_style(Style::new().reversed());
+    frame.render_stateful_widget(table, table_area, &mut self.table_state);
+
+    let changes = self
+      .table_state
+      .selected()
+      .and_then(|selected| self.groups.get(selected))
+      .map(|(_, indices)| {
+        indices
+          .iter()
+          .flat_map(|&index| {
+            describe_change(&self.changes[index])
+              .lines()
+              .map(|line| Line::from(line.to_string()))
+              .collect::<Vec<_>>()
+          })
+          .collect::<Vec<_>>()
+      })
+      .unwrap_or_default();
+    frame.render_widget(
+      Paragraph::new(Text::from(changes)).block(Block::bordered().title(" Changes of the group ")),
+      changes_area,
+    );
+    frame.render_widget(Paragraph::new(HELP).dim(), help_area);
+  }
+
+  /// The approved changes. Undecided changes are rejected.
+  fn into_approved(self) -> Vec<PlannedChange> {
+    self
+      .changes
+      .into_iter()
+      .zip(self.decisions)
+      .filter_map(|(change, decision)| (decision == Some(true)).then_some(change))
+      .collect()
+  }
+}
+
+fn run_screen(terminal: &mut DefaultTerminal, screen: &mut ReviewScreen) -> io::Result<KeyOutcome> {
+  loop {
+    terminal.draw(|frame| screen.render(frame))?;
+    if let Event::Key(key) = event::read()? {
+      match screen.handle_key(key) {
+        KeyOutcome::Continue => {},
+        outcome => return Ok(outcome),
+      }
+    }
+  }
+}
+
+/// Shows the changes of a plan in an interactive table until they are saved or the review is aborted.
+/// Returns the approved changes, or `None` if the review was aborted.
+pub fn review_in_terminal(
+  changes: Vec<PlannedChange>,
+  grouping: ReviewGrouping,
+) -> anyhow::Result<Option<Vec<PlannedChange>>> {
+  let mut screen = ReviewScreen::new(changes, grouping);
+  let mut terminal = ratatui::try_init().context("Failed to set up the terminal")?;
+  let outcome = run_screen(&mut terminal, &mut screen);
+  ratatui::try_restore().context("Failed to restore the terminal")?;
+  match outcome.context("Failed to draw the review screen")? {
+    KeyOutcome::Save => Ok(Some(screen.into_approved())),
+    KeyOutcome::Continue | KeyOutcome::Abort => Ok(None),
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::path::PathBuf;
+
+  use ratatui::{Terminal, backend::TestBackend};
+
+  use super::*;
+  use crate::date_extractors::DateConfidence;
+
+  fn change(path: &str, source: &str) -> PlannedChange {
+    PlannedChange {
+      path: PathBuf::from(path),
+      old_exif_date: None,
+      new_exif_date: Some(String::from("2019-08-18 00:00:00")),
+      new_exif_offset: None,
+      old_modified_time: Some(String::from("2099-01-01 00:00:00")),
+      new_modified_time: Some(String::from("2019-08-18 00:00:00")),
+      source: Some(String::from(source)),
+      confidence: Some(DateConfidence::Day),
+    }
+  }
+
+  fn press(screen: &mut ReviewScreen, keys: &str) -> KeyOutcome {
+    let mut outcome = KeyOutcome::Continue;
+    for c in keys.chars() {
+      let code = match c {
+        '\n' => KeyCode::Enter,
+        '\x1b' => KeyCode::Esc,
+        c => KeyCode::Char(c),
+      };
+      outcome = screen.handle_key(KeyEvent::from(code));
+    }
+    outcome
+  }
+
+  #[test]
+  fn review_screen() {
+    let changes = vec![
+      change("/photos/a/IMG_20190818_000000.jpg", "filename:android"),
+      change("/photos/a/IMG-20190818-WA0001.jpg", "filename:whatsapp"),
+      change("/photos/b/IMG-20190818-WA0002.jpg", "filename:whatsapp"),
+    ];
+
+    // Groups are sorted, so the android group is selected first.
+    let mut screen = ReviewScreen::new(changes.clone(), ReviewGrouping::Extractor);
+    assert_eq!(screen.groups.len(), 2);
+    assert_eq!(press(&mut screen, "ny"), KeyOutcome::Continue);
+    assert_eq!(screen.group_status(&screen.groups[0].1), "rejected");
+    assert_eq!(press(&mut screen, "q"), KeyOutcome::Save);
+    let approved = screen.into_approved();
+    assert_eq!(approved.len(), 2);
+    assert!(
+      approved
+        .iter()
+        .all(|change| change.source.as_deref() == Some("filename:whatsapp"))
+    );
+
+    // Decisions are kept per change when regrouping by directory.
+    let mut screen = ReviewScreen::new(changes.clone(), ReviewGrouping::Extractor);
+    press(&mut screen, "jyg");
+    assert_eq!(screen.grouping, ReviewGrouping::Confidence);
+    press(&mut screen, "g");
+    assert_eq!(
+      screen
+        .groups
+        .iter()
+        .map(|(group, indices)| (group.as_str(), screen.group_status(indices)))
+        .collect::<Vec<_>>(),
+      [("/photos/a", "mixed"), ("/photos/b", "approved")]
+    );
+
+    // Only the groups matching the filter are approved with `Y`.
+    let mut screen = ReviewScreen::new(changes.clone(), ReviewGrouping::Extractor);
+    press(&mut screen, "/WHATS\nY");
+    assert_eq!(screen.groups.len(), 1);
+    press(&mut screen, "\x1b");
+    assert_eq!(screen.groups.len(), 2);
+    assert_eq!(screen.into_approved().len(), 2);
+
+    // Aborting keeps nothing, and undecided changes are rejected.
+    let mut screen = ReviewScreen::new(changes, ReviewGrouping::Extractor);
+    assert_eq!(
+      screen.handle_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+      KeyOutcome::Abort
+    );
+    assert!(screen.into_approved().is_empty());
+  }
+
+  #[test]
+  fn review_screen_rendering() {
+    let mut screen = ReviewScreen::new(
+      vec![change(
+        "/photos/IMG-20190818-WA0001.jpg",
+        "filename:whatsapp",
+      )],
+      ReviewGrouping::Extractor,
+    );
+    press(&mut screen, "y");
+    let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+    terminal.draw(|frame| screen.render(frame)).unwrap();
+    let rendered = terminal
+      .backend()
+      .buffer()
+      .content()
+      .iter()
+      .map(ratatui::buffer::Cell::symbol)
+      .collect::<String>();
+    assert!(rendered.contains("Planned changes by extractor"));
+    assert!(rendered.contains("approved"));
+    assert!(rendered.contains("filename:whatsapp"));
+    assert!(rendered.contains("/photos/IMG-20190818-WA0001.jpg"));
+  }
+}
@@ -0,0 +1,298 @@
+use alloc::collections::BTreeSet;
+use core::time::Duration;
+use std::{
+  fs::{File, TryLockError},
+  io::{self, Write as _},
+  path::{Path, PathBuf},
+};
+
+use anyhow::Context as _;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+/// Name of the lock file in every folder that is processed, so overlapping runs do not race on the same files.
+pub const LOCK_FILE_NAME: &str = ".image-date-fixer.lock";
+
+const WAIT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The run holding a lock, written to the lock file.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct LockOwner {
+  pid: u32,
+  /// `None` if the host name is unknown.
+  host: Option<String>,
+  started: String,
+}
+
+fn host_name() -> Option<String> {
+  std::fs::read_to_string("/proc/sys/kernel/hostname")
+    .ok()
+    .map(|host_name| host_name.trim().to_string())
+}
+
+/// Whether `path` still refers to the opened `file`, and was not removed or replaced in the meantime.
+#[cfg(unix)]
+fn is_same_file(file: &File, path: &Path) -> io::Result<bool> {
+  use std::os::unix::fs::MetadataExt as _;
+
+  let path_metadata = match std::fs::metadata(path) {
+    Ok(path_metadata) => path_metadata,
+    Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+    Err(e) => return Err(e),
+  };
+  let file_metadata = file.metadata()?;
+  Ok(file_metadata.dev() == path_metadata.dev() && file_metadata.ino() == path_metadata.ino())
+}
+
+/// Whether `path` still refers to the opened `file`, and was not removed in the meantime.
+#[cfg(not(unix))]
+fn is_same_file(_file: &File, path: &Path) -> io::Result<bool> {
+  path.try_exists()
+}
+
+/// An advisory lock on a folder, removed when dropped.
+/// The lock file is locked by the operating system, so the lock is released even if the run is killed.
+pub struct RunLock {
+  lock_path: PathBuf,
+  /// Holds the lock until it is closed.
+  _file: File,
+}
+
+impl RunLock {
+  /// Returns `None` if another run holds the lock.
+  /// A lock file that is not locked, e.g. because its run was killed, is taken over.
+  fn try_lock(lock_path: &Path) -> io::Result<Option<Self>> {
+    loop {
+      let mut file = File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(lock_path)?;
+      match file.try_lock() {
+        Ok(()) => {},
+        Err(TryLockError::WouldBlock) => return Ok(None),
+        Err(TryLockError::Error(e)) => return Err(e),
+      }
+      // The previous holder removes the lock file before releasing the lock,
+      // so the locked file may no longer be the lock file of the folder.
+      if !is_same_file(&file, lock_path)? {
+        continue;
+      }
+
+      let owner = LockOwner {
+        pid: std::process::id(),
+        host: host_name(),
+        started: Local::now().to_rfc3339(),
+      };
+      file.set_len(0)?;
+      file.write_all(serde_json::to_string(&owner)?.as_bytes())?;
+      return Ok(Some(Self {
+        lock_path: lock_path.to_path_buf(),
+        _file: file,
+      }));
+    }
+  }
+
+  /// Locks `folder_path`. If another run holds the lock of the folder or of one of its ancestors,
+  /// which also covers this folder, waits until it is released if `wait` is set or fails otherwise.
+  pub fn acquire(folder_path: &Path, wait: bool) -> anyhow::Result<Self> {
+    let lock_path = folder_path.join(LOCK_FILE_NAME);
+    let mut logged_waiting = false;
+    loop {
+      let held_lock_path = if let Some(ancestor_lock_path) = held_ancestor_lock(folder_path)? {
+        ancestor_lock_path
+      } else {
+        if let Some(run_lock) = Self::try_lock(&lock_path)
+          .with_context(|| format!("\"{}\": Failed to lock the lock file", lock_path.display()))?
+        {
+          return Ok(run_lock);
+        }
+        lock_path.clone()
+      };
+
+      let holder = lock_holder(&held_lock_path);
+      let locked_folder_path = held_lock_path.parent().unwrap_or(folder_path);
+      if !wait {
+        anyhow::bail!(
+          "\"{}\": {holder} is processing this folder. Pass --wait to wait for it to finish",
+          locked_folder_path.display()
+        );
+      }
+      if !logged_waiting {
+        info!(
+          file_path = %locked_folder_path.display(),
+          "{holder} is processing this folder, waiting for it to finish",
+        );
+        logged_waiting = true;
+      }
+      std::thread::sleep(WAIT_INTERVAL);
+    }
+  }
+}
+
+/// Describes the run holding the lock file at `lock_path`.
+fn lock_holder(lock_path: &Path) -> String {
+  // The owner may not be written yet, or not be readable while it is locked on Windows.
+  let owner = std::fs::read_to_string(lock_path)
+    .ok()
+    .and_then(|content| serde_json::from_str::<LockOwner>(&content).ok());
+  owner.map_or_else(
+    || String::from("Another run"),
+    |owner| {
+      let started = DateTime::parse_from_rfc3339(&owner.started).map_or(owner.started, |started| {
+        started.format("%Y-%m-%d %H:%M:%S").to_string()
+      });
+      format!("Another run (pid {}, started {started})", owner.pid)
+    },
+  )
+}
+
+/// The lock file of the closest ancestor of `folder_path` that is locked by another run.
+fn held_ancestor_lock(folder_path: &Path) -> io::Result<Option<PathBuf>> {
+  for ancestor_path in folder_path.ancestors().skip(1) {
+    let lock_path = ancestor_path.join(LOCK_FILE_NAME);
+    let file = match File::open(&lock_path) {
+      Ok(file) => file,
+      Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+      // Lock files that can not be read, e.g. of another user, can not be checked either.
+      Err(e) if e.kind() == io::ErrorKind::PermissionDenied => continue,
+      Err(e) => return Err(e),
+    };
+    // The lock is only tested, it is released again when the file is closed.
+    match file.try_lock() {
+      Ok(()) => {},
+      Err(TryLockError::WouldBlock) => return Ok(Some(lock_path)),
+      Err(TryLockError::Error(e)) => return Err(e),
+    }
+  }
+  Ok(None)
+}
+
+/// The folders to lock for processing `paths`: folders themselves and the parent folders of files,
+/// canonicalized so that different spellings of a folder share its lock.
+/// Folders inside another folder to lock are covered by its lock.
+pub fn lock_folder_paths<'a>(paths: impl IntoIterator<Item = &'a Path>) -> BTreeSet<PathBuf> {
+  let folder_paths = paths
+    .into_iter()
+    .filter_map(|path| {
+      let folder_path = if path.is_dir() {
+        path
+      } else {
+        path
+          .parent()
+          .filter(|parent_path| !parent_path.as_os_str().is_empty())
+          .unwrap_or_else(|| Path::new("."))
+      };
+      // Files that do not exist fail when they are processed.
+      folder_path.canonicalize().ok()
+    })
+    .collect::<BTreeSet<_>>();
+  folder_paths
+    .iter()
+    .filter(|folder_path| {
+      !folder_path
+        .ancestors()
+        .skip(1)
+        .any(|ancestor_path| folder_paths.contains(ancestor_path))
+    })
+    .cloned()
+    .collect()
+}
+
+impl Drop for RunLock {
+  fn drop(&mut self) {
+    // Removed while it is still locked, the file is closed and unlocked afterwards.
+    if let Err(e) = std::fs::remove_file(&self.lock_path) {
+      debug!(
+        file_path = %self.lock_path.display(),
+        "Failed to remove the lock file: {e}",
+      );
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn run_locks() {
+    let folder_path = std::env::temp_dir().join(format!(
+      "image-date-fixer-run-lock-test-{}",
+      std::process::id()
+    ));
+    std::fs::create_dir_all(&folder_path).unwrap();
+    let lock_path = folder_path.join(LOCK_FILE_NAME);
+
+    let run_lock = RunLock::acquire(&folder_path, false).unwrap();
+    assert!(RunLock::acquire(&folder_path, false).is_err());
+    drop(run_lock);
+    assert!(!lock_path.exists());
+
+    // The lock file of a run that was killed is no longer locked, so it is taken over.
+    let killed_owner = LockOwner {
+      pid: u32::MAX,
+      host: host_name(),
+      started: Local::now().to_rfc3339(),
+    };
+    std::fs::write(&lock_path, serde_json::to_string(&killed_owner).unwrap()).unwrap();
+    let run_lock = RunLock::acquire(&folder_path, false).unwrap();
+    let owner =
+      serde_json::from_str::<LockOwner>(&std::fs::read_to_string(&lock_path).unwrap()).unwrap();
+    assert_eq!(owner.pid, std::process::id());
+    assert!(RunLock::acquire(&folder_path, false).is_err());
+    drop(run_lock);
+
+    // A lock file removed by its holder after it was opened is detected, so the new lock file is locked instead.
+    let removed_file = File::create(&lock_path).unwrap();
+    assert!(is_same_file(&removed_file, &lock_path).unwrap());
+    std::fs::remove_file(&lock_path).unwrap();
+    assert!(!is_same_file(&removed_file, &lock_path).unwrap());
+    File::create(&lock_path).unwrap();
+    assert!(!is_same_file(&removed_file, &lock_path).unwrap());
+
+    std::fs::remove_dir_all(&folder_path).unwrap();
+  }
+
+  #[test]
+  fn nested_run_locks() {
+    let folder_path = std::env::temp_dir()
+      .join(format!(
+        "image-date-fixer-nested-run-lock-test-{}",
+        std::process::id()
+      ))
+      .join("photos");
+    let nested_folder_path = folder_path.join("2019");
+    std::fs::create_dir_all(&nested_folder_path).unwrap();
+    let file_path = nested_folder_path.join("IMG_20190701_120000.jpg");
+    File::create(&file_path).unwrap();
+
+    // Files lock their folder, and folders inside another folder are covered by its lock.
+    let other_spelling = nested_folder_path.join("..");
+    assert_eq!(
+      lock_folder_paths([file_path.as_path()]),
+      BTreeSet::from([nested_folder_path.canonicalize().unwrap()])
+    );
+    assert_eq!(
+      lock_folder_paths([file_path.as_path(), other_spelling.as_path()]),
+      BTreeSet::from([folder_path.canonicalize().unwrap()])
+    );
+
+    // A run on a nested folder or file is kept out by the lock of the folder it is in.
+    let run_lock = RunLock::acquire(&folder_path.canonicalize().unwrap(), false).unwrap();
+    assert_eq!(
+      held_ancestor_lock(&nested_folder_path).unwrap(),
+      Some(folder_path.join(LOCK_FILE_NAME))
+    );
+    for nested_path in lock_folder_paths([file_path.as_path()]) {
+      assert!(RunLock::acquire(&nested_path, false).is_err());
+    }
+    drop(run_lock);
+    let nested_run_lock = RunLock::acquire(&nested_folder_path, false).unwrap();
+    drop(nested_run_lock);
+
+    std::fs::remove_dir_all(folder_path.parent().unwrap()).unwrap();
+  }
+}
@@ -0,0 +1,300 @@
+use alloc::collections::BTreeMap;
+use core::sync::atomic::Ordering;
+use std::{
+  path::{Path, PathBuf},
+  sync::{LazyLock, Mutex},
+};
+
+use anyhow::Context as _;
+use chrono::{Datelike as _, NaiveDateTime};
+use regex::Regex;
+use tracing::{error, info};
+
+use crate::{
+  ProcessState,
+  date_extractors::{ConfidentNaiveDateTime, DateConfidence},
+  errors::ErrorWithFilePath,
+  event_stream::{FileEvent, emit_event},
+  exiftool::{set_exif_date, wrap_with_exiftool_repair},
+  modified_time_from_exif_date,
+  xmp_sidecar::date_write_target,
+};
+
+struct SiblingFile {
+  file_path: PathBuf,
+  sequence_number: u64,
+  date: Option<NaiveDateTime>,
+}
+
+/// Collects the dates of sequence-numbered files (e.g. `DSC_0001.jpg`) during the per-file pass,
+/// so that date-less files can later be interpolated from their neighbors.
+pub struct SiblingDates {
+  /// Files grouped by their folder and the non-numeric prefix of their file name.
+  sequences: Mutex<BTreeMap<(PathBuf, String), Vec<SiblingFile>>>,
+}
+
+impl SiblingDates {
+  #[must_use]
+  pub const fn new() -> Self {
+    Self {
+      sequences: Mutex::new(BTreeMap::new()),
+    }
+  }
+
+  /// Records the resolved date of a file, or its lack of one.
+  /// Files without a sequence number are ignored.
+  pub fn record(&self, file_path: &Path, date: Option<NaiveDateTime>) {
+    let Some((prefix, sequence_number)) = parse_sequence_number(file_path) else {
+      return;
+    };
+    let folder_path = file_path
+      .parent()
+      .map(Path::to_path_buf)
+      .unwrap_or_default();
+
+    self
+      .sequences
+      .lock()
+      .expect("Sibling dates lock should not be poisoned")
+      .entry((folder_path, prefix))
+      .or_default()
+      .push(SiblingFile {
+        file_path: file_path.to_path_buf(),
+        sequence_number,
+        date,
+      });
+  }
+
+  /// Interpolates a date for every recorded file without a date that is enclosed by dated siblings.
+  /// This consumes the recorded files.
+  #[must_use]
+  pub fn interpolate(&self) -> Vec<(PathBuf, ConfidentNaiveDateTime)> {
+    let mut sequences = core::mem::take(
+      &mut *self
+        .sequences
+        .lock()
+        .expect("Sibling dates lock should not be poisoned"),
+    );
+
+    let mut interpolated = Vec::new();
+    for files in sequences.values_mut() {
+      files.sort_by_key(|file| file.sequence_number);
+
+      for (index, file) in files.iter().enumerate() {
+        if file.date.is_some() {
+          continue;
+        }
+        let lower = files[..index]
+          .iter()
+          .rev()
+          .find_map(|file| Some((file.sequence_number, file.date?)));
+        let upper = files[index + 1..]
+          .iter()
+          .find_map(|file| Some((file.sequence_number, file.date?)));
+
+        if let (Some(lower), Some(upper)) = (lower, upper)
+          && let Some(date) = interpolate_between(lower, upper, file.sequence_number)
+        {
+          interpolated.push((file.file_path.clone(), date));
+        }
+      }
+    }
+    interpolated
+  }
+}
+
+/// Splits a file stem like `DSC_0042` into its prefix (`DSC_`) and sequence number (`42`).
+fn parse_sequence_number(file_path: &Path) -> Option<(String, u64)> {
+  static RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(?P<prefix>[A-Za-z_\-\s]*)(?P<number>\d{3,})$").unwrap());
+
+  let file_stem = file_path.file_stem()?.to_str()?;
+  let captures = RE.captures(file_stem)?;
+  let prefix = captures.name("prefix")?.as_str().to_string();
+  let sequence_number = captures.name("number")?.as_str().parse().ok()?;
+  Some((prefix, sequence_number))
+}
+
+/// Linearly interpolates the date of `sequence_number` between two dated siblings.
+/// The confidence reflects how close the siblings are to each other and never exceeds a day.
+fn interpolate_between(
+  (lower_number, lower_date): (u64, NaiveDateTime),
+  (upper_number, upper_date): (u64, NaiveDateTime),
+  sequence_number: u64,
+) -> Option<ConfidentNaiveDateTime> {
+  if upper_date < lower_date || upper_number <= lower_number {
+    return None;
+  }
+
+  let confidence = if lower_date.date() == upper_date.date() {
+    DateConfidence::Day
+  } else if lower_date.year() == upper_date.year() && lower_date.month() == upper_date.month() {
+    DateConfidence::Month
+  } else if lower_date.year() == upper_date.year() {
+    DateConfidence::Year
+  } else {
+    // The siblings are too far apart to say anything useful.
+    return None;
+  };
+
+  let span = upper_date - lower_date;
+  let steps = i32::try_from(upper_number - lower_number).ok()?;
+  let step = i32::try_from(sequence_number - lower_number).ok()?;
+  let date = lower_date + span / steps * step;
+  Some(ConfidentNaiveDateTime::new(date, confidence))
+}
+
+/// Runs the interpolation pass and writes the interpolated EXIF dates.
+pub fn apply_sibling_interpolation(sibling_dates: &SiblingDates, process_state: &ProcessState) {
+  for (file_path, date) in sibling_dates.interpolate() {
+    if process_state.should_exit.load(Ordering::Relaxed) {
+      return;
+    }
+
+    if !process_state.passes_min_confidence(&file_path, &date) {
+      continue;
+    }
+
+    let file_path = date_write_target(&file_path, process_state.options.sidecar_for_raw);
+
+    info!(
+      file_path = %file_path.display(),
+      "Setting EXIF date to date interpolated from sibling files {}",
+      date,
+    );
+
+    let set_exif_date_result = wrap_with_exiftool_repair(
+      &file_path,
+      process_state.options.repair_exif_errors,
+      process_state.options.dry_run,
+      || {
+        set_exif_date(
+          &file_path,
+          &date,
+          "sibling-interpolation",
+          process_state.options.exif_write_options(),
+        )
+      },
+    )
+    .with_context(|| format!("Failed to set EXIF date to interpolated date {date}"));
+
+    match set_exif_date_result {
+      Ok(()) => {
+        emit_event(&FileEvent::guessed(
+          &file_path,
+          &date.date,
+          "sibling-interpolation",
+        ));
+        process_state
+          .stat_exif_interpolated
+          .fetch_add(1, Ordering::Relaxed);
+        if let Some(folder_dates) = &process_state.folder_dates
+          && let Some(modified_time) =
+            modified_time_from_exif_date(&date, process_state.options.default_utc_offset)
+        {
+          folder_dates.record(&file_path, modified_time);
+        }
+      },
+      Err(e) => {
+        let e = ErrorWithFilePath::new(&file_path, e);
+        error!(
+          file_path = %file_path.display(),
+          source = ?e,
+          "Failed to process file",
+        );
+        process_state
+          .stat_files_errors
+          .fetch_add(1, Ordering::Relaxed);
+        process_state.record_error(&e.file_path, &e.source);
+      },
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn date(date: &str) -> NaiveDateTime {
+    NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S").unwrap()
+  }
+
+  #[test]
+  fn sequence_numbers() {
+    assert_eq!(
+      parse_sequence_number(Path::new("/a/DSC_0042.JPG")),
+      Some(("DSC_".to_string(), 42))
+    );
+    assert_eq!(
+      parse_sequence_number(Path::new("/a/P1010001.JPG")),
+      Some(("P".to_string(), 1_010_001))
+    );
+    assert_eq!(
+      parse_sequence_number(Path::new("/a/DSC_0042_edit.JPG")),
+      None
+    );
+  }
+
+  #[test]
+  fn interpolation() {
+    let sibling_dates = SiblingDates::new();
+    sibling_dates.record(
+      Path::new("/a/DSC_0001.JPG"),
+      Some(date("2019-07-01 10:00:00")),
+    );
+    sibling_dates.record(Path::new("/a/DSC_0002.JPG"), None);
+    sibling_dates.record(Path::new("/a/DSC_0003.JPG"), None);
+    sibling_dates.record(Path::new("/a/DSC_0004.JPG"), None);
+    sibling_dates.record(
+      Path::new("/a/DSC_0005.JPG"),
+      Some(date("2019-07-01 14:00:00")),
+    );
+    // Not enclosed by dated siblings.
+    sibling_dates.record(Path::new("/a/DSC_0006.JPG"), None);
+    // Different folder.
+    sibling_dates.record(Path::new("/b/DSC_0003.JPG"), None);
+
+    let mut interpolated = sibling_dates.interpolate();
+    interpolated.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(
+      interpolated,
+      vec![
+        (
+          PathBuf::from("/a/DSC_0002.JPG"),
+          ConfidentNaiveDateTime::new(date("2019-07-01 11:00:00"), DateConfidence::Day)
+        ),
+        (
+          PathBuf::from("/a/DSC_0003.JPG"),
+          ConfidentNaiveDateTime::new(date("2019-07-01 12:00:00"), DateConfidence::Day)
+        ),
+        (
+          PathBuf::from("/a/DSC_0004.JPG"),
+          ConfidentNaiveDateTime::new(date("2019-07-01 13:00:00"), DateConfidence::Day)
+        ),
+      ]
+    );
+  }
+
+  #[test]
+  fn interpolation_confidence() {
+    let interpolate = |lower: &str, upper: &str| {
+      interpolate_between((1, date(lower)), (3, date(upper)), 2).map(|date| date.confidence)
+    };
+    assert_eq!(
+      interpolate("2019-07-01 10:00:00", "2019-07-20 10:00:00"),
+      Some(DateConfidence::Month)
+    );
+    assert_eq!(
+      interpolate("2019-07-01 10:00:00", "2019-09-01 10:00:00"),
+      Some(DateConfidence::Year)
+    );
+    assert_eq!(
+      interpolate("2019-07-01 10:00:00", "2020-07-01 10:00:00"),
+      None
+    );
+    assert_eq!(
+      interpolate("2019-07-02 10:00:00", "2019-07-01 10:00:00"),
+      None
+    );
+  }
+}
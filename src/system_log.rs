@@ -0,0 +1,183 @@
+use core::{fmt::Write as _, str::FromStr};
+
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::{Layer, layer::Context};
+
+use crate::logging::PathVisitor;
+
+const SYSLOG_IDENTIFIER: &str = "image-date-fixer";
+
+/// Where the log is written to.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum LogTarget {
+  #[default]
+  Stdout,
+  /// The native protocol of the systemd journal, with the file path as the `FILE_PATH` field.
+  Journald,
+  /// The local syslog daemon at `/dev/log`.
+  Syslog,
+}
+
+impl FromStr for LogTarget {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "stdout" => Ok(Self::Stdout),
+      "journald" => Ok(Self::Journald),
+      "syslog" => Ok(Self::Syslog),
+      _ => Err(anyhow::anyhow!("Unknown log target: {s}")),
+    }
+  }
+}
+
+/// The syslog severity of a level, which journald uses as the priority as well.
+const fn syslog_severity(level: Level) -> u8 {
+  match level {
+    Level::ERROR => 3,
+    Level::WARN => 4,
+    Level::INFO => 6,
+    Level::DEBUG | Level::TRACE => 7,
+  }
+}
+
+/// The message with the file path prefix and the other fields, like the pretty log format without the timestamp and level.
+fn message_line(visitor: &PathVisitor) -> String {
+  let mut line = String::new();
+  if let Some(path) = &visitor.file_path {
+    let _ = write!(line, "\"{path}\": ");
+  }
+  if let Some(message) = &visitor.message {
+    line.push_str(message);
+  }
+  line.push_str(&visitor.others);
+  line
+}
+
+/// Appends a field in the journal export format, which needs a length prefix for values with line breaks.
+fn push_journal_field(datagram: &mut Vec<u8>, name: &str, value: &str) {
+  datagram.extend_from_slice(name.as_bytes());
+  if value.contains('\n') {
+    datagram.push(b'\n');
+    datagram.extend_from_slice(&(value.len() as u64).to_le_bytes());
+  } else {
+    datagram.push(b'=');
+  }
+  datagram.extend_from_slice(value.as_bytes());
+  datagram.push(b'\n');
+}
+
+fn journal_datagram(level: Level, target: &str, visitor: &PathVisitor) -> Vec<u8> {
+  let mut datagram = Vec::new();
+  push_journal_field(
+    &mut datagram,
+    "PRIORITY",
+    &syslog_severity(level).to_string(),
+  );
+  push_journal_field(&mut datagram, "SYSLOG_IDENTIFIER", SYSLOG_IDENTIFIER);
+  push_journal_field(&mut datagram, "TARGET", target);
+  if let Some(path) = &visitor.file_path {
+    push_journal_field(&mut datagram, "FILE_PATH", path);
+  }
+  push_journal_field(&mut datagram, "MESSAGE", &message_line(visitor));
+  datagram
+}
+
+/// An RFC 3164 message with the user facility, the syslog daemon adds the timestamp and host name.
+fn syslog_datagram(level: Level, visitor: &PathVisitor) -> Vec<u8> {
+  const USER_FACILITY: u8 = 1;
+  format!(
+    "<{}>{SYSLOG_IDENTIFIER}[{}]: {}",
+    USER_FACILITY * 8 + syslog_severity(level),
+    std::process::id(),
+    message_line(visitor)
+  )
+  .into_bytes()
+}
+
+/// Sends every event to journald or syslog instead of stdout.
+pub struct SystemLogLayer {
+  target: LogTarget,
+  #[cfg(unix)]
+  socket: std::os::unix::net::UnixDatagram,
+}
+
+impl SystemLogLayer {
+  #[cfg(unix)]
+  pub fn connect(target: LogTarget) -> anyhow::Result<Self> {
+    use anyhow::Context as _;
+
+    let socket_path = match target {
+      LogTarget::Stdout => anyhow::bail!("stdout is not a system log"),
+      LogTarget::Journald => "/run/systemd/journal/socket",
+      LogTarget::Syslog => "/dev/log",
+    };
+    let socket = std::os::unix::net::UnixDatagram::unbound()?;
+    socket
+      .connect(socket_path)
+      .with_context(|| format!("\"{socket_path}\": Failed to connect to the system log"))?;
+    Ok(Self { target, socket })
+  }
+
+  #[cfg(not(unix))]
+  pub fn connect(_target: LogTarget) -> anyhow::Result<Self> {
+    anyhow::bail!("Logging to journald or syslog is only supported on Unix")
+  }
+}
+
+impl<S: Subscriber> Layer<S> for SystemLogLayer {
+  fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+    let mut visitor = PathVisitor::default();
+    event.record(&mut visitor);
+    let level = *event.metadata().level();
+    let datagram = match self.target {
+      LogTarget::Journald => journal_datagram(level, event.metadata().target(), &visitor),
+      LogTarget::Stdout | LogTarget::Syslog => syslog_datagram(level, &visitor),
+    };
+    // There is nowhere to report a failure to log.
+    #[cfg(unix)]
+    let _ = self.socket.send(&datagram);
+    #[cfg(not(unix))]
+    drop(datagram);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn datagrams() {
+    let visitor = PathVisitor {
+      file_path: Some(String::from("/photos/IMG_1.jpg")),
+      message: Some(String::from("Failed to process file")),
+      others: String::from(" source=\"Broken\nJPEG\""),
+    };
+    let mut expected = b"PRIORITY=3\n\
+      SYSLOG_IDENTIFIER=image-date-fixer\n\
+      TARGET=image_date_fixer\n\
+      FILE_PATH=/photos/IMG_1.jpg\n\
+      MESSAGE\n"
+      .to_vec();
+    let message = "\"/photos/IMG_1.jpg\": Failed to process file source=\"Broken\nJPEG\"";
+    expected.extend_from_slice(&(message.len() as u64).to_le_bytes());
+    expected.extend_from_slice(message.as_bytes());
+    expected.push(b'\n');
+    assert_eq!(
+      journal_datagram(Level::ERROR, "image_date_fixer", &visitor),
+      expected
+    );
+
+    let visitor = PathVisitor {
+      message: Some(String::from("Processing top level directory")),
+      ..Default::default()
+    };
+    assert_eq!(
+      String::from_utf8(syslog_datagram(Level::INFO, &visitor)).unwrap(),
+      format!(
+        "<14>image-date-fixer[{}]: Processing top level directory",
+        std::process::id()
+      )
+    );
+  }
+}
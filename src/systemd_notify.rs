@@ -0,0 +1,175 @@
+use alloc::sync::Arc;
+use core::{
+  sync::atomic::{AtomicBool, Ordering},
+  time::Duration,
+};
+use std::{os::unix::net::UnixDatagram, thread::JoinHandle, time::Instant};
+
+use tracing::debug;
+
+use crate::{ProcessState, pretty_duration::pretty_duration};
+
+/// How often the status is sent if systemd does not expect a more frequent watchdog ping.
+const STATUS_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Connects to the socket systemd passes in `NOTIFY_SOCKET` to services of `Type=notify`.
+fn connect(socket_path: &str) -> std::io::Result<UnixDatagram> {
+  let socket = UnixDatagram::unbound()?;
+  if let Some(abstract_name) = socket_path.strip_prefix('@') {
+    #[cfg(target_os = "linux")]
+    {
+      use std::os::{linux::net::SocketAddrExt as _, unix::net::SocketAddr};
+
+      socket.connect_addr(&SocketAddr::from_abstract_name(abstract_name)?)?;
+    }
+    #[cfg(not(target_os = "linux"))]
+    return Err(std::io::Error::new(
+      std::io::ErrorKind::Unsupported,
+      format!("Abstract socket {abstract_name} is only supported on Linux"),
+    ));
+  } else {
+    socket.connect(socket_path)?;
+  }
+  Ok(socket)
+}
+
+/// Half of `WATCHDOG_USEC`, as recommended by systemd, if the watchdog is enabled for this process.
+fn watchdog_interval() -> Option<Duration> {
+  if let Ok(watchdog_pid) = std::env::var("WATCHDOG_PID")
+    && watchdog_pid.parse() != Ok(std::process::id())
+  {
+    return None;
+  }
+  let watchdog_usec = std::env::var("WATCHDOG_USEC").ok()?.parse::<u64>().ok()?;
+  Some(Duration::from_micros(watchdog_usec) / 2)
+}
+
+fn status_line(process_state: &ProcessState) -> String {
+  format!(
+    "{} files checked, {} EXIF dates and {} modified times fixed, {} files with errors",
+    process_state.stat_files_checked.load(Ordering::Relaxed),
+    process_state.stat_exif_updated.load(Ordering::Relaxed)
+      + process_state.stat_exif_overwritten.load(Ordering::Relaxed),
+    process_state
+      .stat_modified_time_updated
+      .load(Ordering::Relaxed),
+    process_state.stat_files_errors.load(Ordering::Relaxed),
+  )
+}
+
+/// Whether the run checked more files since the last watchdog ping,
+/// so systemd restarts a run that hangs instead of one that is merely alive.
+struct WatchdogProgress {
+  last_files_checked: usize,
+}
+
+impl WatchdogProgress {
+  const fn advanced(&mut self, files_checked: usize) -> bool {
+    let advanced = files_checked != self.last_files_checked;
+    self.last_files_checked = files_checked;
+    advanced
+  }
+}
+
+/// Tells systemd that the service is ready and sends the progress as its status and watchdog pings,
+/// when running as a service of `Type=notify`.
+pub struct SystemdNotifier {
+  socket: Arc<UnixDatagram>,
+  stop: Arc<AtomicBool>,
+  thread: JoinHandle<()>,
+  start: Instant,
+  process_state: Arc<ProcessState>,
+}
+
+impl SystemdNotifier {
+  /// `None` if not started by systemd with `NOTIFY_SOCKET`.
+  #[must_use]
+  pub fn start(process_state: &Arc<ProcessState>) -> Option<Self> {
+    let socket_path = std::env::var("NOTIFY_SOCKET").ok()?;
+    let socket = match connect(&socket_path) {
+      Ok(socket) => Arc::new(socket),
+      Err(e) => {
+        debug!("Failed to connect to the systemd notify socket \"{socket_path}\": {e}");
+        return None;
+      },
+    };
+    let _ = socket.send(b"READY=1");
+
+    let watchdog_interval = watchdog_interval();
+    let watchdog = watchdog_interval.is_some();
+    let interval =
+      watchdog_interval.map_or(STATUS_INTERVAL, |interval| interval.min(STATUS_INTERVAL));
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = Arc::clone(&stop);
+    let thread_socket = Arc::clone(&socket);
+    let thread_process_state = Arc::clone(process_state);
+    let thread = std::thread::spawn(move || {
+      let mut watchdog_progress = WatchdogProgress {
+        last_files_checked: 0,
+      };
+      while !thread_stop.load(Ordering::Relaxed) {
+        let mut message = format!("STATUS={}", status_line(&thread_process_state));
+        let files_checked = thread_process_state
+          .stat_files_checked
+          .load(Ordering::Relaxed);
+        if watchdog && watchdog_progress.advanced(files_checked) {
+          message.push_str("\nWATCHDOG=1");
+        }
+        let _ = thread_socket.send(message.as_bytes());
+        std::thread::park_timeout(interval);
+      }
+    });
+    Some(Self {
+      socket,
+      stop,
+      thread,
+      start: Instant::now(),
+      process_state: Arc::clone(process_state),
+    })
+  }
+
+  /// Sends the final status along with the time the run took.
+  pub fn finish(self) {
+    self.stop.store(true, Ordering::Relaxed);
+    self.thread.thread().unpark();
+    let _ = self.thread.join();
+    let message = format!(
+      "STOPPING=1\nSTATUS=Finished in {}: {}",
+      pretty_duration(self.start.elapsed()),
+      status_line(&self.process_state)
+    );
+    let _ = self.socket.send(message.as_bytes());
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn notify_socket() {
+    let socket_path = std::env::temp_dir().join(format!(
+      "image-date-fixer-notify-test-{}.sock",
+      std::process::id()
+    ));
+    let _ = std::fs::remove_file(&socket_path);
+    let systemd_socket = UnixDatagram::bind(&socket_path).unwrap();
+    let socket = connect(socket_path.to_str().unwrap()).unwrap();
+    socket.send(b"READY=1").unwrap();
+    let mut buffer = [0; 16];
+    let length = systemd_socket.recv(&mut buffer).unwrap();
+    assert_eq!(&buffer[..length], b"READY=1");
+    std::fs::remove_file(&socket_path).unwrap();
+  }
+
+  #[test]
+  fn watchdog_pings_follow_progress() {
+    let mut watchdog_progress = WatchdogProgress {
+      last_files_checked: 0,
+    };
+    assert!(!watchdog_progress.advanced(0));
+    assert!(watchdog_progress.advanced(3));
+    assert!(!watchdog_progress.advanced(3));
+    assert!(watchdog_progress.advanced(4));
+  }
+}
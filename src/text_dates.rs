@@ -0,0 +1,60 @@
+use std::path::PathBuf;
+
+use chrono::NaiveDateTime;
+
+use crate::date_extractors::{ConfidentNaiveDateTime, DateExtractorRegistry};
+
+/// Finds a date mentioned in free text, e.g. `Scanned 2003-05-12`, with the file name extractors.
+/// The extractors match at the start of a file name, so they are tried on a file named after the text from every number on.
+/// The file has no parent folder, so folder dates do not leak in.
+/// Returns the most confident date and the name of its extractor, the earliest mention wins ties.
+#[must_use]
+pub fn extract_date_from_text<'a>(
+  registry: &'a DateExtractorRegistry,
+  text: &str,
+  current_time: NaiveDateTime,
+) -> Option<(&'a str, ConfidentNaiveDateTime)> {
+  text
+    .char_indices()
+    .filter(|&(index, character)| {
+      character.is_ascii_digit()
+        && !text[..index]
+          .chars()
+          .next_back()
+          .is_some_and(char::is_alphanumeric)
+    })
+    .filter_map(|(index, _)| {
+      let file_name = format!("{}.txt", text[index..].replace(['/', '\\'], " "));
+      registry.extract_with_name(&PathBuf::from(&file_name), &file_name, current_time, false)
+    })
+    .rev()
+    .max_by_key(|(_, date)| date.confidence)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::date_extractors::{DateConfidence, DateRangePolicy};
+
+  #[test]
+  fn text_dates() {
+    let registry = DateExtractorRegistry::with_builtin_extractors(DateRangePolicy::default());
+    let current_time =
+      NaiveDateTime::parse_from_str("2024-01-01 00:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+    let extract =
+      |text: &str| extract_date_from_text(&registry, text, current_time).map(|(_, date)| date);
+
+    let date = extract("Scanned 2003-05-12").unwrap();
+    assert_eq!(date.date.format("%Y-%m-%d").to_string(), "2003-05-12");
+    assert_eq!(date.confidence, DateConfidence::Day);
+
+    let date = extract("Page 3, scanned 2003-05-12 14:30:00 at 600 dpi").unwrap();
+    assert_eq!(
+      date.date.format("%Y-%m-%d %H:%M:%S").to_string(),
+      "2003-05-12 14:30:00"
+    );
+
+    assert!(extract("SCAN0001").is_none());
+    assert!(extract("").is_none());
+  }
+}
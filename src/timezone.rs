@@ -0,0 +1,162 @@
+#[cfg(feature = "gps-timezone")]
+use std::sync::LazyLock;
+
+use chrono::{FixedOffset, NaiveDateTime, TimeDelta};
+#[cfg(feature = "gps-timezone")]
+use chrono::{Offset as _, TimeZone as _};
+
+/// Time zones are whole quarter hours away from UTC.
+const QUARTER_HOUR_SECONDS: i64 = 15 * 60;
+/// Camera clocks drift, so the difference to the GPS time may be off by this much.
+const MAX_CLOCK_DRIFT: TimeDelta = TimeDelta::minutes(5);
+/// The largest UTC offset in use (UTC+14:00).
+const MAX_UTC_OFFSET: TimeDelta = TimeDelta::hours(14);
+
+/// Parses a UTC offset such as `+02:00`, `-0530`, `Z` or `UTC`.
+pub fn parse_utc_offset(offset: &str) -> anyhow::Result<FixedOffset> {
+  if offset.eq_ignore_ascii_case("z") || offset.eq_ignore_ascii_case("utc") {
+    return Ok(FixedOffset::east_opt(0).expect("UTC is a valid offset"));
+  }
+  offset
+    .parse::<FixedOffset>()
+    .map_err(|_| anyhow::anyhow!("Invalid UTC offset: {offset}"))
+}
+
+/// The UTC offset of `local_date`, derived from the GPS date of the same moment in UTC.
+/// None if the difference is no plausible time zone.
+#[must_use]
+pub fn utc_offset_from_gps(
+  local_date: &NaiveDateTime,
+  gps_date: &NaiveDateTime,
+) -> Option<FixedOffset> {
+  let difference = *local_date - *gps_date;
+  if difference.abs() > MAX_UTC_OFFSET + MAX_CLOCK_DRIFT {
+    return None;
+  }
+  let quarter_hours =
+    (difference.num_seconds() + QUARTER_HOUR_SECONDS / 2).div_euclid(QUARTER_HOUR_SECONDS);
+  let offset = TimeDelta::seconds(quarter_hours * QUARTER_HOUR_SECONDS);
+  if (difference - offset).abs() > MAX_CLOCK_DRIFT || offset.abs() > MAX_UTC_OFFSET {
+    return None;
+  }
+  FixedOffset::east_opt(i32::try_from(offset.num_seconds()).ok()?)
+}
+
+/// A GPS position as latitude and longitude in degrees.
+pub type GpsPosition = (f64, f64);
+
+#[cfg(feature = "gps-timezone")]
+static TIMEZONE_FINDER: LazyLock<tzf_rs::DefaultFinder> = LazyLock::new(tzf_rs::DefaultFinder::new);
+
+/// The time zone at `position`, looked up in the embedded time zone boundaries.
+#[cfg(feature = "gps-timezone")]
+#[must_use]
+fn timezone_at((latitude, longitude): GpsPosition) -> Option<chrono_tz::Tz> {
+  TIMEZONE_FINDER
+    .get_tz_name(longitude, latitude)
+    .parse()
+    .ok()
+}
+
+/// The UTC offset of the wall time `local_date` at `position`.
+/// None if the time zone is unknown or the wall time is ambiguous, e.g. during the daylight saving time switch.
+#[cfg(feature = "gps-timezone")]
+#[must_use]
+pub fn utc_offset_at(position: GpsPosition, local_date: &NaiveDateTime) -> Option<FixedOffset> {
+  let timezone = timezone_at(position)?;
+  let date = timezone.from_local_datetime(local_date).single()?;
+  Some(date.offset().fix())
+}
+
+/// The wall time and UTC offset at `position` of the UTC date `utc_date`.
+#[cfg(feature = "gps-timezone")]
+#[must_use]
+pub fn local_date_at(
+  position: GpsPosition,
+  utc_date: &NaiveDateTime,
+) -> Option<(NaiveDateTime, FixedOffset)> {
+  let date = timezone_at(position)?.from_utc_datetime(utc_date);
+  Some((date.naive_local(), date.offset().fix()))
+}
+
+#[cfg(not(feature = "gps-timezone"))]
+#[must_use]
+pub const fn utc_offset_at(
+  _position: GpsPosition,
+  _local_date: &NaiveDateTime,
+) -> Option<FixedOffset> {
+  None
+}
+
+#[cfg(not(feature = "gps-timezone"))]
+#[must_use]
+pub const fn local_date_at(
+  _position: GpsPosition,
+  _utc_date: &NaiveDateTime,
+) -> Option<(NaiveDateTime, FixedOffset)> {
+  None
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn date(date: &str) -> NaiveDateTime {
+    NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S").unwrap()
+  }
+
+  #[test]
+  fn utc_offsets() {
+    assert_eq!(
+      parse_utc_offset("+02:00").unwrap(),
+      FixedOffset::east_opt(7200).unwrap()
+    );
+    assert_eq!(
+      parse_utc_offset("-0530").unwrap(),
+      FixedOffset::west_opt(19800).unwrap()
+    );
+    assert_eq!(
+      parse_utc_offset("Z").unwrap(),
+      FixedOffset::east_opt(0).unwrap()
+    );
+    assert!(parse_utc_offset("Europe/Vienna").is_err());
+
+    let gps_date = date("2019-08-18 12:30:00");
+    assert_eq!(
+      utc_offset_from_gps(&date("2019-08-18 14:31:10"), &gps_date),
+      FixedOffset::east_opt(7200)
+    );
+    assert_eq!(
+      utc_offset_from_gps(&date("2019-08-18 07:00:00"), &gps_date),
+      FixedOffset::west_opt(19800)
+    );
+    assert_eq!(
+      utc_offset_from_gps(&date("2019-08-18 14:38:00"), &gps_date),
+      None
+    );
+    assert_eq!(
+      utc_offset_from_gps(&date("2019-08-19 12:30:00"), &gps_date),
+      None
+    );
+  }
+  #[cfg(feature = "gps-timezone")]
+  #[test]
+  fn gps_timezones() {
+    let vienna = (48.2082, 16.3738);
+    assert_eq!(
+      utc_offset_at(vienna, &date("2019-08-18 14:30:00")),
+      FixedOffset::east_opt(7200)
+    );
+    assert_eq!(
+      utc_offset_at(vienna, &date("2019-12-24 18:00:00")),
+      FixedOffset::east_opt(3600)
+    );
+    assert_eq!(
+      local_date_at(vienna, &date("2019-08-18 12:30:00")),
+      Some((
+        date("2019-08-18 14:30:00"),
+        FixedOffset::east_opt(7200).unwrap()
+      ))
+    );
+  }
+}
@@ -0,0 +1,460 @@
+use alloc::collections::BTreeMap;
+use std::{
+  fs::File,
+  io::{self, BufRead as _, BufReader, Write as _},
+  path::{Path, PathBuf},
+  sync::{Mutex, OnceLock},
+};
+
+use anyhow::Context as _;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use crate::{
+  errors::ErrorWithFilePath,
+  exiftool::{restore_date_time_original, restore_exif_tags},
+  provenance::{DATE_SOURCE_TAG, install_provenance_config},
+};
+
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+/// Modified times are recorded with nanoseconds, so they are restored exactly.
+const MODIFIED_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f";
+
+/// A line of the undo journal, recorded before a file is changed.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "kebab-case")]
+enum JournalEntry {
+  /// The `DateTimeOriginal` of the file is about to be written natively.
+  ExifDate {
+    path: PathBuf,
+    /// `None` if the file had no `DateTimeOriginal`.
+    date_time_original: Option<String>,
+    /// In UTC.
+    modified_time: Option<String>,
+  },
+  /// The date tags of the file are about to be written with exiftool.
+  ExifTags {
+    path: PathBuf,
+    /// The previous values of all tags that are written, as printed by exiftool.
+    /// `None` if the file did not have the tag.
+    tags: BTreeMap<String, Option<String>>,
+    /// In UTC.
+    modified_time: Option<String>,
+  },
+  /// The modified time of the file is about to be set.
+  ModifiedTime {
+    path: PathBuf,
+    /// In UTC.
+    modified_time: String,
+  },
+}
+
+/// An append-only file recording the previous date tags and modified time of every changed file,
+/// so that a run can be reverted with the `undo` subcommand.
+pub struct UndoJournal {
+  file: Mutex<File>,
+}
+
+static UNDO_JOURNAL: OnceLock<UndoJournal> = OnceLock::new();
+
+/// Records the previous dates of all files that are changed from now on to the journal at `journal_path`.
+/// Entries are appended if the journal already exists.
+pub fn install_undo_journal(journal_path: &Path) -> anyhow::Result<()> {
+  let file = File::options()
+    .create(true)
+    .append(true)
+    .open(journal_path)
+    .with_context(|| {
+      format!(
+        "\"{}\": Failed to open the undo journal",
+        journal_path.display()
+      )
+    })?;
+  let _ = UNDO_JOURNAL.set(UndoJournal {
+    file: Mutex::new(file),
+  });
+  Ok(())
+}
+
+/// The undo journal, if enabled.
+#[must_use]
+pub fn undo_journal() -> Option<&'static UndoJournal> {
+  UNDO_JOURNAL.get()
+}
+
+/// The current modified time of a file in UTC.
+fn modified_time(file_path: &Path) -> Option<NaiveDateTime> {
+  let modified_time = std::fs::metadata(file_path).ok()?.modified().ok()?;
+  Some(DateTime::<Utc>::from(modified_time).naive_utc())
+}
+
+impl UndoJournal {
+  /// Must be called before the EXIF date of `file_path` is written natively.
+  #[cfg(feature = "native-jpeg-write")]
+  pub fn record_exif_date(
+    &self,
+    file_path: &Path,
+    date_time_original: Option<NaiveDateTime>,
+  ) -> Result<(), ErrorWithFilePath> {
+    self.record_exif_date_at(file_path, date_time_original, modified_time(file_path))
+  }
+
+  /// Records the EXIF date and modified time `file_path` had before it was written.
+  #[cfg(feature = "native-jpeg-write")]
+  pub fn record_exif_date_at(
+    &self,
+    file_path: &Path,
+    date_time_original: Option<NaiveDateTime>,
+    modified_time: Option<NaiveDateTime>,
+  ) -> Result<(), ErrorWithFilePath> {
+    self.record(
+      file_path,
+      &JournalEntry::ExifDate {
+        path: file_path.to_path_buf(),
+        date_time_original: date_time_original.map(|date| date.format(DATE_FORMAT).to_string()),
+        modified_time: modified_time
+          .map(|modified_time| modified_time.format(MODIFIED_TIME_FORMAT).to_string()),
+      },
+    )
+  }
+
+  /// Must be called before the date tags of `file_path` are written with exiftool.
+  pub fn record_exif_tags(
+    &self,
+    file_path: &Path,
+    tags: BTreeMap<String, Option<String>>,
+  ) -> Result<(), ErrorWithFilePath> {
+    self.record_exif_tags_at(file_path, tags, modified_time(file_path))
+  }
+
+  /// Records the date tags and modified time `file_path` had before it was written.
+  pub fn record_exif_tags_at(
+    &self,
+    file_path: &Path,
+    tags: BTreeMap<String, Option<String>>,
+    modified_time: Option<NaiveDateTime>,
+  ) -> Result<(), ErrorWithFilePath> {
+    self.record(
+      file_path,
+      &JournalEntry::ExifTags {
+        path: file_path.to_path_buf(),
+        tags,
+        modified_time: modified_time
+          .map(|modified_time| modified_time.format(MODIFIED_TIME_FORMAT).to_string()),
+      },
+    )
+  }
+
+  /// Must be called before the modified time of `file_path` is set.
+  pub fn record_modified_time(&self, file_path: &Path) -> Result<(), ErrorWithFilePath> {
+    let Some(modified_time) = modified_time(file_path) else {
+      return Ok(());
+    };
+    self.record(
+      file_path,
+      &JournalEntry::ModifiedTime {
+        path: file_path.to_path_buf(),
+        modified_time: modified_time.format(MODIFIED_TIME_FORMAT).to_string(),
+      },
+    )
+  }
+
+  fn record(&self, file_path: &Path, entry: &JournalEntry) -> Result<(), ErrorWithFilePath> {
+    let mut line = serde_json::to_string(entry)
+      .context("Failed to serialize the undo journal entry")
+      .map_err(ErrorWithFilePath::from_source(file_path))?;
+    line.push('\n');
+    // The whole line is written at once, so concurrent entries never interleave.
+    self
+      .file
+      .lock()
+      .expect("Undo journal lock should not be poisoned")
+      .write_all(line.as_bytes())
+      .context("Failed to write to the undo journal")
+      .map_err(ErrorWithFilePath::from_source(file_path))
+  }
+}
+
+fn parse_modified_time(modified_time: &str) -> anyhow::Result<NaiveDateTime> {
+  NaiveDateTime::parse_from_str(modified_time, MODIFIED_TIME_FORMAT)
+    .with_context(|| format!("Invalid modified time: {modified_time}"))
+}
+
+fn restore_modified_time(
+  file_path: &Path,
+  modified_time: &str,
+  dry_run: bool,
+) -> anyhow::Result<()> {
+  let modified_time = parse_modified_time(modified_time)?;
+  if dry_run {
+    info!(
+      file_path = %file_path.display(),
+      "Would restore the modified time {}",
+      modified_time.format(DATE_FORMAT),
+    );
+    return Ok(());
+  }
+  File::options()
+    .write(true)
+    .open(file_path)
+    .context("Failed to open file")?
+    .set_modified(DateTime::<Utc>::from_naive_utc_and_offset(modified_time, Utc).into())
+    .context("Failed to set modified time")
+}
+
+fn undo_entry(entry: &JournalEntry, dry_run: bool) -> Result<(), ErrorWithFilePath> {
+  match entry {
+    JournalEntry::ExifDate {
+      path,
+      date_time_original,
+      modified_time,
+    } => {
+      let date_time_original = date_time_original
+        .as_deref()
+        .map(|date| NaiveDateTime::parse_from_str(date, DATE_FORMAT))
+        .transpose()
+        .context("Invalid DateTimeOriginal")
+        .map_err(ErrorWithFilePath::from_source(path))?;
+      restore_date_time_original(path, date_time_original.as_ref(), dry_run)?;
+      if let Some(modified_time) = modified_time {
+        restore_modified_time(path, modified_time, dry_run)
+          .map_err(ErrorWithFilePath::from_source(path))?;
+      }
+      Ok(())
+    },
+    JournalEntry::ExifTags {
+      path,
+      tags,
+      modified_time,
+    } => {
+      restore_exif_tags(path, tags, dry_run)?;
+      if let Some(modified_time) = modified_time {
+        restore_modified_time(path, modified_time, dry_run)
+          .map_err(ErrorWithFilePath::from_source(path))?;
+      }
+      Ok(())
+    },
+    JournalEntry::ModifiedTime {
+      path,
+      modified_time,
+    } => restore_modified_time(path, modified_time, dry_run)
+      .map_err(ErrorWithFilePath::from_source(path)),
+  }
+}
+
+fn read_journal(journal_path: &Path) -> anyhow::Result<Vec<JournalEntry>> {
+  let journal = File::open(journal_path).with_context(|| {
+    format!(
+      "\"{}\": Failed to open the undo journal",
+      journal_path.display()
+    )
+  })?;
+  let mut entries = Vec::new();
+  for (index, line) in BufReader::new(journal).lines().enumerate() {
+    let line = line.context("Failed to read the undo journal")?;
+    if line.trim().is_empty() {
+      continue;
+    }
+    entries.push(
+      serde_json::from_str::<JournalEntry>(&line).with_context(|| {
+        format!(
+          "\"{}\": Invalid undo journal entry in line {}",
+          journal_path.display(),
+          index + 1
+        )
+      })?,
+    );
+  }
+  Ok(entries)
+}
+
+/// Implements the `undo` subcommand.
+/// The entries are reverted newest first, so files changed more than once end up with their oldest recorded dates.
+/// Returns whether all entries were reverted.
+pub fn run_undo_command(journal_path: &Path, dry_run: bool) -> anyhow::Result<bool> {
+  let entries = read_journal(journal_path)?;
+  // exiftool can only write the provenance tag if it knows its namespace.
+  if entries.iter().any(|entry| {
+    matches!(entry, JournalEntry::ExifTags { tags, .. } if tags.contains_key(DATE_SOURCE_TAG))
+  }) {
+    install_provenance_config()?;
+  }
+
+  let mut reverted = 0_usize;
+  let mut failed = 0_usize;
+  for entry in entries.iter().rev() {
+    match undo_entry(entry, dry_run) {
+      Ok(()) => reverted += 1,
+      Err(e) => {
+        e.log_error();
+        failed += 1;
+      },
+    }
+  }
+
+  let mut stdout = io::stdout().lock();
+  writeln!(&mut stdout, "Journal entries reverted: {reverted}")?;
+  if failed > 0 {
+    error!("Failed to revert {failed} journal entries. Check the logs for more details.");
+  }
+  Ok(failed == 0)
+}
+
+#[cfg(test)]
+mod test {
+  use alloc::borrow::Cow;
+
+  use chrono::FixedOffset;
+
+  use super::*;
+  use crate::{
+    date_extractors::{ConfidentNaiveDateTime, DateConfidence},
+    exiftool::{ExifWriteOptions, date_write_args, restore_args, written_tags},
+  };
+
+  #[test]
+  fn journal_entries() {
+    let entry = JournalEntry::ExifDate {
+      path: PathBuf::from("/photos/IMG_1234.JPG"),
+      date_time_original: None,
+      modified_time: Some(String::from("2024-05-01 10:00:00.123456789")),
+    };
+    let line = serde_json::to_string(&entry).unwrap();
+    assert_eq!(
+      line,
+      r#"{"change":"exif-date","path":"/photos/IMG_1234.JPG","date_time_original":null,"modified_time":"2024-05-01 10:00:00.123456789"}"#
+    );
+    assert_eq!(serde_json::from_str::<JournalEntry>(&line).unwrap(), entry);
+
+    assert_eq!(
+      parse_modified_time("2024-05-01 10:00:00.123456789")
+        .unwrap()
+        .and_utc()
+        .timestamp_subsec_nanos(),
+      123_456_789
+    );
+    assert!(parse_modified_time("2024-05-01 10:00:00").is_ok());
+  }
+
+  /// Records the tags that writing a date to `file_path` changes, and checks that undoing restores every one of them.
+  fn assert_undo_restores_written_tags(file_path: &Path, options: ExifWriteOptions) {
+    let date = ConfidentNaiveDateTime::new(
+      NaiveDateTime::parse_from_str("2019-08-18 14:30:00.250", "%Y-%m-%d %H:%M:%S%.3f").unwrap(),
+      DateConfidence::Millisecond,
+    )
+    .with_offset(FixedOffset::east_opt(7200).unwrap());
+    let written_tags = written_tags(&date_write_args(file_path, &date, "test", options));
+    // Every other tag was missing before.
+    let previous_values = written_tags
+      .iter()
+      .enumerate()
+      .map(|(index, tag)| {
+        let value = (index % 2 == 0).then(|| format!("2001:02:03 04:05:{index:02}"));
+        (tag.clone(), value)
+      })
+      .collect::<BTreeMap<_, _>>();
+
+    let journal_path = std::env::temp_dir().join(format!(
+      "image-date-fixer-undo-{}-test-{}",
+      file_path.extension().unwrap().display(),
+      std::process::id()
+    ));
+    let journal = UndoJournal {
+      file: Mutex::new(File::create(&journal_path).unwrap()),
+    };
+    journal
+      .record_exif_tags_at(file_path, previous_values.clone(), None)
+      .unwrap();
+    let entries = read_journal(&journal_path).unwrap();
+    std::fs::remove_file(&journal_path).unwrap();
+    let [JournalEntry::ExifTags { path, tags, .. }] = entries.as_slice() else {
+      panic!("Expected a single exif-tags entry, got {entries:?}");
+    };
+    assert_eq!(path, file_path);
+    assert_eq!(tags, &previous_values);
+
+    let args = restore_args(path, tags);
+    for tag in &written_tags {
+      let restore_arg = format!(
+        "-{tag}={}",
+        previous_values[tag].as_deref().unwrap_or_default()
+      );
+      assert!(
+        args.contains(&Cow::Owned(restore_arg.clone())),
+        "{restore_arg} is missing from {args:?}"
+      );
+    }
+  }
+
+  #[test]
+  fn undo_video() {
+    let file_path = Path::new("/videos/VID_20190818_143000.mp4");
+    let options = ExifWriteOptions {
+      write_all_dates: true,
+      ..Default::default()
+    };
+    assert_undo_restores_written_tags(file_path, options);
+    let written_tags = written_tags(&date_write_args(
+      file_path,
+      &ConfidentNaiveDateTime::new(NaiveDateTime::default(), DateConfidence::Second),
+      "test",
+      options,
+    ));
+    assert_eq!(
+      written_tags,
+      [
+        "QuickTime:CreateDate",
+        "QuickTime:MediaCreateDate",
+        "QuickTime:ModifyDate",
+        "QuickTime:TrackCreateDate",
+        "XMP:DateCreated"
+      ]
+    );
+    let args = restore_args(
+      file_path,
+      &BTreeMap::from([(String::from("QuickTime:CreateDate"), None)]),
+    );
+    assert_eq!(&args[2..4], ["-api", "QuickTimeUTC"]);
+  }
+
+  #[test]
+  fn undo_write_all_dates() {
+    let options = ExifWriteOptions {
+      write_all_dates: true,
+      write_iptc: true,
+      write_provenance: true,
+      ..Default::default()
+    };
+    assert_undo_restores_written_tags(Path::new("/photos/IMG_20190818_143000.jpg"), options);
+    assert_undo_restores_written_tags(Path::new("/photos/IMG_20190818_143000.webp"), options);
+
+    let written_tags = written_tags(&date_write_args(
+      Path::new("/photos/IMG_20190818_143000.jpg"),
+      &ConfidentNaiveDateTime::new(NaiveDateTime::default(), DateConfidence::Millisecond)
+        .with_offset(FixedOffset::east_opt(0).unwrap()),
+      "test",
+      options,
+    ));
+    for tag in [
+      "DateTimeOriginal",
+      "CreateDate",
+      "ModifyDate",
+      "XMP:DateCreated",
+      "SubSecTimeOriginal",
+      "SubSecTimeDigitized",
+      "SubSecTime",
+      "OffsetTimeOriginal",
+      "OffsetTimeDigitized",
+      "OffsetTime",
+      "IPTC:DateCreated",
+      "IPTC:TimeCreated",
+      DATE_SOURCE_TAG,
+    ] {
+      assert!(
+        written_tags.iter().any(|written_tag| written_tag == tag),
+        "{tag}"
+      );
+    }
+  }
+}
@@ -0,0 +1,93 @@
+use core::fmt::Write as _;
+use std::{
+  path::{Path, PathBuf},
+  sync::Mutex,
+};
+
+use anyhow::Context as _;
+
+use crate::change_report::write_atomically;
+
+/// File names with more digits than this likely contain a date that no extractor recognizes.
+pub const DATE_LIKE_DIGIT_COUNT: usize = 4;
+
+/// Collects the media files with neither an EXIF date nor a guessable date for `--unresolved-out`,
+/// so that they can be renamed manually.
+pub struct UnresolvedFiles {
+  files: Mutex<Vec<(PathBuf, usize)>>,
+}
+
+impl UnresolvedFiles {
+  #[must_use]
+  pub const fn new() -> Self {
+    Self {
+      files: Mutex::new(Vec::new()),
+    }
+  }
+
+  pub fn record(&self, file_path: &Path, digit_count_in_file_name: usize) {
+    self
+      .files
+      .lock()
+      .expect("Unresolved files lock should not be poisoned")
+      .push((file_path.to_path_buf(), digit_count_in_file_name));
+  }
+
+  #[must_use]
+  pub fn len(&self) -> usize {
+    self
+      .files
+      .lock()
+      .expect("Unresolved files lock should not be poisoned")
+      .len()
+  }
+
+  fn contents(&self) -> anyhow::Result<String> {
+    let mut files = self
+      .files
+      .lock()
+      .expect("Unresolved files lock should not be poisoned");
+    files.sort();
+
+    let mut contents = String::from("file\tdigits in file name\thint\n");
+    for (file_path, digit_count) in files.iter() {
+      let hint = if *digit_count > DATE_LIKE_DIGIT_COUNT {
+        "the file name may contain an unrecognized date"
+      } else {
+        ""
+      };
+      writeln!(contents, "{}\t{digit_count}\t{hint}", file_path.display())?;
+    }
+    drop(files);
+    Ok(contents)
+  }
+
+  /// Writes the files as tab separated values sorted by file path.
+  pub fn write(&self, output_path: &Path) -> anyhow::Result<()> {
+    write_atomically(output_path, &self.contents()?).with_context(|| {
+      format!(
+        "\"{}\": Failed to write the unresolved files",
+        output_path.display()
+      )
+    })
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn unresolved_files() {
+    let unresolved_files = UnresolvedFiles::new();
+    unresolved_files.record(Path::new("/photos/scan.jpg"), 0);
+    unresolved_files.record(Path::new("/photos/DCIM_20190818.jpg"), 8);
+    assert_eq!(unresolved_files.len(), 2);
+    assert_eq!(
+      unresolved_files.contents().unwrap(),
+      "file\tdigits in file name\thint\n\
+       /photos/DCIM_20190818.jpg\t8\tthe file name may contain an unrecognized date\n\
+       /photos/scan.jpg\t0\t\n"
+    );
+  }
+}
@@ -0,0 +1,223 @@
+use core::fmt::Write as _;
+use std::{
+  path::{Path, PathBuf},
+  sync::Mutex,
+};
+
+use anyhow::Context as _;
+use chrono::{NaiveDateTime, TimeDelta};
+use tracing::info;
+
+use crate::{corroboration::is_corroborated, date_extractors::ConfidentNaiveDateTime};
+
+/// Two dates of a file that disagree by more than the tolerance.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Mismatch {
+  FileNameExif,
+  FileNameModifiedTime,
+  ExifModifiedTime,
+}
+
+impl Mismatch {
+  #[must_use]
+  pub const fn as_str(self) -> &'static str {
+    match self {
+      Self::FileNameExif => "file name/EXIF",
+      Self::FileNameModifiedTime => "file name/modified time",
+      Self::ExifModifiedTime => "EXIF/modified time",
+    }
+  }
+}
+
+/// The dates of a file, all in UTC.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FileDates {
+  /// The date derived from the file name or its folders.
+  pub file_name_date: Option<ConfidentNaiveDateTime>,
+  pub exif_date: Option<ConfidentNaiveDateTime>,
+  pub modified_time: Option<NaiveDateTime>,
+}
+
+impl FileDates {
+  /// The pairs of dates that disagree by more than `tolerance`, beyond the time span covered by their confidence.
+  #[must_use]
+  fn mismatches(&self, tolerance: TimeDelta) -> Vec<Mismatch> {
+    let mut mismatches = Vec::new();
+    if let (Some(file_name_date), Some(exif_date)) = (self.file_name_date, self.exif_date) {
+      // The less precise date covers a time span, which the more precise one must lie in.
+      let (guess, date) = if file_name_date.confidence <= exif_date.confidence {
+        (file_name_date, exif_date)
+      } else {
+        (exif_date, file_name_date)
+      };
+      if !is_corroborated(&guess, &date.date, tolerance) {
+        mismatches.push(Mismatch::FileNameExif);
+      }
+    }
+    if let Some(modified_time) = self.modified_time {
+      if let Some(file_name_date) = self.file_name_date
+        && !is_corroborated(&file_name_date, &modified_time, tolerance)
+      {
+        mismatches.push(Mismatch::FileNameModifiedTime);
+      }
+      if let Some(exif_date) = self.exif_date
+        && !is_corroborated(&exif_date, &modified_time, tolerance)
+      {
+        mismatches.push(Mismatch::ExifModifiedTime);
+      }
+    }
+    mismatches
+  }
+}
+
+struct VerificationEntry {
+  file_path: PathBuf,
+  mismatches: Vec<Mismatch>,
+  dates: FileDates,
+}
+
+/// Collects the files whose file name date, EXIF date and modified time disagree, found by the `verify` subcommand.
+pub struct Verification {
+  tolerance: TimeDelta,
+  entries: Mutex<Vec<VerificationEntry>>,
+}
+
+impl Verification {
+  #[must_use]
+  pub const fn new(tolerance: TimeDelta) -> Self {
+    Self {
+      tolerance,
+      entries: Mutex::new(Vec::new()),
+    }
+  }
+
+  /// Records the file if any of its dates disagree.
+  pub fn check(&self, file_path: &Path, dates: FileDates) {
+    let mismatches = dates.mismatches(self.tolerance);
+    if mismatches.is_empty() {
+      return;
+    }
+    info!(
+      file_path = %file_path.display(),
+      "Dates disagree: {}",
+      mismatches
+        .iter()
+        .map(|mismatch| mismatch.as_str())
+        .collect::<Vec<_>>()
+        .join(", "),
+    );
+    self
+      .entries
+      .lock()
+      .expect("Verification lock should not be poisoned")
+      .push(VerificationEntry {
+        file_path: file_path.to_path_buf(),
+        mismatches,
+        dates,
+      });
+  }
+
+  #[must_use]
+  pub fn len(&self) -> usize {
+    self
+      .entries
+      .lock()
+      .expect("Verification lock should not be poisoned")
+      .len()
+  }
+
+  /// Writes the files with disagreeing dates as tab separated values sorted by file path.
+  pub fn write(&self, report_path: &Path) -> anyhow::Result<()> {
+    let mut entries = self
+      .entries
+      .lock()
+      .expect("Verification lock should not be poisoned");
+    entries.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+
+    let format_date = |date: Option<NaiveDateTime>| {
+      date
+        .map(|date| date.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default()
+    };
+    let mut contents = String::from(
+      "file\tmismatches\tfile name date (UTC)\tconfidence\tEXIF date (UTC)\tmodified time (UTC)\n",
+    );
+    for entry in entries.iter() {
+      writeln!(
+        contents,
+        "{}\t{}\t{}\t{}\t{}\t{}",
+        entry.file_path.display(),
+        entry
+          .mismatches
+          .iter()
+          .map(|mismatch| mismatch.as_str())
+          .collect::<Vec<_>>()
+          .join(", "),
+        format_date(entry.dates.file_name_date.map(|date| date.date)),
+        entry
+          .dates
+          .file_name_date
+          .map(|date| format!("{:?}", date.confidence))
+          .unwrap_or_default(),
+        format_date(entry.dates.exif_date.map(|date| date.date)),
+        format_date(entry.dates.modified_time),
+      )?;
+    }
+    drop(entries);
+
+    std::fs::write(report_path, contents).with_context(|| {
+      format!(
+        "\"{}\": Failed to write the verification report",
+        report_path.display()
+      )
+    })
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::date_extractors::DateConfidence;
+
+  fn date(date: &str) -> NaiveDateTime {
+    NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S").unwrap()
+  }
+
+  #[test]
+  fn mismatches() {
+    let file_name_date =
+      ConfidentNaiveDateTime::new(date("2019-08-18 00:00:00"), DateConfidence::Day);
+    let dates = FileDates {
+      file_name_date: Some(file_name_date),
+      exif_date: Some(ConfidentNaiveDateTime::new(
+        date("2019-08-18 14:30:00"),
+        DateConfidence::Second,
+      )),
+      modified_time: Some(date("2019-08-18 14:30:05")),
+    };
+    assert_eq!(dates.mismatches(TimeDelta::minutes(1)), []);
+
+    let dates = FileDates {
+      exif_date: Some(ConfidentNaiveDateTime::new(
+        date("2023-01-01 10:00:00"),
+        DateConfidence::Second,
+      )),
+      ..dates
+    };
+    assert_eq!(
+      dates.mismatches(TimeDelta::days(1)),
+      [Mismatch::FileNameExif, Mismatch::ExifModifiedTime]
+    );
+
+    let dates = FileDates {
+      file_name_date: Some(file_name_date),
+      exif_date: None,
+      modified_time: Some(date("2019-08-21 00:00:00")),
+    };
+    assert_eq!(
+      dates.mismatches(TimeDelta::days(1)),
+      [Mismatch::FileNameModifiedTime]
+    );
+    assert_eq!(dates.mismatches(TimeDelta::days(2)), []);
+  }
+}
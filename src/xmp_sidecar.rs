@@ -0,0 +1,199 @@
+use std::{
+  path::{Path, PathBuf},
+  sync::LazyLock,
+};
+
+use anyhow::Context as _;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use regex::Regex;
+
+use crate::{
+  date_extractors::{ConfidentNaiveDateTime, DateConfidence},
+  errors::ErrorWithFilePath,
+  file_pairing::is_raw_file,
+};
+
+/// The XMP tags that carry the capture date, in order of preference.
+const XMP_DATE_TAGS: [&str; 3] = [
+  "exif:DateTimeOriginal",
+  "photoshop:DateCreated",
+  "xmp:CreateDate",
+];
+
+/// Finds the XMP sidecar of a file.
+/// Both the `photo.CR2.xmp` and the `photo.xmp` naming conventions are supported.
+#[must_use]
+pub fn find_xmp_sidecar(file_path: &Path) -> Option<PathBuf> {
+  let file_name = file_path.file_name()?.to_string_lossy();
+  let file_stem = file_path.file_stem()?.to_string_lossy();
+
+  [
+    format!("{file_name}.xmp"),
+    format!("{file_name}.XMP"),
+    format!("{file_stem}.xmp"),
+    format!("{file_stem}.XMP"),
+  ]
+  .into_iter()
+  .map(|sidecar_name| file_path.with_file_name(sidecar_name))
+  .find(|sidecar_path| sidecar_path != file_path && sidecar_path.is_file())
+}
+
+/// The file a date for `file_path` is written to.
+/// With `sidecar_for_raw`, RAW files are never modified and the date is written to their XMP sidecar instead,
+/// which exiftool creates as `photo.xmp` if it does not exist yet.
+#[must_use]
+pub fn date_write_target(file_path: &Path, sidecar_for_raw: bool) -> PathBuf {
+  if !sidecar_for_raw || !is_raw_file(file_path) {
+    return file_path.to_path_buf();
+  }
+  find_xmp_sidecar(file_path).unwrap_or_else(|| file_path.with_extension("xmp"))
+}
+
+/// Reads the capture date from an XMP sidecar.
+pub fn get_xmp_sidecar_date(
+  sidecar_path: &Path,
+) -> Result<Option<ConfidentNaiveDateTime>, ErrorWithFilePath> {
+  let xmp = std::fs::read_to_string(sidecar_path)
+    .context("Failed to read XMP sidecar")
+    .map_err(ErrorWithFilePath::from_source(sidecar_path))?;
+
+  Ok(
+    XMP_DATE_TAGS
+      .iter()
+      .find_map(|tag| find_xmp_tag_value(&xmp, tag).and_then(parse_xmp_date)),
+  )
+}
+
+/// Finds the value of a tag that is either written as an attribute (`xmp:CreateDate="..."`)
+/// or as an element (`<xmp:CreateDate>...</xmp:CreateDate>`).
+fn find_xmp_tag_value<'a>(xmp: &'a str, tag: &str) -> Option<&'a str> {
+  let attribute_prefix = format!("{tag}=\"");
+  if let Some(start) = xmp.find(&attribute_prefix) {
+    let value = &xmp[start + attribute_prefix.len()..];
+    return Some(&value[..value.find('"')?]);
+  }
+
+  let element_start = format!("<{tag}>");
+  let start = xmp.find(&element_start)? + element_start.len();
+  let value = &xmp[start..];
+  Some(value[..value.find('<')?].trim())
+}
+
+/// Parses an XMP date (`YYYY[-MM[-DD[Thh:mm[:ss[.s]][TZD]]]]`).
+/// The time zone designator is ignored, as EXIF dates are local times as well.
+fn parse_xmp_date(value: &str) -> Option<ConfidentNaiveDateTime> {
+  static RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+      r"^(?P<year>\d{4})(?:-(?P<month>\d{2})(?:-(?P<day>\d{2})(?:T(?P<hour>\d{2}):(?P<minute>\d{2})(?::(?P<second>\d{2})(?:\.(?P<fraction>\d+))?)?)?)?)?",
+    )
+    .unwrap()
+  });
+  let captures = RE.captures(value.trim())?;
+  let parse = |name: &str| -> Option<u32> { captures.name(name)?.as_str().parse().ok() };
+
+  let confidence = [
+    ("fraction", DateConfidence::Millisecond),
+    ("second", DateConfidence::Second),
+    ("minute", DateConfidence::Minute),
+    ("day", DateConfidence::Day),
+    ("month", DateConfidence::Month),
+  ]
+  .into_iter()
+  .find_map(|(name, confidence)| captures.name(name).map(|_| confidence))
+  .unwrap_or(DateConfidence::Year);
+
+  let millisecond = captures
+    .name("fraction")
+    .and_then(|fraction| format!("{:0<3}", fraction.as_str())[..3].parse().ok())
+    .unwrap_or(0);
+
+  let date_time = NaiveDateTime::new(
+    NaiveDate::from_ymd_opt(
+      captures.name("year")?.as_str().parse().ok()?,
+      parse("month").unwrap_or(1),
+      parse("day").unwrap_or(1),
+    )?,
+    NaiveTime::from_hms_milli_opt(
+      parse("hour").unwrap_or(0),
+      parse("minute").unwrap_or(0),
+      parse("second").unwrap_or(0),
+      millisecond,
+    )?,
+  );
+  Some(ConfidentNaiveDateTime::new(date_time, confidence))
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn date(date: &str) -> NaiveDateTime {
+    NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S%.f").unwrap()
+  }
+
+  #[test]
+  fn raw_write_target() {
+    assert_eq!(
+      date_write_target(Path::new("/nonexistent/IMG_0001.CR2"), true),
+      Path::new("/nonexistent/IMG_0001.xmp")
+    );
+    assert_eq!(
+      date_write_target(Path::new("/nonexistent/IMG_0001.CR2"), false),
+      Path::new("/nonexistent/IMG_0001.CR2")
+    );
+    assert_eq!(
+      date_write_target(Path::new("/nonexistent/IMG_0001.jpg"), true),
+      Path::new("/nonexistent/IMG_0001.jpg")
+    );
+  }
+
+  #[test]
+  fn xmp_dates() {
+    assert_eq!(
+      parse_xmp_date("2019-08-18T13:08:41+02:00"),
+      Some(ConfidentNaiveDateTime::new(
+        date("2019-08-18 13:08:41"),
+        DateConfidence::Second
+      ))
+    );
+    assert_eq!(
+      parse_xmp_date("2019-08-18T13:08:41.12"),
+      Some(ConfidentNaiveDateTime::new(
+        date("2019-08-18 13:08:41.120"),
+        DateConfidence::Millisecond
+      ))
+    );
+    assert_eq!(
+      parse_xmp_date("2019-08-18T13:08Z"),
+      Some(ConfidentNaiveDateTime::new(
+        date("2019-08-18 13:08:00"),
+        DateConfidence::Minute
+      ))
+    );
+    assert_eq!(
+      parse_xmp_date("2019-08"),
+      Some(ConfidentNaiveDateTime::new(
+        date("2019-08-01 00:00:00"),
+        DateConfidence::Month
+      ))
+    );
+    assert_eq!(parse_xmp_date("2019-13-18"), None);
+  }
+
+  #[test]
+  fn xmp_tag_values() {
+    let attribute =
+      r#"<rdf:Description xmp:CreateDate="2019-08-18T13:08:41" xmp:ModifyDate="2020-01-01"/>"#;
+    assert_eq!(
+      find_xmp_tag_value(attribute, "xmp:CreateDate"),
+      Some("2019-08-18T13:08:41")
+    );
+
+    let element = "<rdf:Description>\n  <xmp:CreateDate> 2019-08-18T13:08:41 </xmp:CreateDate>\n</rdf:Description>";
+    assert_eq!(
+      find_xmp_tag_value(element, "xmp:CreateDate"),
+      Some("2019-08-18T13:08:41")
+    );
+    assert_eq!(find_xmp_tag_value(element, "exif:DateTimeOriginal"), None);
+  }
+}
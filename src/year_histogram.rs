@@ -0,0 +1,72 @@
+use alloc::collections::BTreeMap;
+use std::sync::Mutex;
+
+use chrono::{Datelike as _, NaiveDateTime};
+
+/// The width of the bar of the year with the most dates.
+const BAR_WIDTH: usize = 40;
+
+/// Counts the written EXIF dates per year, to check that the fixes match when the photos were taken.
+pub struct YearHistogram {
+  years: Mutex<BTreeMap<i32, usize>>,
+}
+
+impl YearHistogram {
+  #[must_use]
+  pub const fn new() -> Self {
+    Self {
+      years: Mutex::new(BTreeMap::new()),
+    }
+  }
+
+  pub fn record(&self, date: &NaiveDateTime) {
+    *self
+      .years
+      .lock()
+      .expect("Year histogram lock should not be poisoned")
+      .entry(date.year())
+      .or_default() += 1;
+  }
+
+  /// One line per year with a bar scaled to the year with the most dates, in chronological order.
+  #[must_use]
+  pub fn lines(&self) -> Vec<String> {
+    let years = self
+      .years
+      .lock()
+      .expect("Year histogram lock should not be poisoned");
+    let max_count = years.values().copied().max().unwrap_or_default();
+    let count_width = max_count.to_string().len();
+    years
+      .iter()
+      .map(|(year, count)| {
+        let bar_length = (count * BAR_WIDTH).div_ceil(max_count);
+        format!(
+          "{year}: {count:>count_width$} files fixed {}",
+          "#".repeat(bar_length)
+        )
+      })
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn histogram() {
+    let histogram = YearHistogram::new();
+    let date = |date: &str| NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S").unwrap();
+    for _ in 0..20 {
+      histogram.record(&date("2014-07-01 12:00:00"));
+    }
+    histogram.record(&date("2009-12-31 23:59:59"));
+    let lines = histogram.lines();
+    assert_eq!(lines[0], "2009:  1 files fixed ##");
+    assert_eq!(
+      lines[1],
+      format!("2014: 20 files fixed {}", "#".repeat(BAR_WIDTH))
+    );
+  }
+}